@@ -1,6 +1,504 @@
-use std::process;
+mod load;
+mod manifest;
+
+#[cfg(test)]
+mod tests;
+
+use std::{
+    env,
+    fmt::Write as _,
+    fs,
+    io::Write,
+    path::{Path, PathBuf},
+    process,
+};
+
+use ceylon_errors::Diagnostic;
+use ceylon_interp::Value;
+use ceylon_lower::ir::Program;
+use ceylon_parser::{
+    cfg::{self, Cfg},
+    serialize,
+};
+use ceylon_span::{SourceMap, Symbol};
+use ceylon_typeck::{resolve::ModId, ty::Ty, Def, Tcx};
+use load::{Loader, Package};
+use manifest::MANIFEST_NAME;
+
+const USAGE: &str = "usage: ceylon --emit=ast-json|ast-bin|layout|ir <file>
+       ceylon --run|--test <file>
+       ceylon build|run|test|check [--manifest-path=<path>]
+       ceylon --print cfg
+
+options: --cfg <name>|<name>=\"<value>\"  enable a configuration option
+         --release                       build without `debug`
+         --unchecked-indexing            don't check indices against the length";
+
+/// What the compiler should produce for the input file.
+enum Emit {
+    /// The AST as JSON.
+    AstJson,
+    /// The AST in the compact binary format.
+    AstBinary,
+    /// The memory layout of every struct and enum, after type checking.
+    Layout,
+    /// The lowered functions.
+    Ir,
+    /// Nothing, the program is run by the interpreter instead and the value
+    /// returned by `main` is printed.
+    Run,
+    /// Nothing, the functions marked `@test` are run by the interpreter
+    /// instead, and whether each of them panicked is printed.
+    Test,
+}
+
+struct Options {
+    emit: Emit,
+    input: String,
+    cfg: Cfg,
+    /// Whether indexing checks that the index is in bounds.
+    bounds_checks: bool,
+}
+
+/// What to do with a package.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum PackageCommand {
+    /// Type check the package.
+    Check,
+    /// Type check and lower the package, writing its AST and IR to
+    /// `target/`.
+    Build,
+    /// Build the package, then run it like `--run`.
+    Run,
+    /// Build the package, then run its tests like `--test`.
+    Test,
+}
+
+enum Command {
+    /// Compile a single file.
+    File(Options),
+    Package {
+        command: PackageCommand,
+        /// The manifest to use instead of the one found from the current
+        /// directory.
+        manifest_path: Option<String>,
+        cfg: Cfg,
+        bounds_checks: bool,
+    },
+    /// Print the configuration `@cfg` predicates are checked against.
+    PrintCfg(Cfg),
+}
+
+fn parse_command(args: &[String]) -> Result<Command, String> {
+    let BuildArgs {
+        rest: args,
+        cfg,
+        bounds_checks,
+        print,
+    } = parse_build_args(args)?;
+    if print {
+        if let Some(arg) = args.first() {
+            return Err(format!("unexpected argument `{}`", arg));
+        }
+        return Ok(Command::PrintCfg(cfg));
+    }
+    let command = match args.first().map(String::as_str) {
+        Some("check") => PackageCommand::Check,
+        Some("build") => PackageCommand::Build,
+        Some("run") => PackageCommand::Run,
+        Some("test") => PackageCommand::Test,
+        _ => return parse_args(&args, cfg, bounds_checks).map(Command::File),
+    };
+    let mut cfg = cfg;
+    if command == PackageCommand::Test {
+        cfg.insert("test", None);
+    }
+    let mut manifest_path = None;
+    for arg in &args[1..] {
+        if let Some(path) = arg.strip_prefix("--manifest-path=") {
+            manifest_path = Some(path.to_string());
+        } else if arg.starts_with('-') {
+            return Err(format!("unknown option `{}`", arg));
+        } else {
+            return Err(format!("unexpected argument `{}`", arg));
+        }
+    }
+    Ok(Command::Package {
+        command,
+        manifest_path,
+        cfg,
+        bounds_checks,
+    })
+}
+
+/// The options which apply to every command.
+struct BuildArgs {
+    /// The other arguments.
+    rest: Vec<String>,
+    cfg: Cfg,
+    bounds_checks: bool,
+    /// Whether `--print cfg` was given.
+    print: bool,
+}
+
+/// Takes the options setting the configuration and how the program is
+/// built out of `args`.
+fn parse_build_args(args: &[String]) -> Result<BuildArgs, String> {
+    let mut rest = Vec::new();
+    let mut options = Vec::new();
+    let mut release = false;
+    let mut bounds_checks = true;
+    let mut print = false;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--cfg" => {
+                let spec = args.next().ok_or("`--cfg` takes an option")?;
+                options.push(cfg::parse_option(spec)?);
+            }
+            "--release" => release = true,
+            "--unchecked-indexing" => bounds_checks = false,
+            "--print" => match args.next().map(String::as_str) {
+                Some("cfg") => print = true,
+                Some(request) => return Err(format!("unknown print request `{}`", request)),
+                None => return Err("`--print` takes what to print".to_string()),
+            },
+            _ => rest.push(arg.clone()),
+        }
+    }
+    let mut cfg = host_cfg(release);
+    for (name, value) in &options {
+        cfg.insert(name, value.as_deref());
+    }
+    Ok(BuildArgs {
+        rest,
+        cfg,
+        bounds_checks,
+        print,
+    })
+}
+
+/// The configuration of the machine the compiler runs on, which programs
+/// are built for.
+fn host_cfg(release: bool) -> Cfg {
+    let mut cfg = Cfg::default();
+    cfg.insert("target_os", Some(env::consts::OS));
+    cfg.insert("target_arch", Some(env::consts::ARCH));
+    if !env::consts::FAMILY.is_empty() {
+        cfg.insert("target_family", Some(env::consts::FAMILY));
+    }
+    if !release {
+        cfg.insert("debug", None);
+    }
+    cfg
+}
+
+fn parse_args(args: &[String], mut cfg: Cfg, bounds_checks: bool) -> Result<Options, String> {
+    let mut emit = None;
+    let mut input = None;
+    for arg in args {
+        if let Some(kind) = arg.strip_prefix("--emit=") {
+            emit = Some(match kind {
+                "ast-json" => Emit::AstJson,
+                "ast-bin" => Emit::AstBinary,
+                "layout" => Emit::Layout,
+                "ir" => Emit::Ir,
+                _ => return Err(format!("unknown emit kind `{}`", kind)),
+            });
+        } else if arg == "--run" {
+            emit = Some(Emit::Run);
+        } else if arg == "--test" {
+            emit = Some(Emit::Test);
+            cfg.insert("test", None);
+        } else if arg.starts_with('-') {
+            return Err(format!("unknown option `{}`", arg));
+        } else if input.is_none() {
+            input = Some(arg.clone());
+        } else {
+            return Err(format!("unexpected argument `{}`", arg));
+        }
+    }
+    Ok(Options {
+        emit: emit.ok_or("no `--emit` kind, `--run` or `--test` given")?,
+        input: input.ok_or("no input file given")?,
+        cfg,
+        bounds_checks,
+    })
+}
+
+fn run(args: &[String]) -> Result<(), String> {
+    let options = match parse_command(args).map_err(|e| format!("{}\n{}", e, USAGE))? {
+        Command::File(options) => options,
+        Command::Package {
+            command,
+            manifest_path,
+            cfg,
+            bounds_checks,
+        } => return run_package(command, manifest_path, cfg, bounds_checks),
+        Command::PrintCfg(cfg) => {
+            print!("{}", cfg);
+            return Ok(());
+        }
+    };
+    let lower_options = lower_options(&options.cfg, options.bounds_checks);
+    let mut loader = Loader::new(&|path| fs::read_to_string(path), options.cfg);
+    let module = loader.load(Path::new(&options.input))?;
+    let source_map = &loader.source_map;
+    report(&loader.diagnostics, source_map)?;
+    let output = match options.emit {
+        Emit::AstJson => serialize::to_json(&module).into_bytes(),
+        Emit::AstBinary => serialize::to_binary(&module),
+        Emit::Layout | Emit::Ir | Emit::Run | Emit::Test => {
+            let (tcx, diagnostics) = ceylon_typeck::check_module(&module);
+            report(&diagnostics, source_map)?;
+            match options.emit {
+                Emit::Layout => print_layouts(&tcx).into_bytes(),
+                Emit::Ir => {
+                    let program = lower(&module, &tcx, lower_options, source_map)?;
+                    ceylon_lower::pretty::program_to_string(&tcx, &program).into_bytes()
+                }
+                Emit::Test => run_tests(&module, &tcx, lower_options, source_map)?.into_bytes(),
+                _ => run_main(&module, &tcx, lower_options, source_map)?.into_bytes(),
+            }
+        }
+    };
+    std::io::stdout()
+        .write_all(&output)
+        .map_err(|e| e.to_string())
+}
+
+fn run_package(
+    command: PackageCommand,
+    manifest_path: Option<String>,
+    cfg: Cfg,
+    bounds_checks: bool,
+) -> Result<(), String> {
+    let manifest_path = match manifest_path {
+        Some(path) => PathBuf::from(path),
+        None => find_manifest()?,
+    };
+    let options = lower_options(&cfg, bounds_checks);
+    let mut loader = Loader::new(&|path| fs::read_to_string(path), cfg);
+    let package = loader.load_package(&manifest_path);
+    let source_map = &loader.source_map;
+    report(&loader.diagnostics, source_map)?;
+    let Package {
+        manifest,
+        dir,
+        module,
+    } = package?;
+    let status = match command {
+        PackageCommand::Check => "Checking",
+        PackageCommand::Build | PackageCommand::Run | PackageCommand::Test => "Compiling",
+    };
+    eprintln!(
+        "{:>12} {} v{} ({})",
+        status,
+        manifest.name,
+        manifest.version,
+        fs::canonicalize(Path::new(".").join(&dir))
+            .unwrap_or_else(|_| dir.clone())
+            .display()
+    );
+    let (tcx, diagnostics) = ceylon_typeck::check_module(&module);
+    report(&diagnostics, source_map)?;
+    if command == PackageCommand::Check {
+        return Ok(());
+    }
+
+    let program = lower(&module, &tcx, options, source_map)?;
+    let target = dir.join("target");
+    let write = |extension: &str, contents: &[u8]| {
+        let path = target.join(&manifest.name).with_extension(extension);
+        fs::write(&path, contents).map_err(|e| format!("couldn't write {}: {}", path.display(), e))
+    };
+    fs::create_dir_all(&target)
+        .map_err(|e| format!("couldn't create {}: {}", target.display(), e))?;
+    write("ast", &serialize::to_binary(&module))?;
+    write(
+        "ir",
+        ceylon_lower::pretty::program_to_string(&tcx, &program).as_bytes(),
+    )?;
+    let output = match command {
+        PackageCommand::Run => run_main(&module, &tcx, options, source_map)?,
+        PackageCommand::Test => run_tests(&module, &tcx, options, source_map)?,
+        PackageCommand::Check | PackageCommand::Build => return Ok(()),
+    };
+    std::io::stdout()
+        .write_all(output.as_bytes())
+        .map_err(|e| e.to_string())
+}
+
+/// Finds the manifest in the current directory or the closest of its
+/// ancestors, returning its path relative to the current directory.
+fn find_manifest() -> Result<PathBuf, String> {
+    let cwd = env::current_dir().map_err(|e| e.to_string())?;
+    let mut relative = PathBuf::new();
+    for dir in cwd.ancestors() {
+        if dir.join(MANIFEST_NAME).is_file() {
+            return Ok(relative.join(MANIFEST_NAME));
+        }
+        relative.push("..");
+    }
+    Err(format!(
+        "could not find `{}` in `{}` or any parent directory",
+        MANIFEST_NAME,
+        cwd.display()
+    ))
+}
+
+/// Prints diagnostics to stderr, failing if any of them is an error.
+fn report(diagnostics: &[Diagnostic], source_map: &SourceMap) -> Result<(), String> {
+    for diag in diagnostics {
+        eprint!("{}", diag.render_in(source_map));
+    }
+    if diagnostics.iter().any(|diag| diag.is_error()) {
+        return Err("aborting due to previous error".to_string());
+    }
+    Ok(())
+}
+
+/// Integer arithmetic panics on overflow in debug builds, and wraps around
+/// in release builds. Indices are checked in both unless
+/// `--unchecked-indexing` is given.
+fn lower_options(cfg: &Cfg, bounds_checks: bool) -> ceylon_lower::Options {
+    ceylon_lower::Options {
+        overflow_checks: cfg.contains("debug", None),
+        bounds_checks,
+    }
+}
+
+fn lower(
+    module: &ceylon_parser::ast::Module,
+    tcx: &Tcx,
+    options: ceylon_lower::Options,
+    source_map: &SourceMap,
+) -> Result<Program, String> {
+    ceylon_lower::lower_module(module, tcx, options).map_err(|diag| {
+        eprint!("{}", diag.render_in(source_map));
+        "aborting due to previous error".to_string()
+    })
+}
+
+/// Interprets the `main` function of the root module, returning what it
+/// evaluates to.
+fn run_main(
+    module: &ceylon_parser::ast::Module,
+    tcx: &Tcx,
+    options: ceylon_lower::Options,
+    source_map: &SourceMap,
+) -> Result<String, String> {
+    let Some(Def::Fn(main)) = tcx.def(Symbol::intern("main")) else {
+        return Err("no `main` function found".to_string());
+    };
+    let sig = tcx.fn_sig(main);
+    if !sig.generics.params.is_empty() {
+        return Err("`main` must not be generic".to_string());
+    }
+    if !sig.inputs.is_empty() {
+        return Err("`main` must not take arguments".to_string());
+    }
+    let program = lower(module, tcx, options, source_map)?;
+    let main = program
+        .instance(main, &[])
+        .expect("non-generic functions are lowered");
+    match ceylon_interp::call(&program, main, Vec::new()) {
+        Ok(Value::Void) => Ok(String::new()),
+        Ok(value) => Ok(format!("{}\n", value.display(tcx, &sig.output))),
+        Err(panic) => Err(format!("the program panicked: {}", panic.message)),
+    }
+}
+
+/// Runs the functions marked `@test`, returning a line for each of them and
+/// a summary. If any of them panicked, the report is printed and the run
+/// fails.
+fn run_tests(
+    module: &ceylon_parser::ast::Module,
+    tcx: &Tcx,
+    options: ceylon_lower::Options,
+    source_map: &SourceMap,
+) -> Result<String, String> {
+    let program = lower(module, tcx, options, source_map)?;
+    let mut out = String::new();
+    let mut failed = 0;
+    writeln!(out, "running {} tests", tcx.tests.len()).unwrap();
+    for &(scope, test) in &tcx.tests {
+        let name = match scope {
+            ModId::ROOT => tcx.fn_sig(test).name.to_string(),
+            _ => format!("{}::{}", tcx.module_path(scope), tcx.fn_sig(test).name),
+        };
+        let body = program
+            .instance(test, &[])
+            .expect("non-generic functions are lowered");
+        match ceylon_interp::call(&program, body, Vec::new()) {
+            Ok(_) => writeln!(out, "test {} ... ok", name).unwrap(),
+            Err(panic) => {
+                failed += 1;
+                writeln!(out, "test {} ... FAILED: {}", name, panic.message).unwrap();
+            }
+        }
+    }
+    let result = if failed == 0 { "ok" } else { "FAILED" };
+    writeln!(
+        out,
+        "test result: {}. {} passed; {} failed",
+        result,
+        tcx.tests.len() - failed,
+        failed
+    )
+    .unwrap();
+    if failed > 0 {
+        print!("{}", out);
+        return Err(format!("{} of {} tests failed", failed, tcx.tests.len()));
+    }
+    Ok(out)
+}
+
+fn print_layouts(tcx: &Tcx) -> String {
+    let mut out = String::new();
+    for (i, adt) in tcx.adts.iter().enumerate() {
+        // Generic ADTs only have layouts once instantiated.
+        if !adt.generics.params.is_empty() {
+            continue;
+        }
+        let ty = Ty::Adt(ceylon_typeck::ty::AdtId::from_usize(i), Vec::new());
+        // Types too big to lay out have been reported by the type checker.
+        let Ok(layout) = tcx.layout_of(&ty) else {
+            writeln!(out, "{}: layout unavailable", adt.name).unwrap();
+            continue;
+        };
+        writeln!(
+            out,
+            "{}: size {}, align {}",
+            adt.name, layout.size, layout.align
+        )
+        .unwrap();
+        match &layout.variants {
+            None => {
+                let fields = &adt.non_enum_variant().fields;
+                for (field, offset) in fields.iter().zip(&layout.field_offsets) {
+                    writeln!(out, "    {}: offset {}", field.name, offset).unwrap();
+                }
+            }
+            Some(variants) => {
+                writeln!(out, "    tag: size {}", variants.tag_size).unwrap();
+                for (variant, layout) in adt.variants.iter().zip(&variants.variants) {
+                    writeln!(out, "    {}: size {}", variant.name, layout.size).unwrap();
+                    for (field, offset) in variant.fields.iter().zip(&layout.field_offsets) {
+                        writeln!(out, "        {}: offset {}", field.name, offset).unwrap();
+                    }
+                }
+            }
+        }
+    }
+    out
+}
 
 pub fn main() -> ! {
-    println!("Hello, Ceylon!");
+    let args: Vec<String> = env::args().skip(1).collect();
+    if let Err(e) = run(&args) {
+        eprintln!("error: {}", e);
+        process::exit(1);
+    }
     process::exit(0)
 }
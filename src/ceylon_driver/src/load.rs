@@ -0,0 +1,340 @@
+//! Loading the files of a program. The module of `main.cy` declares its
+//! submodules with `mod name;`, each of which is parsed from `name.cy` next
+//! to it, and the submodules of those from the directory `name/`.
+//!
+//! ```text
+//! main.cy              // mod geometry;
+//! geometry.cy          // mod shapes;
+//! geometry/shapes.cy
+//! ```
+//!
+//! A package instead names its entry file and the directories to look up
+//! the files of its top-level modules in, and the root modules of its
+//! dependencies are added to its root module. See [`crate::manifest`].
+//!
+//! Each file is stripped of its items disabled by `@cfg` once parsed, so the
+//! files of disabled modules aren't loaded, and then linted.
+
+use std::{
+    collections::HashMap,
+    io,
+    path::{Component, Path, PathBuf},
+};
+
+use ceylon_errors::Diagnostic;
+use ceylon_parser::{
+    ast,
+    cfg::{self, Cfg},
+    lint,
+};
+use ceylon_span::{SourceMap, Symbol};
+
+use crate::manifest::{self, Manifest, MANIFEST_NAME};
+
+/// Reads the file at a path.
+pub(crate) type ReadFile<'a> = &'a dyn Fn(&Path) -> io::Result<String>;
+
+/// A loaded package, with the modules of its dependencies.
+pub(crate) struct Package {
+    pub(crate) manifest: Manifest,
+    /// The directory containing the manifest.
+    pub(crate) dir: PathBuf,
+    pub(crate) module: ast::Module,
+}
+
+pub(crate) struct Loader<'a> {
+    read: ReadFile<'a>,
+    /// The configuration `@cfg` predicates are checked against.
+    cfg: Cfg,
+    pub(crate) source_map: SourceMap,
+    /// The id of the next AST node, so that the ids of the nodes of
+    /// different files don't collide.
+    next_node_id: u32,
+    /// The packages loaded as dependencies by their absolute directory, with
+    /// their name and the item adding them to the first package depending on
+    /// them. `None` for those which failed to load.
+    dependencies: HashMap<PathBuf, Option<(String, ast::NodeId)>>,
+    pub(crate) diagnostics: Vec<Diagnostic>,
+}
+
+impl<'a> Loader<'a> {
+    pub(crate) fn new(read: ReadFile<'a>, cfg: Cfg) -> Self {
+        Loader {
+            read,
+            cfg,
+            source_map: SourceMap::default(),
+            next_node_id: 0,
+            dependencies: HashMap::new(),
+            diagnostics: Vec::new(),
+        }
+    }
+
+    /// Parses the root module of a program and its submodules. Fails if the
+    /// root file cannot be read; missing files of submodules are reported
+    /// as diagnostics instead.
+    pub(crate) fn load(&mut self, path: &Path) -> Result<ast::Module, String> {
+        let dir = path.parent().unwrap_or(Path::new("")).to_path_buf();
+        self.load_in(path, &[dir])
+    }
+
+    /// Like [`Loader::load`], but looks up the files of the top-level
+    /// modules in each of `dirs` in order.
+    fn load_in(&mut self, path: &Path, dirs: &[PathBuf]) -> Result<ast::Module, String> {
+        let src =
+            (self.read)(path).map_err(|e| format!("couldn't read {}: {}", path.display(), e))?;
+        let mut module = self.parse(path, src);
+        self.load_submodules(&mut module, dirs);
+        Ok(module)
+    }
+
+    /// Loads the package whose manifest is at `path`, and its dependencies.
+    /// Fails if the manifest cannot be read; other errors are reported as
+    /// diagnostics.
+    pub(crate) fn load_package(&mut self, path: &Path) -> Result<Package, String> {
+        let src =
+            (self.read)(path).map_err(|e| format!("couldn't read {}: {}", path.display(), e))?;
+        self.load_package_from(path, src, &mut Vec::new())
+            .ok_or_else(|| "aborting due to previous error".to_string())
+    }
+
+    /// Loads a package from its manifest `src`. `dependents` are the
+    /// packages depending on it, to detect cyclic dependencies.
+    ///
+    /// A package which several others depend on is loaded once, and added to
+    /// those after the first as the same module.
+    fn load_package_from(
+        &mut self,
+        path: &Path,
+        src: String,
+        dependents: &mut Vec<(PathBuf, String)>,
+    ) -> Option<Package> {
+        let file = self.source_map.add_file(path.display().to_string(), src);
+        let manifest = match manifest::parse_manifest(&file.src, file.start_pos) {
+            Ok(manifest) => manifest,
+            Err(diagnostics) => {
+                self.diagnostics.extend(diagnostics);
+                return None;
+            }
+        };
+        let dir = path.parent().unwrap_or(Path::new("")).to_path_buf();
+        let entry = normalize(&dir.join(&manifest.entry));
+        let dirs: Vec<PathBuf> = manifest
+            .source_dirs
+            .iter()
+            .map(|source_dir| normalize(&dir.join(source_dir)))
+            .collect();
+        let mut module = match self.load_in(&entry, &dirs) {
+            Ok(module) => module,
+            Err(e) => {
+                self.diagnostics.push(
+                    Diagnostic::error(
+                        manifest.entry_span,
+                        format!(
+                            "failed to load the entry point of package `{}`",
+                            manifest.name
+                        ),
+                    )
+                    .note(e),
+                );
+                return None;
+            }
+        };
+
+        dependents.push((absolute(&dir), manifest.name.clone()));
+        let mut failed = false;
+        for dep in &manifest.dependencies {
+            let dep_dir = normalize(&dir.join(&dep.path));
+            let key = absolute(&dep_dir);
+            if let Some(i) = dependents.iter().position(|(dir, _)| *dir == key) {
+                let cycle: Vec<String> = dependents[i..]
+                    .iter()
+                    .map(|(_, name)| format!("`{}`", name))
+                    .chain([format!("`{}`", dependents[i].1)])
+                    .collect();
+                self.diagnostics.push(
+                    Diagnostic::error(dep.span, "cyclic package dependency").note(format!(
+                        "the packages depend on each other: {}",
+                        cycle.join(" -> ")
+                    )),
+                );
+                failed = true;
+                continue;
+            }
+            let (name, id, dep_module, same_as) = match self.dependencies.get(&key).cloned() {
+                Some(Some((name, first))) => (name, self.next_node_id(), None, Some(first)),
+                // The errors have been reported where it was first loaded.
+                Some(None) => {
+                    failed = true;
+                    continue;
+                }
+                None => {
+                    let dep_path = dep_dir.join(MANIFEST_NAME);
+                    let src = match (self.read)(&dep_path) {
+                        Ok(src) => src,
+                        Err(e) => {
+                            self.diagnostics.push(
+                                Diagnostic::error(
+                                    dep.span,
+                                    format!("failed to load dependency `{}`", dep.name),
+                                )
+                                .note(format!(
+                                    "couldn't read {}: {}",
+                                    dep_path.display(),
+                                    e
+                                )),
+                            );
+                            failed = true;
+                            continue;
+                        }
+                    };
+                    let package = self.load_package_from(&dep_path, src, dependents);
+                    let Some(package) = package else {
+                        self.dependencies.insert(key, None);
+                        failed = true;
+                        continue;
+                    };
+                    // Later dependents refer to the item adding it here.
+                    let id = self.next_node_id();
+                    self.dependencies
+                        .insert(key, Some((package.manifest.name.clone(), id)));
+                    (package.manifest.name, id, Some(package.module), None)
+                }
+            };
+            if name != dep.name {
+                self.diagnostics.push(
+                    Diagnostic::error(
+                        dep.span,
+                        format!("dependency `{}` is the package `{}`", dep.name, name),
+                    )
+                    .help(format!("rename the dependency to `{}`", name)),
+                );
+                failed = true;
+                continue;
+            }
+            let ident = ast::Ident {
+                name: Symbol::intern(&dep.name),
+                span: dep.span,
+            };
+            module.items.push(ast::Item {
+                id,
+                docs: Vec::new(),
+                attrs: Vec::new(),
+                vis: ast::Visibility::Private,
+                ident,
+                kind: ast::ItemKind::Mod(ast::ModDef {
+                    inline: false,
+                    module: dep_module,
+                    package: true,
+                    same_as,
+                }),
+                span: dep.span,
+            });
+        }
+        dependents.pop();
+
+        if failed {
+            return None;
+        }
+        Some(Package {
+            manifest,
+            dir,
+            module,
+        })
+    }
+
+    fn next_node_id(&mut self) -> ast::NodeId {
+        let id = ast::NodeId::from_u32(self.next_node_id);
+        self.next_node_id += 1;
+        id
+    }
+
+    fn parse(&mut self, path: &Path, src: String) -> ast::Module {
+        let file = self.source_map.add_file(path.display().to_string(), src);
+        let (mut module, diagnostics) =
+            ceylon_parser::parse_file_at(&file.src, file.start_pos, &mut self.next_node_id);
+        self.diagnostics.extend(diagnostics);
+        let diagnostics = cfg::strip_module(&mut module, &self.cfg);
+        self.diagnostics.extend(diagnostics);
+        let lints = lint::check_module_at(&file.src, file.start_pos, &module);
+        self.diagnostics.extend(
+            lints
+                .into_iter()
+                .map(|lint| Diagnostic::warning(lint.span, lint.message)),
+        );
+        module
+    }
+
+    /// Loads the files of the modules declared in `module`, looking up the
+    /// files of its submodules in each of `dirs` in order.
+    fn load_submodules(&mut self, module: &mut ast::Module, dirs: &[PathBuf]) {
+        for item in &mut module.items {
+            let ast::ItemKind::Mod(def) = &mut item.kind else {
+                continue;
+            };
+            let name = item.ident.name.as_str();
+            if def.inline {
+                let dirs: Vec<PathBuf> = dirs.iter().map(|dir| dir.join(name)).collect();
+                let module = def.module.as_mut().expect("inline modules are parsed");
+                self.load_submodules(module, &dirs);
+                continue;
+            }
+            let mut errors = Vec::new();
+            for dir in dirs {
+                let path = dir.join(name).with_extension("cy");
+                match (self.read)(&path) {
+                    Ok(src) => {
+                        let mut module = self.parse(&path, src);
+                        self.load_submodules(&mut module, &[dir.join(name)]);
+                        def.module = Some(module);
+                        break;
+                    }
+                    Err(e) => errors.push((path, e)),
+                }
+            }
+            if def.module.is_some() {
+                continue;
+            }
+            let mut diag = Diagnostic::error(
+                item.span,
+                format!("file not found for module `{}`", item.ident.name),
+            );
+            for (path, e) in &errors {
+                diag = diag.note(format!("couldn't read {}: {}", path.display(), e));
+            }
+            if let Some((path, _)) = errors.first() {
+                diag = diag.help(format!(
+                    "to create the module `{}`, create the file `{}`",
+                    item.ident.name,
+                    path.display()
+                ));
+            }
+            self.diagnostics.push(diag);
+        }
+    }
+}
+
+/// The normalized absolute path of a directory, to tell whether two paths
+/// relative to different directories are the same.
+fn absolute(dir: &Path) -> PathBuf {
+    let dir = Path::new(".").join(dir);
+    normalize(&std::path::absolute(&dir).unwrap_or(dir))
+}
+
+/// Removes the `.` components of a path, and the `..` components following
+/// a directory name along with it, so that paths to the same file compare
+/// equal and print short.
+fn normalize(path: &Path) -> PathBuf {
+    let mut out = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir
+                if matches!(out.components().next_back(), Some(Component::Normal(_))) =>
+            {
+                out.pop();
+            }
+            component => out.push(component),
+        }
+    }
+    out
+}
@@ -0,0 +1,170 @@
+//! Package manifests. A package is a directory with a `Ceylon.toml`:
+//!
+//! ```toml
+//! [package]
+//! name = "shapes"
+//! version = "0.1.0"
+//! entry = "src/main.cy"   # the default
+//! source-dirs = ["src"]   # the default, the directory of the entry
+//!
+//! [dependencies]
+//! geometry = { path = "../geometry" }
+//! ```
+//!
+//! The files of the submodules of the root module are looked up in the
+//! source directories, in order. Dependencies are packages in local
+//! directories, and become modules of the root module named after them.
+
+use std::{collections::BTreeMap, ops::Range, path::PathBuf};
+
+use ceylon_errors::Diagnostic;
+use ceylon_lexer::{is_id_continue, is_id_start};
+use ceylon_span::{Span, Symbol};
+use serde::Deserialize;
+use toml::Spanned;
+
+pub(crate) const MANIFEST_NAME: &str = "Ceylon.toml";
+
+pub(crate) struct Manifest {
+    pub(crate) name: String,
+    pub(crate) version: String,
+    /// The file of the root module, relative to the package directory.
+    pub(crate) entry: PathBuf,
+    /// The span of the entry point if given, or else of the name.
+    pub(crate) entry_span: Span,
+    /// The directories the files of the submodules of the root module are
+    /// looked up in, relative to the package directory.
+    pub(crate) source_dirs: Vec<PathBuf>,
+    pub(crate) dependencies: Vec<Dependency>,
+}
+
+pub(crate) struct Dependency {
+    pub(crate) name: String,
+    /// The directory of the package, relative to the package depending on
+    /// it.
+    pub(crate) path: PathBuf,
+    pub(crate) span: Span,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawManifest {
+    package: RawPackage,
+    #[serde(default)]
+    dependencies: BTreeMap<Spanned<String>, RawDependency>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+struct RawPackage {
+    name: Spanned<String>,
+    version: Spanned<String>,
+    entry: Option<Spanned<PathBuf>>,
+    source_dirs: Option<Vec<PathBuf>>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawDependency {
+    path: PathBuf,
+}
+
+/// Parses the manifest `src`, which starts at `start_pos` in the source map.
+pub(crate) fn parse_manifest(src: &str, start_pos: usize) -> Result<Manifest, Vec<Diagnostic>> {
+    let span = |range: Range<usize>| Span {
+        start_pos: start_pos + range.start,
+        len: range.len(),
+    };
+    let raw: RawManifest = toml::from_str(src).map_err(|e| {
+        let range = e.span().unwrap_or(0..0);
+        vec![Diagnostic::error(span(range), e.message())]
+    })?;
+    let mut diagnostics = Vec::new();
+
+    let name = &raw.package.name;
+    if !is_ident(name.get_ref()) {
+        diagnostics.push(
+            Diagnostic::error(
+                span(name.span()),
+                format!("invalid package name `{}`", name.get_ref()),
+            )
+            .note("package names must be identifiers, as dependents refer to them in paths"),
+        );
+    }
+    let version = &raw.package.version;
+    if !is_version(version.get_ref()) {
+        diagnostics.push(
+            Diagnostic::error(
+                span(version.span()),
+                format!("invalid version `{}`", version.get_ref()),
+            )
+            .help("versions have the form `MAJOR.MINOR.PATCH`, like `0.1.0`"),
+        );
+    }
+    let mut entry_span = span(name.span());
+    let entry = match raw.package.entry {
+        Some(entry) => {
+            entry_span = span(entry.span());
+            if entry.get_ref().extension().is_none_or(|ext| ext != "cy") {
+                diagnostics.push(Diagnostic::error(
+                    span(entry.span()),
+                    format!(
+                        "the entry point `{}` is not a `.cy` file",
+                        entry.get_ref().display()
+                    ),
+                ));
+            }
+            entry.into_inner()
+        }
+        None => PathBuf::from("src/main.cy"),
+    };
+    let source_dirs = raw.package.source_dirs.unwrap_or_else(|| {
+        let dir = entry.parent().unwrap_or(&entry).to_path_buf();
+        vec![dir]
+    });
+
+    let mut dependencies = Vec::new();
+    for (name, dep) in raw.dependencies {
+        let span = span(name.span());
+        let name = name.into_inner();
+        if !is_ident(&name) {
+            diagnostics.push(
+                Diagnostic::error(span, format!("invalid dependency name `{}`", name))
+                    .note("dependencies are named after their package"),
+            );
+        }
+        dependencies.push(Dependency {
+            name,
+            path: dep.path,
+            span,
+        });
+    }
+
+    if !diagnostics.is_empty() {
+        return Err(diagnostics);
+    }
+    Ok(Manifest {
+        name: raw.package.name.into_inner(),
+        version: raw.package.version.into_inner(),
+        entry,
+        entry_span,
+        source_dirs,
+        dependencies,
+    })
+}
+
+/// Whether `name` can name a module.
+fn is_ident(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars.next().is_some_and(is_id_start)
+        && chars.all(is_id_continue)
+        && !Symbol::intern(name).is_keyword()
+}
+
+fn is_version(version: &str) -> bool {
+    let parts: Vec<&str> = version.split('.').collect();
+    parts.len() == 3
+        && parts
+            .iter()
+            .all(|part| !part.is_empty() && part.bytes().all(|b| b.is_ascii_digit()))
+}
@@ -0,0 +1,529 @@
+use std::{collections::HashMap, io};
+
+use super::*;
+use crate::manifest::MANIFEST_NAME;
+use ceylon_parser::cfg::Cfg;
+use expect_test::{expect, Expect};
+
+/// Loads a program from in-memory files, and type checks it if it parsed.
+/// Returns the rendered diagnostics, or the result of running `main`.
+fn check_program(files: &[(&str, &str)], expect: Expect) {
+    check_files(
+        files,
+        Cfg::default(),
+        |loader| loader.load(Path::new("main.cy")).unwrap(),
+        expect,
+    );
+}
+
+/// Like [`check_program`], but loads the package whose manifest is at
+/// `manifest`.
+fn check_package(manifest: &str, files: &[(&str, &str)], expect: Expect) {
+    check_files(
+        files,
+        Cfg::default(),
+        |loader| match loader.load_package(Path::new(manifest)) {
+            Ok(package) => package.module,
+            Err(_) => ceylon_parser::ast::Module {
+                docs: Vec::new(),
+                items: Vec::new(),
+                span: ceylon_span::Span {
+                    start_pos: 0,
+                    len: 0,
+                },
+            },
+        },
+        expect,
+    );
+}
+
+fn check_files(
+    files: &[(&str, &str)],
+    cfg: Cfg,
+    load: impl FnOnce(&mut Loader) -> ceylon_parser::ast::Module,
+    expect: Expect,
+) {
+    let files: HashMap<&Path, &str> = files
+        .iter()
+        .map(|&(path, src)| (Path::new(path), src))
+        .collect();
+    let read = |path: &Path| match files.get(path) {
+        Some(src) => Ok(src.to_string()),
+        None => Err(io::Error::from(io::ErrorKind::NotFound)),
+    };
+    let options = lower_options(&cfg, true);
+    let mut loader = Loader::new(&read, cfg);
+    let module = load(&mut loader);
+    let render = |diagnostics: &[Diagnostic]| -> String {
+        diagnostics
+            .iter()
+            .map(|diag| diag.render_in(&loader.source_map))
+            .collect()
+    };
+    // Warnings from loading are rendered before the rest of the output.
+    let mut actual = render(&loader.diagnostics);
+    if !loader.diagnostics.iter().any(|diag| diag.is_error()) {
+        let (tcx, diagnostics) = ceylon_typeck::check_module(&module);
+        if diagnostics.is_empty() {
+            actual += &run_main(&module, &tcx, options, &loader.source_map).unwrap();
+        } else {
+            actual += &render(&diagnostics);
+        }
+    }
+    expect.assert_eq(&actual);
+}
+
+#[test]
+fn test_load_modules() {
+    check_program(
+        &[
+            (
+                "main.cy",
+                "mod geometry;\nimport geometry::shapes::Square;\n\
+                 fn main() -> i64 { geometry::area(Square { side: 3 }) + geometry::origin() }",
+            ),
+            (
+                "geometry.cy",
+                "pub mod shapes;\nimport geometry::shapes::Square;\n\
+                 pub fn area(s: Square) -> i64 { s.side * s.side }\npub fn origin() -> i64 { 0 }",
+            ),
+            ("geometry/shapes.cy", "pub struct Square { side: i64 }"),
+        ],
+        expect![[r#"
+            9
+        "#]],
+    );
+}
+
+#[test]
+fn test_load_errors() {
+    check_program(
+        &[
+            ("main.cy", "mod util;\nmod missing;\nfn main() {}"),
+            ("util.cy", "pub fn f() -> i64 { 1 +  }"),
+        ],
+        expect![[r#"
+            error: expected expression, found `}`
+             --> util.cy:1:26
+              |
+            1 | pub fn f() -> i64 { 1 +  }
+              |                          ^
+            error: file not found for module `missing`
+             --> main.cy:2:1
+              |
+            2 | mod missing;
+              | ^^^^^^^^^^^^
+              = note: couldn't read missing.cy: entity not found
+              = help: to create the module `missing`, create the file `missing.cy`
+        "#]],
+    );
+}
+
+#[test]
+fn test_import_cycle_across_files() {
+    check_program(
+        &[
+            ("main.cy", "mod a;\nmod b;\nfn main() {}"),
+            ("a.cy", "import b::g;\npub fn f() -> i64 { g() }"),
+            ("b.cy", "import a::f;\npub fn g() -> i64 { f() }"),
+        ],
+        expect![[r#"
+            error: import cycle not allowed
+             --> b.cy:1:1
+              |
+            1 | import a::f;
+              | ^^^^^^^^^^^^
+              = note: the modules import each other: `a` -> `b` -> `a`
+              = help: move the items they share into a module of their own
+        "#]],
+    );
+}
+
+#[test]
+fn test_package_dependencies() {
+    check_package(
+        MANIFEST_NAME,
+        &[
+            (
+                "Ceylon.toml",
+                "[package]\nname = \"app\"\nversion = \"0.1.0\"\nsource-dirs = [\"src\", \"gen\"]\n\n\
+                 [dependencies]\ngeometry = { path = \"../geometry\" }\n",
+            ),
+            (
+                "src/main.cy",
+                "mod util;\nimport geometry::shapes::Square;\n\
+                 fn main() -> i64 { geometry::area(Square { side: 3 }) + util::one() }",
+            ),
+            ("gen/util.cy", "pub fn one() -> i64 { 1 }"),
+            (
+                "../geometry/Ceylon.toml",
+                "[package]\nname = \"geometry\"\nversion = \"1.2.0\"\nentry = \"src/lib.cy\"\n",
+            ),
+            (
+                "../geometry/src/lib.cy",
+                "pub mod shapes;\nimport shapes::Square;\n\
+                 pub fn area(s: Square) -> i64 { s.side * s.side }",
+            ),
+            ("../geometry/src/shapes.cy", "pub struct Square { side: i64 }"),
+        ],
+        expect![[r#"
+            10
+        "#]],
+    );
+}
+
+#[test]
+fn test_manifest_errors() {
+    check_package(
+        MANIFEST_NAME,
+        &[(
+            "Ceylon.toml",
+            "[package]\nname = \"my-app\"\nversion = \"0.1\"\nentry = \"main.rs\"\n\n\
+             [dependencies]\nfn = { path = \"../fn\" }\n",
+        )],
+        expect![[r#"
+            error: invalid package name `my-app`
+             --> Ceylon.toml:2:8
+              |
+            2 | name = "my-app"
+              |        ^^^^^^^^
+              = note: package names must be identifiers, as dependents refer to them in paths
+            error: invalid version `0.1`
+             --> Ceylon.toml:3:11
+              |
+            3 | version = "0.1"
+              |           ^^^^^
+              = help: versions have the form `MAJOR.MINOR.PATCH`, like `0.1.0`
+            error: the entry point `main.rs` is not a `.cy` file
+             --> Ceylon.toml:4:9
+              |
+            4 | entry = "main.rs"
+              |         ^^^^^^^^^
+            error: invalid dependency name `fn`
+             --> Ceylon.toml:7:1
+              |
+            7 | fn = { path = "../fn" }
+              | ^^
+              = note: dependencies are named after their package
+        "#]],
+    );
+    check_package(
+        MANIFEST_NAME,
+        &[(
+            "Ceylon.toml",
+            "[package]\nname = \"app\"\nversion = \"0.1.0\"\nedition = 1\n",
+        )],
+        expect![[r#"
+            error: unknown field `edition`, expected one of `name`, `version`, `entry`, `source-dirs`
+             --> Ceylon.toml:4:1
+              |
+            4 | edition = 1
+              | ^^^^^^^
+        "#]],
+    );
+}
+
+#[test]
+fn test_diamond_dependency() {
+    // `point` is loaded once, and its lints are reported once. The
+    // "\u{430}" is CYRILLIC SMALL LETTER A, which looks like "a".
+    check_package(
+        "app/Ceylon.toml",
+        &[
+            (
+                "app/Ceylon.toml",
+                "[package]\nname = \"app\"\nversion = \"0.1.0\"\n\n\
+                 [dependencies]\nleft = { path = \"../left\" }\nright = { path = \"../right\" }\n",
+            ),
+            (
+                "app/src/main.cy",
+                "fn main() -> i64 { right::norm(left::make()) }",
+            ),
+            (
+                "left/Ceylon.toml",
+                "[package]\nname = \"left\"\nversion = \"0.1.0\"\n\n\
+                 [dependencies]\npoint = { path = \"../point\" }\n",
+            ),
+            (
+                "left/src/main.cy",
+                "pub fn make() -> point::Pt { point::Pt { x: 3, y: 4 } }",
+            ),
+            (
+                "right/Ceylon.toml",
+                "[package]\nname = \"right\"\nversion = \"0.1.0\"\n\n\
+                 [dependencies]\npoint = { path = \"./../point\" }\n",
+            ),
+            (
+                "right/src/main.cy",
+                "import point::Pt;\npub fn norm(p: Pt) -> i64 { p.x * p.x + p.y * p.y }",
+            ),
+            (
+                "point/Ceylon.toml",
+                "[package]\nname = \"point\"\nversion = \"0.1.0\"\n",
+            ),
+            (
+                "point/src/main.cy",
+                "pub struct Pt { x: i64, y: i64 }\nfn a() {}\nfn \u{430}() {}",
+            ),
+        ],
+        expect![[r#"
+            warning: identifier pair considered confusable between `a` and `а`
+             --> point/src/main.cy:3:4
+              |
+            3 | fn а() {}
+              |    ^
+            25
+        "#]],
+    );
+}
+
+#[test]
+fn test_package_dependency_errors() {
+    check_package(
+        "app/Ceylon.toml",
+        &[
+            (
+                "app/Ceylon.toml",
+                "[package]\nname = \"app\"\nversion = \"0.1.0\"\n\n\
+                 [dependencies]\ngeo = { path = \"../geometry\" }\nnet = { path = \"../net\" }\n",
+            ),
+            ("app/src/main.cy", "fn main() {}"),
+            (
+                "geometry/Ceylon.toml",
+                "[package]\nname = \"geometry\"\nversion = \"1.2.0\"\n",
+            ),
+            ("geometry/src/main.cy", ""),
+            (
+                "net/Ceylon.toml",
+                "[package]\nname = \"net\"\nversion = \"0.1.0\"\n\n\
+                 [dependencies]\napp = { path = \"../app\" }\n",
+            ),
+            ("net/src/main.cy", ""),
+        ],
+        expect![[r#"
+            error: dependency `geo` is the package `geometry`
+             --> app/Ceylon.toml:6:1
+              |
+            6 | geo = { path = "../geometry" }
+              | ^^^
+              = help: rename the dependency to `geometry`
+            error: cyclic package dependency
+             --> net/Ceylon.toml:6:1
+              |
+            6 | app = { path = "../app" }
+              | ^^^
+              = note: the packages depend on each other: `app` -> `net` -> `app`
+        "#]],
+    );
+    check_package(
+        MANIFEST_NAME,
+        &[
+            (
+                "Ceylon.toml",
+                "[package]\nname = \"app\"\nversion = \"0.1.0\"\n\n\
+                 [dependencies]\ngeometry = { path = \"../geometry\" }\n",
+            ),
+            (
+                "src/main.cy",
+                "import geometry::helper;\nfn main() -> i64 { geometry::area() }",
+            ),
+            (
+                "../geometry/Ceylon.toml",
+                "[package]\nname = \"geometry\"\nversion = \"1.2.0\"\n",
+            ),
+            (
+                "../geometry/src/main.cy",
+                "pub fn area() -> i64 { helper() }\nfn helper() -> i64 { 1 }\nimport app::main;",
+            ),
+        ],
+        expect![[r#"
+            error: function `helper` is private
+             --> src/main.cy:1:18
+              |
+            1 | import geometry::helper;
+              |                  ^^^^^^
+              = note: items without `pub` can only be used in their module and its submodules
+            error: unresolved import `app::main`
+             --> ../geometry/src/main.cy:3:8
+              |
+            3 | import app::main;
+              |        ^^^
+              = note: there is no `app` in the root module of package `geometry`
+        "#]],
+    );
+}
+
+#[test]
+fn test_run_tests() {
+    let src = "@test fn adds() { 1 + 2; }\nmod m { @test fn inner() -> i64 { 3 } }\n\
+               fn main() {}";
+    let (module, diagnostics) = ceylon_parser::parse_file(src);
+    assert!(diagnostics.is_empty());
+    let (tcx, diagnostics) = ceylon_typeck::check_module(&module);
+    assert!(diagnostics.is_empty());
+    let actual = run_tests(&module, &tcx, Default::default(), &SourceMap::default()).unwrap();
+    expect![[r#"
+        running 2 tests
+        test adds ... ok
+        test m::inner ... ok
+        test result: ok. 2 passed; 0 failed
+    "#]]
+    .assert_eq(&actual);
+}
+
+#[test]
+fn test_cfg() {
+    let files = [
+        (
+            "main.cy",
+            "@cfg(target_os = \"linux\") mod linux;\n@cfg(not(target_os = \"linux\")) mod other;\n\
+             @cfg(target_os = \"linux\") import linux::name;\n\
+             @cfg(not(target_os = \"linux\")) import other::name;\n\
+             fn main() -> i64 { let x = 1; @cfg(debug) let x = x + 10; name() + x }",
+        ),
+        ("linux.cy", "pub fn name() -> i64 { 100 }"),
+    ];
+    let mut cfg = Cfg::default();
+    cfg.insert("target_os", Some("linux"));
+    check_files(
+        &files,
+        cfg.clone(),
+        |loader| loader.load(Path::new("main.cy")).unwrap(),
+        expect![[r#"
+            101
+        "#]],
+    );
+    cfg.insert("debug", None);
+    check_files(
+        &files,
+        cfg,
+        |loader| loader.load(Path::new("main.cy")).unwrap(),
+        expect![[r#"
+            111
+        "#]],
+    );
+    check_files(
+        &files,
+        Cfg::default(),
+        |loader| loader.load(Path::new("main.cy")).unwrap(),
+        expect![[r#"
+            error: file not found for module `other`
+             --> main.cy:2:32
+              |
+            2 | @cfg(not(target_os = "linux")) mod other;
+              |                                ^^^^^^^^^^
+              = note: couldn't read other.cy: entity not found
+              = help: to create the module `other`, create the file `other.cy`
+        "#]],
+    );
+}
+
+#[test]
+fn test_cfg_args() {
+    let args = |args: &str| -> Vec<String> { args.split(' ').map(str::to_string).collect() };
+    let print = |args: &[String]| match parse_command(args) {
+        Ok(Command::PrintCfg(cfg)) => {
+            // Leave out the options of the host, which depend on the machine.
+            cfg.to_string()
+                .lines()
+                .filter(|line| !line.starts_with("target_"))
+                .map(|line| format!("{}\n", line))
+                .collect()
+        }
+        Ok(_) => "not a print command".to_string(),
+        Err(e) => e,
+    };
+    expect![[r#"
+        debug
+        feature="net"
+        test
+    "#]]
+    .assert_eq(&print(&args(
+        "--print cfg --cfg test --cfg feature=\"net\"",
+    )));
+    expect![[r#""#]].assert_eq(&print(&args("--release --print cfg")));
+    expect!["unknown print request `target`"].assert_eq(&print(&args("--print target")));
+    expect!["unexpected argument `main.cy`"].assert_eq(&print(&args("--print cfg main.cy")));
+    expect!["the value of `feature=net` must be quoted"]
+        .assert_eq(&print(&args("--cfg feature=net --run main.cy")));
+    let Ok(Command::File(options)) = parse_command(&args("--test main.cy --cfg net")) else {
+        panic!("expected a file command");
+    };
+    assert!(options.cfg.contains("test", None) && options.cfg.contains("net", None));
+    assert!(options.bounds_checks);
+    let Ok(Command::Package { bounds_checks, .. }) =
+        parse_command(&args("run --unchecked-indexing"))
+    else {
+        panic!("expected a package command");
+    };
+    assert!(!bounds_checks);
+    let host = host_cfg(true);
+    assert!(host.contains("target_os", Some(env::consts::OS)) && !host.contains("debug", None));
+}
+
+#[test]
+fn test_release_overflow() {
+    let files = [(
+        "main.cy",
+        "fn main() -> i64 { let x: u8 = 250; let y: i8 = -128; (x + 10) as i64 * 1000 + (-y) as i64 }",
+    )];
+    check_files(
+        &files,
+        Cfg::default(),
+        |loader| loader.load(Path::new("main.cy")).unwrap(),
+        expect![[r#"
+            3872
+        "#]],
+    );
+}
+
+#[test]
+fn test_lints() {
+    // The "\u{430}" in `m.cy` is CYRILLIC SMALL LETTER A, which looks like "a".
+    check_program(
+        &[
+            ("main.cy", "mod m;\nfn a() {}\nfn main() -> i64 { m::f(1) }"),
+            (
+                "m.cy",
+                "pub fn f(\u{430}: i64) -> i64 { \u{430} }\nfn a() {}",
+            ),
+        ],
+        expect![[r#"
+            warning: identifier pair considered confusable between `a` and `а`
+             --> m.cy:1:10
+              |
+            1 | pub fn f(а: i64) -> i64 { а }
+              |          ^
+            1
+        "#]],
+    );
+    check_program(
+        &[
+            ("main.cy", "mod m;\nfn main() -> i64 { m::f(1) }"),
+            (
+                "m.cy",
+                "fn a() {}\n@allow(confusable_idents)\npub fn f(\u{430}: i64) -> i64 { \u{430} }",
+            ),
+        ],
+        expect![[r#"
+            1
+        "#]],
+    );
+}
+
+#[test]
+fn test_print_layouts() {
+    let (module, _) = ceylon_parser::parse_file(
+        "struct Point { x: i64, y: i32 }\nstruct Huge { a: [i64; 4611686018427387904] }",
+    );
+    // The error reported for `Huge` leaves it without a layout.
+    let (tcx, diagnostics) = ceylon_typeck::check_module(&module);
+    assert_eq!(diagnostics.len(), 1);
+    expect![[r#"
+        Point: size 16, align 8
+            x: offset 0
+            y: offset 8
+        Huge: layout unavailable
+    "#]]
+    .assert_eq(&print_layouts(&tcx));
+}
@@ -0,0 +1,180 @@
+//! Diagnostics (errors, warnings, ...) reported by the compiler.
+
+#[cfg(test)]
+mod tests;
+
+use std::fmt::Write;
+
+use ceylon_span::{SourceMap, Span};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Level {
+    Error,
+    Warning,
+    Note,
+    Help,
+}
+
+impl Level {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Level::Error => "error",
+            Level::Warning => "warning",
+            Level::Note => "note",
+            Level::Help => "help",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub level: Level,
+    pub message: String,
+    /// The primary location of the diagnostic.
+    pub span: Span,
+    /// Notes and help messages attached to the diagnostic.
+    pub children: Vec<SubDiagnostic>,
+}
+
+#[derive(Debug, Clone)]
+pub struct SubDiagnostic {
+    pub level: Level,
+    pub message: String,
+    /// The location the note points at, if any.
+    pub span: Option<Span>,
+}
+
+impl Diagnostic {
+    pub fn new(level: Level, span: Span, message: impl Into<String>) -> Self {
+        Diagnostic {
+            level,
+            message: message.into(),
+            span,
+            children: Vec::new(),
+        }
+    }
+
+    pub fn error(span: Span, message: impl Into<String>) -> Self {
+        Diagnostic::new(Level::Error, span, message)
+    }
+
+    pub fn warning(span: Span, message: impl Into<String>) -> Self {
+        Diagnostic::new(Level::Warning, span, message)
+    }
+
+    /// Attaches a note explaining the diagnostic.
+    pub fn note(mut self, message: impl Into<String>) -> Self {
+        self.children.push(SubDiagnostic {
+            level: Level::Note,
+            message: message.into(),
+            span: None,
+        });
+        self
+    }
+
+    /// Attaches a note pointing at another location, e.g. the declaration
+    /// of something the diagnostic is about.
+    pub fn span_note(mut self, span: Span, message: impl Into<String>) -> Self {
+        self.children.push(SubDiagnostic {
+            level: Level::Note,
+            message: message.into(),
+            span: Some(span),
+        });
+        self
+    }
+
+    /// Attaches a help message suggesting how to fix the problem.
+    pub fn help(mut self, message: impl Into<String>) -> Self {
+        self.children.push(SubDiagnostic {
+            level: Level::Help,
+            message: message.into(),
+            span: None,
+        });
+        self
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.level == Level::Error
+    }
+
+    /// Renders the diagnostic for humans, pointing at the offending source.
+    ///
+    /// ```text
+    /// error: expected `;`, found `}`
+    ///  --> main.cy:1:12
+    ///   |
+    /// 1 | fn f() { 1 }
+    ///   |            ^
+    /// ```
+    pub fn render(&self, src: &str, file_name: &str) -> String {
+        self.render_at(src, file_name, 0)
+    }
+
+    /// Renders the diagnostic in the file of the source map it points into.
+    pub fn render_in(&self, source_map: &SourceMap) -> String {
+        let file = source_map.lookup_file(self.span.start_pos);
+        self.render_at(&file.src, &file.name, file.start_pos)
+    }
+
+    /// Renders the diagnostic, where `offset` is where `src` starts among
+    /// the positions of the spans. Notes with a span point into the same
+    /// file.
+    fn render_at(&self, src: &str, file_name: &str, offset: usize) -> String {
+        let mut out = String::new();
+        writeln!(out, "{}: {}", self.level.as_str(), self.message).unwrap();
+        let pad = render_snippet(&mut out, src, file_name, self.span, offset);
+        for child in self.children.iter().filter(|child| child.span.is_none()) {
+            writeln!(out, "{} = {}: {}", pad, child.level.as_str(), child.message).unwrap();
+        }
+        for child in &self.children {
+            if let Some(span) = child.span {
+                writeln!(out, "{}: {}", child.level.as_str(), child.message).unwrap();
+                render_snippet(&mut out, src, file_name, span, offset);
+            }
+        }
+        out
+    }
+}
+
+/// Writes the location of `span` and its line with the span underlined.
+/// Returns the padding of the gutter.
+fn render_snippet(
+    out: &mut String,
+    src: &str,
+    file_name: &str,
+    span: Span,
+    offset: usize,
+) -> String {
+    let pos = span.start_pos - offset;
+    let (line, col) = line_col(src, pos);
+    let line_text = src.lines().nth(line).unwrap_or("");
+    let line_no = (line + 1).to_string();
+    let pad = " ".repeat(line_no.len());
+    // Underline at least one column, and at most up to the end of the line.
+    let width = line_text[col..]
+        .chars()
+        .take(src[pos..pos + span.len].chars().count())
+        .count()
+        .max(1);
+
+    writeln!(out, "{}--> {}:{}:{}", pad, file_name, line_no, col + 1).unwrap();
+    writeln!(out, "{} |", pad).unwrap();
+    writeln!(out, "{} | {}", line_no, line_text).unwrap();
+    writeln!(
+        out,
+        "{} | {}{}",
+        pad,
+        " ".repeat(line_text[..col].chars().count()),
+        "^".repeat(width)
+    )
+    .unwrap();
+    pad
+}
+
+/// Returns the 0-based line and byte column of `pos` in `src`.
+fn line_col(src: &str, pos: usize) -> (usize, usize) {
+    let before = &src[..pos];
+    let line = before.matches('\n').count();
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    (line, pos - line_start)
+}
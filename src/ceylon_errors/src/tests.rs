@@ -0,0 +1,82 @@
+use super::*;
+use expect_test::expect;
+
+#[test]
+fn test_render() {
+    let src = "fn f() {\n    1 + ;\n}\n";
+    let diag = Diagnostic::error(
+        Span {
+            start_pos: 17,
+            len: 1,
+        },
+        "expected expression, found `;`",
+    )
+    .note("an expression is needed after `+`");
+    expect![[r#"
+        error: expected expression, found `;`
+         --> main.cy:2:9
+          |
+        2 |     1 + ;
+          |         ^
+          = note: an expression is needed after `+`
+    "#]]
+    .assert_eq(&diag.render(src, "main.cy"));
+}
+
+#[test]
+fn test_render_in_source_map() {
+    let mut source_map = SourceMap::default();
+    source_map.add_file("main.cy".to_string(), "mod math;\n".to_string());
+    let start_pos = source_map
+        .add_file("math.cy".to_string(), "fn f() {}\nfn f() {}\n".to_string())
+        .start_pos;
+    let diag = Diagnostic::error(
+        Span {
+            start_pos: start_pos + 13,
+            len: 1,
+        },
+        "the name `f` is defined multiple times",
+    );
+    expect![[r#"
+        error: the name `f` is defined multiple times
+         --> math.cy:2:4
+          |
+        2 | fn f() {}
+          |    ^
+    "#]]
+    .assert_eq(&diag.render_in(&source_map));
+}
+
+#[test]
+fn test_render_span_note() {
+    let src = "fn f(x: i64) {\n    x = 1;\n}\n";
+    let diag = Diagnostic::error(
+        Span {
+            start_pos: 19,
+            len: 1,
+        },
+        "cannot assign to immutable parameter `x`",
+    )
+    .span_note(
+        Span {
+            start_pos: 5,
+            len: 1,
+        },
+        "the parameter is declared here",
+    )
+    .help("consider adding `mut`");
+    expect![[r#"
+        error: cannot assign to immutable parameter `x`
+         --> main.cy:2:5
+          |
+        2 |     x = 1;
+          |     ^
+          = help: consider adding `mut`
+        note: the parameter is declared here
+         --> main.cy:1:6
+          |
+        1 | fn f(x: i64) {
+          |      ^
+    "#]]
+    .assert_eq(&diag.render(src, "main.cy"));
+}
@@ -0,0 +1,638 @@
+//! An interpreter for the IR, evaluating the expression trees directly.
+//!
+//! ```
+//! let src = "fn sq(x: i64) -> i64 { x * x } fn main() -> i64 { sq(7) }";
+//! let (module, _) = ceylon_parser::parse_file(src);
+//! let (tcx, _) = ceylon_typeck::check_module(&module);
+//! let program = ceylon_lower::lower_module(&module, &tcx, Default::default()).unwrap();
+//! let main = ceylon_span::Symbol::intern("main");
+//! let Some(ceylon_typeck::Def::Fn(main)) = tcx.def(main) else { panic!() };
+//! let main = program.instance(main, &[]).unwrap();
+//! let value = ceylon_interp::call(&program, main, Vec::new());
+//! assert_eq!(value, Ok(ceylon_interp::Value::I64(49)));
+//! ```
+
+mod num;
+#[cfg(test)]
+mod tests;
+
+use std::sync::{Arc, Mutex};
+
+use ceylon_lower::ir::{
+    BinOp, BodyId, Case, Const, Decision, Expr, ExprKind, LocalDecl, LocalId, Place, Program,
+    Projection, Stmt, UnOp, VtableId,
+};
+use ceylon_span::Symbol;
+use ceylon_typeck::{
+    ty::{CtorKind, Ty},
+    Tcx,
+};
+use num::{eval_binary, eval_cast, eval_overflowing, eval_unary};
+
+/// Calls deeper than this abort the program instead of overflowing the
+/// stack of the interpreter.
+const MAX_CALL_DEPTH: usize = 10_000;
+
+/// The interpreter recurses for every call and nested expression, so it
+/// runs on a thread with a stack large enough for `MAX_CALL_DEPTH` calls.
+const STACK_SIZE: usize = 1 << 30;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    I8(i8),
+    I16(i16),
+    I32(i32),
+    I64(i64),
+    I128(i128),
+    U8(u8),
+    U16(u16),
+    U32(u32),
+    U64(u64),
+    U128(u128),
+    Usize(usize),
+    F32(f32),
+    F64(f64),
+    Bool(bool),
+    Char(char),
+    Str(Symbol),
+    Void,
+    /// A struct or an enum. Structs are always variant 0.
+    Adt {
+        variant: usize,
+        fields: Vec<Value>,
+    },
+    Tuple(Vec<Value>),
+    /// An array, or a slice of one.
+    Array(Vec<Value>),
+    /// A `dyn` value: a value of type `ty` and the vtable of its type.
+    Dyn {
+        vtable: VtableId,
+        ty: Ty,
+        value: Box<Value>,
+    },
+    /// A function value: the body to call and the values of the variables
+    /// it captures.
+    Closure {
+        body: BodyId,
+        captures: Vec<Value>,
+    },
+    /// A variable a closure captures by reference, shared by the closure and
+    /// the body which created it.
+    Cell(Cell),
+}
+
+/// The storage of a variable shared with closures. Cells are equal if they
+/// are the same cell.
+#[derive(Debug, Clone)]
+pub struct Cell(Arc<Mutex<Value>>);
+
+impl Cell {
+    fn new(value: Value) -> Self {
+        Cell(Arc::new(Mutex::new(value)))
+    }
+
+    fn get(&self) -> std::sync::MutexGuard<'_, Value> {
+        self.0.lock().unwrap()
+    }
+}
+
+impl PartialEq for Cell {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Value {
+    /// Formats a value of type `ty` the way it would be written in the
+    /// source, e.g. `Shape::Circle(1.0)`.
+    pub fn display(&self, tcx: &Tcx, ty: &Ty) -> String {
+        match self {
+            Value::F32(f) => format!("{:?}", f),
+            Value::F64(f) => format!("{:?}", f),
+            Value::Bool(b) => b.to_string(),
+            Value::Char(c) => format!("{:?}", c),
+            Value::Str(s) => format!("{:?}", s.as_str()),
+            Value::Void => "void".to_string(),
+            Value::Dyn { ty, value, .. } => value.display(tcx, ty),
+            Value::Closure { .. } => tcx.ty_to_string(ty),
+            Value::Cell(cell) => cell.get().display(tcx, ty),
+            Value::Tuple(elems) => {
+                let Ty::Tuple(tys) = ty else { unreachable!() };
+                let elems: Vec<String> = elems
+                    .iter()
+                    .zip(tys)
+                    .map(|(elem, ty)| elem.display(tcx, ty))
+                    .collect();
+                match &elems[..] {
+                    [elem] => format!("({},)", elem),
+                    _ => format!("({})", elems.join(", ")),
+                }
+            }
+            Value::Array(elems) => {
+                let (Ty::Array(elem_ty, _) | Ty::Slice(elem_ty)) = ty else {
+                    unreachable!()
+                };
+                let elems: Vec<String> = elems
+                    .iter()
+                    .map(|elem| elem.display(tcx, elem_ty))
+                    .collect();
+                format!("[{}]", elems.join(", "))
+            }
+            Value::Adt { variant, fields } => {
+                let Ty::Adt(adt, _) = ty else { unreachable!() };
+                let adt = tcx.adt(*adt);
+                let variant_def = &adt.variants[*variant];
+                let path = adt.variant_path(*variant);
+                let field_tys = ceylon_lower::field_tys(tcx, ty, *variant);
+                let fields = variant_def
+                    .fields
+                    .iter()
+                    .zip(field_tys)
+                    .zip(fields)
+                    .map(|((def, ty), value)| (def.name, value.display(tcx, &ty)));
+                match variant_def.ctor_kind {
+                    CtorKind::Const => path,
+                    CtorKind::Fn => {
+                        let fields: Vec<String> = fields.map(|(_, value)| value).collect();
+                        format!("{}({})", path, fields.join(", "))
+                    }
+                    CtorKind::Struct => {
+                        let fields: Vec<String> = fields
+                            .map(|(name, value)| format!("{}: {}", name, value))
+                            .collect();
+                        if fields.is_empty() {
+                            format!("{} {{}}", path)
+                        } else {
+                            format!("{} {{ {} }}", path, fields.join(", "))
+                        }
+                    }
+                }
+            }
+            // The integers, which are written the same whatever their type.
+            n => n.int_to_string().unwrap(),
+        }
+    }
+}
+
+/// An error which stops the program, e.g. an arithmetic overflow.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Panic {
+    pub message: String,
+}
+
+impl Panic {
+    fn new(message: impl Into<String>) -> Self {
+        Panic {
+            message: message.into(),
+        }
+    }
+}
+
+/// Why the evaluation of an expression stopped before producing a value.
+enum Unwind {
+    Panic(Panic),
+    /// A `return`, with the value returned from the body being evaluated.
+    Return(Value),
+    /// A `break` out of the innermost loop.
+    Break,
+}
+
+impl From<Panic> for Unwind {
+    fn from(panic: Panic) -> Self {
+        Unwind::Panic(panic)
+    }
+}
+
+/// Calls a function of the program and returns its result.
+pub fn call(program: &Program, id: BodyId, args: Vec<Value>) -> Result<Value, Panic> {
+    std::thread::scope(|scope| {
+        let thread = std::thread::Builder::new()
+            .stack_size(STACK_SIZE)
+            .spawn_scoped(scope, || Interpreter { program, depth: 0 }.call(id, args))
+            .expect("failed to spawn the interpreter thread");
+        thread
+            .join()
+            .unwrap_or_else(|payload| std::panic::resume_unwind(payload))
+    })
+}
+
+struct Interpreter<'a> {
+    program: &'a Program,
+    depth: usize,
+}
+
+/// The locals of a function being evaluated.
+struct Frame<'a> {
+    decls: &'a [LocalDecl],
+    locals: Vec<Value>,
+}
+
+impl Interpreter<'_> {
+    fn call(&mut self, id: BodyId, args: Vec<Value>) -> Result<Value, Panic> {
+        if self.depth == MAX_CALL_DEPTH {
+            return Err(Panic::new("stack overflow"));
+        }
+        let body = self.program.body(id);
+        let mut frame = Frame {
+            decls: &body.locals,
+            locals: vec![Value::Void; body.locals.len()],
+        };
+        for (param, arg) in body.params.iter().zip(args) {
+            frame.init(*param, arg);
+        }
+        self.depth += 1;
+        let result = self.eval(&mut frame, &body.value);
+        self.depth -= 1;
+        match result {
+            Ok(value) | Err(Unwind::Return(value)) => Ok(value),
+            Err(Unwind::Panic(panic)) => Err(panic),
+            Err(Unwind::Break) => unreachable!("`break` outside of a loop"),
+        }
+    }
+
+    fn eval(&mut self, frame: &mut Frame, expr: &Expr) -> Result<Value, Unwind> {
+        let value = match &expr.kind {
+            ExprKind::Const(value) => const_to_value(value, &expr.ty),
+            ExprKind::Place(place) => frame.read(place)?,
+            ExprKind::Field(base, index) => match self.eval(frame, base)? {
+                Value::Adt { mut fields, .. } | Value::Tuple(mut fields) => {
+                    fields.swap_remove(*index)
+                }
+                _ => unreachable!("field of a non-ADT"),
+            },
+            ExprKind::Unary(op, operand) => {
+                let operand = self.eval(frame, operand)?;
+                eval_unary(*op, operand)?
+            }
+            ExprKind::Cast(operand) => {
+                let operand = self.eval(frame, operand)?;
+                eval_cast(operand, &expr.ty)
+            }
+            ExprKind::Binary(op, lhs, rhs) => {
+                let lhs = self.eval(frame, lhs)?;
+                let rhs = self.eval(frame, rhs)?;
+                eval_binary(*op, lhs, rhs)?
+            }
+            ExprKind::Overflowing(overflow, op, lhs, rhs) => {
+                let lhs = self.eval(frame, lhs)?;
+                let rhs = self.eval(frame, rhs)?;
+                eval_overflowing(*overflow, *op, lhs, rhs)?
+            }
+            ExprKind::Call(id, args) => {
+                let args = args
+                    .iter()
+                    .map(|arg| self.eval(frame, arg))
+                    .collect::<Result<_, _>>()?;
+                self.call(*id, args)?
+            }
+            ExprKind::CallIndirect(callee, args) => {
+                let Value::Closure { body, mut captures } = self.eval(frame, callee)? else {
+                    unreachable!("call of a non-function value")
+                };
+                for arg in args {
+                    captures.push(self.eval(frame, arg)?);
+                }
+                self.call(body, captures)?
+            }
+            ExprKind::DynCall(index, args) => {
+                let mut args: Vec<Value> = args
+                    .iter()
+                    .map(|arg| self.eval(frame, arg))
+                    .collect::<Result<_, _>>()?;
+                let Value::Dyn { vtable, value, .. } = std::mem::replace(&mut args[0], Value::Void)
+                else {
+                    unreachable!("virtual call on a non-`dyn` value")
+                };
+                args[0] = *value;
+                let id = self.program.vtable(vtable).methods[*index];
+                self.call(id, args)?
+            }
+            ExprKind::MakeDyn(value, vtable) => Value::Dyn {
+                vtable: *vtable,
+                ty: self.program.vtable(*vtable).ty.clone(),
+                value: Box::new(self.eval(frame, value)?),
+            },
+            ExprKind::Adt {
+                variant, fields, ..
+            } => Value::Adt {
+                variant: *variant,
+                fields: fields
+                    .iter()
+                    .map(|field| self.eval(frame, field))
+                    .collect::<Result<_, _>>()?,
+            },
+            ExprKind::Tuple(elems) => Value::Tuple(self.eval_all(frame, elems)?),
+            ExprKind::Array(elems) => Value::Array(self.eval_all(frame, elems)?),
+            ExprKind::Repeat(elem, len) => {
+                let elem = self.eval(frame, elem)?;
+                Value::Array(vec![elem; *len as usize])
+            }
+            ExprKind::Unsize(value) => self.eval(frame, value)?,
+            ExprKind::Len(value) => match self.eval(frame, value)? {
+                Value::Array(elems) => Value::Usize(elems.len()),
+                _ => unreachable!("length of a non-array"),
+            },
+            ExprKind::SubSlice {
+                base,
+                start,
+                end,
+                inclusive,
+            } => {
+                let Value::Array(mut elems) = self.eval(frame, base)? else {
+                    unreachable!("slice of a non-array")
+                };
+                let start = self.eval(frame, start)?.as_int().unwrap() as usize;
+                let end = match end {
+                    Some(end) => self.eval(frame, end)?.as_int().unwrap() as usize,
+                    None => elems.len(),
+                };
+                let end = if *inclusive {
+                    end.checked_add(1)
+                        .ok_or_else(|| Panic::new("attempted to index slice up to maximum usize"))?
+                } else {
+                    end
+                };
+                if end > elems.len() {
+                    return Err(Panic::new(format!(
+                        "range end index {} out of range for slice of length {}",
+                        end,
+                        elems.len()
+                    ))
+                    .into());
+                }
+                if start > end {
+                    return Err(Panic::new(format!(
+                        "slice index starts at {} but ends at {}",
+                        start, end
+                    ))
+                    .into());
+                }
+                elems.truncate(end);
+                Value::Array(elems.split_off(start))
+            }
+            ExprKind::BoundsCheck { index, len } => {
+                let index = self.eval(frame, index)?;
+                let len = self.eval(frame, len)?;
+                if index.as_int() >= len.as_int() {
+                    return Err(Panic::new(format!(
+                        "index out of bounds: the len is {} but the index is {}",
+                        len.int_to_string().unwrap(),
+                        index.int_to_string().unwrap()
+                    ))
+                    .into());
+                }
+                Value::Void
+            }
+            ExprKind::Closure { body, captures } => Value::Closure {
+                body: *body,
+                captures: captures
+                    .iter()
+                    .map(|capture| {
+                        let local = capture.local.as_usize();
+                        if capture.by_ref {
+                            // The cell itself, which the local lives in.
+                            Ok(frame.locals[local].clone())
+                        } else {
+                            frame.read(&Place {
+                                local: capture.local,
+                                projection: Vec::new(),
+                            })
+                        }
+                    })
+                    .collect::<Result<_, _>>()?,
+            },
+            ExprKind::Assign(place, value) => {
+                let value = self.eval(frame, value)?;
+                frame.write(place, value)?;
+                Value::Void
+            }
+            ExprKind::Return(value) => {
+                let value = self.eval(frame, value)?;
+                return Err(Unwind::Return(value));
+            }
+            ExprKind::Block(stmts, value) => {
+                for stmt in stmts {
+                    match stmt {
+                        Stmt::Let(local, init) => {
+                            let value = self.eval(frame, init)?;
+                            frame.init(*local, value);
+                        }
+                        Stmt::Expr(expr) => {
+                            self.eval(frame, expr)?;
+                        }
+                    }
+                }
+                self.eval(frame, value)?
+            }
+            ExprKind::If(cond, then, otherwise) => {
+                if self.eval(frame, cond)? == Value::Bool(true) {
+                    self.eval(frame, then)?
+                } else {
+                    self.eval(frame, otherwise)?
+                }
+            }
+            ExprKind::Loop(body) => loop {
+                match self.eval(frame, body) {
+                    Ok(_) => (),
+                    Err(Unwind::Break) => break Value::Void,
+                    Err(unwind) => return Err(unwind),
+                }
+            },
+            ExprKind::Break => return Err(Unwind::Break),
+            ExprKind::Match {
+                scrutinee,
+                local,
+                tree,
+                arms,
+            } => {
+                let value = self.eval(frame, scrutinee)?;
+                frame.init(*local, value);
+                let mut decision = tree;
+                loop {
+                    match decision {
+                        Decision::Leaf { arm, bindings } => {
+                            frame.bind(bindings)?;
+                            break self.eval(frame, &arms[*arm].body)?;
+                        }
+                        Decision::Guard {
+                            arm,
+                            bindings,
+                            otherwise,
+                        } => {
+                            frame.bind(bindings)?;
+                            let guard = arms[*arm].guard.as_ref().unwrap();
+                            if self.eval(frame, guard)? == Value::Bool(true) {
+                                break self.eval(frame, &arms[*arm].body)?;
+                            }
+                            decision = otherwise;
+                        }
+                        Decision::Switch {
+                            place,
+                            cases,
+                            default,
+                        } => {
+                            let value = frame.read(place)?;
+                            let case = cases.iter().find(|(case, _)| matches_case(&value, case));
+                            decision = match (case, default) {
+                                (Some((_, decision)), _) => decision,
+                                (None, Some(default)) => default,
+                                (None, None) => unreachable!("switch without a matching case"),
+                            };
+                        }
+                        Decision::Failure => unreachable!("non-exhaustive match"),
+                    }
+                }
+            }
+        };
+        Ok(value)
+    }
+
+    fn eval_all(&mut self, frame: &mut Frame, exprs: &[Expr]) -> Result<Vec<Value>, Unwind> {
+        exprs.iter().map(|expr| self.eval(frame, expr)).collect()
+    }
+}
+
+impl Frame<'_> {
+    /// Stores the initial value of a local. Locals shared with closures are
+    /// put in a cell, unless the value is the cell of a captured variable.
+    fn init(&mut self, local: LocalId, value: Value) {
+        let shared = self.decls[local.as_usize()].shared;
+        self.locals[local.as_usize()] = match value {
+            Value::Cell(_) => value,
+            value if shared => Value::Cell(Cell::new(value)),
+            value => value,
+        };
+    }
+
+    /// Reads a place. Its indices may be out of bounds if the lowering
+    /// didn't check them, which is undefined behavior the interpreter
+    /// reports as a panic.
+    fn read(&self, place: &Place) -> Result<Value, Panic> {
+        let indices = self.indices(place);
+        match &self.locals[place.local.as_usize()] {
+            Value::Cell(cell) => Ok(project(&cell.get(), &place.projection, &indices)?.clone()),
+            value => Ok(project(value, &place.projection, &indices)?.clone()),
+        }
+    }
+
+    fn write(&mut self, place: &Place, value: Value) -> Result<(), Panic> {
+        let indices = self.indices(place);
+        match &mut self.locals[place.local.as_usize()] {
+            Value::Cell(cell) => {
+                *project_mut(&mut cell.get(), &place.projection, &indices)? = value
+            }
+            local => *project_mut(local, &place.projection, &indices)? = value,
+        }
+        Ok(())
+    }
+
+    /// The values of the indices in the projection of a place.
+    fn indices(&self, place: &Place) -> Vec<usize> {
+        place
+            .projection
+            .iter()
+            .filter_map(|projection| match projection {
+                Projection::Field(_) => None,
+                Projection::Index(local) => {
+                    let index = match &self.locals[local.as_usize()] {
+                        Value::Cell(cell) => cell.get().as_int(),
+                        value => value.as_int(),
+                    };
+                    Some(index.unwrap() as usize)
+                }
+            })
+            .collect()
+    }
+
+    fn bind(&mut self, bindings: &[(LocalId, Place)]) -> Result<(), Panic> {
+        for (local, place) in bindings {
+            let value = self.read(place)?;
+            self.init(*local, value);
+        }
+        Ok(())
+    }
+}
+
+/// Follows a projection from a value, taking the index of each element from
+/// `indices`.
+fn project<'v>(
+    mut value: &'v Value,
+    projection: &[Projection],
+    indices: &[usize],
+) -> Result<&'v Value, Panic> {
+    let mut indices = indices.iter();
+    for projection in projection {
+        value = match (projection, value) {
+            (Projection::Field(field), Value::Adt { variant, fields }) => {
+                debug_assert_eq!(*variant, field.variant);
+                &fields[field.index]
+            }
+            (Projection::Field(field), Value::Tuple(elems)) => &elems[field.index],
+            (Projection::Index(_), Value::Array(elems)) => {
+                let index = *indices.next().unwrap();
+                elems
+                    .get(index)
+                    .ok_or_else(|| unchecked_out_of_bounds(elems, index))?
+            }
+            _ => unreachable!("invalid projection"),
+        };
+    }
+    Ok(value)
+}
+
+fn project_mut<'v>(
+    mut value: &'v mut Value,
+    projection: &[Projection],
+    indices: &[usize],
+) -> Result<&'v mut Value, Panic> {
+    let mut indices = indices.iter();
+    for projection in projection {
+        value = match (projection, value) {
+            (Projection::Field(field), Value::Adt { fields, .. } | Value::Tuple(fields)) => {
+                &mut fields[field.index]
+            }
+            (Projection::Index(_), Value::Array(elems)) => {
+                let index = *indices.next().unwrap();
+                if index >= elems.len() {
+                    return Err(unchecked_out_of_bounds(elems, index));
+                }
+                &mut elems[index]
+            }
+            _ => unreachable!("invalid projection"),
+        };
+    }
+    Ok(value)
+}
+
+fn unchecked_out_of_bounds(elems: &[Value], index: usize) -> Panic {
+    Panic::new(format!(
+        "undefined behavior: unchecked index out of bounds: the len is {} but the index is {}",
+        elems.len(),
+        index
+    ))
+}
+
+fn const_to_value(value: &Const, ty: &Ty) -> Value {
+    match value {
+        Const::Int(n) => Value::from_int(*n, ty),
+        Const::Float(f) => match ty {
+            Ty::F32 => Value::F32(*f as f32),
+            _ => Value::F64(*f),
+        },
+        Const::Bool(b) => Value::Bool(*b),
+        Const::Char(c) => Value::Char(*c),
+        Const::Str(s) => Value::Str(*s),
+        Const::Void => Value::Void,
+    }
+}
+
+fn matches_case(value: &Value, case: &Case) -> bool {
+    match (value, case) {
+        (Value::Adt { variant, .. }, Case::Variant(expected)) => variant == expected,
+        (value, Case::Const(Const::Int(expected))) => value.as_int() == Some(*expected),
+        (Value::Bool(b), Case::Const(Const::Bool(expected))) => b == expected,
+        (Value::Char(c), Case::Const(Const::Char(expected))) => c == expected,
+        (Value::Str(s), Case::Const(Const::Str(expected))) => s == expected,
+        _ => false,
+    }
+}
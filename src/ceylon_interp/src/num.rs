@@ -0,0 +1,329 @@
+//! Operators and casts on primitive values. The integer types share their
+//! implementation through the `Int` trait, implemented by the Rust type of
+//! each of them.
+
+use ceylon_lower::ir::Overflow;
+
+use super::*;
+
+/// The Rust type holding the values of an integer type of the program.
+trait Int: Copy + PartialOrd + Default {
+    fn into_value(self) -> Value;
+    /// `a op b` for an arithmetic or bitwise operator, or `None` if it
+    /// overflows, divides by zero or shifts by at least the bit width.
+    fn checked(op: BinOp, a: Self, b: Self) -> Option<Self>;
+    /// `a op b` for `+`, `-`, `*`, `<<` or `>>`, wrapping around on
+    /// overflow. Shifts take the amount modulo the bit width.
+    fn wrapping(op: BinOp, a: Self, b: Self) -> Self;
+    fn checked_neg(self) -> Option<Self>;
+    fn bit_not(self) -> Self;
+}
+
+/// Implements `Int` for the Rust types of the integer variants of `Value`,
+/// which are named like the corresponding variants of `Ty`, and the
+/// functions which need to know every integer variant.
+macro_rules! ints {
+    ($($variant:ident($int:ty)),* $(,)?) => {
+        $(impl Int for $int {
+            fn into_value(self) -> Value {
+                Value::$variant(self)
+            }
+
+            fn checked(op: BinOp, a: Self, b: Self) -> Option<Self> {
+                match op {
+                    BinOp::Add => a.checked_add(b),
+                    BinOp::Sub => a.checked_sub(b),
+                    BinOp::Mul => a.checked_mul(b),
+                    BinOp::Div => a.checked_div(b),
+                    BinOp::Rem => a.checked_rem(b),
+                    BinOp::BitAnd => Some(a & b),
+                    BinOp::BitOr => Some(a | b),
+                    BinOp::BitXor => Some(a ^ b),
+                    BinOp::Shl => u32::try_from(b).ok().and_then(|b| a.checked_shl(b)),
+                    BinOp::Shr => u32::try_from(b).ok().and_then(|b| a.checked_shr(b)),
+                    _ => unreachable!("`{:?}` is not arithmetic", op),
+                }
+            }
+
+            fn wrapping(op: BinOp, a: Self, b: Self) -> Self {
+                match op {
+                    BinOp::Add => a.wrapping_add(b),
+                    BinOp::Sub => a.wrapping_sub(b),
+                    BinOp::Mul => a.wrapping_mul(b),
+                    BinOp::Shl => a.wrapping_shl(b as u32),
+                    BinOp::Shr => a.wrapping_shr(b as u32),
+                    _ => unreachable!("`{:?}` doesn't wrap around", op),
+                }
+            }
+
+            fn checked_neg(self) -> Option<Self> {
+                <$int>::checked_neg(self)
+            }
+
+            fn bit_not(self) -> Self {
+                !self
+            }
+        })*
+
+        impl Value {
+            /// The value of an integer, sign-extended or zero-extended to
+            /// 128 bits. `u128` values above `i128::MAX` wrap around, like
+            /// the integer constants of the IR.
+            pub(crate) fn as_int(&self) -> Option<i128> {
+                match self {
+                    $(Value::$variant(n) => Some(*n as i128),)*
+                    _ => None,
+                }
+            }
+
+            /// Truncates an integer to the integer type `ty`.
+            pub(crate) fn from_int(n: i128, ty: &Ty) -> Value {
+                match ty {
+                    $(Ty::$variant => Value::$variant(n as $int),)*
+                    _ => unreachable!("`{:?}` is not an integer type", ty),
+                }
+            }
+
+            /// Converts a float to the integer type `ty`, rounding toward
+            /// zero and saturating at its bounds.
+            fn from_float(f: f64, ty: &Ty) -> Value {
+                match ty {
+                    $(Ty::$variant => Value::$variant(f as $int),)*
+                    _ => unreachable!("`{:?}` is not an integer type", ty),
+                }
+            }
+
+            /// Converts an integer to the float type `ty`.
+            fn int_to_float(&self, ty: &Ty) -> Value {
+                match (self, ty) {
+                    $((Value::$variant(n), Ty::F32) => Value::F32(*n as f32),
+                    (Value::$variant(n), _) => Value::F64(*n as f64),)*
+                    _ => unreachable!("{:?} is not an integer", self),
+                }
+            }
+
+            pub(crate) fn int_to_string(&self) -> Option<String> {
+                match self {
+                    $(Value::$variant(n) => Some(n.to_string()),)*
+                    _ => None,
+                }
+            }
+        }
+
+        /// Applies an operator to integers of the same type. Arithmetic
+        /// panics on overflow unless `overflow` says otherwise.
+        fn int_binary(
+            op: BinOp,
+            overflow: Option<Overflow>,
+            lhs: Value,
+            rhs: Value,
+        ) -> Result<Value, Panic> {
+            match (lhs, rhs) {
+                $((Value::$variant(a), Value::$variant(b)) => int_op(op, overflow, a, b),)*
+                (lhs, rhs) => unreachable!("`{:?}` applied to {:?} and {:?}", op, lhs, rhs),
+            }
+        }
+
+        fn int_unary(op: UnOp, operand: Value) -> Result<Value, Panic> {
+            match operand {
+                $(Value::$variant(n) => int_unary_op(op, n),)*
+                operand => unreachable!("`{:?}` applied to {:?}", op, operand),
+            }
+        }
+    };
+}
+
+// `usize` is 64 bits wide, like on the targets the compiler supports.
+ints!(
+    I8(i8),
+    I16(i16),
+    I32(i32),
+    I64(i64),
+    I128(i128),
+    U8(u8),
+    U16(u16),
+    U32(u32),
+    U64(u64),
+    U128(u128),
+    Usize(usize),
+);
+
+pub(crate) fn eval_unary(op: UnOp, operand: Value) -> Result<Value, Panic> {
+    let value = match (op, operand) {
+        (UnOp::Neg, Value::F32(f)) => Value::F32(-f),
+        (UnOp::Neg, Value::F64(f)) => Value::F64(-f),
+        (UnOp::Not, Value::Bool(b)) => Value::Bool(!b),
+        (op, operand) => int_unary(op, operand)?,
+    };
+    Ok(value)
+}
+
+fn int_unary_op<T: Int>(op: UnOp, n: T) -> Result<Value, Panic> {
+    let n = match op {
+        UnOp::Neg => n
+            .checked_neg()
+            .ok_or_else(|| Panic::new("attempt to negate with overflow"))?,
+        UnOp::BitNot => n.bit_not(),
+        UnOp::Not => unreachable!("`!` applied to an integer"),
+    };
+    Ok(n.into_value())
+}
+
+/// Converts a primitive value to the type `ty`, as described by
+/// `Ty::can_cast_to`.
+pub(crate) fn eval_cast(value: Value, ty: &Ty) -> Value {
+    // Integers go through `i128`, which holds the bits of every integer,
+    // then truncate. Float to integer conversions of Rust saturate.
+    match value {
+        Value::F32(f) => float_cast(f as f64, ty),
+        Value::F64(f) => float_cast(f, ty),
+        Value::Char(c) => Value::from_int(c as i128, ty),
+        Value::Bool(b) => Value::from_int(b as i128, ty),
+        Value::U8(n) if *ty == Ty::Char => Value::Char(n as char),
+        value if ty.is_float() => value.int_to_float(ty),
+        value => {
+            let n = value
+                .as_int()
+                .unwrap_or_else(|| unreachable!("cast of {:?} to `{:?}`", value, ty));
+            Value::from_int(n, ty)
+        }
+    }
+}
+
+fn float_cast(f: f64, ty: &Ty) -> Value {
+    match ty {
+        Ty::F32 => Value::F32(f as f32),
+        Ty::F64 => Value::F64(f),
+        ty => Value::from_float(f, ty),
+    }
+}
+
+pub(crate) fn eval_binary(op: BinOp, lhs: Value, rhs: Value) -> Result<Value, Panic> {
+    let value = match (lhs, rhs) {
+        (Value::F32(a), Value::F32(b)) => float_binary(op, a, b, Value::F32),
+        (Value::F64(a), Value::F64(b)) => float_binary(op, a, b, Value::F64),
+        (Value::Char(a), Value::Char(b)) => Value::Bool(compare(op, &a, &b)),
+        (Value::Bool(a), Value::Bool(b)) => Value::Bool(match op {
+            BinOp::BitAnd => a & b,
+            BinOp::BitOr => a | b,
+            BinOp::BitXor => a ^ b,
+            _ => compare(op, &a, &b),
+        }),
+        (Value::Str(a), Value::Str(b)) => Value::Bool(compare(op, &a.as_str(), &b.as_str())),
+        (lhs, rhs) => int_binary(op, None, lhs, rhs)?,
+    };
+    Ok(value)
+}
+
+/// Integer arithmetic which wraps around or returns an `Option` instead of
+/// panicking.
+pub(crate) fn eval_overflowing(
+    overflow: Overflow,
+    op: BinOp,
+    lhs: Value,
+    rhs: Value,
+) -> Result<Value, Panic> {
+    int_binary(op, Some(overflow), lhs, rhs)
+}
+
+fn int_op<T: Int>(op: BinOp, overflow: Option<Overflow>, a: T, b: T) -> Result<Value, Panic> {
+    if is_comparison(op) {
+        return Ok(Value::Bool(compare(op, &a, &b)));
+    }
+    let value = match overflow {
+        None if matches!(op, BinOp::Div | BinOp::Rem) => {
+            checked_div(op, a, b, |a, b| T::checked(op, a, b))?.into_value()
+        }
+        None => T::checked(op, a, b)
+            .ok_or_else(|| overflow_panic(op))?
+            .into_value(),
+        Some(Overflow::Wrap) => T::wrapping(op, a, b).into_value(),
+        // `Option::Some` is variant 0 and `Option::None` variant 1.
+        Some(Overflow::Check) => match T::checked(op, a, b) {
+            Some(n) => Value::Adt {
+                variant: 0,
+                fields: vec![n.into_value()],
+            },
+            None => Value::Adt {
+                variant: 1,
+                fields: Vec::new(),
+            },
+        },
+    };
+    Ok(value)
+}
+
+fn float_binary<T>(op: BinOp, a: T, b: T, value: fn(T) -> Value) -> Value
+where
+    T: Copy
+        + PartialOrd
+        + std::ops::Add<Output = T>
+        + std::ops::Sub<Output = T>
+        + std::ops::Mul<Output = T>
+        + std::ops::Div<Output = T>
+        + std::ops::Rem<Output = T>,
+{
+    match op {
+        BinOp::Add => value(a + b),
+        BinOp::Sub => value(a - b),
+        BinOp::Mul => value(a * b),
+        BinOp::Div => value(a / b),
+        BinOp::Rem => value(a % b),
+        _ => Value::Bool(compare(op, &a, &b)),
+    }
+}
+
+fn overflow_panic(op: BinOp) -> Panic {
+    let verb = match op {
+        BinOp::Add => "add",
+        BinOp::Sub => "subtract",
+        BinOp::Mul => "multiply",
+        BinOp::Shl => "shift left",
+        BinOp::Shr => "shift right",
+        _ => unreachable!(),
+    };
+    Panic::new(format!("attempt to {} with overflow", verb))
+}
+
+/// Division or remainder, `op`, which panics when dividing by zero or
+/// overflowing, like `i8::MIN / -1`.
+fn checked_div<T: Default + PartialEq>(
+    op: BinOp,
+    a: T,
+    b: T,
+    div: impl FnOnce(T, T) -> Option<T>,
+) -> Result<T, Panic> {
+    let (by_zero, overflow) = match op {
+        BinOp::Div => (
+            "attempt to divide by zero",
+            "attempt to divide with overflow",
+        ),
+        _ => (
+            "attempt to calculate the remainder with a divisor of zero",
+            "attempt to calculate the remainder with overflow",
+        ),
+    };
+    if b == T::default() {
+        return Err(Panic::new(by_zero));
+    }
+    div(a, b).ok_or_else(|| Panic::new(overflow))
+}
+
+fn is_comparison(op: BinOp) -> bool {
+    matches!(
+        op,
+        BinOp::Eq | BinOp::Ne | BinOp::Lt | BinOp::Gt | BinOp::Le | BinOp::Ge
+    )
+}
+
+fn compare<T: PartialOrd>(op: BinOp, a: &T, b: &T) -> bool {
+    match op {
+        BinOp::Eq => a == b,
+        BinOp::Ne => a != b,
+        BinOp::Lt => a < b,
+        BinOp::Gt => a > b,
+        BinOp::Le => a <= b,
+        BinOp::Ge => a >= b,
+        _ => unreachable!("`{:?}` is not a comparison", op),
+    }
+}
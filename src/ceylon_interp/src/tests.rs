@@ -0,0 +1,533 @@
+use super::*;
+use expect_test::{expect, Expect};
+
+/// Runs `main` and prints what it returns, or the message it panics with.
+fn check_run(src: &str, expect: Expect) {
+    check_run_with(src, Default::default(), expect)
+}
+
+fn check_run_with(src: &str, options: ceylon_lower::Options, expect: Expect) {
+    let (module, diagnostics) = ceylon_parser::parse_file(src);
+    assert!(diagnostics.is_empty(), "{:?}", diagnostics);
+    let (tcx, diagnostics) = ceylon_typeck::check_module(&module);
+    assert!(diagnostics.is_empty(), "{:?}", diagnostics);
+    let program = ceylon_lower::lower_module(&module, &tcx, options).unwrap();
+    let Some(ceylon_typeck::Def::Fn(main)) = tcx.def(Symbol::intern("main")) else {
+        panic!("no main function");
+    };
+    let body = program.instance(main, &[]).unwrap();
+    let actual = match call(&program, body, Vec::new()) {
+        Ok(value) => value.display(&tcx, &tcx.fn_sig(main).output),
+        Err(panic) => format!("panicked: {}", panic.message),
+    };
+    expect.assert_eq(&actual)
+}
+
+#[test]
+fn test_run_structs() {
+    check_run(
+        r#"
+struct Point { x: i64, y: i64 }
+fn main() -> Point {
+    let mut p = Point { y: 2, x: 1 };
+    p.x = p.x + p.y * 10;
+    p
+}
+"#,
+        expect!["Point { x: 21, y: 2 }"],
+    )
+}
+
+#[test]
+fn test_run_match() {
+    check_run(
+        r#"
+enum Shape { Circle(f64), Rect { w: f64, h: f64 }, Dot }
+fn area(s: Shape) -> f64 {
+    match s {
+        Shape::Circle(r) => r * r * 3.0,
+        Shape::Rect { w, h } if w > 0.0 => w * h,
+        Shape::Rect { .. } | Shape::Dot => 0.5,
+    }
+}
+fn main() -> f64 {
+    area(Shape::Circle(2.0)) + area(Shape::Rect { w: 2.0, h: 5.0 })
+        + area(Shape::Rect { w: -1.0, h: 5.0 }) + area(Shape::Dot)
+}
+"#,
+        expect!["23.0"],
+    )
+}
+
+#[test]
+fn test_run_or_pattern_bindings() {
+    check_run(
+        r#"
+enum E { A(i64), B(i64, bool), C }
+fn f(e: E) -> i64 {
+    match e {
+        E::A(n) | E::B(n, true) => n,
+        E::B(n, false) if n > 100 => 100,
+        E::B(_, _) => 0 - 1,
+        E::C => 0,
+    }
+}
+fn main() -> i64 {
+    f(E::A(1)) + f(E::B(20, true)) * 10 + f(E::B(300, false)) * 100 + f(E::B(4, false)) + f(E::C)
+}
+"#,
+        expect!["10200"],
+    )
+}
+
+#[test]
+fn test_run_recursion() {
+    check_run(
+        r#"
+enum Nat { Zero, Succ(u64) }
+fn pred(n: u64) -> Nat { match n { 0 => Nat::Zero, n => Nat::Succ(n - 1) } }
+fn fact(n: u64) -> u64 {
+    match pred(n) {
+        Nat::Zero => 1,
+        Nat::Succ(m) => n * fact(m),
+    }
+}
+fn main() -> u64 { fact(20) }
+"#,
+        expect!["2432902008176640000"],
+    )
+}
+
+#[test]
+fn test_run_panics() {
+    check_run(
+        "fn main() -> i64 { 9223372036854775807 + 1 }",
+        expect!["panicked: attempt to add with overflow"],
+    );
+    check_run(
+        "fn main() -> u64 { let z: u64 = 0; 1 / z }",
+        expect!["panicked: attempt to divide by zero"],
+    );
+    check_run(
+        "fn f(n: i64) -> i64 { f(n + 1) } fn main() -> i64 { f(0) }",
+        expect!["panicked: stack overflow"],
+    );
+}
+
+#[test]
+fn test_run_generics() {
+    check_run(
+        r#"
+struct Pair<A, B> { a: A, b: B }
+enum List<T> { Nil, Cons(T, i64) }
+fn max<T: Ord>(a: T, b: T) -> T { match a > b { true => a, false => b } }
+fn pair<A, B>(a: A, b: B) -> Pair<A, B> { Pair { a: a, b: b } }
+fn main() -> Pair<Pair<u64, char>, List<bool>> {
+    pair(pair(max(3, 7), max('a', 'z')), List::Cons(true, 1))
+}
+"#,
+        expect!["Pair { a: Pair { a: 7, b: 'z' }, b: List::Cons(true, 1) }"],
+    )
+}
+
+#[test]
+fn test_run_traits() {
+    check_run(
+        r#"
+trait Shape { fn area(self) -> f64; fn name(self) -> str { "shape" } }
+struct Circle { r: f64 }
+struct Square { s: f64 }
+impl Circle { fn new(r: f64) -> Circle { Circle { r: r } } }
+impl Shape for Circle {
+    fn area(self) -> f64 { 3.0 * self.r * self.r }
+    fn name(self) -> str { "circle" }
+}
+impl Shape for Square { fn area(self) -> f64 { self.s * self.s } }
+struct Pair { a: dyn Shape, b: dyn Shape }
+fn total(p: Pair) -> f64 { p.a.area() + p.b.area() }
+fn main() -> Pair {
+    let p = Pair { a: Circle::new(1.0), b: Square { s: 2.0 } };
+    match p.a.name() == "circle" {
+        true => Pair { a: Square { s: total(p) }, b: p.a },
+        false => p,
+    }
+}
+"#,
+        expect!["Pair { a: Square { s: 7.0 }, b: Circle { r: 1.0 } }"],
+    )
+}
+
+#[test]
+fn test_run_overloaded_operators() {
+    check_run(
+        r#"
+struct Money { cents: u64 }
+impl Add for Money { fn add(self, rhs: Money) -> Money { Money { cents: self.cents + rhs.cents } } }
+impl Mul for Money { fn mul(self, rhs: Money) -> Money { Money { cents: self.cents * rhs.cents / 100 } } }
+impl Eq for Money { fn eq(self, other: Money) -> bool { self.cents == other.cents } }
+impl Ord for Money { fn lt(self, other: Money) -> bool { self.cents < other.cents } }
+fn max<T: Ord>(a: T, b: T) -> T { match a >= b { true => a, false => b } }
+fn main() -> Money {
+    let a = Money { cents: 250 };
+    let b = Money { cents: 400 } * Money { cents: 50 };
+    let c = Money { cents: 450 };
+    match a + b == c {
+        true => max(a, b) + max(Money { cents: 1 }, Money { cents: 0 }),
+        false => a,
+    }
+}
+"#,
+        expect!["Money { cents: 251 }"],
+    )
+}
+
+#[test]
+fn test_run_closures() {
+    check_run(
+        r#"
+enum Option<T> { Some(T), None }
+fn map<T, U>(o: Option<T>, f: fn(T) -> U) -> Option<U> {
+    match o { Option::Some(x) => Option::Some(f(x)), Option::None => Option::None }
+}
+fn filter<T>(o: Option<T>, pred: fn(T) -> bool) -> Option<T> {
+    match o { Option::Some(x) if pred(x) => Option::Some(x), _ => Option::None }
+}
+fn make_adder(n: i64) -> fn(i64) -> i64 { |x| x + n }
+fn main() -> Option<i64> {
+    let mut offset = 10;
+    let add = make_adder(offset);
+    offset = 0;
+    filter(map(Option::Some(5), add), |x| x > offset)
+}
+"#,
+        expect!["Option::Some(15)"],
+    );
+}
+
+#[test]
+fn test_run_captured_by_reference() {
+    check_run(
+        r#"
+struct Stats { calls: i64, total: i64 }
+fn twice(f: fn(i64)) { f(1); f(2); }
+fn main() -> Stats {
+    let mut stats = Stats { calls: 0, total: 0 };
+    let mut count = 0;
+    let record = |x: i64| {
+        stats.calls = stats.calls + 1;
+        stats.total = stats.total + x;
+        let bump = || count = count + x;
+        bump();
+    };
+    twice(record);
+    record(10);
+    Stats { calls: stats.calls, total: stats.total * 100 + count }
+}
+"#,
+        expect!["Stats { calls: 3, total: 1313 }"],
+    );
+}
+
+#[test]
+fn test_run_try() {
+    check_run(
+        r#"
+fn digit(c: char) -> Result<i64, char> {
+    match c { '0' => Result::Ok(0), '1' => Result::Ok(1), c => Result::Err(c) }
+}
+fn number(a: char, b: char) -> Result<i64, char> { Result::Ok(digit(a)? * 2 + digit(b)?) }
+fn both(a: Option<i64>, b: Option<i64>) -> Option<Result<i64, char>> {
+    let f = |x: Option<i64>| -> Option<i64> { Option::Some(x? * 10) };
+    Option::Some(Result::Ok(f(a)? + b?))
+}
+fn main() -> Pair {
+    let n = match number('1', '0') { Result::Ok(n) => n, Result::Err(_) => 0 };
+    Pair { a: both(Option::Some(n), Option::Some(1)), b: both(Option::Some(n), Option::None) }
+}
+struct Pair { a: Option<Result<i64, char>>, b: Option<Result<i64, char>> }
+"#,
+        expect!["Pair { a: Option::Some(Result::Ok(21)), b: Option::None }"],
+    );
+    check_run(
+        r#"
+fn digit(c: char) -> Result<i64, char> {
+    match c { '0' => Result::Ok(0), '1' => Result::Ok(1), c => Result::Err(c) }
+}
+fn number(a: char, b: char) -> Result<i64, char> { Result::Ok(digit(a)? * 2 + digit(b)?) }
+fn main() -> Result<i64, char> { number('1', 'x') }
+"#,
+        expect!["Result::Err('x')"],
+    );
+}
+
+#[test]
+fn test_run_casts() {
+    check_run(
+        r#"
+fn main() -> Casts {
+    let big = 100000000000000000000000000000.0;
+    Casts {
+        wrap: -1 as u64,
+        trunc: 18446744073709551615u64 as i64,
+        sat: big as i64,
+        neg: -big as u64,
+        nan: (0.0 / 0.0) as i64,
+        toward_zero: -3.9 as i64,
+        ch: 'A' as i64 + true as i64,
+        float: 7 as f64 / 2.0,
+        not: ~5 + (~0 as u64 == 18446744073709551615) as i64,
+    }
+}
+struct Casts {
+    wrap: u64, trunc: i64, sat: i64, neg: u64, nan: i64, toward_zero: i64,
+    ch: i64, float: f64, not: i64,
+}
+"#,
+        expect!["Casts { wrap: 18446744073709551615, trunc: -1, sat: 9223372036854775807, neg: 0, nan: 0, toward_zero: -3, ch: 66, float: 3.5, not: -5 }"],
+    );
+}
+
+#[test]
+fn test_run_int_widths() {
+    check_run(
+        r#"
+fn main() -> Widths {
+    let small: i8 = -128;
+    let byte = 200u8;
+    Widths {
+        a: small / 2 + 127i8 - 63,
+        b: (byte as i8) as i64,
+        c: 300i16 as u8,
+        d: 65535u16 as i16 + (4294967295u32 as i32) as i16 * 2,
+        e: 340282366920938463463374607431768211455u128 / 3,
+        f: -170141183460469231731687303715884105728i128,
+        g: (18446744073709551615usize - 1) as u128,
+        h: 0.1f32 + 0.2,
+        i: 16777217 as f32,
+        j: 65u8 as char,
+        k: ~0u32,
+    }
+}
+struct Widths {
+    a: i8, b: i64, c: u8, d: i16, e: u128, f: i128, g: u128, h: f32, i: f32, j: char, k: u32,
+}
+"#,
+        expect!["Widths { a: 0, b: -56, c: 44, d: -3, e: 113427455640312821154458202477256070485, f: -170141183460469231731687303715884105728, g: 18446744073709551614, h: 0.3, i: 16777216.0, j: 'A', k: 4294967295 }"],
+    );
+    check_run(
+        r#"
+fn main() -> u8 { let x = 255u8; x + 1 }
+"#,
+        expect!["panicked: attempt to add with overflow"],
+    );
+    check_run(
+        r#"
+fn main() -> i8 { let x: i8 = -128; -x }
+"#,
+        expect!["panicked: attempt to negate with overflow"],
+    );
+}
+
+#[test]
+fn test_run_wrapping_checked() {
+    check_run(
+        r#"
+fn main() -> Results {
+    let max = 127i8;
+    Results {
+        wrapped: max.wrapping_add(1),
+        wrapped_neg: 0u16.wrapping_sub(1),
+        checked: max.checked_sub(27),
+        overflow: max.checked_mul(2),
+        div_zero: 7u64.checked_div(0),
+        div_min: (-128i8).checked_div(-1),
+    }
+}
+struct Results {
+    wrapped: i8, wrapped_neg: u16, checked: Option<i8>, overflow: Option<i8>,
+    div_zero: Option<u64>, div_min: Option<i8>,
+}
+"#,
+        expect!["Results { wrapped: -128, wrapped_neg: 65535, checked: Option::Some(100), overflow: Option::None, div_zero: Option::None, div_min: Option::None }"],
+    );
+}
+
+#[test]
+fn test_run_arrays() {
+    check_run(
+        r#"
+struct P { x: i64, ys: [i64; 2] }
+fn sum(xs: [i64]) -> i64 {
+    let mut total = 0;
+    match xs.len() { 0 => {} _ => { total = xs[0] + xs[xs.len() - 1]; } }
+    total
+}
+fn main() -> ((i64, bool), [P; 2], [i64; 3], i64, usize) {
+    let mut a = [1, 2, 3];
+    a[1] = a[0] + a[2] * 10;
+    let mut ps = [P { x: 0, ys: [0; 2] }; 2];
+    ps[1].ys[0] = 7;
+    ps[0].x = ps[1].ys[0] + 1;
+    let t = (a[1], true);
+    let s: [i64] = a;
+    ((t.0, t.1), ps, a, sum(a) + sum(s), s.len())
+}
+"#,
+        expect!["((31, true), [P { x: 8, ys: [0, 0] }, P { x: 0, ys: [7, 0] }], [1, 31, 3], 8, 3)"],
+    );
+    let src = r#"
+fn get(xs: [u8], i: usize) -> u8 { xs[i] }
+fn main() -> u8 { get([1, 2, 3], 5) }
+"#;
+    check_run(
+        src,
+        expect!["panicked: index out of bounds: the len is 3 but the index is 5"],
+    );
+    check_run(
+        r#"
+fn main() -> ([i64], [i64], [i64], i64) {
+    let a = [1, 2, 3, 4];
+    let s: [i64] = a;
+    (a[1..3], s[2..], a[0..=0], s[1..][1..][0])
+}
+"#,
+        expect!["([2, 3], [3, 4], [1], 3)"],
+    );
+    check_run(
+        "fn main() -> [i64] { let a = [1, 2, 3]; a[1..5] }",
+        expect!["panicked: range end index 5 out of range for slice of length 3"],
+    );
+    check_run(
+        "fn main() -> [i64] { let a = [1, 2, 3]; let i = 2; a[i..1] }",
+        expect!["panicked: slice index starts at 2 but ends at 1"],
+    );
+    check_run(
+        r#"
+fn main() -> ([i64], [i64]) {
+    let a = [1, 2, 3, 4];
+    let r = 1..3;
+    let s = 2..=3;
+    (a[r], a[s])
+}
+"#,
+        expect!["([2, 3], [3, 4])"],
+    );
+    check_run(
+        "fn main() -> [i64] { let a = [1, 2, 3]; a[1..=18446744073709551615] }",
+        expect!["panicked: attempted to index slice up to maximum usize"],
+    );
+    check_run(
+        "fn main() -> [i64] { let a = [1, 2, 3]; a[0..=3] }",
+        expect!["panicked: range end index 4 out of range for slice of length 3"],
+    );
+    let unchecked = ceylon_lower::Options {
+        bounds_checks: false,
+        ..Default::default()
+    };
+    check_run_with(
+        src,
+        unchecked,
+        expect!["panicked: undefined behavior: unchecked index out of bounds: the len is 3 but the index is 5"],
+    );
+}
+
+#[test]
+fn test_run_for_loops() {
+    check_run(
+        r#"
+struct P { x: i64 }
+fn sum(xs: [i64]) -> i64 {
+    let mut total = 0;
+    for x in xs { total = total + x; }
+    total
+}
+fn count(r: RangeInclusive<u8>) -> u32 {
+    let mut n = 0u32;
+    for _ in r { n = n + 1; }
+    n
+}
+fn main() -> (i64, i64, u32, u32, i64, i64) {
+    let mut squares = [0; 5];
+    for i in 0..5 { squares[i] = (i * i) as i64; }
+    let mut empty = 0;
+    for i in 5..0 { empty = empty + i; }
+    let mut total = 0;
+    for P { x } in [P { x: 1 }, P { x: 2 }] { total = total + x; }
+    (sum(squares), empty, count(0..=255), count(3..=2), total, sum([]))
+}
+"#,
+        expect!["(30, 0, 256, 0, 3, 0)"],
+    );
+}
+
+#[test]
+fn test_run_compound_assignment() {
+    check_run(
+        r#"
+struct P { x: i64, ys: [u8; 2] }
+struct Money { cents: i64 }
+impl Add for Money { fn add(self, rhs: Money) -> Money { Money { cents: self.cents + rhs.cents } } }
+fn main() -> (i64, [u8; 2], u32, bool, i64, f64, Money) {
+    let mut ps = [P { x: 7, ys: [1, 2] }; 2];
+    let i = 0usize;
+    ps[i].x += 10;
+    ps[i].x %= 5;
+    ps[1].ys[i] <<= 3;
+    ps[1].ys[i + 1] |= 240;
+    ps[1].ys[i + 1] ^= 3;
+    let mut n = 1u32 << 31 >> 30;
+    n *= 3;
+    n -= 1;
+    n /= 2;
+    n &= 6;
+    let mut b = true;
+    b &= !false;
+    b ^= true;
+    b |= false;
+    let mut f = 7.5;
+    f %= 2.0;
+    let mut m = Money { cents: 1 };
+    m += Money { cents: 2 };
+    (ps[0].x, ps[1].ys, n, b, -7 % 3, f, m)
+}
+"#,
+        expect!["(2, [8, 241], 2, false, -1, 1.5, Money { cents: 3 })"],
+    );
+    check_run(
+        "fn main() -> i64 { let z = 0; 1 % z }",
+        expect!["panicked: attempt to calculate the remainder with a divisor of zero"],
+    );
+    check_run(
+        "fn main() -> i8 { let m: i8 = -128; m % -1 }",
+        expect!["panicked: attempt to calculate the remainder with overflow"],
+    );
+    let src = "fn main() -> u8 { let s = 9u8; 3 << s }";
+    check_run(
+        src,
+        expect!["panicked: attempt to shift left with overflow"],
+    );
+    let unchecked = ceylon_lower::Options {
+        overflow_checks: false,
+        ..Default::default()
+    };
+    check_run_with(src, unchecked, expect!["6"]);
+}
+
+#[test]
+fn test_run_tuple_patterns() {
+    check_run(
+        r#"
+fn classify(t: (i64, bool)) -> i64 {
+    match t { (0, _) => 1, (n, true) => n * 10, (_, false) => 2 }
+}
+fn main() -> (i64, i64, i64, i64, char, i64) {
+    let (a, (b, mut c)) = (1, ('x', 2));
+    c += a;
+    let mut total = 0;
+    for (i, (x,)) in [(1, (10,)), (2, (20,))] { total += i * x; }
+    (classify((0, true)), classify((4, true)), classify((4, false)), c, b, total)
+}
+"#,
+        expect!["(1, 40, 2, 3, 'x', 50)"],
+    );
+}
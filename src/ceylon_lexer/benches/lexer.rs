@@ -0,0 +1,44 @@
+use ceylon_lexer::{tokenize, StringReader, TokenKind};
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+
+/// A function exercising most kinds of tokens.
+const SNIPPET: &str = r#"/// Computes something important.
+fn compute_値(a: i64, b: u64, c: char) -> i64 {
+    // Arithmetic and comparisons.
+    1 + 2 * 3 - 4 / 5 == 6 != 7 <= 8 >= 9;
+    /* A /* nested */ block comment. */
+    3.141592 * 2.718281 < 1.0;
+    'a'; '\n'; "Hello, world!\n"; "unicode: 日本語";
+}
+
+"#;
+
+/// Generates a source of at least `size` bytes by repeating `SNIPPET`.
+fn generate_source(size: usize) -> String {
+    SNIPPET.repeat(size / SNIPPET.len() + 1)
+}
+
+fn bench_lexer(c: &mut Criterion) {
+    let mut group = c.benchmark_group("lexer");
+    for size in [1 << 20, 8 << 20] {
+        let src = generate_source(size);
+        group.throughput(Throughput::Bytes(src.len() as u64));
+        group.bench_with_input(BenchmarkId::new("tokenize", size), &src, |b, src| {
+            b.iter(|| tokenize(black_box(src)).count())
+        });
+        group.bench_with_input(BenchmarkId::new("string_reader", size), &src, |b, src| {
+            b.iter(|| {
+                let mut reader = StringReader::new(black_box(src));
+                let mut n = 0usize;
+                while reader.next_token().kind != TokenKind::Eof {
+                    n += 1;
+                }
+                n
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_lexer);
+criterion_main!(benches);
@@ -1,55 +1,108 @@
-use std::str::Chars;
-
 const EOF_CHAR: char = '\0';
 
+/// Peekable iterator over the characters of the input.
+///
+/// The cursor works on the underlying bytes, so peeking the next characters
+/// and bumping take constant time regardless of the position in the input.
 #[derive(Debug)]
 pub(crate) struct Cursor<'a> {
-    initial_len: usize,
-    chars: Chars<'a>,
+    input: &'a str,
+    /// Byte offset of the next character.
+    pos: usize,
+    /// Byte offset where the current token started.
+    token_start: usize,
 }
 
 impl<'a> Cursor<'a> {
     pub(crate) fn new(input: &'a str) -> Cursor<'a> {
         Cursor {
-            initial_len: input.len(),
-            chars: input.chars(),
+            input,
+            pos: 0,
+            token_start: 0,
         }
     }
 
-    /// Returns nth character relative to the current cursor position.
-    /// If requested position doesn't exist, `EOF_CHAR` is returned.
-    pub(crate) fn nth_char(&self, n: usize) -> char {
-        self.chars().nth(n).unwrap_or(EOF_CHAR)
-    }
-
     /// Peeks the next symbol from the input stream without consuming it.
+    /// If requested position doesn't exist, `EOF_CHAR` is returned.
     pub(crate) fn first(&self) -> char {
-        self.nth_char(0)
+        self.char_at(self.pos)
     }
 
     /// Peeks the second symbol from the input stream without consuming it.
+    /// If requested position doesn't exist, `EOF_CHAR` is returned.
     pub(crate) fn second(&self) -> char {
-        self.nth_char(1)
+        match self.input.as_bytes().get(self.pos) {
+            Some(&b) => self.char_at(self.pos + utf8_char_width(b)),
+            None => EOF_CHAR,
+        }
     }
 
     /// Checks if there is nothing more to consume.
     pub(crate) fn is_eof(&self) -> bool {
-        self.chars.as_str().is_empty()
+        self.pos >= self.input.len()
+    }
+
+    /// Returns the byte offset of the next character in the input.
+    pub(crate) fn pos(&self) -> usize {
+        self.pos
+    }
+
+    /// Returns the byte offset where the current token started.
+    pub(crate) fn token_start(&self) -> usize {
+        self.token_start
     }
 
-    /// Returns amount of already consumed symbols.
+    /// Returns amount of bytes consumed since the start of the current token.
     pub(crate) fn len_consumed(&self) -> usize {
-        self.initial_len - self.chars.as_str().len()
+        self.pos - self.token_start
     }
 
-    /// Returns a `Chars` iterator over the remaining characters.
-    pub(crate) fn chars(&self) -> Chars<'a> {
-        self.chars.clone()
+    /// Marks the current position as the start of the next token.
+    pub(crate) fn reset_len_consumed(&mut self) {
+        self.token_start = self.pos;
     }
 
     /// Moves to the next character.
     pub(crate) fn bump(&mut self) -> Option<char> {
-        let c = self.chars.next()?;
+        let &b = self.input.as_bytes().get(self.pos)?;
+        if b < 0x80 {
+            self.pos += 1;
+            return Some(b as char);
+        }
+        let c = self.input[self.pos..].chars().next()?;
+        self.pos += c.len_utf8();
         Some(c)
     }
+
+    /// Skips everything up to (but not including) the next occurrence of `byte`,
+    /// or to the end of file. `byte` must be ASCII so that the cursor stays on
+    /// a char boundary.
+    pub(crate) fn eat_until(&mut self, byte: u8) {
+        debug_assert!(byte.is_ascii());
+        self.pos = match self.input.as_bytes()[self.pos..]
+            .iter()
+            .position(|&b| b == byte)
+        {
+            Some(i) => self.pos + i,
+            None => self.input.len(),
+        };
+    }
+
+    fn char_at(&self, pos: usize) -> char {
+        match self.input.as_bytes().get(pos) {
+            None => EOF_CHAR,
+            Some(&b) if b < 0x80 => b as char,
+            Some(_) => self.input[pos..].chars().next().unwrap_or(EOF_CHAR),
+        }
+    }
+}
+
+/// Returns the length in bytes of the UTF-8 sequence starting with `b`.
+fn utf8_char_width(b: u8) -> usize {
+    match b {
+        0x00..=0x7F => 1,
+        0xC0..=0xDF => 2,
+        0xE0..=0xEF => 3,
+        _ => 4,
+    }
 }
@@ -3,44 +3,99 @@ mod cursor;
 #[cfg(test)]
 mod tests;
 
+pub use ceylon_span::Span;
+
 use self::TokenKind::*;
+use ceylon_span::{kw, Symbol};
 use cursor::Cursor;
-
+use serde::{Deserialize, Serialize};
+
+/// Produces the tokens of a source string with absolute spans.
+///
+/// Identifiers are distinguished from keywords. Whitespace and non-doc
+/// comments ("trivia") are skipped unless the reader is created with
+/// `StringReader::with_trivia`, but unterminated block comments are always
+/// produced so that they can be reported.
+///
+/// The reader can be used as an iterator over the tokens and their text:
+///
+/// ```
+/// use ceylon_lexer::StringReader;
+///
+/// let texts: Vec<&str> = StringReader::new("fn main")
+///     .map(|(_token, text)| text)
+///     .collect();
+/// assert_eq!(texts, ["fn", "main"]);
+/// ```
 pub struct StringReader<'a> {
     pub src: &'a str,
-    pos: usize,
-    end_index: usize,
+    cursor: Cursor<'a>,
+    /// Whether whitespace and comments are produced as tokens.
+    trivia: bool,
+    /// Position of the source in the source map, which the spans of the
+    /// tokens are relative to.
+    start_pos: usize,
 }
 
 impl<'a> StringReader<'a> {
     pub fn new(src: &'a str) -> Self {
+        // Skip the byte order mark so that it doesn't become an unknown token.
+        let mut cursor = Cursor::new(src);
+        if cursor.first() == '\u{FEFF}' {
+            cursor.bump();
+        }
         StringReader {
             src,
-            pos: 0,
-            end_index: src.len(),
+            cursor,
+            trivia: false,
+            start_pos: 0,
+        }
+    }
+
+    /// Creates a reader for a file which starts at `start_pos` in the source
+    /// map, e.g. one of the modules of a program.
+    pub fn with_start_pos(src: &'a str, start_pos: usize) -> Self {
+        StringReader {
+            start_pos,
+            ..StringReader::new(src)
+        }
+    }
+
+    /// Creates a reader which also produces whitespace and comments, so that
+    /// the source can be reconstructed from the tokens.
+    pub fn with_trivia(src: &'a str) -> Self {
+        StringReader {
+            trivia: true,
+            ..StringReader::new(src)
         }
     }
 
     pub fn next_token(&mut self) -> Token {
         loop {
-            let text = &self.src[self.pos..self.end_index];
-            if text.is_empty() {
+            if self.cursor.is_eof() {
                 let span = Span {
-                    start_pos: self.pos,
+                    start_pos: self.start_pos + self.cursor.pos(),
                     len: 0,
                 };
                 return Token::new(TokenKind::Eof, span);
             }
 
-            let mut token = first_token(text);
-            token.span.start_pos = self.pos;
-            self.pos += token.span.len;
+            let mut token = self.cursor.advance_token();
+            token.span.start_pos += self.start_pos;
 
             match token.kind {
-                TokenKind::Whitespace | TokenKind::LineComment => (),
                 TokenKind::Ident => {
                     return self.ident_to_keyword(token);
                 }
+                _ if self.trivia => {
+                    return token;
+                }
+                TokenKind::Whitespace
+                | TokenKind::LineComment { doc_style: None }
+                | TokenKind::BlockComment {
+                    doc_style: None,
+                    terminated: true,
+                } => (),
                 _ => {
                     return token;
                 }
@@ -50,17 +105,47 @@ impl<'a> StringReader<'a> {
 
     fn ident_to_keyword(&self, t: Token) -> Token {
         debug_assert!(matches!(t.kind, TokenKind::Ident));
-        let s = self.span_to_str(&t.span);
-        let kw_kind = match s {
-            "i64" => KwKind::I64,
-            "u64" => KwKind::U64,
-            "char" => KwKind::Char,
-            "str" => KwKind::Str,
-            "void" => KwKind::Void,
-            "if" => KwKind::If,
-            _ => {
-                return t;
-            }
+        let sym = Symbol::intern(self.span_to_str(&t.span));
+        if !sym.is_keyword() {
+            return t;
+        }
+        let kw_kind = match sym {
+            kw::I64 => KwKind::I64,
+            kw::U64 => KwKind::U64,
+            kw::Bool => KwKind::Bool,
+            kw::Char => KwKind::Char,
+            kw::Str => KwKind::Str,
+            kw::Void => KwKind::Void,
+            kw::If => KwKind::If,
+            kw::Fn => KwKind::Fn,
+            kw::Struct => KwKind::Struct,
+            kw::Let => KwKind::Let,
+            kw::True => KwKind::True,
+            kw::False => KwKind::False,
+            kw::Enum => KwKind::Enum,
+            kw::Match => KwKind::Match,
+            kw::F64 => KwKind::F64,
+            kw::Trait => KwKind::Trait,
+            kw::Impl => KwKind::Impl,
+            kw::For => KwKind::For,
+            kw::Dyn => KwKind::Dyn,
+            kw::Pub => KwKind::Pub,
+            kw::Mod => KwKind::Mod,
+            kw::Import => KwKind::Import,
+            kw::As => KwKind::As,
+            kw::I8 => KwKind::I8,
+            kw::I16 => KwKind::I16,
+            kw::I32 => KwKind::I32,
+            kw::I128 => KwKind::I128,
+            kw::U8 => KwKind::U8,
+            kw::U16 => KwKind::U16,
+            kw::U32 => KwKind::U32,
+            kw::U128 => KwKind::U128,
+            kw::Usize => KwKind::Usize,
+            kw::F32 => KwKind::F32,
+            kw::In => KwKind::In,
+            kw::Mut => KwKind::Mut,
+            _ => unreachable!("keyword `{}` has no `KwKind`", sym),
         };
         Token {
             kind: TokenKind::Keyword { kind: kw_kind },
@@ -68,13 +153,42 @@ impl<'a> StringReader<'a> {
         }
     }
 
-    pub fn span_to_str(&self, span: &Span) -> &str {
-        &self.src[span.start_pos..span.start_pos + span.len]
+    pub fn span_to_str(&self, span: &Span) -> &'a str {
+        let start = span.start_pos - self.start_pos;
+        &self.src[start..start + span.len]
+    }
+
+    /// Returns the text of a doc comment without the comment markers.
+    pub fn doc_comment_to_str(&self, t: &Token) -> &str {
+        let s = self.span_to_str(&t.span);
+        match t.kind {
+            // "/// text", "//! text"
+            TokenKind::LineComment { doc_style: Some(_) } => &s[3..],
+            // "/** text */", "/*! text */"
+            TokenKind::BlockComment {
+                doc_style: Some(_),
+                terminated: true,
+            } => &s[3..s.len() - 2],
+            _ => unreachable!(),
+        }
     }
 
     pub fn quoted_to_str(&self, span: &Span) -> &str {
-        debug_assert!(span.start_pos + 1 <= span.start_pos + span.len - 1);
-        &self.src[span.start_pos + 1..span.start_pos + span.len - 1]
+        debug_assert!(span.len >= 2);
+        let start = span.start_pos - self.start_pos;
+        &self.src[start + 1..start + span.len - 1]
+    }
+}
+
+impl<'a> Iterator for StringReader<'a> {
+    type Item = (Token, &'a str);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let token = self.next_token();
+        if token.kind == TokenKind::Eof {
+            return None;
+        }
+        Some((token, self.span_to_str(&token.span)))
     }
 }
 
@@ -84,23 +198,6 @@ pub struct Token {
     pub span: Span,
 }
 
-#[derive(Debug, Copy, Clone)]
-pub struct Span {
-    pub start_pos: usize,
-    pub len: usize,
-}
-
-impl Span {
-    pub fn append(&self, s: Span) -> Self {
-        let start_pos = std::cmp::min(self.start_pos, s.start_pos);
-        let end_pos = std::cmp::max(self.start_pos + self.len, s.start_pos + s.len);
-        Span {
-            start_pos,
-            len: end_pos - start_pos,
-        }
-    }
-}
-
 impl Token {
     pub fn new(kind: TokenKind, span: Span) -> Token {
         Token { kind, span }
@@ -110,8 +207,15 @@ impl Token {
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum TokenKind {
     // Multi-char tokens:
-    /// "// comment"
-    LineComment,
+    /// "// comment", "/// doc comment", "//! inner doc comment"
+    LineComment { doc_style: Option<DocStyle> },
+    /// "/* block comment */", "/** doc comment */", "/*! inner doc comment */"
+    ///
+    /// Block comments can be nested, so "/* /* */ */" is a single comment.
+    BlockComment {
+        doc_style: Option<DocStyle>,
+        terminated: bool,
+    },
     /// Any whitespace characters sequence.
     Whitespace,
     /// "ident"
@@ -122,6 +226,10 @@ pub enum TokenKind {
     Literal { kind: LitKind },
     /// "->"
     Arrow,
+    /// "=>"
+    FatArrow,
+    /// "::"
+    PathSep,
     /// "=="
     EqEq,
     /// "!="
@@ -130,6 +238,34 @@ pub enum TokenKind {
     LtEq,
     /// ">="
     GtEq,
+    /// ".."
+    DotDot,
+    /// "..="
+    DotDotEq,
+    /// "<<"
+    Shl,
+    /// ">>"
+    Shr,
+    /// "+="
+    PlusEq,
+    /// "-="
+    MinusEq,
+    /// "*="
+    StarEq,
+    /// "/="
+    SlashEq,
+    /// "%="
+    PercentEq,
+    /// "&="
+    AndEq,
+    /// "|="
+    OrEq,
+    /// "^="
+    CaretEq,
+    /// "<<="
+    ShlEq,
+    /// ">>="
+    ShrEq,
     // One-char tokens:
     /// ";"
     Semi,
@@ -191,6 +327,14 @@ pub enum TokenKind {
     Eof,
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DocStyle {
+    /// "///", "/**": documents the item that follows the comment.
+    Outer,
+    /// "//!", "/*!": documents the enclosing module.
+    Inner,
+}
+
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum KwKind {
     /// "i64"
@@ -207,13 +351,69 @@ pub enum KwKind {
     Str,
     /// "if"
     If,
+    /// "fn"
+    Fn,
+    /// "struct"
+    Struct,
+    /// "let"
+    Let,
+    /// "true"
+    True,
+    /// "false"
+    False,
+    /// "enum"
+    Enum,
+    /// "match"
+    Match,
+    /// "f64"
+    F64,
+    /// "trait"
+    Trait,
+    /// "impl"
+    Impl,
+    /// "for"
+    For,
+    /// "dyn"
+    Dyn,
+    /// "pub"
+    Pub,
+    /// "mod"
+    Mod,
+    /// "import"
+    Import,
+    /// "as"
+    As,
+    /// "i8"
+    I8,
+    /// "i16"
+    I16,
+    /// "i32"
+    I32,
+    /// "i128"
+    I128,
+    /// "u8"
+    U8,
+    /// "u16"
+    U16,
+    /// "u32"
+    U32,
+    /// "u128"
+    U128,
+    /// "usize"
+    Usize,
+    /// "f32"
+    F32,
+    /// "in"
+    In,
+    /// "mut"
+    Mut,
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum LitKind {
-    /// "12", "39"
+    /// "12", "1_000", "300u8"
     Int,
-    /// "12.34", "5.0"
+    /// "12.34", "5.0", "1.5f32"
     Float,
     /// "'a'", "'\n'"
     Char { terminated: bool },
@@ -221,51 +421,81 @@ pub enum LitKind {
     Str { terminated: bool },
 }
 
-fn is_whitespace(c: char) -> bool {
+/// True if `c` is considered a whitespace according to the Unicode
+/// `Pattern_White_Space` property.
+///
+/// This is narrower than `char::is_whitespace`: other whitespace such as
+/// NO-BREAK SPACE (U+00A0) or IDEOGRAPHIC SPACE (U+3000) looks like a space
+/// but isn't meant to separate tokens, and usually ends up in source by
+/// accident. It is lexed as `Unknown`, which the parser reports with an
+/// error of its own.
+pub fn is_whitespace(c: char) -> bool {
     matches!(
         c,
+        // Usual ASCII suspects
         '\u{0009}'   // \t
         | '\u{000A}' // \n
+        | '\u{000B}' // vertical tab
+        | '\u{000C}' // form feed
         | '\u{000D}' // \r
         | '\u{0020}' // space
+
+        // NEXT LINE from latin1
+        | '\u{0085}'
+
+        // Bidi markers
+        | '\u{200E}' // LEFT-TO-RIGHT MARK
+        | '\u{200F}' // RIGHT-TO-LEFT MARK
+
+        // Dedicated whitespace characters from Unicode
+        | '\u{2028}' // LINE SEPARATOR
+        | '\u{2029}' // PARAGRAPH SEPARATOR
     )
 }
 
-fn is_id_start(c: char) -> bool {
-    c.is_ascii_alphabetic() || c == '_'
+/// True if `c` is valid as a first character of an identifier
+/// (`XID_Start` or an underscore).
+pub fn is_id_start(c: char) -> bool {
+    // ASCII is checked first since it is the common case.
+    c.is_ascii_alphabetic() || c == '_' || (c > '\x7f' && unicode_xid::UnicodeXID::is_xid_start(c))
 }
 
-fn is_id_continue(c: char) -> bool {
-    c.is_ascii_alphanumeric() || c == '_'
+/// True if `c` is valid as a non-first character of an identifier
+/// (`XID_Continue`).
+pub fn is_id_continue(c: char) -> bool {
+    c.is_ascii_alphanumeric()
+        || c == '_'
+        || (c > '\x7f' && unicode_xid::UnicodeXID::is_xid_continue(c))
 }
 
 /// Parses the first token from the provided input string.
+/// Unlike `tokenize`, identifiers are not converted into keywords.
 pub fn first_token(input: &str) -> Token {
     debug_assert!(!input.is_empty());
     Cursor::new(input).advance_token()
 }
 
-/// Creates an iterator that produces tokens from the input string.
-pub fn tokenize(mut input: &str) -> impl Iterator<Item = Token> + '_ {
-    std::iter::from_fn(move || {
-        if input.is_empty() {
-            return None;
-        }
-
-        let token = first_token(input);
-        input = &input[token.span.len..];
-        Some(token)
-    })
+/// Creates an iterator that produces all tokens from the input string,
+/// including whitespace and comments, together with their text.
+/// This is a shorthand for `StringReader::with_trivia`.
+pub fn tokenize(input: &str) -> StringReader<'_> {
+    StringReader::with_trivia(input)
 }
 
 impl Cursor<'_> {
     /// Parses a token from the input string.
     fn advance_token(&mut self) -> Token {
+        self.reset_len_consumed();
         let first_char = self.bump().unwrap();
         let token_kind = match first_char {
             // Slash or comment.
             '/' => match self.first() {
                 '/' => self.line_comment(),
+                '*' => self.block_comment(),
+                '=' => {
+                    self.bump();
+                    SlashEq
+                }
                 _ => Slash,
             },
             // Whitespace sequence.
@@ -284,7 +514,18 @@ impl Cursor<'_> {
             // One-symbol tokens.
             ';' => Semi,
             ',' => Comma,
-            '.' => Dot,
+            '.' => match (self.first(), self.second()) {
+                ('.', '=') => {
+                    self.bump();
+                    self.bump();
+                    DotDotEq
+                }
+                ('.', _) => {
+                    self.bump();
+                    DotDot
+                }
+                _ => Dot,
+            },
             '(' => OpenParen,
             ')' => CloseParen,
             '{' => OpenBrace,
@@ -295,16 +536,26 @@ impl Cursor<'_> {
             '#' => Pound,
             '~' => Tilde,
             '?' => Question,
-            ':' => Colon,
-            '$' => Dollar,
-            '=' => {
-                if self.first() == '=' {
+            ':' => {
+                if self.first() == ':' {
                     self.bump();
-                    EqEq
+                    PathSep
                 } else {
-                    Eq
+                    Colon
                 }
             }
+            '$' => Dollar,
+            '=' => match self.first() {
+                '=' => {
+                    self.bump();
+                    EqEq
+                }
+                '>' => {
+                    self.bump();
+                    FatArrow
+                }
+                _ => Eq,
+            },
             '!' => {
                 if self.first() == '=' {
                     self.bump();
@@ -313,36 +564,55 @@ impl Cursor<'_> {
                     Bang
                 }
             }
-            '<' => {
-                if self.first() == '=' {
+            '<' => match (self.first(), self.second()) {
+                ('<', '=') => {
+                    self.bump();
+                    self.bump();
+                    ShlEq
+                }
+                ('<', _) => {
+                    self.bump();
+                    Shl
+                }
+                ('=', _) => {
                     self.bump();
                     LtEq
-                } else {
-                    Lt
                 }
-            }
-            '>' => {
-                if self.first() == '=' {
+                _ => Lt,
+            },
+            '>' => match (self.first(), self.second()) {
+                ('>', '=') => {
+                    self.bump();
+                    self.bump();
+                    ShrEq
+                }
+                ('>', _) => {
+                    self.bump();
+                    Shr
+                }
+                ('=', _) => {
                     self.bump();
                     GtEq
-                } else {
-                    Gt
                 }
-            }
-            '-' => {
-                if self.first() == '>' {
+                _ => Gt,
+            },
+            '-' => match self.first() {
+                '>' => {
                     self.bump();
                     Arrow
-                } else {
-                    Minus
                 }
-            }
-            '&' => And,
-            '|' => Or,
-            '+' => Plus,
-            '*' => Star,
-            '^' => Caret,
-            '%' => Percent,
+                '=' => {
+                    self.bump();
+                    MinusEq
+                }
+                _ => Minus,
+            },
+            '&' => self.with_eq(And, AndEq),
+            '|' => self.with_eq(Or, OrEq),
+            '+' => self.with_eq(Plus, PlusEq),
+            '*' => self.with_eq(Star, StarEq),
+            '^' => self.with_eq(Caret, CaretEq),
+            '%' => self.with_eq(Percent, PercentEq),
 
             // Character literal.
             '\'' => {
@@ -362,19 +632,75 @@ impl Cursor<'_> {
 
             _ => Unknown,
         };
-        // NOTE: The tokenizer doesn't know the start position of the token.
-        // For now tokenizer sets token.span.start_pos 0.
         let span = Span {
-            start_pos: 0,
+            start_pos: self.token_start(),
             len: self.len_consumed(),
         };
         Token::new(token_kind, span)
     }
 
+    /// Returns `with_eq` if an operator is followed by `=`, e.g. `+=`, and
+    /// `kind` otherwise.
+    fn with_eq(&mut self, kind: TokenKind, with_eq: TokenKind) -> TokenKind {
+        if self.first() == '=' {
+            self.bump();
+            with_eq
+        } else {
+            kind
+        }
+    }
+
     fn line_comment(&mut self) -> TokenKind {
         self.bump(); // The second slash of "// comment"
-        self.eat_while(|c| c != '\n');
-        LineComment
+
+        let doc_style = match self.first() {
+            // "//!" is an inner doc comment.
+            '!' => Some(DocStyle::Inner),
+            // "///" is an outer doc comment, but "////" (4+ slashes) is not.
+            '/' if self.second() != '/' => Some(DocStyle::Outer),
+            _ => None,
+        };
+
+        self.eat_until(b'\n');
+        LineComment { doc_style }
+    }
+
+    fn block_comment(&mut self) -> TokenKind {
+        self.bump(); // The asterisk of "/* comment */"
+
+        let doc_style = match self.first() {
+            // "/*!" is an inner doc comment.
+            '!' => Some(DocStyle::Inner),
+            // "/**" is an outer doc comment, but "/***" and "/**/" are not.
+            '*' if !matches!(self.second(), '*' | '/') => Some(DocStyle::Outer),
+            _ => None,
+        };
+
+        let mut depth = 1usize;
+        while let Some(c) = self.bump() {
+            match c {
+                '/' if self.first() == '*' => {
+                    self.bump();
+                    depth += 1;
+                }
+                '*' if self.first() == '/' => {
+                    self.bump();
+                    depth -= 1;
+                    if depth == 0 {
+                        // This block comment is closed, so for a construction like
+                        // "/* */ */" there will be a successfully parsed block
+                        // comment "/* */" and " */" will be processed separately.
+                        break;
+                    }
+                }
+                _ => (),
+            }
+        }
+
+        BlockComment {
+            doc_style,
+            terminated: depth == 0,
+        }
     }
 
     fn whitespace(&mut self) -> TokenKind {
@@ -392,23 +718,44 @@ impl Cursor<'_> {
         self.eat_decimal_digits();
 
         match self.first() {
-            // Integer literals followed by dot can represent afield/method access
-            '.' if !is_id_start(self.second()) => {
+            // Integer literals followed by dot can represent a field/method
+            // access, or the start of a range.
+            '.' if self.second() != '.' && !is_id_start(self.second()) => {
                 self.bump(); // .
-                if self.first().is_digit(10) {
+                if self.first().is_ascii_digit() {
                     self.eat_decimal_digits();
                 }
+                self.eat_suffix();
                 LitKind::Float
             }
-            _ => LitKind::Int,
+            _ => {
+                self.eat_suffix();
+                LitKind::Int
+            }
+        }
+    }
+
+    /// Eats the type a number literal may end with, e.g. `u8` in `300u8`.
+    fn eat_suffix(&mut self) {
+        if is_id_start(self.first()) {
+            self.eat_while(is_id_continue);
         }
     }
 
     fn eat_decimal_digits(&mut self) -> bool {
         let mut has_digits = false;
-        while let '0'..='9' = self.first() {
-            has_digits = true;
-            self.bump();
+        loop {
+            match self.first() {
+                // Digits may be separated by underscores, e.g. `1_000`.
+                '_' => {
+                    self.bump();
+                }
+                '0'..='9' => {
+                    has_digits = true;
+                    self.bump();
+                }
+                _ => break,
+            }
         }
         has_digits
     }
@@ -11,49 +11,81 @@ fn check_lexing(src: &str, expect: Expect) {
 #[test]
 fn test_all_tokens() {
     check_lexing(
-        "// comment\n ;,.(){}[]@#~?:$=!<>-&|+*/^% -> == != <= >= ",
-        expect![[r#"
-            Token { kind: LineComment, span: Span { start_pos: 0, len: 10 } }
-            Token { kind: Whitespace, span: Span { start_pos: 0, len: 2 } }
-            Token { kind: Semi, span: Span { start_pos: 0, len: 1 } }
-            Token { kind: Comma, span: Span { start_pos: 0, len: 1 } }
-            Token { kind: Dot, span: Span { start_pos: 0, len: 1 } }
-            Token { kind: OpenParen, span: Span { start_pos: 0, len: 1 } }
-            Token { kind: CloseParen, span: Span { start_pos: 0, len: 1 } }
-            Token { kind: OpenBrace, span: Span { start_pos: 0, len: 1 } }
-            Token { kind: CloseBrace, span: Span { start_pos: 0, len: 1 } }
-            Token { kind: OpenBracket, span: Span { start_pos: 0, len: 1 } }
-            Token { kind: CloseBracket, span: Span { start_pos: 0, len: 1 } }
-            Token { kind: At, span: Span { start_pos: 0, len: 1 } }
-            Token { kind: Pound, span: Span { start_pos: 0, len: 1 } }
-            Token { kind: Tilde, span: Span { start_pos: 0, len: 1 } }
-            Token { kind: Question, span: Span { start_pos: 0, len: 1 } }
-            Token { kind: Colon, span: Span { start_pos: 0, len: 1 } }
-            Token { kind: Dollar, span: Span { start_pos: 0, len: 1 } }
-            Token { kind: Eq, span: Span { start_pos: 0, len: 1 } }
-            Token { kind: Bang, span: Span { start_pos: 0, len: 1 } }
-            Token { kind: Lt, span: Span { start_pos: 0, len: 1 } }
-            Token { kind: Gt, span: Span { start_pos: 0, len: 1 } }
-            Token { kind: Minus, span: Span { start_pos: 0, len: 1 } }
-            Token { kind: And, span: Span { start_pos: 0, len: 1 } }
-            Token { kind: Or, span: Span { start_pos: 0, len: 1 } }
-            Token { kind: Plus, span: Span { start_pos: 0, len: 1 } }
-            Token { kind: Star, span: Span { start_pos: 0, len: 1 } }
-            Token { kind: Slash, span: Span { start_pos: 0, len: 1 } }
-            Token { kind: Caret, span: Span { start_pos: 0, len: 1 } }
-            Token { kind: Percent, span: Span { start_pos: 0, len: 1 } }
-            Token { kind: Whitespace, span: Span { start_pos: 0, len: 1 } }
-            Token { kind: Arrow, span: Span { start_pos: 0, len: 2 } }
-            Token { kind: Whitespace, span: Span { start_pos: 0, len: 1 } }
-            Token { kind: EqEq, span: Span { start_pos: 0, len: 2 } }
-            Token { kind: Whitespace, span: Span { start_pos: 0, len: 1 } }
-            Token { kind: BangEq, span: Span { start_pos: 0, len: 2 } }
-            Token { kind: Whitespace, span: Span { start_pos: 0, len: 1 } }
-            Token { kind: LtEq, span: Span { start_pos: 0, len: 2 } }
-            Token { kind: Whitespace, span: Span { start_pos: 0, len: 1 } }
-            Token { kind: GtEq, span: Span { start_pos: 0, len: 2 } }
-            Token { kind: Whitespace, span: Span { start_pos: 0, len: 1 } }
-        "#]],
+        "// comment\n ;,.(){}[]@#~?:$=!<>-&|+*/^% -> => :: == != <= >= .. ..= << >> += -= *= /= %= &= |= ^= <<= >>= ",
+        expect![[r##"
+            (Token { kind: LineComment { doc_style: None }, span: Span { start_pos: 0, len: 10 } }, "// comment")
+            (Token { kind: Whitespace, span: Span { start_pos: 10, len: 2 } }, "\n ")
+            (Token { kind: Semi, span: Span { start_pos: 12, len: 1 } }, ";")
+            (Token { kind: Comma, span: Span { start_pos: 13, len: 1 } }, ",")
+            (Token { kind: Dot, span: Span { start_pos: 14, len: 1 } }, ".")
+            (Token { kind: OpenParen, span: Span { start_pos: 15, len: 1 } }, "(")
+            (Token { kind: CloseParen, span: Span { start_pos: 16, len: 1 } }, ")")
+            (Token { kind: OpenBrace, span: Span { start_pos: 17, len: 1 } }, "{")
+            (Token { kind: CloseBrace, span: Span { start_pos: 18, len: 1 } }, "}")
+            (Token { kind: OpenBracket, span: Span { start_pos: 19, len: 1 } }, "[")
+            (Token { kind: CloseBracket, span: Span { start_pos: 20, len: 1 } }, "]")
+            (Token { kind: At, span: Span { start_pos: 21, len: 1 } }, "@")
+            (Token { kind: Pound, span: Span { start_pos: 22, len: 1 } }, "#")
+            (Token { kind: Tilde, span: Span { start_pos: 23, len: 1 } }, "~")
+            (Token { kind: Question, span: Span { start_pos: 24, len: 1 } }, "?")
+            (Token { kind: Colon, span: Span { start_pos: 25, len: 1 } }, ":")
+            (Token { kind: Dollar, span: Span { start_pos: 26, len: 1 } }, "$")
+            (Token { kind: Eq, span: Span { start_pos: 27, len: 1 } }, "=")
+            (Token { kind: Bang, span: Span { start_pos: 28, len: 1 } }, "!")
+            (Token { kind: Lt, span: Span { start_pos: 29, len: 1 } }, "<")
+            (Token { kind: Gt, span: Span { start_pos: 30, len: 1 } }, ">")
+            (Token { kind: Minus, span: Span { start_pos: 31, len: 1 } }, "-")
+            (Token { kind: And, span: Span { start_pos: 32, len: 1 } }, "&")
+            (Token { kind: Or, span: Span { start_pos: 33, len: 1 } }, "|")
+            (Token { kind: Plus, span: Span { start_pos: 34, len: 1 } }, "+")
+            (Token { kind: Star, span: Span { start_pos: 35, len: 1 } }, "*")
+            (Token { kind: Slash, span: Span { start_pos: 36, len: 1 } }, "/")
+            (Token { kind: Caret, span: Span { start_pos: 37, len: 1 } }, "^")
+            (Token { kind: Percent, span: Span { start_pos: 38, len: 1 } }, "%")
+            (Token { kind: Whitespace, span: Span { start_pos: 39, len: 1 } }, " ")
+            (Token { kind: Arrow, span: Span { start_pos: 40, len: 2 } }, "->")
+            (Token { kind: Whitespace, span: Span { start_pos: 42, len: 1 } }, " ")
+            (Token { kind: FatArrow, span: Span { start_pos: 43, len: 2 } }, "=>")
+            (Token { kind: Whitespace, span: Span { start_pos: 45, len: 1 } }, " ")
+            (Token { kind: PathSep, span: Span { start_pos: 46, len: 2 } }, "::")
+            (Token { kind: Whitespace, span: Span { start_pos: 48, len: 1 } }, " ")
+            (Token { kind: EqEq, span: Span { start_pos: 49, len: 2 } }, "==")
+            (Token { kind: Whitespace, span: Span { start_pos: 51, len: 1 } }, " ")
+            (Token { kind: BangEq, span: Span { start_pos: 52, len: 2 } }, "!=")
+            (Token { kind: Whitespace, span: Span { start_pos: 54, len: 1 } }, " ")
+            (Token { kind: LtEq, span: Span { start_pos: 55, len: 2 } }, "<=")
+            (Token { kind: Whitespace, span: Span { start_pos: 57, len: 1 } }, " ")
+            (Token { kind: GtEq, span: Span { start_pos: 58, len: 2 } }, ">=")
+            (Token { kind: Whitespace, span: Span { start_pos: 60, len: 1 } }, " ")
+            (Token { kind: DotDot, span: Span { start_pos: 61, len: 2 } }, "..")
+            (Token { kind: Whitespace, span: Span { start_pos: 63, len: 1 } }, " ")
+            (Token { kind: DotDotEq, span: Span { start_pos: 64, len: 3 } }, "..=")
+            (Token { kind: Whitespace, span: Span { start_pos: 67, len: 1 } }, " ")
+            (Token { kind: Shl, span: Span { start_pos: 68, len: 2 } }, "<<")
+            (Token { kind: Whitespace, span: Span { start_pos: 70, len: 1 } }, " ")
+            (Token { kind: Shr, span: Span { start_pos: 71, len: 2 } }, ">>")
+            (Token { kind: Whitespace, span: Span { start_pos: 73, len: 1 } }, " ")
+            (Token { kind: PlusEq, span: Span { start_pos: 74, len: 2 } }, "+=")
+            (Token { kind: Whitespace, span: Span { start_pos: 76, len: 1 } }, " ")
+            (Token { kind: MinusEq, span: Span { start_pos: 77, len: 2 } }, "-=")
+            (Token { kind: Whitespace, span: Span { start_pos: 79, len: 1 } }, " ")
+            (Token { kind: StarEq, span: Span { start_pos: 80, len: 2 } }, "*=")
+            (Token { kind: Whitespace, span: Span { start_pos: 82, len: 1 } }, " ")
+            (Token { kind: SlashEq, span: Span { start_pos: 83, len: 2 } }, "/=")
+            (Token { kind: Whitespace, span: Span { start_pos: 85, len: 1 } }, " ")
+            (Token { kind: PercentEq, span: Span { start_pos: 86, len: 2 } }, "%=")
+            (Token { kind: Whitespace, span: Span { start_pos: 88, len: 1 } }, " ")
+            (Token { kind: AndEq, span: Span { start_pos: 89, len: 2 } }, "&=")
+            (Token { kind: Whitespace, span: Span { start_pos: 91, len: 1 } }, " ")
+            (Token { kind: OrEq, span: Span { start_pos: 92, len: 2 } }, "|=")
+            (Token { kind: Whitespace, span: Span { start_pos: 94, len: 1 } }, " ")
+            (Token { kind: CaretEq, span: Span { start_pos: 95, len: 2 } }, "^=")
+            (Token { kind: Whitespace, span: Span { start_pos: 97, len: 1 } }, " ")
+            (Token { kind: ShlEq, span: Span { start_pos: 98, len: 3 } }, "<<=")
+            (Token { kind: Whitespace, span: Span { start_pos: 101, len: 1 } }, " ")
+            (Token { kind: ShrEq, span: Span { start_pos: 102, len: 3 } }, ">>=")
+            (Token { kind: Whitespace, span: Span { start_pos: 105, len: 1 } }, " ")
+        "##]],
     )
 }
 
@@ -62,13 +94,13 @@ fn test_textual_literal() {
     check_lexing(
         "'a''b''\\n''\\t''\\r'\"Hello\"\"//\"",
         expect![[r#"
-            Token { kind: Literal { kind: Char { terminated: true } }, span: Span { start_pos: 0, len: 3 } }
-            Token { kind: Literal { kind: Char { terminated: true } }, span: Span { start_pos: 0, len: 3 } }
-            Token { kind: Literal { kind: Char { terminated: true } }, span: Span { start_pos: 0, len: 4 } }
-            Token { kind: Literal { kind: Char { terminated: true } }, span: Span { start_pos: 0, len: 4 } }
-            Token { kind: Literal { kind: Char { terminated: true } }, span: Span { start_pos: 0, len: 4 } }
-            Token { kind: Literal { kind: Str { terminated: true } }, span: Span { start_pos: 0, len: 7 } }
-            Token { kind: Literal { kind: Str { terminated: true } }, span: Span { start_pos: 0, len: 4 } }
+            (Token { kind: Literal { kind: Char { terminated: true } }, span: Span { start_pos: 0, len: 3 } }, "'a'")
+            (Token { kind: Literal { kind: Char { terminated: true } }, span: Span { start_pos: 3, len: 3 } }, "'b'")
+            (Token { kind: Literal { kind: Char { terminated: true } }, span: Span { start_pos: 6, len: 4 } }, "'\\n'")
+            (Token { kind: Literal { kind: Char { terminated: true } }, span: Span { start_pos: 10, len: 4 } }, "'\\t'")
+            (Token { kind: Literal { kind: Char { terminated: true } }, span: Span { start_pos: 14, len: 4 } }, "'\\r'")
+            (Token { kind: Literal { kind: Str { terminated: true } }, span: Span { start_pos: 18, len: 7 } }, "\"Hello\"")
+            (Token { kind: Literal { kind: Str { terminated: true } }, span: Span { start_pos: 25, len: 4 } }, "\"//\"")
         "#]],
     )
 }
@@ -78,7 +110,7 @@ fn test_unterminated_string_literal() {
     check_lexing(
         "\"",
         expect![[r#"
-            Token { kind: Literal { kind: Str { terminated: false } }, span: Span { start_pos: 0, len: 1 } }
+            (Token { kind: Literal { kind: Str { terminated: false } }, span: Span { start_pos: 0, len: 1 } }, "\"")
         "#]],
     );
 }
@@ -88,7 +120,7 @@ fn test_unterminated_char_literal() {
     check_lexing(
         "\'",
         expect![[r#"
-            Token { kind: Literal { kind: Char { terminated: false } }, span: Span { start_pos: 0, len: 1 } }
+            (Token { kind: Literal { kind: Char { terminated: false } }, span: Span { start_pos: 0, len: 1 } }, "'")
         "#]],
     );
 }
@@ -96,15 +128,23 @@ fn test_unterminated_char_literal() {
 #[test]
 fn test_numerical_literal() {
     check_lexing(
-        "0 1 638462071 3.141592",
+        "0 1 638462071 3.141592 0..10 1..=2.5",
         expect![[r#"
-            Token { kind: Literal { kind: Int }, span: Span { start_pos: 0, len: 1 } }
-            Token { kind: Whitespace, span: Span { start_pos: 0, len: 1 } }
-            Token { kind: Literal { kind: Int }, span: Span { start_pos: 0, len: 1 } }
-            Token { kind: Whitespace, span: Span { start_pos: 0, len: 1 } }
-            Token { kind: Literal { kind: Int }, span: Span { start_pos: 0, len: 9 } }
-            Token { kind: Whitespace, span: Span { start_pos: 0, len: 1 } }
-            Token { kind: Literal { kind: Float }, span: Span { start_pos: 0, len: 8 } }
+            (Token { kind: Literal { kind: Int }, span: Span { start_pos: 0, len: 1 } }, "0")
+            (Token { kind: Whitespace, span: Span { start_pos: 1, len: 1 } }, " ")
+            (Token { kind: Literal { kind: Int }, span: Span { start_pos: 2, len: 1 } }, "1")
+            (Token { kind: Whitespace, span: Span { start_pos: 3, len: 1 } }, " ")
+            (Token { kind: Literal { kind: Int }, span: Span { start_pos: 4, len: 9 } }, "638462071")
+            (Token { kind: Whitespace, span: Span { start_pos: 13, len: 1 } }, " ")
+            (Token { kind: Literal { kind: Float }, span: Span { start_pos: 14, len: 8 } }, "3.141592")
+            (Token { kind: Whitespace, span: Span { start_pos: 22, len: 1 } }, " ")
+            (Token { kind: Literal { kind: Int }, span: Span { start_pos: 23, len: 1 } }, "0")
+            (Token { kind: DotDot, span: Span { start_pos: 24, len: 2 } }, "..")
+            (Token { kind: Literal { kind: Int }, span: Span { start_pos: 26, len: 2 } }, "10")
+            (Token { kind: Whitespace, span: Span { start_pos: 28, len: 1 } }, " ")
+            (Token { kind: Literal { kind: Int }, span: Span { start_pos: 29, len: 1 } }, "1")
+            (Token { kind: DotDotEq, span: Span { start_pos: 30, len: 3 } }, "..=")
+            (Token { kind: Literal { kind: Float }, span: Span { start_pos: 33, len: 3 } }, "2.5")
         "#]],
     )
 }
@@ -114,17 +154,17 @@ fn test_method_access() {
     check_lexing(
         "10.foo() 2.72.foo()",
         expect![[r#"
-            Token { kind: Literal { kind: Int }, span: Span { start_pos: 0, len: 2 } }
-            Token { kind: Dot, span: Span { start_pos: 0, len: 1 } }
-            Token { kind: Ident, span: Span { start_pos: 0, len: 3 } }
-            Token { kind: OpenParen, span: Span { start_pos: 0, len: 1 } }
-            Token { kind: CloseParen, span: Span { start_pos: 0, len: 1 } }
-            Token { kind: Whitespace, span: Span { start_pos: 0, len: 1 } }
-            Token { kind: Literal { kind: Float }, span: Span { start_pos: 0, len: 4 } }
-            Token { kind: Dot, span: Span { start_pos: 0, len: 1 } }
-            Token { kind: Ident, span: Span { start_pos: 0, len: 3 } }
-            Token { kind: OpenParen, span: Span { start_pos: 0, len: 1 } }
-            Token { kind: CloseParen, span: Span { start_pos: 0, len: 1 } }
+            (Token { kind: Literal { kind: Int }, span: Span { start_pos: 0, len: 2 } }, "10")
+            (Token { kind: Dot, span: Span { start_pos: 2, len: 1 } }, ".")
+            (Token { kind: Ident, span: Span { start_pos: 3, len: 3 } }, "foo")
+            (Token { kind: OpenParen, span: Span { start_pos: 6, len: 1 } }, "(")
+            (Token { kind: CloseParen, span: Span { start_pos: 7, len: 1 } }, ")")
+            (Token { kind: Whitespace, span: Span { start_pos: 8, len: 1 } }, " ")
+            (Token { kind: Literal { kind: Float }, span: Span { start_pos: 9, len: 4 } }, "2.72")
+            (Token { kind: Dot, span: Span { start_pos: 13, len: 1 } }, ".")
+            (Token { kind: Ident, span: Span { start_pos: 14, len: 3 } }, "foo")
+            (Token { kind: OpenParen, span: Span { start_pos: 17, len: 1 } }, "(")
+            (Token { kind: CloseParen, span: Span { start_pos: 18, len: 1 } }, ")")
         "#]],
     )
 }
@@ -134,28 +174,54 @@ fn smoke_test() {
     check_lexing(
         "// my source file\nfunc main() -> void { println(\"Hello.\"); }\n",
         expect![[r#"
-            Token { kind: LineComment, span: Span { start_pos: 0, len: 17 } }
-            Token { kind: Whitespace, span: Span { start_pos: 0, len: 1 } }
-            Token { kind: Ident, span: Span { start_pos: 0, len: 4 } }
-            Token { kind: Whitespace, span: Span { start_pos: 0, len: 1 } }
-            Token { kind: Ident, span: Span { start_pos: 0, len: 4 } }
-            Token { kind: OpenParen, span: Span { start_pos: 0, len: 1 } }
-            Token { kind: CloseParen, span: Span { start_pos: 0, len: 1 } }
-            Token { kind: Whitespace, span: Span { start_pos: 0, len: 1 } }
-            Token { kind: Arrow, span: Span { start_pos: 0, len: 2 } }
-            Token { kind: Whitespace, span: Span { start_pos: 0, len: 1 } }
-            Token { kind: Ident, span: Span { start_pos: 0, len: 4 } }
-            Token { kind: Whitespace, span: Span { start_pos: 0, len: 1 } }
-            Token { kind: OpenBrace, span: Span { start_pos: 0, len: 1 } }
-            Token { kind: Whitespace, span: Span { start_pos: 0, len: 1 } }
-            Token { kind: Ident, span: Span { start_pos: 0, len: 7 } }
-            Token { kind: OpenParen, span: Span { start_pos: 0, len: 1 } }
-            Token { kind: Literal { kind: Str { terminated: true } }, span: Span { start_pos: 0, len: 8 } }
-            Token { kind: CloseParen, span: Span { start_pos: 0, len: 1 } }
-            Token { kind: Semi, span: Span { start_pos: 0, len: 1 } }
-            Token { kind: Whitespace, span: Span { start_pos: 0, len: 1 } }
-            Token { kind: CloseBrace, span: Span { start_pos: 0, len: 1 } }
-            Token { kind: Whitespace, span: Span { start_pos: 0, len: 1 } }
+            (Token { kind: LineComment { doc_style: None }, span: Span { start_pos: 0, len: 17 } }, "// my source file")
+            (Token { kind: Whitespace, span: Span { start_pos: 17, len: 1 } }, "\n")
+            (Token { kind: Ident, span: Span { start_pos: 18, len: 4 } }, "func")
+            (Token { kind: Whitespace, span: Span { start_pos: 22, len: 1 } }, " ")
+            (Token { kind: Ident, span: Span { start_pos: 23, len: 4 } }, "main")
+            (Token { kind: OpenParen, span: Span { start_pos: 27, len: 1 } }, "(")
+            (Token { kind: CloseParen, span: Span { start_pos: 28, len: 1 } }, ")")
+            (Token { kind: Whitespace, span: Span { start_pos: 29, len: 1 } }, " ")
+            (Token { kind: Arrow, span: Span { start_pos: 30, len: 2 } }, "->")
+            (Token { kind: Whitespace, span: Span { start_pos: 32, len: 1 } }, " ")
+            (Token { kind: Keyword { kind: Void }, span: Span { start_pos: 33, len: 4 } }, "void")
+            (Token { kind: Whitespace, span: Span { start_pos: 37, len: 1 } }, " ")
+            (Token { kind: OpenBrace, span: Span { start_pos: 38, len: 1 } }, "{")
+            (Token { kind: Whitespace, span: Span { start_pos: 39, len: 1 } }, " ")
+            (Token { kind: Ident, span: Span { start_pos: 40, len: 7 } }, "println")
+            (Token { kind: OpenParen, span: Span { start_pos: 47, len: 1 } }, "(")
+            (Token { kind: Literal { kind: Str { terminated: true } }, span: Span { start_pos: 48, len: 8 } }, "\"Hello.\"")
+            (Token { kind: CloseParen, span: Span { start_pos: 56, len: 1 } }, ")")
+            (Token { kind: Semi, span: Span { start_pos: 57, len: 1 } }, ";")
+            (Token { kind: Whitespace, span: Span { start_pos: 58, len: 1 } }, " ")
+            (Token { kind: CloseBrace, span: Span { start_pos: 59, len: 1 } }, "}")
+            (Token { kind: Whitespace, span: Span { start_pos: 60, len: 1 } }, "\n")
+        "#]],
+    )
+}
+
+#[test]
+fn test_comments() {
+    check_lexing(
+        "// a\n/// b\n//! c\n//// d\n/* e /* f */ g */ /** h */ /*! i */ /**/ /*",
+        expect![[r#"
+            (Token { kind: LineComment { doc_style: None }, span: Span { start_pos: 0, len: 4 } }, "// a")
+            (Token { kind: Whitespace, span: Span { start_pos: 4, len: 1 } }, "\n")
+            (Token { kind: LineComment { doc_style: Some(Outer) }, span: Span { start_pos: 5, len: 5 } }, "/// b")
+            (Token { kind: Whitespace, span: Span { start_pos: 10, len: 1 } }, "\n")
+            (Token { kind: LineComment { doc_style: Some(Inner) }, span: Span { start_pos: 11, len: 5 } }, "//! c")
+            (Token { kind: Whitespace, span: Span { start_pos: 16, len: 1 } }, "\n")
+            (Token { kind: LineComment { doc_style: None }, span: Span { start_pos: 17, len: 6 } }, "//// d")
+            (Token { kind: Whitespace, span: Span { start_pos: 23, len: 1 } }, "\n")
+            (Token { kind: BlockComment { doc_style: None, terminated: true }, span: Span { start_pos: 24, len: 17 } }, "/* e /* f */ g */")
+            (Token { kind: Whitespace, span: Span { start_pos: 41, len: 1 } }, " ")
+            (Token { kind: BlockComment { doc_style: Some(Outer), terminated: true }, span: Span { start_pos: 42, len: 8 } }, "/** h */")
+            (Token { kind: Whitespace, span: Span { start_pos: 50, len: 1 } }, " ")
+            (Token { kind: BlockComment { doc_style: Some(Inner), terminated: true }, span: Span { start_pos: 51, len: 8 } }, "/*! i */")
+            (Token { kind: Whitespace, span: Span { start_pos: 59, len: 1 } }, " ")
+            (Token { kind: BlockComment { doc_style: None, terminated: true }, span: Span { start_pos: 60, len: 4 } }, "/**/")
+            (Token { kind: Whitespace, span: Span { start_pos: 64, len: 1 } }, " ")
+            (Token { kind: BlockComment { doc_style: None, terminated: false }, span: Span { start_pos: 65, len: 2 } }, "/*")
         "#]],
     )
 }
@@ -199,17 +265,81 @@ fn test_string_reader() {
 #[test]
 fn test_keyword() {
     check_string_reader(
-        "i64 u64 char str bool void if main",
+        "i64 u64 char str bool void if mut main",
         expect![[r#"
             Token { kind: Keyword { kind: I64 }, span: Span { start_pos: 0, len: 3 } }
             Token { kind: Keyword { kind: U64 }, span: Span { start_pos: 4, len: 3 } }
             Token { kind: Keyword { kind: Char }, span: Span { start_pos: 8, len: 4 } }
             Token { kind: Keyword { kind: Str }, span: Span { start_pos: 13, len: 3 } }
-            Token { kind: Ident, span: Span { start_pos: 17, len: 4 } }
+            Token { kind: Keyword { kind: Bool }, span: Span { start_pos: 17, len: 4 } }
             Token { kind: Keyword { kind: Void }, span: Span { start_pos: 22, len: 4 } }
             Token { kind: Keyword { kind: If }, span: Span { start_pos: 27, len: 2 } }
-            Token { kind: Ident, span: Span { start_pos: 30, len: 4 } }
-            Token { kind: Eof, span: Span { start_pos: 34, len: 0 } }
+            Token { kind: Keyword { kind: Mut }, span: Span { start_pos: 30, len: 3 } }
+            Token { kind: Ident, span: Span { start_pos: 34, len: 4 } }
+            Token { kind: Eof, span: Span { start_pos: 38, len: 0 } }
+        "#]],
+    )
+}
+
+#[test]
+fn test_string_reader_doc_comments() {
+    check_string_reader(
+        "/* a */ /// b\n// c\nfn /** d */",
+        expect![[r#"
+            Token { kind: LineComment { doc_style: Some(Outer) }, span: Span { start_pos: 8, len: 5 } }
+            Token { kind: Keyword { kind: Fn }, span: Span { start_pos: 19, len: 2 } }
+            Token { kind: BlockComment { doc_style: Some(Outer), terminated: true }, span: Span { start_pos: 22, len: 8 } }
+            Token { kind: Eof, span: Span { start_pos: 30, len: 0 } }
+        "#]],
+    )
+}
+
+#[test]
+fn test_unicode_ident_and_whitespace() {
+    check_string_reader(
+        "\u{FEFF}café\u{2028}_Δx\u{0085}変数1 \u{00A0}",
+        expect![[r#"
+            Token { kind: Ident, span: Span { start_pos: 3, len: 5 } }
+            Token { kind: Ident, span: Span { start_pos: 11, len: 4 } }
+            Token { kind: Ident, span: Span { start_pos: 17, len: 7 } }
+            Token { kind: Unknown, span: Span { start_pos: 25, len: 2 } }
+            Token { kind: Eof, span: Span { start_pos: 27, len: 0 } }
+        "#]],
+    )
+}
+
+#[test]
+fn test_numerical_literal_suffix() {
+    check_lexing(
+        "300u8 1_000 12_u8 1.5f32",
+        expect![[r#"
+        (Token { kind: Literal { kind: Int }, span: Span { start_pos: 0, len: 5 } }, "300u8")
+        (Token { kind: Whitespace, span: Span { start_pos: 5, len: 1 } }, " ")
+        (Token { kind: Literal { kind: Int }, span: Span { start_pos: 6, len: 5 } }, "1_000")
+        (Token { kind: Whitespace, span: Span { start_pos: 11, len: 1 } }, " ")
+        (Token { kind: Literal { kind: Int }, span: Span { start_pos: 12, len: 5 } }, "12_u8")
+        (Token { kind: Whitespace, span: Span { start_pos: 17, len: 1 } }, " ")
+        (Token { kind: Literal { kind: Float }, span: Span { start_pos: 18, len: 6 } }, "1.5f32")
+    "#]],
+    )
+}
+
+#[test]
+fn test_primitive_keyword() {
+    check_string_reader(
+        "i8 i16 i32 i128 u8 u16 u32 u128 usize f32",
+        expect![[r#"
+            Token { kind: Keyword { kind: I8 }, span: Span { start_pos: 0, len: 2 } }
+            Token { kind: Keyword { kind: I16 }, span: Span { start_pos: 3, len: 3 } }
+            Token { kind: Keyword { kind: I32 }, span: Span { start_pos: 7, len: 3 } }
+            Token { kind: Keyword { kind: I128 }, span: Span { start_pos: 11, len: 4 } }
+            Token { kind: Keyword { kind: U8 }, span: Span { start_pos: 16, len: 2 } }
+            Token { kind: Keyword { kind: U16 }, span: Span { start_pos: 19, len: 3 } }
+            Token { kind: Keyword { kind: U32 }, span: Span { start_pos: 23, len: 3 } }
+            Token { kind: Keyword { kind: U128 }, span: Span { start_pos: 27, len: 4 } }
+            Token { kind: Keyword { kind: Usize }, span: Span { start_pos: 32, len: 5 } }
+            Token { kind: Keyword { kind: F32 }, span: Span { start_pos: 38, len: 3 } }
+            Token { kind: Eof, span: Span { start_pos: 41, len: 0 } }
         "#]],
     )
 }
@@ -0,0 +1,248 @@
+//! Compiles the arms of a `match` to a decision tree, following "Compiling
+//! pattern matching to good decision trees" by Luc Maranget.
+//!
+//! The arms form a matrix with a row per arm, each row a list of columns
+//! testing a place against a pattern. A column is picked and the rows are
+//! split by the constructor they expect at its place, which becomes a
+//! `Switch`. Every value is tested at most once, so the code for a match
+//! is linear in the size of the value, whatever the number of arms.
+
+use super::*;
+use ir::{Arm, Case, Decision};
+
+/// A pattern, with the variables it binds resolved to locals.
+#[derive(Debug, Clone)]
+pub(crate) enum Pat {
+    Wild,
+    Binding(LocalId),
+    Const(Const),
+    Variant(AdtId, usize, Vec<Pat>),
+    Tuple(Vec<Pat>),
+    Or(Vec<Pat>),
+}
+
+/// A test that the value at `place`, of type `ty`, matches `pat`.
+#[derive(Debug, Clone)]
+struct Column {
+    place: Place,
+    ty: Ty,
+    pat: Pat,
+}
+
+#[derive(Debug, Clone)]
+struct Row {
+    columns: Vec<Column>,
+    arm: usize,
+    /// Places to copy into the variables of the arm, collected as the
+    /// columns binding them are removed.
+    bindings: Vec<(LocalId, Place)>,
+}
+
+/// Builds the decision tree selecting one of `arms`, whose patterns are
+/// `pats`, for the value stored in `local`.
+pub(crate) fn compile(
+    tcx: &Tcx,
+    local: LocalId,
+    ty: &Ty,
+    pats: Vec<Pat>,
+    arms: &[Arm],
+) -> Decision {
+    let root = Place {
+        local,
+        projection: Vec::new(),
+    };
+    let rows = pats
+        .into_iter()
+        .enumerate()
+        .map(|(arm, pat)| Row {
+            columns: vec![Column {
+                place: root.clone(),
+                ty: ty.clone(),
+                pat,
+            }],
+            arm,
+            bindings: Vec::new(),
+        })
+        .collect();
+    let cx = Compiler { tcx, arms };
+    cx.compile(rows)
+}
+
+struct Compiler<'a> {
+    tcx: &'a Tcx,
+    arms: &'a [Arm],
+}
+
+impl Compiler<'_> {
+    fn compile(&self, rows: Vec<Row>) -> Decision {
+        let mut rows: Vec<Row> = rows
+            .into_iter()
+            .flat_map(|row| self.simplify(row))
+            .collect();
+        if rows.is_empty() {
+            return Decision::Failure;
+        }
+        // If the first row has nothing left to test, its arm is taken.
+        if rows[0].columns.is_empty() {
+            let row = rows.remove(0);
+            if self.arms[row.arm].guard.is_none() {
+                return Decision::Leaf {
+                    arm: row.arm,
+                    bindings: row.bindings,
+                };
+            }
+            return Decision::Guard {
+                arm: row.arm,
+                bindings: row.bindings,
+                otherwise: Box::new(self.compile(rows)),
+            };
+        }
+
+        // The first row must be tested on every one of its columns before it
+        // can be taken. Among those, the place tested by the most rows is
+        // tested first, which usually keeps the tree small.
+        let tested = |column: &Column| {
+            rows.iter()
+                .filter(|row| row.column(&column.place).is_some())
+                .count()
+        };
+        let mut column = &rows[0].columns[0];
+        for candidate in &rows[0].columns[1..] {
+            if tested(candidate) > tested(column) {
+                column = candidate;
+            }
+        }
+        let place = column.place.clone();
+        let ty = column.ty.clone();
+
+        let mut cases: Vec<Case> = Vec::new();
+        for row in &rows {
+            if let Some(i) = row.column(&place) {
+                let case = match &row.columns[i].pat {
+                    Pat::Const(value) => Case::Const(value.clone()),
+                    Pat::Variant(_, variant, _) => Case::Variant(*variant),
+                    _ => unreachable!("rows are simplified"),
+                };
+                if !cases.contains(&case) {
+                    cases.push(case);
+                }
+            }
+        }
+        let complete = match &ty {
+            Ty::Adt(adt, _) => cases.len() == self.tcx.adt(*adt).variants.len(),
+            Ty::Bool => cases.len() == 2,
+            _ => false,
+        };
+        let default = if complete {
+            None
+        } else {
+            let rows = rows
+                .iter()
+                .filter(|row| row.column(&place).is_none())
+                .cloned()
+                .collect();
+            Some(Box::new(self.compile(rows)))
+        };
+        let cases = cases
+            .into_iter()
+            .map(|case| {
+                let rows = rows
+                    .iter()
+                    .filter_map(|row| self.specialize(row, &place, &case))
+                    .collect();
+                (case, self.compile(rows))
+            })
+            .collect();
+        Decision::Switch {
+            place,
+            cases,
+            default,
+        }
+    }
+
+    /// Removes the columns which always match, expands or-patterns into
+    /// several rows and splits structs into their fields.
+    fn simplify(&self, mut row: Row) -> Vec<Row> {
+        let mut i = 0;
+        while i < row.columns.len() {
+            match &row.columns[i].pat {
+                Pat::Wild => {
+                    row.columns.remove(i);
+                }
+                &Pat::Binding(local) => {
+                    let column = row.columns.remove(i);
+                    row.bindings.push((local, column.place));
+                }
+                Pat::Or(alts) => {
+                    return alts
+                        .iter()
+                        .flat_map(|alt| {
+                            let mut row = row.clone();
+                            row.columns[i].pat = alt.clone();
+                            self.simplify(row)
+                        })
+                        .collect();
+                }
+                // A struct always matches its only variant, and a tuple is
+                // only made of its elements.
+                Pat::Variant(adt, _, _) if !self.tcx.adt(*adt).is_enum() => {
+                    let column = row.columns.remove(i);
+                    row.columns.splice(i..i, self.field_columns(&column));
+                }
+                Pat::Tuple(_) => {
+                    let column = row.columns.remove(i);
+                    row.columns.splice(i..i, self.field_columns(&column));
+                }
+                Pat::Const(_) | Pat::Variant(..) => i += 1,
+            }
+        }
+        vec![row]
+    }
+
+    /// Returns the row for the values at `place` matching `case`, or `None`
+    /// if the row requires another case.
+    fn specialize(&self, row: &Row, place: &Place, case: &Case) -> Option<Row> {
+        let Some(i) = row.column(place) else {
+            return Some(row.clone());
+        };
+        let mut row = row.clone();
+        let column = row.columns.remove(i);
+        match (&column.pat, case) {
+            (Pat::Const(value), Case::Const(expected)) if value == expected => (),
+            (Pat::Variant(_, variant, _), Case::Variant(expected)) if variant == expected => {
+                let fields = self.field_columns(&column);
+                row.columns.splice(i..i, fields);
+            }
+            _ => return None,
+        }
+        Some(row)
+    }
+
+    /// Returns a column for each field of a variant or tuple pattern.
+    fn field_columns(&self, column: &Column) -> Vec<Column> {
+        let (variant, pats) = match &column.pat {
+            Pat::Variant(_, variant, pats) => (*variant, pats),
+            Pat::Tuple(pats) => (0, pats),
+            _ => unreachable!(),
+        };
+        field_tys(self.tcx, &column.ty, variant)
+            .into_iter()
+            .zip(pats)
+            .enumerate()
+            .map(|(index, (ty, pat))| Column {
+                place: column.place.field(Field { variant, index }),
+                ty,
+                pat: pat.clone(),
+            })
+            .collect()
+    }
+}
+
+impl Row {
+    /// Returns the index of the column testing `place`, if any.
+    fn column(&self, place: &Place) -> Option<usize> {
+        self.columns
+            .iter()
+            .position(|column| column.place == *place)
+    }
+}
@@ -0,0 +1,328 @@
+//! The intermediate representation backends consume: a tree of typed
+//! expressions with names resolved, struct literals in declaration order,
+//! `match` expressions compiled to decision trees, `for` loops turned into
+//! plain loops and the bounds checks of indexing made explicit.
+
+use ceylon_span::Symbol;
+use ceylon_typeck::ty::{AdtId, FnId, TraitId, Ty};
+
+pub use ceylon_parser::ast::{BinOp, UnOp};
+
+/// The lowered functions of a module. Generic functions are lowered once
+/// for each list of generic arguments they are called with, and so are the
+/// closures they contain.
+#[derive(Debug)]
+pub struct Program {
+    pub bodies: Vec<Body>,
+    /// The vtables of the types converted to `dyn` values.
+    pub vtables: Vec<Vtable>,
+}
+
+impl Program {
+    pub fn body(&self, id: BodyId) -> &Body {
+        &self.bodies[id.as_usize()]
+    }
+
+    pub fn vtable(&self, id: VtableId) -> &Vtable {
+        &self.vtables[id.as_usize()]
+    }
+
+    /// Returns the body of a function instantiated with `args`, if the
+    /// program uses it.
+    pub fn instance(&self, fn_id: FnId, args: &[Ty]) -> Option<BodyId> {
+        self.bodies
+            .iter()
+            .position(|body| body.closure.is_none() && body.fn_id == fn_id && body.args == args)
+            .map(BodyId::from_usize)
+    }
+}
+
+/// Index of a body in `Program::bodies`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct BodyId(u32);
+
+impl BodyId {
+    pub fn from_usize(n: usize) -> Self {
+        BodyId(n as u32)
+    }
+
+    pub fn as_usize(self) -> usize {
+        self.0 as usize
+    }
+}
+
+/// The methods of a trait for a type, which calls of methods on `dyn`
+/// values of the type look up.
+#[derive(Debug)]
+pub struct Vtable {
+    pub ty: Ty,
+    pub trait_id: TraitId,
+    /// The body of each method of the trait, in declaration order.
+    pub methods: Vec<BodyId>,
+}
+
+/// Index of a vtable in `Program::vtables`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct VtableId(u32);
+
+impl VtableId {
+    pub fn from_usize(n: usize) -> Self {
+        VtableId(n as u32)
+    }
+
+    pub fn as_usize(self) -> usize {
+        self.0 as usize
+    }
+}
+
+/// The body of a function or of a closure.
+#[derive(Debug)]
+pub struct Body {
+    /// The function, or the function containing the closure.
+    pub fn_id: FnId,
+    pub name: Symbol,
+    /// The generic arguments the function is instantiated with.
+    pub args: Vec<Ty>,
+    /// For the body of a closure, its index among the closures of the
+    /// function.
+    pub closure: Option<usize>,
+    /// The locals holding the arguments. Closures take the variables they
+    /// capture first.
+    pub params: Vec<LocalId>,
+    /// Every variable of the function, including parameters and
+    /// temporaries introduced by the lowering.
+    pub locals: Vec<LocalDecl>,
+    pub value: Expr,
+}
+
+#[derive(Debug)]
+pub struct LocalDecl {
+    pub name: Symbol,
+    pub ty: Ty,
+    /// Whether a closure captures the variable by reference. The variable
+    /// then lives in a cell the closure shares with the enclosing body.
+    pub shared: bool,
+}
+
+/// Index of a local in `Body::locals`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct LocalId(u32);
+
+impl LocalId {
+    pub fn from_usize(n: usize) -> Self {
+        LocalId(n as u32)
+    }
+
+    pub fn as_usize(self) -> usize {
+        self.0 as usize
+    }
+}
+
+#[derive(Debug)]
+pub struct Expr {
+    pub kind: ExprKind,
+    pub ty: Ty,
+}
+
+#[derive(Debug)]
+pub enum ExprKind {
+    Const(Const),
+    /// Reads a local or one of its fields.
+    Place(Place),
+    /// A field of a value which isn't stored in a local, e.g. `f().x`, or an
+    /// element of a tuple.
+    Field(Box<Expr>, usize),
+    Unary(UnOp, Box<Expr>),
+    /// Converts a primitive value to the type of the expression, another
+    /// primitive type. See `Ty::can_cast_to` for the conversions.
+    Cast(Box<Expr>),
+    /// An arithmetic or comparison operator on primitive operands. Integer
+    /// arithmetic panics on overflow.
+    Binary(BinOp, Box<Expr>, Box<Expr>),
+    /// Integer arithmetic which doesn't panic on overflow, e.g.
+    /// `wrapping_add` or `checked_div`.
+    Overflowing(Overflow, BinOp, Box<Expr>, Box<Expr>),
+    Call(BodyId, Vec<Expr>),
+    /// Calls a function value, passing the variables it captures before the
+    /// arguments.
+    CallIndirect(Box<Expr>, Vec<Expr>),
+    /// Calls the method with the given index in the vtable of the first
+    /// argument, a `dyn` value, passing the value inside it as `self`.
+    DynCall(usize, Vec<Expr>),
+    /// Turns a value into a `dyn` value with the vtable of its type.
+    MakeDyn(Box<Expr>, VtableId),
+    /// Builds a struct or an enum. The fields are in declaration order.
+    Adt {
+        adt: AdtId,
+        variant: usize,
+        fields: Vec<Expr>,
+    },
+    Tuple(Vec<Expr>),
+    Array(Vec<Expr>),
+    /// An array of the given number of copies of a value, e.g. `[0; 16]`.
+    Repeat(Box<Expr>, u64),
+    /// Turns an array into a slice of its elements.
+    Unsize(Box<Expr>),
+    /// The number of elements of an array or a slice, as a `usize`.
+    Len(Box<Expr>),
+    /// The elements of an array or a slice from `start` up to `end`, or up
+    /// to its end without one, as a slice. `end` is included if `inclusive`.
+    /// Panics unless `start <= end <= len`, counting from after an included
+    /// end.
+    SubSlice {
+        base: Box<Expr>,
+        start: Box<Expr>,
+        end: Option<Box<Expr>>,
+        inclusive: bool,
+    },
+    /// Panics unless `index < len`. Indexing a place doesn't check the
+    /// index itself, so the lowering puts this before it, unless bounds
+    /// checks are disabled.
+    BoundsCheck {
+        index: Box<Expr>,
+        len: Box<Expr>,
+    },
+    /// A closure, or a function used as a value, which captures nothing.
+    Closure {
+        body: BodyId,
+        captures: Vec<Capture>,
+    },
+    Assign(Place, Box<Expr>),
+    /// Returns the value from the body. The expression produces no value,
+    /// so it has whatever type its context expects.
+    Return(Box<Expr>),
+    Block(Vec<Stmt>, Box<Expr>),
+    /// Evaluates the first branch if the condition holds, and the second
+    /// otherwise.
+    If(Box<Expr>, Box<Expr>, Box<Expr>),
+    /// Evaluates the body over and over until it breaks out of the loop.
+    Loop(Box<Expr>),
+    /// Leaves the innermost `Loop`. Like `Return`, it has whatever type its
+    /// context expects.
+    Break,
+    /// Stores the scrutinee in `local` and evaluates the arm `tree` selects.
+    Match {
+        scrutinee: Box<Expr>,
+        local: LocalId,
+        tree: Decision,
+        arms: Vec<Arm>,
+    },
+}
+
+/// What an `Overflowing` operation does with a result which doesn't fit in
+/// its type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Overflow {
+    /// Wraps around at the bounds of the type.
+    Wrap,
+    /// Returns an `Option`, which is `None` on overflow and on division by
+    /// zero.
+    Check,
+}
+
+/// A variable of the enclosing body used by a closure.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Capture {
+    pub local: LocalId,
+    /// Whether the closure assigns to the variable, in which case it shares
+    /// the variable with the enclosing body. Other variables are copied.
+    pub by_ref: bool,
+}
+
+#[derive(Debug)]
+pub enum Stmt {
+    Let(LocalId, Expr),
+    Expr(Expr),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Const {
+    /// An integer of any integral type.
+    Int(i128),
+    Float(f64),
+    Bool(bool),
+    Char(char),
+    Str(Symbol),
+    Void,
+}
+
+/// A local, or a field or an element nested inside it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Place {
+    pub local: LocalId,
+    pub projection: Vec<Projection>,
+}
+
+impl Place {
+    pub fn field(&self, field: Field) -> Place {
+        self.project(Projection::Field(field))
+    }
+
+    /// The element of the array or slice at the index held by `index`.
+    pub fn index(&self, index: LocalId) -> Place {
+        self.project(Projection::Index(index))
+    }
+
+    fn project(&self, projection: Projection) -> Place {
+        let mut place = self.clone();
+        place.projection.push(projection);
+        place
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Projection {
+    Field(Field),
+    /// An element of an array or a slice, at the index held by a local of
+    /// type `usize`.
+    Index(LocalId),
+}
+
+/// A field of a variant, which must be the active one for enums. Structs
+/// and tuples have a single variant, numbered 0.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Field {
+    pub variant: usize,
+    pub index: usize,
+}
+
+#[derive(Debug)]
+pub struct Arm {
+    pub guard: Option<Expr>,
+    pub body: Expr,
+}
+
+/// How a `match` selects its arm. Each value is tested at most once on the
+/// way from the root to a leaf.
+#[derive(Debug, PartialEq)]
+pub enum Decision {
+    /// Selects `arm` after copying each place into its binding.
+    Leaf {
+        arm: usize,
+        bindings: Vec<(LocalId, Place)>,
+    },
+    /// Like `Leaf`, but the guard of the arm decides whether it is taken. If
+    /// it isn't, the decision continues with `otherwise`.
+    Guard {
+        arm: usize,
+        bindings: Vec<(LocalId, Place)>,
+        otherwise: Box<Decision>,
+    },
+    /// Continues with the case matching the value at `place`, or with
+    /// `default` if there is none.
+    Switch {
+        place: Place,
+        cases: Vec<(Case, Decision)>,
+        default: Option<Box<Decision>>,
+    },
+    /// No arm matches. The type checker makes sure this can't be reached.
+    Failure,
+}
+
+/// What a `Switch` compares a value to.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Case {
+    /// The active variant of an enum.
+    Variant(usize),
+    Const(Const),
+}
@@ -0,0 +1,1585 @@
+//! Lowers type checked functions to the IR in `ir`.
+//!
+//! The lowering relies on the results of the type checker and must only run
+//! on modules which type checked without errors.
+//!
+//! Generic functions are monomorphized: starting from the non-generic
+//! functions, each generic function is lowered once for every list of
+//! generic arguments it is called with, so the IR contains no type
+//! parameters. Calls of trait methods are dispatched statically to the
+//! method of the impl, except on `dyn` values, which carry a vtable.
+//!
+//! `for` loops become plain loops. A loop over a range of integers counts
+//! with a local, as a hand-written loop would. A loop over an array or a
+//! slice follows the iterator protocol: each iteration computes the next
+//! element as an `Option` and matches the pattern of the loop against it,
+//! leaving the loop on `None`.
+//!
+//! Closures are converted to bodies of their own, which take the variables
+//! they capture as extra parameters. A closure copies the variables it uses,
+//! except those it assigns to, which it shares with the enclosing body.
+//!
+//! ```
+//! let src = "enum E { A, B(i64) } fn f(e: E) -> i64 { match e { E::B(n) => n, _ => 0 } }";
+//! let (module, _) = ceylon_parser::parse_file(src);
+//! let (tcx, diagnostics) = ceylon_typeck::check_module(&module);
+//! assert!(diagnostics.is_empty());
+//! let options = ceylon_lower::Options::default();
+//! let program = ceylon_lower::lower_module(&module, &tcx, options).unwrap();
+//! assert_eq!(program.bodies[0].locals.len(), 3);
+//! ```
+
+mod decision;
+pub mod ir;
+pub mod pretty;
+
+#[cfg(test)]
+mod tests;
+
+use std::collections::{HashMap, VecDeque};
+
+use ceylon_errors::Diagnostic;
+use ceylon_parser::{
+    ast::{self, NodeId},
+    visit::{self, Visitor},
+};
+use ceylon_span::{Span, Symbol};
+use ceylon_typeck::{
+    traits::Instance,
+    ty::{AdtId, CtorKind, FnId, TraitId, Ty},
+    Def, Res, Tcx,
+};
+use ir::{
+    BinOp, Body, BodyId, Capture, Const, Expr, ExprKind, Field, LocalDecl, LocalId, Overflow,
+    Place, Stmt, UnOp, Vtable, VtableId,
+};
+
+/// Finds the declaration of every function in a module and its submodules.
+fn collect_fn_decls<'ast>(
+    module: &'ast ast::Module,
+    tcx: &Tcx,
+    decls: &mut HashMap<FnId, &'ast ast::FnDecl>,
+) {
+    for item in &module.items {
+        let items = match &item.kind {
+            ast::ItemKind::Fn(_) => std::slice::from_ref(item),
+            ast::ItemKind::Trait(def) => &def.items[..],
+            ast::ItemKind::Impl(def) => &def.items[..],
+            // A package is lowered where it was first added.
+            ast::ItemKind::Mod(ast::ModDef {
+                same_as: Some(_), ..
+            }) => continue,
+            ast::ItemKind::Mod(def) => {
+                let module = def
+                    .module
+                    .as_ref()
+                    .expect("type checked modules are loaded");
+                collect_fn_decls(module, tcx, decls);
+                continue;
+            }
+            ast::ItemKind::Struct(_) | ast::ItemKind::Enum(_) | ast::ItemKind::Import(_) => {
+                continue
+            }
+        };
+        for item in items {
+            let (ast::ItemKind::Fn(decl), Def::Fn(id)) = (&item.kind, tcx.item_def(item.id)) else {
+                unreachable!()
+            };
+            decls.insert(id, decl);
+        }
+    }
+}
+
+/// How deeply instantiations of generic functions may nest before the
+/// lowering gives up, e.g. because a function calls itself with ever larger
+/// generic arguments.
+const RECURSION_LIMIT: usize = 64;
+
+/// How a module is lowered.
+#[derive(Debug, Clone, Copy)]
+pub struct Options {
+    /// Whether integer arithmetic panics on overflow, as in debug builds,
+    /// or wraps around, as in release builds.
+    pub overflow_checks: bool,
+    /// Whether indexing panics on an index out of bounds. Without the
+    /// checks, such an index is undefined behavior.
+    pub bounds_checks: bool,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            overflow_checks: true,
+            bounds_checks: true,
+        }
+    }
+}
+
+/// Lowers every function of a type checked module, and every instance of a
+/// generic function it uses.
+pub fn lower_module(
+    module: &ast::Module,
+    tcx: &Tcx,
+    options: Options,
+) -> Result<ir::Program, Diagnostic> {
+    let mut decls = HashMap::new();
+    collect_fn_decls(module, tcx, &mut decls);
+    let mut mono = Mono {
+        tcx,
+        options,
+        bodies: Vec::new(),
+        instances: HashMap::new(),
+        queue: VecDeque::new(),
+        vtables: Vec::new(),
+        vtable_ids: HashMap::new(),
+        error: None,
+    };
+    for i in 0..tcx.fns.len() {
+        let id = FnId::from_usize(i);
+        if tcx.fn_sig(id).generics.params.is_empty() {
+            mono.instance(id, Vec::new(), 0, tcx.fn_sig(id).name, None);
+        }
+    }
+    while let Some((id, fn_id, args, depth)) = mono.queue.pop_front() {
+        let body = lower_fn(&mut mono, fn_id, args, depth, decls[&fn_id]);
+        mono.bodies[id.as_usize()] = Some(body);
+        if let Some(diag) = mono.error.take() {
+            return Err(diag);
+        }
+    }
+    let bodies = mono
+        .bodies
+        .into_iter()
+        .map(|body| body.expect("every queued instance is lowered"))
+        .collect();
+    Ok(ir::Program {
+        bodies,
+        vtables: mono.vtables,
+    })
+}
+
+/// The instances of functions found so far.
+struct Mono<'a> {
+    tcx: &'a Tcx,
+    options: Options,
+    /// The bodies of the program, `None` for the instances which haven't
+    /// been lowered yet.
+    bodies: Vec<Option<Body>>,
+    instances: HashMap<(FnId, Vec<Ty>), BodyId>,
+    /// Instances which haven't been lowered yet, in the order of their ids,
+    /// with how deeply they are nested.
+    queue: VecDeque<(BodyId, FnId, Vec<Ty>, usize)>,
+    vtables: Vec<Vtable>,
+    vtable_ids: HashMap<(Ty, TraitId), VtableId>,
+    error: Option<Diagnostic>,
+}
+
+impl Mono<'_> {
+    /// Allocates the id of a body which is lowered later.
+    fn reserve(&mut self) -> BodyId {
+        self.bodies.push(None);
+        BodyId::from_usize(self.bodies.len() - 1)
+    }
+
+    /// Returns the body of an instance, queueing it for lowering if it is
+    /// new. `span` is the call which needs it, if any.
+    fn instance(
+        &mut self,
+        fn_id: FnId,
+        args: Vec<Ty>,
+        depth: usize,
+        name: Symbol,
+        span: Option<Span>,
+    ) -> BodyId {
+        if let Some(&id) = self.instances.get(&(fn_id, args.clone())) {
+            return id;
+        }
+        if depth > RECURSION_LIMIT && self.error.is_none() {
+            self.error = Some(
+                Diagnostic::error(
+                    span.expect("only calls nest instances"),
+                    format!("reached the recursion limit while instantiating `{}`", name),
+                )
+                .note(format!(
+                    "generic functions may only be instantiated {} levels deep",
+                    RECURSION_LIMIT
+                )),
+            );
+        }
+        let id = self.reserve();
+        self.instances.insert((fn_id, args.clone()), id);
+        self.queue.push_back((id, fn_id, args, depth));
+        id
+    }
+
+    /// Returns a body calling a method which has no body of its own, i.e. a
+    /// method called on a `dyn` value or an operator of a primitive type, so
+    /// that the method can be used as a value.
+    fn shim(&mut self, fn_id: FnId, args: Vec<Ty>, instance: Instance) -> BodyId {
+        if let Some(&id) = self.instances.get(&(fn_id, args.clone())) {
+            return id;
+        }
+        let sig = self.tcx.fn_sig(fn_id);
+        let locals: Vec<LocalDecl> = sig
+            .inputs
+            .iter()
+            .enumerate()
+            .map(|(i, ty)| LocalDecl {
+                name: Symbol::intern(if i == 0 && sig.has_self {
+                    "self"
+                } else {
+                    "arg"
+                }),
+                ty: ty.subst(&args),
+                shared: false,
+            })
+            .collect();
+        let params: Vec<LocalId> = (0..locals.len()).map(LocalId::from_usize).collect();
+        let operands = params
+            .iter()
+            .map(|&local| Expr {
+                kind: ExprKind::Place(Place {
+                    local,
+                    projection: Vec::new(),
+                }),
+                ty: locals[local.as_usize()].ty.clone(),
+            })
+            .collect();
+        let kind = match instance {
+            Instance::Virtual(_, index) => ExprKind::DynCall(index, operands),
+            Instance::Intrinsic(fn_id) => lower_intrinsic(self.tcx, self.options, fn_id, operands),
+            Instance::Fn(..) => unreachable!("methods with a body need no shim"),
+        };
+        let value = Expr {
+            kind,
+            ty: sig.output.subst(&args),
+        };
+        let id = self.reserve();
+        self.instances.insert((fn_id, args.clone()), id);
+        self.bodies[id.as_usize()] = Some(Body {
+            fn_id,
+            name: sig.name,
+            args,
+            closure: None,
+            params,
+            locals,
+            value,
+        });
+        id
+    }
+
+    /// Returns the vtable of a trait for a type, instantiating its methods
+    /// if it is new. `span` is the conversion to `dyn` which needs it.
+    fn vtable(&mut self, ty: Ty, trait_id: TraitId, depth: usize, span: Span) -> VtableId {
+        if let Some(&id) = self.vtable_ids.get(&(ty.clone(), trait_id)) {
+            return id;
+        }
+        let methods = self
+            .tcx
+            .trait_def(trait_id)
+            .methods
+            .iter()
+            .map(|&method| {
+                let Instance::Fn(fn_id, args) = self.tcx.resolve_instance(method, vec![ty.clone()])
+                else {
+                    unreachable!("only `dyn` values call methods virtually")
+                };
+                let name = self.tcx.fn_sig(fn_id).name;
+                self.instance(fn_id, args, depth, name, Some(span))
+            })
+            .collect();
+        let id = VtableId::from_usize(self.vtables.len());
+        self.vtables.push(Vtable {
+            ty: ty.clone(),
+            trait_id,
+            methods,
+        });
+        self.vtable_ids.insert((ty, trait_id), id);
+        id
+    }
+}
+
+fn lower_fn(mono: &mut Mono, fn_id: FnId, args: Vec<Ty>, depth: usize, decl: &ast::FnDecl) -> Body {
+    let ret_ty = mono.tcx.fn_sig(fn_id).output.subst(&args);
+    let mut cx = LoweringCx {
+        tcx: mono.tcx,
+        mono,
+        fn_id,
+        args: &args,
+        depth,
+        closures: 0,
+        ret_ty,
+        locals: Vec::new(),
+        local_ids: HashMap::new(),
+    };
+    let self_param = decl
+        .self_param
+        .as_ref()
+        .map(|param| cx.declare(param.id, Symbol::intern("self")));
+    let params = self_param
+        .into_iter()
+        .chain(
+            decl.params
+                .iter()
+                .map(|param| cx.declare(param.id, param.ident.name)),
+        )
+        .collect();
+    let body = decl
+        .body
+        .as_ref()
+        .expect("only functions with bodies are lowered");
+    let value = cx.lower_block(body);
+    let locals = cx.locals;
+    Body {
+        fn_id,
+        name: mono.tcx.fn_sig(fn_id).name,
+        args,
+        closure: None,
+        params,
+        locals,
+        value,
+    }
+}
+
+struct LoweringCx<'a, 'm> {
+    tcx: &'a Tcx,
+    mono: &'a mut Mono<'m>,
+    /// The function being lowered, or containing the closure being lowered.
+    fn_id: FnId,
+    /// The generic arguments of the instance being lowered.
+    args: &'a [Ty],
+    /// How deeply the instance is nested.
+    depth: usize,
+    /// The number of closures of the function lowered so far.
+    closures: usize,
+    /// The return type of the function or closure being lowered.
+    ret_ty: Ty,
+    locals: Vec<LocalDecl>,
+    /// The local of each variable, by the id of its declaration.
+    local_ids: HashMap<NodeId, LocalId>,
+}
+
+impl LoweringCx<'_, '_> {
+    fn node_ty(&self, id: NodeId) -> Ty {
+        self.tcx.typeck_results.node_types[&id].subst(self.args)
+    }
+
+    fn new_local(&mut self, name: Symbol, ty: Ty) -> LocalId {
+        self.locals.push(LocalDecl {
+            name,
+            ty,
+            shared: false,
+        });
+        LocalId::from_usize(self.locals.len() - 1)
+    }
+
+    /// Creates the local for a variable declared by the node `id`.
+    fn declare(&mut self, id: NodeId, name: Symbol) -> LocalId {
+        let local = self.new_local(name, self.node_ty(id));
+        self.local_ids.insert(id, local);
+        local
+    }
+
+    /// Returns the local a binding refers to. Bindings in later alternatives
+    /// of an or-pattern share the local of the first alternative.
+    fn local(&self, id: NodeId) -> LocalId {
+        let results = &self.tcx.typeck_results;
+        let id = results.binding_aliases.get(&id).copied().unwrap_or(id);
+        self.local_ids[&id]
+    }
+
+    fn lower_block(&mut self, block: &ast::Block) -> Expr {
+        let mut stmts = Vec::with_capacity(block.stmts.len());
+        let mut value = None;
+        for (i, stmt) in block.stmts.iter().enumerate() {
+            match &stmt.kind {
+                ast::StmtKind::Let(local) => {
+                    // The initializer can't see the variables, so it is
+                    // lowered first.
+                    let init = self.lower_expr(&local.init);
+                    self.declare_bindings(&local.pat);
+                    let stmt = match &local.pat.kind {
+                        ast::PatKind::Ident(..) => Stmt::Let(self.local(local.pat.id), init),
+                        _ => {
+                            let arm = ir::Arm {
+                                guard: None,
+                                body: Expr {
+                                    kind: ExprKind::Const(Const::Void),
+                                    ty: Ty::Void,
+                                },
+                            };
+                            Stmt::Expr(self.match_irrefutable("value", &local.pat, init, arm))
+                        }
+                    };
+                    stmts.push(stmt);
+                }
+                ast::StmtKind::Expr(expr) if i == block.stmts.len() - 1 => {
+                    value = Some(self.lower_expr(expr));
+                }
+                ast::StmtKind::Semi(expr) | ast::StmtKind::Expr(expr) => {
+                    stmts.push(Stmt::Expr(self.lower_expr(expr)));
+                }
+            }
+        }
+        let value = value.unwrap_or(Expr {
+            kind: ExprKind::Const(Const::Void),
+            ty: Ty::Void,
+        });
+        let ty = value.ty.clone();
+        Expr {
+            kind: ExprKind::Block(stmts, Box::new(value)),
+            ty,
+        }
+    }
+
+    fn lower_expr(&mut self, expr: &ast::Expr) -> Expr {
+        let value = self.lower_expr_uncoerced(expr);
+        if self.tcx.typeck_results.unsize_coercions.contains(&expr.id) {
+            let Ty::Array(elem, _) = &value.ty else {
+                unreachable!("only arrays are turned into slices")
+            };
+            let ty = Ty::Slice(elem.clone());
+            return Expr {
+                kind: ExprKind::Unsize(Box::new(value)),
+                ty,
+            };
+        }
+        let Some(&trait_id) = self.tcx.typeck_results.dyn_coercions.get(&expr.id) else {
+            return value;
+        };
+        // A type parameter may stand for the `dyn` type already.
+        if let Ty::Dyn(_) = value.ty {
+            return value;
+        }
+        let vtable = self
+            .mono
+            .vtable(value.ty.clone(), trait_id, self.depth + 1, expr.span);
+        Expr {
+            kind: ExprKind::MakeDyn(Box::new(value), vtable),
+            ty: Ty::Dyn(trait_id),
+        }
+    }
+
+    fn lower_expr_uncoerced(&mut self, expr: &ast::Expr) -> Expr {
+        let ty = self.node_ty(expr.id);
+        let kind = match &expr.kind {
+            ast::ExprKind::Literal(lit) => ExprKind::Const(lower_lit(lit)),
+            ast::ExprKind::Binary(op, lhs, rhs) => {
+                let lhs = self.lower_expr(lhs);
+                let rhs = self.lower_expr(rhs);
+                match self.tcx.typeck_results.path_res.get(&expr.id) {
+                    Some(&Res::Fn(method)) => {
+                        return self.lower_overloaded_binary(expr, *op, method, lhs, rhs)
+                    }
+                    _ => binary(self.mono.options, *op, Box::new(lhs), Box::new(rhs)),
+                }
+            }
+            // Negated integer literals are constants, as the literal alone
+            // may not fit in its type, e.g. `128` in `-128i8`.
+            ast::ExprKind::Unary(ast::UnOp::Neg, operand)
+                if matches!(
+                    operand.kind,
+                    ast::ExprKind::Literal(ast::LiteralKind::Int(..))
+                ) =>
+            {
+                ExprKind::Const(lower_lit_pat(expr))
+            }
+            ast::ExprKind::Unary(op, operand) => {
+                let operand = self.lower_expr(operand);
+                match self.tcx.typeck_results.path_res.get(&expr.id) {
+                    Some(&Res::Fn(method)) => self.lower_call(method, expr, vec![operand]),
+                    _ if *op == UnOp::Neg => neg(self.mono.options, Box::new(operand)),
+                    _ => ExprKind::Unary(*op, Box::new(operand)),
+                }
+            }
+            ast::ExprKind::Path(_) => match self.tcx.typeck_results.path_res[&expr.id] {
+                Res::Local(id) => ExprKind::Place(Place {
+                    local: self.local(id),
+                    projection: Vec::new(),
+                }),
+                // Only unit variants are values.
+                Res::Variant(adt, variant) => ExprKind::Adt {
+                    adt,
+                    variant,
+                    fields: Vec::new(),
+                },
+                Res::Fn(id) => self.lower_fn_value(id, expr),
+            },
+            ast::ExprKind::Call(callee, args) => {
+                match self.tcx.typeck_results.path_res.get(&callee.id) {
+                    Some(&Res::Fn(id)) => {
+                        let args = args.iter().map(|arg| self.lower_expr(arg)).collect();
+                        self.lower_call(id, callee, args)
+                    }
+                    Some(&Res::Variant(adt, variant)) => ExprKind::Adt {
+                        adt,
+                        variant,
+                        fields: args.iter().map(|arg| self.lower_expr(arg)).collect(),
+                    },
+                    // A function value.
+                    Some(Res::Local(_)) | None => {
+                        let callee = self.lower_expr(callee);
+                        let args = args.iter().map(|arg| self.lower_expr(arg)).collect();
+                        ExprKind::CallIndirect(Box::new(callee), args)
+                    }
+                }
+            }
+            ast::ExprKind::MethodCall(receiver, _, args) => {
+                let args = std::iter::once(receiver.as_ref())
+                    .chain(args)
+                    .map(|arg| self.lower_expr(arg))
+                    .collect();
+                let Res::Fn(id) = self.tcx.typeck_results.path_res[&expr.id] else {
+                    unreachable!("method calls resolve to methods")
+                };
+                self.lower_call(id, expr, args)
+            }
+            ast::ExprKind::Block(block) => return self.lower_block(block),
+            ast::ExprKind::Match(scrutinee, arms) => self.lower_match(scrutinee, arms),
+            ast::ExprKind::Assign(place, value) | ast::ExprKind::AssignOp(_, place, value) => {
+                let value = self.lower_expr(value);
+                let place_ty = self.node_ty(place.id);
+                let mut stmts = Vec::new();
+                let ExprKind::Place(place) = self.lower_place(place, &mut stmts).kind else {
+                    unreachable!("the type checker only accepts places")
+                };
+                // The value is still evaluated before the indices, unless
+                // it is a constant.
+                let mut block = Vec::with_capacity(stmts.len() + 1);
+                let value = if stmts.is_empty() || matches!(value.kind, ExprKind::Const(_)) {
+                    value
+                } else {
+                    self.temp("value", value, &mut block)
+                };
+                block.extend(stmts);
+                // `place op= value` assigns `place op value`, without
+                // computing the indices of the place again.
+                let value = match &expr.kind {
+                    ast::ExprKind::AssignOp(op, ..) => {
+                        let current = Expr {
+                            kind: ExprKind::Place(place.clone()),
+                            ty: place_ty.clone(),
+                        };
+                        let kind = match self.tcx.typeck_results.path_res.get(&expr.id) {
+                            Some(&Res::Fn(method)) => {
+                                self.lower_call(method, expr, vec![current, value])
+                            }
+                            _ => binary(self.mono.options, *op, Box::new(current), Box::new(value)),
+                        };
+                        Expr { kind, ty: place_ty }
+                    }
+                    _ => value,
+                };
+                let assign = ExprKind::Assign(place, Box::new(value));
+                if block.is_empty() {
+                    assign
+                } else {
+                    ExprKind::Block(block, Box::new(void(assign)))
+                }
+            }
+            ast::ExprKind::Field(..) | ast::ExprKind::Index(..) => {
+                let mut stmts = Vec::new();
+                let value = self.lower_place(expr, &mut stmts);
+                if stmts.is_empty() {
+                    return value;
+                }
+                ExprKind::Block(stmts, Box::new(value))
+            }
+            ast::ExprKind::Tuple(elems) => {
+                ExprKind::Tuple(elems.iter().map(|elem| self.lower_expr(elem)).collect())
+            }
+            ast::ExprKind::Array(elems) => {
+                ExprKind::Array(elems.iter().map(|elem| self.lower_expr(elem)).collect())
+            }
+            ast::ExprKind::Repeat(elem, len) => {
+                ExprKind::Repeat(Box::new(self.lower_expr(elem)), *len)
+            }
+            ast::ExprKind::Range(start, end, _) => {
+                let Ty::Adt(adt, _) = ty else {
+                    unreachable!("ranges are structs")
+                };
+                let end = end.as_ref().expect("ranges without an end are indices");
+                ExprKind::Adt {
+                    adt,
+                    variant: 0,
+                    fields: vec![self.lower_expr(start), self.lower_expr(end)],
+                }
+            }
+            ast::ExprKind::ForLoop(pat, iter, body) => self.lower_for_loop(pat, iter, body),
+            ast::ExprKind::Struct(lit) => return self.lower_struct_expr(expr, lit, ty),
+            ast::ExprKind::Closure(closure) => self.lower_closure(closure, &ty),
+            ast::ExprKind::Cast(operand, _) => {
+                let operand = self.lower_expr(operand);
+                // Casts to the same type do nothing.
+                if operand.ty == ty {
+                    return operand;
+                }
+                ExprKind::Cast(Box::new(operand))
+            }
+            ast::ExprKind::Try(operand) => self.lower_try(operand, &ty),
+            ast::ExprKind::Err => unreachable!("the module has type errors"),
+        };
+        Expr { kind, ty }
+    }
+
+    /// Lowers an expression which may be a place, putting the statements
+    /// which compute its indices, and check them, in `stmts`. A place may
+    /// then be read or written after them.
+    fn lower_place(&mut self, expr: &ast::Expr, stmts: &mut Vec<Stmt>) -> Expr {
+        let ty = self.node_ty(expr.id);
+        let kind = match &expr.kind {
+            ast::ExprKind::Field(base, _) => {
+                let index = self.tcx.typeck_results.field_indices[&expr.id];
+                let base = self.lower_place(base, stmts);
+                match base.kind {
+                    ExprKind::Place(place) => {
+                        ExprKind::Place(place.field(Field { variant: 0, index }))
+                    }
+                    _ => ExprKind::Field(Box::new(base), index),
+                }
+            }
+            ast::ExprKind::Index(base, index) => {
+                if self.is_range_index(index) {
+                    return self.lower_sub_slice(ty, base, index, stmts);
+                }
+                let base = self.lower_place(base, stmts);
+                let base_ty = base.ty.clone();
+                let place = match base.kind {
+                    ExprKind::Place(place) => place,
+                    _ => self.temp_place("base", base, stmts),
+                };
+                let index = self.lower_expr(index);
+                let index = match index.kind {
+                    ExprKind::Place(place) if place.projection.is_empty() => place.local,
+                    _ => self.temp_place("index", index, stmts).local,
+                };
+                if self.mono.options.bounds_checks {
+                    let len = match base_ty {
+                        Ty::Array(_, len) => ExprKind::Const(Const::Int(len.into())),
+                        _ => ExprKind::Len(Box::new(Expr {
+                            kind: ExprKind::Place(place.clone()),
+                            ty: base_ty,
+                        })),
+                    };
+                    let check = ExprKind::BoundsCheck {
+                        index: Box::new(Expr {
+                            kind: ExprKind::Place(Place {
+                                local: index,
+                                projection: Vec::new(),
+                            }),
+                            ty: Ty::Usize,
+                        }),
+                        len: Box::new(Expr {
+                            kind: len,
+                            ty: Ty::Usize,
+                        }),
+                    };
+                    stmts.push(Stmt::Expr(Expr {
+                        kind: check,
+                        ty: Ty::Void,
+                    }));
+                }
+                ExprKind::Place(place.index(index))
+            }
+            _ => return self.lower_expr(expr),
+        };
+        Expr { kind, ty }
+    }
+
+    /// Stores a value in a new local, returning the local as a place.
+    fn temp_place(&mut self, name: &str, value: Expr, stmts: &mut Vec<Stmt>) -> Place {
+        let local = self.new_local(Symbol::intern(name), value.ty.clone());
+        stmts.push(Stmt::Let(local, value));
+        Place {
+            local,
+            projection: Vec::new(),
+        }
+    }
+
+    /// Stores a value in a new local, returning an expression reading it.
+    fn temp(&mut self, name: &str, value: Expr, stmts: &mut Vec<Stmt>) -> Expr {
+        let ty = value.ty.clone();
+        Expr {
+            kind: ExprKind::Place(self.temp_place(name, value, stmts)),
+            ty,
+        }
+    }
+
+    /// Returns the generic arguments recorded for `callee`, a path or a
+    /// method call, in the instance being lowered.
+    fn fn_args(&self, callee: &ast::Expr) -> Vec<Ty> {
+        match self.tcx.typeck_results.node_args.get(&callee.id) {
+            Some(fn_args) => fn_args.iter().map(|ty| ty.subst(self.args)).collect(),
+            None => Vec::new(),
+        }
+    }
+
+    /// Returns the body of an instance used by `callee`.
+    fn instance(&mut self, fn_id: FnId, fn_args: Vec<Ty>, callee: &ast::Expr) -> BodyId {
+        let name = self.tcx.fn_sig(fn_id).name;
+        if fn_args.is_empty() {
+            self.mono.instance(fn_id, fn_args, 0, name, None)
+        } else {
+            let depth = self.depth + 1;
+            self.mono
+                .instance(fn_id, fn_args, depth, name, Some(callee.span))
+        }
+    }
+
+    /// Lowers a call of a function, whose generic arguments are recorded for
+    /// `callee`, the callee path or the method call.
+    fn lower_call(&mut self, fn_id: FnId, callee: &ast::Expr, args: Vec<Expr>) -> ExprKind {
+        match self.tcx.resolve_instance(fn_id, self.fn_args(callee)) {
+            Instance::Fn(fn_id, fn_args) => {
+                ExprKind::Call(self.instance(fn_id, fn_args, callee), args)
+            }
+            Instance::Virtual(_, index) => ExprKind::DynCall(index, args),
+            Instance::Intrinsic(fn_id) => lower_intrinsic(self.tcx, self.mono.options, fn_id, args),
+        }
+    }
+
+    /// Lowers a function used as a value to a closure capturing nothing.
+    fn lower_fn_value(&mut self, fn_id: FnId, expr: &ast::Expr) -> ExprKind {
+        let fn_args = self.fn_args(expr);
+        let body = match self.tcx.resolve_instance(fn_id, fn_args.clone()) {
+            Instance::Fn(fn_id, fn_args) => self.instance(fn_id, fn_args, expr),
+            instance => self.mono.shim(fn_id, fn_args, instance),
+        };
+        ExprKind::Closure {
+            body,
+            captures: Vec::new(),
+        }
+    }
+
+    /// Lowers a closure to a body of its own, which takes the captured
+    /// variables before the parameters of the closure.
+    fn lower_closure(&mut self, closure: &ast::Closure, ty: &Ty) -> ExprKind {
+        let captures = self.find_captures(closure);
+        let id = self.mono.reserve();
+        let index = self.closures;
+        let env: Vec<(NodeId, LocalDecl)> = captures
+            .iter()
+            .map(|&(node, by_ref)| {
+                let decl = &mut self.locals[self.local_ids[&node].as_usize()];
+                decl.shared |= by_ref;
+                let decl = LocalDecl {
+                    name: decl.name,
+                    ty: decl.ty.clone(),
+                    shared: by_ref,
+                };
+                (node, decl)
+            })
+            .collect();
+        let mut cx = LoweringCx {
+            tcx: self.tcx,
+            mono: &mut *self.mono,
+            fn_id: self.fn_id,
+            args: self.args,
+            depth: self.depth,
+            closures: index + 1,
+            ret_ty: match ty {
+                Ty::Fn(_, output) => (**output).clone(),
+                _ => unreachable!("closures are functions"),
+            },
+            locals: Vec::new(),
+            local_ids: HashMap::new(),
+        };
+        let mut params = Vec::new();
+        for (node, decl) in env {
+            cx.locals.push(decl);
+            let local = LocalId::from_usize(cx.locals.len() - 1);
+            cx.local_ids.insert(node, local);
+            params.push(local);
+        }
+        for param in &closure.params {
+            params.push(cx.declare(param.id, param.ident.name));
+        }
+        let value = cx.lower_expr(&closure.body);
+        let (locals, closures) = (cx.locals, cx.closures);
+        self.closures = closures;
+        self.mono.bodies[id.as_usize()] = Some(Body {
+            fn_id: self.fn_id,
+            name: self.tcx.fn_sig(self.fn_id).name,
+            args: self.args.to_vec(),
+            closure: Some(index),
+            params,
+            locals,
+            value,
+        });
+        let captures = captures
+            .into_iter()
+            .map(|(node, by_ref)| Capture {
+                local: self.local_ids[&node],
+                by_ref,
+            })
+            .collect();
+        ExprKind::Closure { body: id, captures }
+    }
+
+    /// Finds the variables of the enclosing body a closure uses, in the
+    /// order of their first use, and whether the closure assigns to them.
+    fn find_captures(&self, closure: &ast::Closure) -> Vec<(NodeId, bool)> {
+        struct Captures<'a> {
+            tcx: &'a Tcx,
+            outer: &'a HashMap<NodeId, LocalId>,
+            captures: Vec<(NodeId, bool)>,
+        }
+        impl Captures<'_> {
+            fn capture(&mut self, expr: &ast::Expr, by_ref: bool) {
+                let results = &self.tcx.typeck_results;
+                let Some(&Res::Local(id)) = results.path_res.get(&expr.id) else {
+                    return;
+                };
+                let id = results.binding_aliases.get(&id).copied().unwrap_or(id);
+                if !self.outer.contains_key(&id) {
+                    return;
+                }
+                match self.captures.iter_mut().find(|(node, _)| *node == id) {
+                    Some((_, shared)) => *shared |= by_ref,
+                    None => self.captures.push((id, by_ref)),
+                }
+            }
+        }
+        impl<'ast> Visitor<'ast> for Captures<'_> {
+            fn visit_expr(&mut self, expr: &'ast ast::Expr) {
+                visit::walk_expr(self, expr);
+                match &expr.kind {
+                    ast::ExprKind::Path(_) => self.capture(expr, false),
+                    ast::ExprKind::Assign(place, _) | ast::ExprKind::AssignOp(_, place, _) => {
+                        let mut place = &**place;
+                        while let ast::ExprKind::Field(base, _) | ast::ExprKind::Index(base, _) =
+                            &place.kind
+                        {
+                            place = base;
+                        }
+                        self.capture(place, true);
+                    }
+                    _ => (),
+                }
+            }
+        }
+
+        let mut captures = Captures {
+            tcx: self.tcx,
+            outer: &self.local_ids,
+            captures: Vec::new(),
+        };
+        captures.visit_expr(&closure.body);
+        captures.captures
+    }
+
+    /// Lowers a binary operator on operands which aren't primitive to a call
+    /// of the method of its trait: `a != b` is `!Eq::eq(a, b)`, `a > b` is
+    /// `Ord::lt(b, a)`, `a <= b` is `!Ord::lt(b, a)` and `a >= b` is
+    /// `!Ord::lt(a, b)`.
+    fn lower_overloaded_binary(
+        &mut self,
+        expr: &ast::Expr,
+        op: BinOp,
+        method: FnId,
+        lhs: Expr,
+        rhs: Expr,
+    ) -> Expr {
+        let mut stmts = Vec::new();
+        let args = if matches!(op, BinOp::Gt | BinOp::Le) {
+            let is_pure =
+                |expr: &Expr| matches!(expr.kind, ExprKind::Place(_) | ExprKind::Const(_));
+            if is_pure(&lhs) && is_pure(&rhs) {
+                vec![rhs, lhs]
+            } else {
+                // The operands are still evaluated from left to right.
+                let lhs = self.temp("lhs", lhs, &mut stmts);
+                let rhs = self.temp("rhs", rhs, &mut stmts);
+                vec![rhs, lhs]
+            }
+        } else {
+            vec![lhs, rhs]
+        };
+        let ty = self.node_ty(expr.id);
+        let call = Expr {
+            kind: self.lower_call(method, expr, args),
+            ty: ty.clone(),
+        };
+        let value = match op {
+            BinOp::Ne | BinOp::Le | BinOp::Ge => Expr {
+                kind: ExprKind::Unary(UnOp::Not, Box::new(call)),
+                ty: ty.clone(),
+            },
+            _ => call,
+        };
+        if stmts.is_empty() {
+            return value;
+        }
+        Expr {
+            kind: ExprKind::Block(stmts, Box::new(value)),
+            ty,
+        }
+    }
+
+    /// Lowers a struct literal. Its fields are evaluated in the order they
+    /// are written, which may differ from the declaration order.
+    fn lower_struct_expr(&mut self, expr: &ast::Expr, lit: &ast::StructExpr, ty: Ty) -> Expr {
+        let Res::Variant(adt, variant) = self.tcx.typeck_results.path_res[&expr.id] else {
+            unreachable!()
+        };
+        let variant_def = &self.tcx.adt(adt).variants[variant];
+        let indices: Vec<usize> = lit
+            .fields
+            .iter()
+            .map(|field| variant_def.field_index(field.ident.name).unwrap())
+            .collect();
+        let mut values: Vec<Expr> = lit
+            .fields
+            .iter()
+            .map(|field| self.lower_expr(&field.expr))
+            .collect();
+        if indices.iter().enumerate().all(|(i, index)| i == *index) {
+            return Expr {
+                kind: ExprKind::Adt {
+                    adt,
+                    variant,
+                    fields: values,
+                },
+                ty,
+            };
+        }
+        // Otherwise each value goes through a temporary.
+        let mut stmts = Vec::with_capacity(values.len());
+        let mut fields: Vec<Option<Expr>> = (0..values.len()).map(|_| None).collect();
+        for (value, index) in values.drain(..).zip(indices) {
+            let name = self.tcx.adt(adt).variants[variant].fields[index].name;
+            let value_ty = value.ty.clone();
+            let local = self.new_local(name, value_ty.clone());
+            stmts.push(Stmt::Let(local, value));
+            fields[index] = Some(Expr {
+                kind: ExprKind::Place(Place {
+                    local,
+                    projection: Vec::new(),
+                }),
+                ty: value_ty,
+            });
+        }
+        let value = Expr {
+            kind: ExprKind::Adt {
+                adt,
+                variant,
+                fields: fields.into_iter().map(Option::unwrap).collect(),
+            },
+            ty: ty.clone(),
+        };
+        Expr {
+            kind: ExprKind::Block(stmts, Box::new(value)),
+            ty,
+        }
+    }
+
+    fn lower_match(&mut self, scrutinee: &ast::Expr, arms: &[ast::Arm]) -> ExprKind {
+        let scrutinee = self.lower_expr(scrutinee);
+        let local = self.new_local(Symbol::intern("scrutinee"), scrutinee.ty.clone());
+        let mut pats = Vec::with_capacity(arms.len());
+        let mut ir_arms = Vec::with_capacity(arms.len());
+        for arm in arms {
+            self.declare_bindings(&arm.pat);
+            pats.push(self.lower_pat(&arm.pat));
+            ir_arms.push(ir::Arm {
+                guard: arm.guard.as_ref().map(|guard| self.lower_expr(guard)),
+                body: self.lower_expr(&arm.body),
+            });
+        }
+        let tree = decision::compile(self.tcx, local, &scrutinee.ty, pats, &ir_arms);
+        ExprKind::Match {
+            scrutinee: Box::new(scrutinee),
+            local,
+            tree,
+            arms: ir_arms,
+        }
+    }
+
+    /// Lowers `for pat in iter { body }`.
+    fn lower_for_loop(&mut self, pat: &ast::Pat, iter: &ast::Expr, body: &ast::Block) -> ExprKind {
+        let iter_ty = self.node_ty(iter.id);
+        let lang = self.tcx.lang;
+        let mut stmts = Vec::new();
+        let value = match iter_ty {
+            Ty::Adt(adt, args) if adt == lang.range || adt == lang.range_inclusive => {
+                let ty = args[0].clone();
+                // The range is only built if it comes from elsewhere.
+                let (start, end) = match &iter.kind {
+                    ast::ExprKind::Range(start, Some(end), _) => {
+                        (self.lower_expr(start), self.lower_expr(end))
+                    }
+                    _ => {
+                        let range = self.lower_expr(iter);
+                        let range = self.temp_place("range", range, &mut stmts);
+                        let field = |index| Expr {
+                            kind: ExprKind::Place(range.field(Field { variant: 0, index })),
+                            ty: ty.clone(),
+                        };
+                        (field(0), field(1))
+                    }
+                };
+                let i = self.temp_place("i", start, &mut stmts).local;
+                let end = self.temp_place("end", end, &mut stmts).local;
+                let inclusive = adt == lang.range_inclusive;
+                self.lower_range_loop(pat, body, i, end, ty, inclusive)
+            }
+            _ => {
+                let seq = self.lower_expr(iter);
+                let seq = self.temp_place("seq", seq, &mut stmts).local;
+                self.lower_seq_loop(pat, body, seq, iter_ty, &mut stmts)
+            }
+        };
+        ExprKind::Block(stmts, Box::new(value))
+    }
+
+    /// Lowers a loop over the integers from `i` up to `end`, which `i`
+    /// counts through. It is
+    /// `loop { if i < end { body; i = i + 1 } else { break } }`, and for an
+    /// inclusive range, which may end at the largest integer of its type,
+    /// `if i <= end { loop { body; if i == end { break } else { i = i + 1 } } }`.
+    /// The increment can't overflow, so it isn't checked.
+    fn lower_range_loop(
+        &mut self,
+        pat: &ast::Pat,
+        body: &ast::Block,
+        i: LocalId,
+        end: LocalId,
+        ty: Ty,
+        inclusive: bool,
+    ) -> Expr {
+        let compare = |op| Expr {
+            kind: ExprKind::Binary(
+                op,
+                Box::new(read(i, ty.clone())),
+                Box::new(read(end, ty.clone())),
+            ),
+            ty: Ty::Bool,
+        };
+        let one = Expr {
+            kind: ExprKind::Const(Const::Int(1)),
+            ty: ty.clone(),
+        };
+        let next = ExprKind::Overflowing(
+            Overflow::Wrap,
+            BinOp::Add,
+            Box::new(read(i, ty.clone())),
+            Box::new(one),
+        );
+        let step = void(ExprKind::Assign(
+            Place {
+                local: i,
+                projection: Vec::new(),
+            },
+            Box::new(Expr {
+                kind: next,
+                ty: ty.clone(),
+            }),
+        ));
+        let item = self.bind_item(pat, read(i, ty.clone()), body);
+        if inclusive {
+            let last = void(ExprKind::If(
+                Box::new(compare(BinOp::Eq)),
+                Box::new(void(ExprKind::Break)),
+                Box::new(step),
+            ));
+            let iteration = void(ExprKind::Block(item, Box::new(last)));
+            void(ExprKind::If(
+                Box::new(compare(BinOp::Le)),
+                Box::new(void(ExprKind::Loop(Box::new(iteration)))),
+                Box::new(void(ExprKind::Const(Const::Void))),
+            ))
+        } else {
+            let iteration = void(ExprKind::Block(item, Box::new(step)));
+            void(ExprKind::Loop(Box::new(void(ExprKind::If(
+                Box::new(compare(BinOp::Lt)),
+                Box::new(iteration),
+                Box::new(void(ExprKind::Break)),
+            )))))
+        }
+    }
+
+    /// Lowers `base[index]` where `index` is a range, which is the slice of
+    /// the elements in the range. `base[start..]` has no end, and slices up
+    /// to the end of `base`.
+    /// Whether `index` is a range, with which `base[index]` is a slice.
+    fn is_range_index(&self, index: &ast::Expr) -> bool {
+        let lang = self.tcx.lang;
+        match index.kind {
+            // Its type is only recorded if it has an end.
+            ast::ExprKind::Range(..) => true,
+            _ => matches!(
+                self.node_ty(index.id),
+                Ty::Adt(adt, _) if adt == lang.range || adt == lang.range_inclusive
+            ),
+        }
+    }
+
+    fn lower_sub_slice(
+        &mut self,
+        ty: Ty,
+        base: &ast::Expr,
+        index: &ast::Expr,
+        stmts: &mut Vec<Stmt>,
+    ) -> Expr {
+        let base = self.lower_expr(base);
+        let (base, start, end, inclusive) = match &index.kind {
+            ast::ExprKind::Range(start, end, limits) => (
+                base,
+                self.lower_expr(start),
+                end.as_ref().map(|end| self.lower_expr(end)),
+                *limits == ast::RangeLimits::Closed,
+            ),
+            // The base is evaluated before the range which comes from
+            // elsewhere.
+            _ => {
+                let base = self.temp("base", base, stmts);
+                let Ty::Adt(adt, _) = self.node_ty(index.id) else {
+                    unreachable!("only ranges slice")
+                };
+                let range = self.lower_expr(index);
+                let range = self.temp_place("range", range, stmts);
+                let field = |index| Expr {
+                    kind: ExprKind::Place(range.field(Field { variant: 0, index })),
+                    ty: Ty::Usize,
+                };
+                (
+                    base,
+                    field(0),
+                    Some(field(1)),
+                    adt == self.tcx.lang.range_inclusive,
+                )
+            }
+        };
+        Expr {
+            kind: ExprKind::SubSlice {
+                base: Box::new(base),
+                start: Box::new(start),
+                end: end.map(Box::new),
+                inclusive,
+            },
+            ty,
+        }
+    }
+
+    /// Lowers a loop over the elements of the array or slice `seq`, of type
+    /// `ty`, with the iterator protocol:
+    /// `loop { match next { Some(pat) => body, None => break } }`, where
+    /// `next` is
+    /// `if index < len(seq) { let item = seq[index]; index = index + 1; Some(item) } else { None }`.
+    /// `index` is initialized in `stmts`.
+    fn lower_seq_loop(
+        &mut self,
+        pat: &ast::Pat,
+        body: &ast::Block,
+        seq: LocalId,
+        ty: Ty,
+        stmts: &mut Vec<Stmt>,
+    ) -> Expr {
+        let (Ty::Array(elem, _) | Ty::Slice(elem)) = &ty else {
+            unreachable!("only ranges, arrays and slices are iterated over")
+        };
+        let elem = (**elem).clone();
+        let option_ty = Ty::Adt(self.tcx.lang.option, vec![elem.clone()]);
+        let zero = Expr {
+            kind: ExprKind::Const(Const::Int(0)),
+            ty: Ty::Usize,
+        };
+        let index = self.new_local(Symbol::intern("index"), Ty::Usize);
+        stmts.push(Stmt::Let(index, zero));
+        let len = match ty {
+            Ty::Array(_, len) => ExprKind::Const(Const::Int(len.into())),
+            _ => ExprKind::Len(Box::new(read(seq, ty.clone()))),
+        };
+        let in_bounds = Expr {
+            kind: ExprKind::Binary(
+                BinOp::Lt,
+                Box::new(read(index, Ty::Usize)),
+                Box::new(Expr {
+                    kind: len,
+                    ty: Ty::Usize,
+                }),
+            ),
+            ty: Ty::Bool,
+        };
+
+        // The next element, which is in bounds.
+        let mut next_stmts = Vec::new();
+        let seq_place = Place {
+            local: seq,
+            projection: Vec::new(),
+        };
+        let item = Expr {
+            kind: ExprKind::Place(seq_place.index(index)),
+            ty: elem.clone(),
+        };
+        let item = self.temp("item", item, &mut next_stmts);
+        let one = Expr {
+            kind: ExprKind::Const(Const::Int(1)),
+            ty: Ty::Usize,
+        };
+        let next_index = Expr {
+            kind: ExprKind::Overflowing(
+                Overflow::Wrap,
+                BinOp::Add,
+                Box::new(read(index, Ty::Usize)),
+                Box::new(one),
+            ),
+            ty: Ty::Usize,
+        };
+        next_stmts.push(Stmt::Expr(void(ExprKind::Assign(
+            Place {
+                local: index,
+                projection: Vec::new(),
+            },
+            Box::new(next_index),
+        ))));
+        let option = |variant, fields| Expr {
+            kind: ExprKind::Adt {
+                adt: self.tcx.lang.option,
+                variant,
+                fields,
+            },
+            ty: option_ty.clone(),
+        };
+        let some = Expr {
+            kind: ExprKind::Block(next_stmts, Box::new(option(0, vec![item]))),
+            ty: option_ty.clone(),
+        };
+        let next = Expr {
+            kind: ExprKind::If(
+                Box::new(in_bounds),
+                Box::new(some),
+                Box::new(option(1, Vec::new())),
+            ),
+            ty: option_ty.clone(),
+        };
+
+        let local = self.new_local(Symbol::intern("next"), option_ty.clone());
+        self.declare_bindings(pat);
+        let pats = vec![
+            decision::Pat::Variant(self.tcx.lang.option, 0, vec![self.lower_pat(pat)]),
+            decision::Pat::Variant(self.tcx.lang.option, 1, Vec::new()),
+        ];
+        let arms = vec![
+            ir::Arm {
+                guard: None,
+                body: self.lower_block(body),
+            },
+            ir::Arm {
+                guard: None,
+                body: void(ExprKind::Break),
+            },
+        ];
+        let tree = decision::compile(self.tcx, local, &option_ty, pats, &arms);
+        let iteration = void(ExprKind::Match {
+            scrutinee: Box::new(next),
+            local,
+            tree,
+            arms,
+        });
+        void(ExprKind::Loop(Box::new(iteration)))
+    }
+
+    /// Returns the statements binding the pattern of a `for` loop to an item
+    /// and running the body of the loop. A variable is bound directly, and
+    /// other patterns, which can't fail, with a `match`.
+    fn bind_item(&mut self, pat: &ast::Pat, item: Expr, body: &ast::Block) -> Vec<Stmt> {
+        self.declare_bindings(pat);
+        match &pat.kind {
+            ast::PatKind::Ident(..) => {
+                let local = self.local(pat.id);
+                let body = self.lower_block(body);
+                vec![Stmt::Let(local, item), Stmt::Expr(body)]
+            }
+            ast::PatKind::Wild => vec![Stmt::Expr(self.lower_block(body))],
+            _ => {
+                let arm = ir::Arm {
+                    guard: None,
+                    body: self.lower_block(body),
+                };
+                vec![Stmt::Expr(self.match_irrefutable("item", pat, item, arm))]
+            }
+        }
+    }
+
+    /// Lowers the match of a value, stored in a local named `name`, against
+    /// a pattern which can't fail and whose variables have been declared,
+    /// running `arm` afterwards.
+    fn match_irrefutable(&mut self, name: &str, pat: &ast::Pat, value: Expr, arm: ir::Arm) -> Expr {
+        let local = self.new_local(Symbol::intern(name), value.ty.clone());
+        let arms = vec![arm];
+        let pats = vec![self.lower_pat(pat)];
+        let tree = decision::compile(self.tcx, local, &value.ty, pats, &arms);
+        Expr {
+            ty: arms[0].body.ty.clone(),
+            kind: ExprKind::Match {
+                scrutinee: Box::new(value),
+                local,
+                tree,
+                arms,
+            },
+        }
+    }
+
+    /// Lowers `operand?` of type `ty` to a `match` which evaluates to the
+    /// value in a `Some` or an `Ok`, and returns `None` or the `Err` from the
+    /// body otherwise.
+    fn lower_try(&mut self, operand: &ast::Expr, ty: &Ty) -> ExprKind {
+        let scrutinee = self.lower_expr(operand);
+        let Ty::Adt(adt, _) = scrutinee.ty else {
+            unreachable!("`?` is applied to an `Option` or a `Result`")
+        };
+        let local = self.new_local(Symbol::intern("scrutinee"), scrutinee.ty.clone());
+        let place = Place {
+            local,
+            projection: Vec::new(),
+        };
+        // `Some` and `Ok` come first, `None` and `Err` second.
+        let (value, residual) = (0, 1);
+        let fields = if adt == self.tcx.lang.option {
+            Vec::new()
+        } else {
+            let field = Field {
+                variant: residual,
+                index: 0,
+            };
+            vec![Expr {
+                kind: ExprKind::Place(place.field(field)),
+                ty: field_tys(self.tcx, &scrutinee.ty, residual).remove(0),
+            }]
+        };
+        let residual_value = Expr {
+            kind: ExprKind::Adt {
+                adt,
+                variant: residual,
+                fields,
+            },
+            ty: self.ret_ty.clone(),
+        };
+        let arms = vec![
+            ir::Arm {
+                guard: None,
+                body: Expr {
+                    kind: ExprKind::Place(place.field(Field {
+                        variant: value,
+                        index: 0,
+                    })),
+                    ty: ty.clone(),
+                },
+            },
+            ir::Arm {
+                guard: None,
+                body: Expr {
+                    kind: ExprKind::Return(Box::new(residual_value)),
+                    ty: ty.clone(),
+                },
+            },
+        ];
+        let leaf = |arm| ir::Decision::Leaf {
+            arm,
+            bindings: Vec::new(),
+        };
+        let tree = ir::Decision::Switch {
+            place,
+            cases: vec![
+                (ir::Case::Variant(value), leaf(0)),
+                (ir::Case::Variant(residual), leaf(1)),
+            ],
+            default: None,
+        };
+        ExprKind::Match {
+            scrutinee: Box::new(scrutinee),
+            local,
+            tree,
+            arms,
+        }
+    }
+
+    /// Creates the locals for the variables a pattern binds.
+    fn declare_bindings(&mut self, pat: &ast::Pat) {
+        match &pat.kind {
+            ast::PatKind::Ident(_, ident) => {
+                if !self
+                    .tcx
+                    .typeck_results
+                    .binding_aliases
+                    .contains_key(&pat.id)
+                {
+                    self.declare(pat.id, ident.name);
+                }
+            }
+            ast::PatKind::Wild | ast::PatKind::Lit(_) | ast::PatKind::Path(_) => (),
+            ast::PatKind::Tuple(pats)
+            | ast::PatKind::TupleStruct(_, pats)
+            | ast::PatKind::Or(pats) => {
+                for pat in pats {
+                    self.declare_bindings(pat);
+                }
+            }
+            ast::PatKind::Struct(_, fields, _) => {
+                for field in fields {
+                    self.declare_bindings(&field.pat);
+                }
+            }
+        }
+    }
+
+    fn lower_pat(&self, pat: &ast::Pat) -> decision::Pat {
+        use decision::Pat;
+        match &pat.kind {
+            ast::PatKind::Wild => Pat::Wild,
+            ast::PatKind::Ident(..) => Pat::Binding(self.local(pat.id)),
+            ast::PatKind::Lit(expr) => Pat::Const(lower_lit_pat(expr)),
+            ast::PatKind::Tuple(pats) => {
+                Pat::Tuple(pats.iter().map(|pat| self.lower_pat(pat)).collect())
+            }
+            ast::PatKind::Path(_) | ast::PatKind::TupleStruct(..) | ast::PatKind::Struct(..) => {
+                let Res::Variant(adt, variant) = self.tcx.typeck_results.path_res[&pat.id] else {
+                    unreachable!()
+                };
+                let variant_def = &self.tcx.adt(adt).variants[variant];
+                let mut fields = vec![Pat::Wild; variant_def.fields.len()];
+                match &pat.kind {
+                    ast::PatKind::TupleStruct(_, pats) => {
+                        for (field, pat) in fields.iter_mut().zip(pats) {
+                            *field = self.lower_pat(pat);
+                        }
+                    }
+                    ast::PatKind::Struct(_, pat_fields, _) => {
+                        for field in pat_fields {
+                            let index = variant_def.field_index(field.ident.name).unwrap();
+                            fields[index] = self.lower_pat(&field.pat);
+                        }
+                    }
+                    _ => debug_assert_eq!(variant_def.ctor_kind, CtorKind::Const),
+                }
+                Pat::Variant(adt, variant, fields)
+            }
+            ast::PatKind::Or(alts) => Pat::Or(alts.iter().map(|alt| self.lower_pat(alt)).collect()),
+        }
+    }
+}
+
+/// Lowers a call of a method of a built-in trait on a primitive type to the
+/// operator the method stands for, or of `len` on an array or a slice.
+fn lower_intrinsic(tcx: &Tcx, options: Options, fn_id: FnId, args: Vec<Expr>) -> ExprKind {
+    let mut args = args.into_iter().map(Box::new);
+    let lhs = args.next().unwrap();
+    if fn_id == tcx.neg_method() {
+        return neg(options, lhs);
+    }
+    if fn_id == tcx.trait_def(tcx.lang.len).methods[0] {
+        return ExprKind::Len(lhs);
+    }
+    let ops = [BinOp::Add, BinOp::Sub, BinOp::Mul, BinOp::Div];
+    for (trait_id, overflow) in [
+        (tcx.lang.wrapping, Overflow::Wrap),
+        (tcx.lang.checked, Overflow::Check),
+    ] {
+        let methods = &tcx.trait_def(trait_id).methods;
+        if let Some(index) = methods.iter().position(|&method| method == fn_id) {
+            return ExprKind::Overflowing(overflow, ops[index], lhs, args.next().unwrap());
+        }
+    }
+    let op = [
+        BinOp::Add,
+        BinOp::Sub,
+        BinOp::Mul,
+        BinOp::Div,
+        BinOp::Rem,
+        BinOp::BitAnd,
+        BinOp::BitOr,
+        BinOp::BitXor,
+        BinOp::Shl,
+        BinOp::Shr,
+        BinOp::Eq,
+        BinOp::Lt,
+    ]
+    .into_iter()
+    .find(|&op| tcx.binop_method(op) == fn_id)
+    .unwrap();
+    binary(options, op, lhs, args.next().unwrap())
+}
+
+/// Lowers an operator on primitive operands. Integer arithmetic and shifts
+/// wrap around on overflow when overflow checks are disabled, except for
+/// division and remainder, whose overflow always panics.
+fn binary(options: Options, op: BinOp, lhs: Box<Expr>, rhs: Box<Expr>) -> ExprKind {
+    let may_wrap = matches!(
+        op,
+        BinOp::Add | BinOp::Sub | BinOp::Mul | BinOp::Shl | BinOp::Shr
+    );
+    if !options.overflow_checks && may_wrap && lhs.ty.is_integral() {
+        ExprKind::Overflowing(Overflow::Wrap, op, lhs, rhs)
+    } else {
+        ExprKind::Binary(op, lhs, rhs)
+    }
+}
+
+/// Lowers unary `-` on a primitive operand, which is `0 - operand` when
+/// integers wrap around on overflow.
+fn neg(options: Options, operand: Box<Expr>) -> ExprKind {
+    if !options.overflow_checks && operand.ty.is_integral() {
+        let zero = Expr {
+            kind: ExprKind::Const(Const::Int(0)),
+            ty: operand.ty.clone(),
+        };
+        ExprKind::Overflowing(Overflow::Wrap, BinOp::Sub, Box::new(zero), operand)
+    } else {
+        ExprKind::Unary(UnOp::Neg, operand)
+    }
+}
+
+fn lower_lit(lit: &ast::LiteralKind) -> Const {
+    match lit {
+        ast::LiteralKind::Str(s) => Const::Str(*s),
+        ast::LiteralKind::Char(c) => Const::Char(*c),
+        // Integers above `i128::MAX` wrap around, like `u128` values cast
+        // to `i128`.
+        ast::LiteralKind::Int(n, _) => Const::Int(*n as i128),
+        ast::LiteralKind::Float(f, _) => Const::Float(*f),
+        ast::LiteralKind::Bool(b) => Const::Bool(*b),
+    }
+}
+
+/// Evaluates a literal, possibly negated, e.g. of a pattern.
+fn lower_lit_pat(expr: &ast::Expr) -> Const {
+    match &expr.kind {
+        ast::ExprKind::Literal(lit) => lower_lit(lit),
+        ast::ExprKind::Unary(ast::UnOp::Neg, operand) => match lower_lit_pat(operand) {
+            Const::Int(n) => Const::Int(n.wrapping_neg()),
+            _ => unreachable!("only integers can be negated in patterns"),
+        },
+        _ => unreachable!("not a literal pattern"),
+    }
+}
+
+/// Reads a local of type `ty`.
+fn read(local: LocalId, ty: Ty) -> Expr {
+    Expr {
+        kind: ExprKind::Place(Place {
+            local,
+            projection: Vec::new(),
+        }),
+        ty,
+    }
+}
+
+/// An expression which produces no value, like an assignment or a loop.
+fn void(kind: ExprKind) -> Expr {
+    Expr { kind, ty: Ty::Void }
+}
+
+/// Returns the types of the fields of a variant of the struct or enum `ty`,
+/// or of the elements of the tuple `ty`.
+pub fn field_tys(tcx: &Tcx, ty: &Ty, variant: usize) -> Vec<Ty> {
+    match ty {
+        Ty::Adt(adt, args) => tcx.adt(*adt).variants[variant]
+            .fields
+            .iter()
+            .map(|field| field.ty.subst(args))
+            .collect(),
+        Ty::Tuple(elems) => elems.clone(),
+        _ => unreachable!("only ADTs and tuples have fields"),
+    }
+}
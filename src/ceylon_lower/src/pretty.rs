@@ -0,0 +1,562 @@
+//! Prints the IR in a syntax close to the source, for `--emit=ir` and
+//! tests. Locals are written `_N` after their index, places inside enums
+//! `(_N as Variant).field` and decision trees as nested `switch`es. Closures
+//! are named after their function, e.g. `main::{closure#0}`, and functions
+//! marked `@inline` keep the attribute.
+
+use ceylon_typeck::ty::{AdtKind, FnContainer};
+
+use super::*;
+use ir::{Arm, Case, Decision, Program, Projection, Vtable};
+
+pub fn program_to_string(tcx: &Tcx, program: &Program) -> String {
+    let mut printer = Printer {
+        tcx,
+        program,
+        body: None,
+        out: String::new(),
+        indent: 0,
+    };
+    for body in &program.bodies {
+        printer.body(body);
+    }
+    for vtable in &program.vtables {
+        printer.vtable(vtable);
+    }
+    printer.out
+}
+
+struct Printer<'a> {
+    tcx: &'a Tcx,
+    program: &'a Program,
+    body: Option<&'a Body>,
+    out: String,
+    indent: usize,
+}
+
+impl<'a> Printer<'a> {
+    fn newline(&mut self) {
+        self.out.push('\n');
+        for _ in 0..self.indent {
+            self.out.push_str("    ");
+        }
+    }
+
+    fn ty(&self, ty: &Ty) -> String {
+        self.tcx.ty_to_string(ty)
+    }
+
+    /// Formats the name of a body, e.g. `max::<i64>` for an instance of a
+    /// generic function, `Circle::new` for a method of an inherent impl or
+    /// `<Circle as Shape>::area` for a method of a trait.
+    fn body_name(&self, body: &Body) -> String {
+        let (prefix, args) = match self.tcx.fn_sig(body.fn_id).container {
+            FnContainer::Free => (String::new(), &body.args[..]),
+            FnContainer::Trait(id) => (
+                format!(
+                    "<{} as {}>::",
+                    self.ty(&body.args[0]),
+                    self.tcx.trait_def(id).name
+                ),
+                &body.args[1..],
+            ),
+            FnContainer::Impl(id) => {
+                let imp = self.tcx.impl_def(id);
+                let (impl_args, args) = body.args.split_at(imp.generics.params.len());
+                let self_ty = self.ty(&imp.self_ty.subst(impl_args));
+                let prefix = match imp.trait_ref {
+                    Some(trait_id) => {
+                        format!("<{} as {}>::", self_ty, self.tcx.trait_def(trait_id).name)
+                    }
+                    None => format!("{}::", self_ty),
+                };
+                (prefix, args)
+            }
+        };
+        let name = if args.is_empty() {
+            format!("{}{}", prefix, body.name)
+        } else {
+            let args: Vec<String> = args.iter().map(|arg| self.ty(arg)).collect();
+            format!("{}{}::<{}>", prefix, body.name, args.join(", "))
+        };
+        match body.closure {
+            Some(index) => format!("{}::{{closure#{}}}", name, index),
+            None => name,
+        }
+    }
+
+    fn vtable(&mut self, vtable: &Vtable) {
+        self.out += &format!(
+            "vtable <{} as {}> {{",
+            self.ty(&vtable.ty),
+            self.tcx.trait_def(vtable.trait_id).name
+        );
+        self.indent += 1;
+        for &method in &vtable.methods {
+            self.newline();
+            self.out += &format!("{},", self.body_name(self.program.body(method)));
+        }
+        self.indent -= 1;
+        self.newline();
+        self.out += "}\n";
+    }
+
+    fn body(&mut self, body: &'a Body) {
+        self.body = Some(body);
+        let params: Vec<String> = body
+            .params
+            .iter()
+            .map(|param| {
+                let local = &body.locals[param.as_usize()];
+                format!(
+                    "{}_{}: {}",
+                    if local.shared { "shared " } else { "" },
+                    param.as_usize(),
+                    self.ty(&local.ty)
+                )
+            })
+            .collect();
+        if body.closure.is_none() && self.tcx.fn_sig(body.fn_id).inline {
+            self.out += "@inline ";
+        }
+        self.out += &format!(
+            "fn {}({}) -> {} {{",
+            self.body_name(body),
+            params.join(", "),
+            self.ty(&body.value.ty)
+        );
+        self.indent += 1;
+        for (i, local) in body.locals.iter().enumerate() {
+            if body.params.contains(&LocalId::from_usize(i)) {
+                continue;
+            }
+            self.newline();
+            self.out += &format!(
+                "let {}_{}: {}; // {}",
+                if local.shared { "shared " } else { "" },
+                i,
+                self.ty(&local.ty),
+                local.name
+            );
+        }
+        match &body.value.kind {
+            ExprKind::Block(stmts, value) => self.block_contents(stmts, value),
+            _ => {
+                self.newline();
+                self.expr(&body.value);
+            }
+        }
+        self.indent -= 1;
+        self.newline();
+        self.out += "}\n";
+    }
+
+    fn block_contents(&mut self, stmts: &[Stmt], value: &Expr) {
+        for stmt in stmts {
+            self.newline();
+            match stmt {
+                Stmt::Let(local, init) => {
+                    self.out += &format!("_{} = ", local.as_usize());
+                    self.expr(init);
+                }
+                Stmt::Expr(expr) => self.expr(expr),
+            }
+            self.out.push(';');
+        }
+        if !matches!(value.kind, ExprKind::Const(Const::Void)) {
+            self.newline();
+            self.expr(value);
+        }
+    }
+
+    fn expr(&mut self, expr: &Expr) {
+        match &expr.kind {
+            ExprKind::Const(value) => self.out += &const_to_string(value),
+            ExprKind::Place(place) => self.out += &self.place(place),
+            ExprKind::Field(base, index) => {
+                self.out.push('(');
+                self.expr(base);
+                match base.ty {
+                    Ty::Adt(adt, _) => {
+                        let name = self.tcx.adt(adt).variants[0].fields[*index].name;
+                        self.out += &format!(").{}", name);
+                    }
+                    _ => self.out += &format!(").{}", index),
+                }
+            }
+            ExprKind::Unary(op, operand) => {
+                self.out.push(match op {
+                    UnOp::Neg => '-',
+                    UnOp::Not => '!',
+                    UnOp::BitNot => '~',
+                });
+                self.expr(operand);
+            }
+            ExprKind::Cast(operand) => {
+                self.out.push('(');
+                self.expr(operand);
+                self.out += &format!(" as {})", self.ty(&expr.ty));
+            }
+            ExprKind::Binary(op, lhs, rhs) => {
+                self.out.push('(');
+                self.expr(lhs);
+                self.out += &format!(" {} ", binop_to_str(*op));
+                self.expr(rhs);
+                self.out.push(')');
+            }
+            ExprKind::Overflowing(overflow, op, lhs, rhs) => {
+                let prefix = match overflow {
+                    Overflow::Wrap => "wrapping",
+                    Overflow::Check => "checked",
+                };
+                let name = match op {
+                    BinOp::Add => "add",
+                    BinOp::Sub => "sub",
+                    BinOp::Mul => "mul",
+                    BinOp::Div => "div",
+                    BinOp::Shl => "shl",
+                    BinOp::Shr => "shr",
+                    _ => unreachable!("`{:?}` can't overflow", op),
+                };
+                self.out += &format!("{}_{}(", prefix, name);
+                self.expr(lhs);
+                self.out += ", ";
+                self.expr(rhs);
+                self.out.push(')');
+            }
+            ExprKind::Call(id, args) => {
+                self.out += &format!("{}(", self.body_name(self.program.body(*id)));
+                self.comma_separated(args);
+                self.out.push(')');
+            }
+            ExprKind::CallIndirect(callee, args) => {
+                self.out.push('(');
+                self.expr(callee);
+                self.out += ")(";
+                self.comma_separated(args);
+                self.out.push(')');
+            }
+            ExprKind::DynCall(index, args) => {
+                let Ty::Dyn(trait_id) = args[0].ty else {
+                    unreachable!()
+                };
+                let method = self.tcx.trait_def(trait_id).methods[*index];
+                self.out += &format!(
+                    "<{}>::{}(",
+                    self.ty(&args[0].ty),
+                    self.tcx.fn_sig(method).name
+                );
+                self.comma_separated(args);
+                self.out.push(')');
+            }
+            ExprKind::MakeDyn(value, _) => {
+                self.out.push('(');
+                self.expr(value);
+                self.out += &format!(" as {})", self.ty(&expr.ty));
+            }
+            ExprKind::Adt {
+                adt,
+                variant,
+                fields,
+            } => {
+                let adt_def = self.tcx.adt(*adt);
+                let variant_def = &adt_def.variants[*variant];
+                self.out += &adt_def.variant_path(*variant);
+                match variant_def.ctor_kind {
+                    CtorKind::Const => (),
+                    CtorKind::Fn => {
+                        self.out.push('(');
+                        self.comma_separated(fields);
+                        self.out.push(')');
+                    }
+                    CtorKind::Struct => {
+                        self.out += " {";
+                        for (i, (field, def)) in fields.iter().zip(&variant_def.fields).enumerate()
+                        {
+                            if i > 0 {
+                                self.out.push(',');
+                            }
+                            self.out += &format!(" {}: ", def.name);
+                            self.expr(field);
+                        }
+                        self.out += " }";
+                    }
+                }
+            }
+            ExprKind::Tuple(elems) => {
+                self.out.push('(');
+                self.comma_separated(elems);
+                if elems.len() == 1 {
+                    self.out.push(',');
+                }
+                self.out.push(')');
+            }
+            ExprKind::Array(elems) => {
+                self.out.push('[');
+                self.comma_separated(elems);
+                self.out.push(']');
+            }
+            ExprKind::Repeat(elem, len) => {
+                self.out.push('[');
+                self.expr(elem);
+                self.out += &format!("; {}]", len);
+            }
+            ExprKind::Unsize(value) => {
+                self.out.push('(');
+                self.expr(value);
+                self.out += &format!(" as {})", self.ty(&expr.ty));
+            }
+            ExprKind::Len(value) => {
+                self.out += "len(";
+                self.expr(value);
+                self.out.push(')');
+            }
+            ExprKind::SubSlice {
+                base,
+                start,
+                end,
+                inclusive,
+            } => {
+                self.expr(base);
+                self.out.push('[');
+                self.expr(start);
+                self.out += if *inclusive { "..=" } else { ".." };
+                if let Some(end) = end {
+                    self.expr(end);
+                }
+                self.out.push(']');
+            }
+            ExprKind::BoundsCheck { index, len } => {
+                self.out += "bounds_check(";
+                self.expr(index);
+                self.out += ", ";
+                self.expr(len);
+                self.out.push(')');
+            }
+            ExprKind::Closure { body, captures } => {
+                self.out += &self.body_name(self.program.body(*body));
+                if !captures.is_empty() {
+                    let captures: Vec<String> = captures
+                        .iter()
+                        .map(|capture| {
+                            let by_ref = if capture.by_ref { "&" } else { "" };
+                            format!("{}_{}", by_ref, capture.local.as_usize())
+                        })
+                        .collect();
+                    self.out += &format!("[{}]", captures.join(", "));
+                }
+            }
+            ExprKind::Assign(place, value) => {
+                self.out += &format!("{} = ", self.place(place));
+                self.expr(value);
+            }
+            ExprKind::Return(value) => {
+                self.out += "return ";
+                self.expr(value);
+            }
+            ExprKind::Block(..) => self.braced(expr),
+            ExprKind::If(cond, then, otherwise) => {
+                self.out += "if ";
+                self.expr(cond);
+                self.out.push(' ');
+                self.braced(then);
+                if !matches!(otherwise.kind, ExprKind::Const(Const::Void)) {
+                    self.out += " else ";
+                    self.braced(otherwise);
+                }
+            }
+            ExprKind::Loop(body) => {
+                self.out += "loop ";
+                self.braced(body);
+            }
+            ExprKind::Break => self.out += "break",
+            ExprKind::Match {
+                scrutinee,
+                local,
+                tree,
+                arms,
+            } => self.match_expr(scrutinee, *local, tree, arms),
+        }
+    }
+
+    /// Prints an expression in braces, as a block.
+    fn braced(&mut self, expr: &Expr) {
+        self.out.push('{');
+        self.indent += 1;
+        match &expr.kind {
+            ExprKind::Block(stmts, value) => self.block_contents(stmts, value),
+            _ => {
+                self.newline();
+                self.expr(expr);
+            }
+        }
+        self.indent -= 1;
+        self.newline();
+        self.out.push('}');
+    }
+
+    fn comma_separated(&mut self, exprs: &[Expr]) {
+        for (i, expr) in exprs.iter().enumerate() {
+            if i > 0 {
+                self.out += ", ";
+            }
+            self.expr(expr);
+        }
+    }
+
+    fn match_expr(&mut self, scrutinee: &Expr, local: LocalId, tree: &Decision, arms: &[Arm]) {
+        self.out += &format!("match _{} = ", local.as_usize());
+        self.expr(scrutinee);
+        self.out += " {";
+        self.indent += 1;
+        self.newline();
+        self.decision(tree);
+        self.indent -= 1;
+        self.newline();
+        self.out += "} arms {";
+        self.indent += 1;
+        for (i, arm) in arms.iter().enumerate() {
+            self.newline();
+            self.out += &format!("{}", i);
+            if let Some(guard) = &arm.guard {
+                self.out += " if ";
+                self.expr(guard);
+            }
+            self.out += " => ";
+            self.expr(&arm.body);
+            self.out.push(',');
+        }
+        self.indent -= 1;
+        self.newline();
+        self.out.push('}');
+    }
+
+    fn decision(&mut self, decision: &Decision) {
+        match decision {
+            Decision::Leaf { arm, bindings } => {
+                self.out += &format!("arm {}{}", arm, self.bindings(bindings));
+            }
+            Decision::Guard {
+                arm,
+                bindings,
+                otherwise,
+            } => {
+                self.out += &format!("arm {}{} if guard else ", arm, self.bindings(bindings));
+                self.decision(otherwise);
+            }
+            Decision::Switch {
+                place,
+                cases,
+                default,
+            } => {
+                self.out += &format!("switch {} {{", self.place(place));
+                self.indent += 1;
+                for (case, decision) in cases {
+                    self.newline();
+                    let case = match case {
+                        Case::Variant(variant) => {
+                            let ty = self.place_ty(place);
+                            let Ty::Adt(adt, _) = ty else { unreachable!() };
+                            self.tcx.adt(adt).variant_path(*variant)
+                        }
+                        Case::Const(value) => const_to_string(value),
+                    };
+                    self.out += &format!("{} => ", case);
+                    self.decision(decision);
+                }
+                if let Some(default) = default {
+                    self.newline();
+                    self.out += "_ => ";
+                    self.decision(default);
+                }
+                self.indent -= 1;
+                self.newline();
+                self.out.push('}');
+            }
+            Decision::Failure => self.out += "unreachable",
+        }
+    }
+
+    fn bindings(&self, bindings: &[(LocalId, Place)]) -> String {
+        if bindings.is_empty() {
+            return String::new();
+        }
+        let bindings: Vec<String> = bindings
+            .iter()
+            .map(|(local, place)| format!("_{} = {}", local.as_usize(), self.place(place)))
+            .collect();
+        format!(" ({})", bindings.join(", "))
+    }
+
+    fn place(&self, place: &Place) -> String {
+        let body = self.body.unwrap();
+        let mut out = format!("_{}", place.local.as_usize());
+        let mut ty = body.locals[place.local.as_usize()].ty.clone();
+        for projection in &place.projection {
+            match (projection, &ty) {
+                (Projection::Field(field), Ty::Adt(adt, _)) => {
+                    let adt_def = self.tcx.adt(*adt);
+                    let variant = &adt_def.variants[field.variant];
+                    if adt_def.kind == AdtKind::Enum {
+                        out = format!("({} as {})", out, variant.name);
+                    }
+                    out = format!("{}.{}", out, variant.fields[field.index].name);
+                }
+                (Projection::Field(field), _) => out = format!("{}.{}", out, field.index),
+                (Projection::Index(index), _) => out = format!("{}[_{}]", out, index.as_usize()),
+            }
+            ty = projection_ty(self.tcx, &ty, projection);
+        }
+        out
+    }
+
+    fn place_ty(&self, place: &Place) -> Ty {
+        let body = self.body.unwrap();
+        let mut ty = body.locals[place.local.as_usize()].ty.clone();
+        for projection in &place.projection {
+            ty = projection_ty(self.tcx, &ty, projection);
+        }
+        ty
+    }
+}
+
+/// The type of the value a projection of a value of type `ty` reaches.
+fn projection_ty(tcx: &Tcx, ty: &Ty, projection: &Projection) -> Ty {
+    match (projection, ty) {
+        (Projection::Field(field), _) => field_tys(tcx, ty, field.variant).swap_remove(field.index),
+        (Projection::Index(_), Ty::Array(elem, _) | Ty::Slice(elem)) => (**elem).clone(),
+        (Projection::Index(_), _) => unreachable!("only arrays and slices are indexed"),
+    }
+}
+
+fn const_to_string(value: &Const) -> String {
+    match value {
+        Const::Int(n) => n.to_string(),
+        Const::Float(f) => format!("{:?}", f),
+        Const::Bool(b) => b.to_string(),
+        Const::Char(c) => format!("{:?}", c),
+        Const::Str(s) => format!("{:?}", s.as_str()),
+        Const::Void => "void".to_string(),
+    }
+}
+
+fn binop_to_str(op: BinOp) -> &'static str {
+    match op {
+        BinOp::Add => "+",
+        BinOp::Sub => "-",
+        BinOp::Mul => "*",
+        BinOp::Div => "/",
+        BinOp::Rem => "%",
+        BinOp::BitAnd => "&",
+        BinOp::BitOr => "|",
+        BinOp::BitXor => "^",
+        BinOp::Shl => "<<",
+        BinOp::Shr => ">>",
+        BinOp::Eq => "==",
+        BinOp::Ne => "!=",
+        BinOp::Lt => "<",
+        BinOp::Gt => ">",
+        BinOp::Le => "<=",
+        BinOp::Ge => ">=",
+    }
+}
@@ -0,0 +1,875 @@
+use super::*;
+use expect_test::{expect, Expect};
+
+fn check_ir(src: &str, expect: Expect) {
+    check_ir_with(src, Options::default(), expect)
+}
+
+fn check_ir_with(src: &str, options: Options, expect: Expect) {
+    let (module, diagnostics) = ceylon_parser::parse_file(src);
+    assert!(diagnostics.is_empty(), "{:?}", diagnostics);
+    let (tcx, diagnostics) = ceylon_typeck::check_module(&module);
+    assert!(diagnostics.is_empty(), "{:?}", diagnostics);
+    let program = lower_module(&module, &tcx, options).unwrap();
+    expect.assert_eq(&pretty::program_to_string(&tcx, &program))
+}
+
+#[test]
+fn test_lower_exprs() {
+    check_ir(
+        r#"
+struct Point { x: i64, y: i64 }
+struct Line { from: Point, to: Point }
+fn origin() -> Point { Point { y: 0, x: -1 } }
+fn f(mut l: Line) -> i64 {
+    let p = origin();
+    l.to.x = p.y;
+    origin().x + l.from.y * 2
+}
+"#,
+        expect![[r#"
+            fn origin() -> Point {
+                let _0: i64; // y
+                let _1: i64; // x
+                {
+                    _0 = 0;
+                    _1 = -1;
+                    Point { x: _1, y: _0 }
+                }
+            }
+            fn f(_0: Line) -> i64 {
+                let _1: Point; // p
+                _1 = origin();
+                _0.to.x = _1.y;
+                ((origin()).x + (_0.from.y * 2))
+            }
+        "#]],
+    )
+}
+
+#[test]
+fn test_match_enum() {
+    check_ir(
+        r#"
+enum Shape { Circle(f64), Rect { w: f64, h: f64 }, Dot }
+fn area(s: Shape) -> f64 {
+    match s {
+        Shape::Circle(r) => r * r * 3.0,
+        Shape::Rect { w, h } if w > 0.0 => w * h,
+        Shape::Rect { .. } | Shape::Dot => 0.0,
+    }
+}
+"#,
+        expect![[r#"
+            fn area(_0: Shape) -> f64 {
+                let _1: Shape; // scrutinee
+                let _2: f64; // r
+                let _3: f64; // w
+                let _4: f64; // h
+                match _1 = _0 {
+                    switch _1 {
+                        Shape::Circle => arm 0 (_2 = (_1 as Circle).0)
+                        Shape::Rect => arm 1 (_3 = (_1 as Rect).w, _4 = (_1 as Rect).h) if guard else arm 2
+                        Shape::Dot => arm 2
+                    }
+                } arms {
+                    0 => ((_2 * _2) * 3.0),
+                    1 if (_3 > 0.0) => (_3 * _4),
+                    2 => 0.0,
+                }
+            }
+        "#]],
+    )
+}
+
+#[test]
+fn test_match_nested() {
+    // Each field is tested once, whatever the number of arms mentioning it.
+    check_ir(
+        r#"
+enum Opt { None, Some(i64) }
+struct Pair { a: Opt, b: Opt }
+fn f(p: Pair) -> i64 {
+    match p {
+        Pair { a: Opt::None, b: Opt::None } => 0,
+        Pair { a: Opt::Some(1) | Opt::Some(2), b: Opt::None } => 1,
+        Pair { a: Opt::Some(x), b: Opt::Some(_) } | Pair { a: Opt::None, b: Opt::Some(x) } => x,
+        _ => 3,
+    }
+}
+"#,
+        expect![[r#"
+            fn f(_0: Pair) -> i64 {
+                let _1: Pair; // scrutinee
+                let _2: i64; // x
+                match _1 = _0 {
+                    switch _1.a {
+                        Opt::None => switch _1.b {
+                            Opt::None => arm 0
+                            Opt::Some => arm 2 (_2 = (_1.b as Some).0)
+                        }
+                        Opt::Some => switch _1.b {
+                            Opt::None => switch (_1.a as Some).0 {
+                                1 => arm 1
+                                2 => arm 1
+                                _ => arm 3
+                            }
+                            Opt::Some => arm 2 (_2 = (_1.a as Some).0)
+                        }
+                    }
+                } arms {
+                    0 => 0,
+                    1 => 1,
+                    2 => _2,
+                    3 => 3,
+                }
+            }
+        "#]],
+    )
+}
+
+#[test]
+fn test_match_literals() {
+    check_ir(
+        r#"
+fn f(n: i64, c: char, b: bool) -> i64 {
+    let a = match n {
+        0 | -1 => 0,
+        x if x > 10 => 1,
+        10 => 2,
+        _ => 3,
+    };
+    a + match c { 'a' => 1, _ => 0 } + match b { true => 1, false => 0 }
+}
+"#,
+        expect![[r#"
+            fn f(_0: i64, _1: char, _2: bool) -> i64 {
+                let _3: i64; // scrutinee
+                let _4: i64; // x
+                let _5: i64; // a
+                let _6: char; // scrutinee
+                let _7: bool; // scrutinee
+                _5 = match _3 = _0 {
+                    switch _3 {
+                        0 => arm 0
+                        -1 => arm 0
+                        10 => arm 1 (_4 = _3) if guard else arm 2
+                        _ => arm 1 (_4 = _3) if guard else arm 3
+                    }
+                } arms {
+                    0 => 0,
+                    1 if (_4 > 10) => 1,
+                    2 => 2,
+                    3 => 3,
+                };
+                ((_5 + match _6 = _1 {
+                    switch _6 {
+                        'a' => arm 0
+                        _ => arm 1
+                    }
+                } arms {
+                    0 => 1,
+                    1 => 0,
+                }) + match _7 = _2 {
+                    switch _7 {
+                        true => arm 0
+                        false => arm 1
+                    }
+                } arms {
+                    0 => 1,
+                    1 => 0,
+                })
+            }
+        "#]],
+    )
+}
+
+#[test]
+fn test_monomorphize() {
+    check_ir(
+        r#"
+enum Option<T> { Some(T), None }
+fn unwrap_or<T>(o: Option<T>, default: T) -> T {
+    match o { Option::Some(x) => x, Option::None => default }
+}
+fn id<T>(x: T) -> T { unwrap_or(Option::Some(x), x) }
+fn f() -> bool { id(1) == unwrap_or(Option::None, 2) == id(true) }
+"#,
+        expect![[r#"
+            fn f() -> bool {
+                ((id::<i64>(1) == unwrap_or::<i64>(Option::None, 2)) == id::<bool>(true))
+            }
+            fn id::<i64>(_0: i64) -> i64 {
+                unwrap_or::<i64>(Option::Some(_0), _0)
+            }
+            fn unwrap_or::<i64>(_0: Option<i64>, _1: i64) -> i64 {
+                let _2: Option<i64>; // scrutinee
+                let _3: i64; // x
+                match _2 = _0 {
+                    switch _2 {
+                        Option::Some => arm 0 (_3 = (_2 as Some).0)
+                        Option::None => arm 1
+                    }
+                } arms {
+                    0 => _3,
+                    1 => _1,
+                }
+            }
+            fn id::<bool>(_0: bool) -> bool {
+                unwrap_or::<bool>(Option::Some(_0), _0)
+            }
+            fn unwrap_or::<bool>(_0: Option<bool>, _1: bool) -> bool {
+                let _2: Option<bool>; // scrutinee
+                let _3: bool; // x
+                match _2 = _0 {
+                    switch _2 {
+                        Option::Some => arm 0 (_3 = (_2 as Some).0)
+                        Option::None => arm 1
+                    }
+                } arms {
+                    0 => _3,
+                    1 => _1,
+                }
+            }
+        "#]],
+    )
+}
+
+#[test]
+fn test_method_dispatch() {
+    check_ir(
+        r#"
+trait Shape { fn area(self) -> f64; fn name(self) -> str { "shape" } }
+struct Circle { r: f64 }
+struct Wrap<T> { t: T }
+impl Circle { fn new(r: f64) -> Circle { Circle { r: r } } }
+impl Shape for Circle { fn area(self) -> f64 { self.r * self.r } }
+impl<T: Shape> Shape for Wrap<T> { fn area(self) -> f64 { self.t.area() } }
+fn area<T: Shape>(t: T) -> f64 { t.area() }
+fn f(s: dyn Shape) -> f64 {
+    let w: dyn Shape = Wrap { t: Circle::new(1.0) };
+    area(Wrap { t: Circle::new(2.0) }) + s.area() + w.area()
+}
+"#,
+        expect![[r#"
+            fn f(_0: dyn Shape) -> f64 {
+                let _1: dyn Shape; // w
+                _1 = (Wrap { t: Circle::new(1.0) } as dyn Shape);
+                ((area::<Wrap<Circle>>(Wrap { t: Circle::new(2.0) }) + <dyn Shape>::area(_0)) + <dyn Shape>::area(_1))
+            }
+            fn Circle::new(_0: f64) -> Circle {
+                Circle { r: _0 }
+            }
+            fn <Circle as Shape>::area(_0: Circle) -> f64 {
+                (_0.r * _0.r)
+            }
+            fn <Wrap<Circle> as Shape>::area(_0: Wrap<Circle>) -> f64 {
+                <Circle as Shape>::area(_0.t)
+            }
+            fn <Wrap<Circle> as Shape>::name(_0: Wrap<Circle>) -> str {
+                "shape"
+            }
+            fn area::<Wrap<Circle>>(_0: Wrap<Circle>) -> f64 {
+                <Wrap<Circle> as Shape>::area(_0)
+            }
+            vtable <Wrap<Circle> as Shape> {
+                <Wrap<Circle> as Shape>::area,
+                <Wrap<Circle> as Shape>::name,
+            }
+        "#]],
+    )
+}
+
+#[test]
+fn test_overloaded_operators() {
+    check_ir(
+        r#"
+struct V { x: i64 }
+impl Eq for V { fn eq(self, other: V) -> bool { self.x == other.x } }
+impl Ord for V { fn lt(self, other: V) -> bool { self.x < other.x } }
+impl Neg for V { fn neg(self) -> V { V { x: -self.x } } }
+fn max<T: Ord>(a: T, b: T) -> T { match a > b { true => a, false => b } }
+fn f(v: V, w: V) -> bool {
+    let a = max(v, -w) <= max(V { x: 1 }, w);
+    let b = v != w;
+    match a { true => b, false => max(1, 2) > 0 }
+}
+"#,
+        expect![[r#"
+            fn f(_0: V, _1: V) -> bool {
+                let _2: V; // lhs
+                let _3: V; // rhs
+                let _4: bool; // a
+                let _5: bool; // b
+                let _6: bool; // scrutinee
+                _4 = {
+                    _2 = max::<V>(_0, <V as Neg>::neg(_1));
+                    _3 = max::<V>(V { x: 1 }, _1);
+                    !<V as Ord>::lt(_3, _2)
+                };
+                _5 = !<V as Eq>::eq(_0, _1);
+                match _6 = _4 {
+                    switch _6 {
+                        true => arm 0
+                        false => arm 1
+                    }
+                } arms {
+                    0 => _5,
+                    1 => (max::<i64>(1, 2) > 0),
+                }
+            }
+            fn <V as Eq>::eq(_0: V, _1: V) -> bool {
+                (_0.x == _1.x)
+            }
+            fn <V as Ord>::lt(_0: V, _1: V) -> bool {
+                (_0.x < _1.x)
+            }
+            fn <V as Neg>::neg(_0: V) -> V {
+                V { x: -_0.x }
+            }
+            fn max::<V>(_0: V, _1: V) -> V {
+                let _2: bool; // scrutinee
+                match _2 = <V as Ord>::lt(_1, _0) {
+                    switch _2 {
+                        true => arm 0
+                        false => arm 1
+                    }
+                } arms {
+                    0 => _0,
+                    1 => _1,
+                }
+            }
+            fn max::<i64>(_0: i64, _1: i64) -> i64 {
+                let _2: bool; // scrutinee
+                match _2 = (_1 < _0) {
+                    switch _2 {
+                        true => arm 0
+                        false => arm 1
+                    }
+                } arms {
+                    0 => _0,
+                    1 => _1,
+                }
+            }
+        "#]],
+    )
+}
+
+#[test]
+fn test_recursion_limit() {
+    let src = r#"
+struct Wrap<T> { t: T }
+fn f<T>(x: T) -> i64 { f(Wrap { t: x }) }
+fn main() -> i64 { f(1) }
+"#;
+    let (module, _) = ceylon_parser::parse_file(src);
+    let (tcx, diagnostics) = ceylon_typeck::check_module(&module);
+    assert!(diagnostics.is_empty(), "{:?}", diagnostics);
+    let diag = lower_module(&module, &tcx, Options::default()).unwrap_err();
+    expect![[r#"
+        error: reached the recursion limit while instantiating `f`
+         --> test.cy:3:24
+          |
+        3 | fn f<T>(x: T) -> i64 { f(Wrap { t: x }) }
+          |                        ^
+          = note: generic functions may only be instantiated 64 levels deep
+    "#]]
+    .assert_eq(&diag.render(src, "test.cy"));
+}
+
+#[test]
+fn test_closure_conversion() {
+    check_ir(
+        r#"
+struct Counter { n: i64 }
+fn apply<T>(x: T, f: fn(T) -> T) -> T { f(x) }
+fn inc(x: i64) -> i64 { x + 1 }
+fn f(offset: i64) -> i64 {
+    let mut c = Counter { n: 0 };
+    let add = |x| {
+        c.n = c.n + 1;
+        x + offset
+    };
+    let twice = |x: i64| add(add(x));
+    apply(twice(1), inc) + apply(c.n, Neg::neg)
+}
+"#,
+        expect![[r#"
+            fn inc(_0: i64) -> i64 {
+                (_0 + 1)
+            }
+            fn f(_0: i64) -> i64 {
+                let shared _1: Counter; // c
+                let _2: fn(i64) -> i64; // add
+                let _3: fn(i64) -> i64; // twice
+                _1 = Counter { n: 0 };
+                _2 = f::{closure#0}[&_1, _0];
+                _3 = f::{closure#1}[_2];
+                (apply::<i64>((_3)(1), inc) + apply::<i64>(_1.n, <i64 as Neg>::neg))
+            }
+            fn f::{closure#0}(shared _0: Counter, _1: i64, _2: i64) -> i64 {
+                _0.n = (_0.n + 1);
+                (_2 + _1)
+            }
+            fn f::{closure#1}(_0: fn(i64) -> i64, _1: i64) -> i64 {
+                (_0)((_0)(_1))
+            }
+            fn apply::<i64>(_0: i64, _1: fn(i64) -> i64) -> i64 {
+                (_1)(_0)
+            }
+            fn <i64 as Neg>::neg(_0: i64) -> i64 {
+                -_0
+            }
+        "#]],
+    );
+}
+
+#[test]
+fn test_lower_try() {
+    check_ir(
+        r#"
+fn parse(s: str) -> Result<i64, str> { Result::Err(s) }
+fn f(s: str, o: Option<i64>) -> Result<i64, str> {
+    let n = parse(s)?;
+    let g = || -> Option<i64> { Option::Some(o? + 1) };
+    Result::Ok(n)
+}
+"#,
+        expect![[r#"
+            fn parse(_0: str) -> Result<i64, str> {
+                Result::Err(_0)
+            }
+            fn f(_0: str, _1: Option<i64>) -> Result<i64, str> {
+                let _2: Result<i64, str>; // scrutinee
+                let _3: i64; // n
+                let _4: fn() -> Option<i64>; // g
+                _3 = match _2 = parse(_0) {
+                    switch _2 {
+                        Result::Ok => arm 0
+                        Result::Err => arm 1
+                    }
+                } arms {
+                    0 => (_2 as Ok).0,
+                    1 => return Result::Err((_2 as Err).0),
+                };
+                _4 = f::{closure#0}[_1];
+                Result::Ok(_3)
+            }
+            fn f::{closure#0}(_0: Option<i64>) -> Option<i64> {
+                let _1: Option<i64>; // scrutinee
+                Option::Some((match _1 = _0 {
+                    switch _1 {
+                        Option::Some => arm 0
+                        Option::None => arm 1
+                    }
+                } arms {
+                    0 => (_1 as Some).0,
+                    1 => return Option::None,
+                } + 1))
+            }
+        "#]],
+    );
+}
+
+#[test]
+fn test_lower_cast() {
+    check_ir(
+        r#"
+fn f(x: i64, c: char) -> u64 { ~(x as u64) + (c as u64 as u64) + (x as i64 as f64 as u64) }
+"#,
+        expect![[r#"
+            fn f(_0: i64, _1: char) -> u64 {
+                ((~(_0 as u64) + (_1 as u64)) + ((_0 as f64) as u64))
+            }
+        "#]],
+    );
+}
+
+#[test]
+fn test_lower_overflow() {
+    let src = r#"
+fn f(a: u8, b: i32, x: f32) -> Option<u8> {
+    let c = -b * 2 / b;
+    let y = -x + 1.5;
+    let w = a.wrapping_mul(3);
+    w.checked_div(a - 1)
+}
+"#;
+    check_ir(
+        src,
+        expect![[r#"
+        fn f(_0: u8, _1: i32, _2: f32) -> Option<u8> {
+            let _3: i32; // c
+            let _4: f32; // y
+            let _5: u8; // w
+            _3 = ((-_1 * 2) / _1);
+            _4 = (-_2 + 1.5);
+            _5 = wrapping_mul(_0, 3);
+            checked_div(_5, (_0 - 1))
+        }
+    "#]],
+    );
+    let release = Options {
+        overflow_checks: false,
+        ..Options::default()
+    };
+    check_ir_with(
+        src,
+        release,
+        expect![[r#"
+        fn f(_0: u8, _1: i32, _2: f32) -> Option<u8> {
+            let _3: i32; // c
+            let _4: f32; // y
+            let _5: u8; // w
+            _3 = (wrapping_mul(wrapping_sub(0, _1), 2) / _1);
+            _4 = (-_2 + 1.5);
+            _5 = wrapping_mul(_0, 3);
+            checked_div(_5, wrapping_sub(_0, 1))
+        }
+    "#]],
+    );
+}
+
+#[test]
+fn test_lower_compound_assignment() {
+    let src = r#"
+struct P { x: i64 }
+struct V { x: i64 }
+impl Add for V { fn add(self, rhs: V) -> V { V { x: self.x + rhs.x } } }
+fn f(mut ps: [P; 3], i: usize, mut v: V, mut n: u32) -> u32 {
+    ps[i].x += 1;
+    ps[i + 1].x %= ps[0].x;
+    v += V { x: 2 };
+    n <<= n & 7 | 1;
+    n
+}
+"#;
+    check_ir(
+        src,
+        expect![[r#"
+            fn f(_0: [P; 3], _1: usize, _2: V, _3: u32) -> u32 {
+                let _4: usize; // index
+                let _5: usize; // index
+                let _6: i64; // value
+                {
+                    bounds_check(_1, 3);
+                    _0[_1].x = (_0[_1].x + 1)
+                };
+                {
+                    _6 = {
+                        _4 = 0;
+                        bounds_check(_4, 3);
+                        _0[_4].x
+                    };
+                    _5 = (_1 + 1);
+                    bounds_check(_5, 3);
+                    _0[_5].x = (_0[_5].x % _6)
+                };
+                _2 = <V as Add>::add(_2, V { x: 2 });
+                _3 = (_3 << ((_3 & 7) | 1));
+                _3
+            }
+            fn <V as Add>::add(_0: V, _1: V) -> V {
+                V { x: (_0.x + _1.x) }
+            }
+        "#]],
+    );
+    let release = Options {
+        overflow_checks: false,
+        ..Options::default()
+    };
+    check_ir_with(
+        src,
+        release,
+        expect![[r#"
+            fn f(_0: [P; 3], _1: usize, _2: V, _3: u32) -> u32 {
+                let _4: usize; // index
+                let _5: usize; // index
+                let _6: i64; // value
+                {
+                    bounds_check(_1, 3);
+                    _0[_1].x = wrapping_add(_0[_1].x, 1)
+                };
+                {
+                    _6 = {
+                        _4 = 0;
+                        bounds_check(_4, 3);
+                        _0[_4].x
+                    };
+                    _5 = wrapping_add(_1, 1);
+                    bounds_check(_5, 3);
+                    _0[_5].x = (_0[_5].x % _6)
+                };
+                _2 = <V as Add>::add(_2, V { x: 2 });
+                _3 = wrapping_shl(_3, ((_3 & 7) | 1));
+                _3
+            }
+            fn <V as Add>::add(_0: V, _1: V) -> V {
+                V { x: wrapping_add(_0.x, _1.x) }
+            }
+        "#]],
+    );
+}
+
+#[test]
+fn test_lower_arrays() {
+    let src = r#"
+struct P { x: i64 }
+fn f(mut xs: [P], i: usize) -> (i64, usize) {
+    let mut a = [1, 2, 3];
+    a[i + 1] = xs[i].x;
+    xs[0].x = a[2];
+    let s: [i64] = a;
+    (s[i], [0u8; 4].len())
+}
+"#;
+    check_ir(
+        src,
+        expect![[r#"
+        fn f(_0: [P], _1: usize) -> (i64, usize) {
+            let _2: [i64; 3]; // a
+            let _3: usize; // index
+            let _4: i64; // value
+            let _5: usize; // index
+            let _6: usize; // index
+            let _7: i64; // value
+            let _8: [i64]; // s
+            _2 = [1, 2, 3];
+            {
+                _4 = {
+                    bounds_check(_1, len(_0));
+                    _0[_1].x
+                };
+                _3 = (_1 + 1);
+                bounds_check(_3, 3);
+                _2[_3] = _4
+            };
+            {
+                _7 = {
+                    _5 = 2;
+                    bounds_check(_5, 3);
+                    _2[_5]
+                };
+                _6 = 0;
+                bounds_check(_6, len(_0));
+                _0[_6].x = _7
+            };
+            _8 = (_2 as [i64]);
+            ({
+                bounds_check(_1, len(_8));
+                _8[_1]
+            }, len([0; 4]))
+        }
+    "#]],
+    );
+    let unchecked = Options {
+        bounds_checks: false,
+        ..Options::default()
+    };
+    check_ir_with(
+        src,
+        unchecked,
+        expect![[r#"
+        fn f(_0: [P], _1: usize) -> (i64, usize) {
+            let _2: [i64; 3]; // a
+            let _3: usize; // index
+            let _4: i64; // value
+            let _5: usize; // index
+            let _6: usize; // index
+            let _7: i64; // value
+            let _8: [i64]; // s
+            _2 = [1, 2, 3];
+            {
+                _4 = _0[_1].x;
+                _3 = (_1 + 1);
+                _2[_3] = _4
+            };
+            {
+                _7 = {
+                    _5 = 2;
+                    _2[_5]
+                };
+                _6 = 0;
+                _0[_6].x = _7
+            };
+            _8 = (_2 as [i64]);
+            (_8[_1], len([0; 4]))
+        }
+    "#]],
+    );
+}
+
+#[test]
+fn test_lower_sub_slices() {
+    check_ir(
+        r#"
+fn f(xs: [u8], i: usize, r: RangeInclusive<usize>) -> ([u8], u8, [u8]) {
+    let a = [1, 2, 3];
+    (a[i..=2], xs[1..][i], xs[r])
+}
+"#,
+        expect![[r#"
+            fn f(_0: [u8], _1: usize, _2: RangeInclusive<usize>) -> ([u8], u8, [u8]) {
+                let _3: [u8; 3]; // a
+                let _4: [u8]; // base
+                let _5: [u8]; // base
+                let _6: RangeInclusive<usize>; // range
+                _3 = [1, 2, 3];
+                (_3[_1..=2], {
+                    _4 = _0[1..];
+                    bounds_check(_1, len(_4));
+                    _4[_1]
+                }, {
+                    _5 = _0;
+                    _6 = _2;
+                    _5[_6.start..=_6.end]
+                })
+            }
+        "#]],
+    );
+}
+
+#[test]
+fn test_lower_tuple_patterns() {
+    check_ir(
+        r#"
+fn f(t: (i64, (bool, i64))) -> i64 {
+    let (a, (_, b)) = t;
+    match t { (0, (true, _)) => a, (n, _) => n + b }
+}
+"#,
+        expect![[r#"
+            fn f(_0: (i64, (bool, i64))) -> i64 {
+                let _1: i64; // a
+                let _2: i64; // b
+                let _3: (i64, (bool, i64)); // value
+                let _4: (i64, (bool, i64)); // scrutinee
+                let _5: i64; // n
+                match _3 = _0 {
+                    arm 0 (_1 = _3.0, _2 = _3.1.1)
+                } arms {
+                    0 => void,
+                };
+                match _4 = _0 {
+                    switch _4.0 {
+                        0 => switch _4.1.0 {
+                            true => arm 0
+                            _ => arm 1 (_5 = _4.0)
+                        }
+                        _ => arm 1 (_5 = _4.0)
+                    }
+                } arms {
+                    0 => _1,
+                    1 => (_5 + _2),
+                }
+            }
+        "#]],
+    );
+}
+
+#[test]
+fn test_lower_for_loops() {
+    check_ir(
+        r#"
+struct P { x: i64 }
+fn f(n: u8, r: Range<i64>, ps: [P]) -> i64 {
+    let mut total = 0;
+    for i in 0..n { total = total + (i as i64); }
+    for _ in 1..=n {}
+    for i in r { total = total - i; }
+    for P { x } in ps { total = total + x; }
+    total
+}
+"#,
+        expect![[r#"
+            fn f(_0: u8, _1: Range<i64>, _2: [P]) -> i64 {
+                let _3: i64; // total
+                let _4: u8; // i
+                let _5: u8; // end
+                let _6: u8; // i
+                let _7: u8; // i
+                let _8: u8; // end
+                let _9: Range<i64>; // range
+                let _10: i64; // i
+                let _11: i64; // end
+                let _12: i64; // i
+                let _13: [P]; // seq
+                let _14: usize; // index
+                let _15: P; // item
+                let _16: Option<P>; // next
+                let _17: i64; // x
+                _3 = 0;
+                {
+                    _4 = 0;
+                    _5 = _0;
+                    loop {
+                        if (_4 < _5) {
+                            _6 = _4;
+                            {
+                                _3 = (_3 + (_6 as i64));
+                            };
+                            _4 = wrapping_add(_4, 1)
+                        } else {
+                            break
+                        }
+                    }
+                };
+                {
+                    _7 = 1;
+                    _8 = _0;
+                    if (_7 <= _8) {
+                        loop {
+                            {
+                            };
+                            if (_7 == _8) {
+                                break
+                            } else {
+                                _7 = wrapping_add(_7, 1)
+                            }
+                        }
+                    }
+                };
+                {
+                    _9 = _1;
+                    _10 = _9.start;
+                    _11 = _9.end;
+                    loop {
+                        if (_10 < _11) {
+                            _12 = _10;
+                            {
+                                _3 = (_3 - _12);
+                            };
+                            _10 = wrapping_add(_10, 1)
+                        } else {
+                            break
+                        }
+                    }
+                };
+                {
+                    _13 = _2;
+                    _14 = 0;
+                    loop {
+                        match _16 = if (_14 < len(_13)) {
+                            _15 = _13[_14];
+                            _14 = wrapping_add(_14, 1);
+                            Option::Some(_15)
+                        } else {
+                            Option::None
+                        } {
+                            switch _16 {
+                                Option::Some => arm 0 (_17 = (_16 as Some).0.x)
+                                Option::None => arm 1
+                            }
+                        } arms {
+                            0 => {
+                                _3 = (_3 + _17);
+                            },
+                            1 => break,
+                        }
+                    }
+                };
+                _3
+            }
+        "#]],
+    );
+}
@@ -1,41 +1,518 @@
-use ceylon_lexer::Span;
+use ceylon_lexer::{DocStyle, Span};
+use ceylon_span::Symbol;
+use serde::{Deserialize, Serialize};
 
 use super::*;
 
-#[derive(Debug)]
-pub struct Expr {
-    pub kind: ExprKind,
-    pub span: Span,
+/// Identifies an AST node uniquely within a module, so that later passes can
+/// keep side tables (types, resolutions, ...) keyed by the node. See
+/// [`crate::node_map`] to find the node of an id or at a position.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct NodeId(u32);
+
+impl std::fmt::Debug for NodeId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "NodeId({})", self.0)
+    }
 }
 
-impl Expr {
-    pub(crate) fn new(kind: ExprKind, span: Span) -> Self {
-        Expr { kind, span }
+impl NodeId {
+    pub fn from_u32(n: u32) -> Self {
+        NodeId(n)
+    }
+
+    pub fn as_u32(self) -> u32 {
+        self.0
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Module {
+    /// Inner doc comments ("//!") documenting the module itself.
+    pub docs: Vec<Doc>,
+    pub items: Vec<Item>,
+    pub span: Span,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Doc {
+    pub style: DocStyle,
+    /// Text of the comment without the comment markers.
+    pub text: Symbol,
+    pub span: Span,
+}
+
+/// `@inline` or `@deprecated("use bar")`, attached to the item or statement
+/// following it. See [`crate::attr`] for the known attributes.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Attribute {
+    pub meta: MetaItem,
+    /// The span of the attribute including its `@`.
+    pub span: Span,
+}
+
+/// A name with optional arguments, e.g. `allow(confusable_idents)`. The
+/// arguments are themselves meta items or strings.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MetaItem {
+    pub ident: Ident,
+    pub kind: MetaItemKind,
+    pub span: Span,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub enum MetaItemKind {
+    /// `inline`
+    Word,
+    /// `name = "value"`
+    NameValue(Symbol),
+    /// `deprecated("use bar")` or `allow(a, b)`
+    List(Vec<NestedMeta>),
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub enum NestedMeta {
+    Meta(MetaItem),
+    /// A string literal, with its escapes already processed.
+    Str(Symbol, Span),
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Item {
+    pub id: NodeId,
+    /// Outer doc comments ("///") preceding the item.
+    pub docs: Vec<Doc>,
+    pub attrs: Vec<Attribute>,
+    pub vis: Visibility,
+    /// The name of the item. Impls have no name, theirs is empty. Imports
+    /// are named after the last segment of their path.
+    pub ident: Ident,
+    pub kind: ItemKind,
+    pub span: Span,
+}
+
+/// Whether an item can be used outside of its module.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Visibility {
+    /// `pub`: the item can be used anywhere.
+    Public,
+    /// The item can only be used in its module and the submodules of it.
+    Private,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub enum ItemKind {
+    Fn(FnDecl),
+    Struct(StructDef),
+    Enum(EnumDef),
+    Trait(TraitDef),
+    Impl(ImplDef),
+    Mod(ModDef),
+    /// `import math::sqrt;`, which brings an item of another module into
+    /// scope. The path starts at the root module.
+    Import(Path),
+}
+
+/// `mod math { ... }`, or `mod math;` for a module in a file of its own.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ModDef {
+    /// Whether the items are written in braces rather than in another file.
+    pub inline: bool,
+    /// The items of the module. `None` for a module in another file until
+    /// the driver loads the file.
+    pub module: Option<Module>,
+    /// Whether the module is the root module of another package, which the
+    /// driver adds for each dependency. The import paths of the modules of
+    /// a package start at its root module.
+    pub package: bool,
+    /// For a package which another dependency added before, the item adding
+    /// it there, whose module this one is. `module` is then `None`.
+    pub same_as: Option<NodeId>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FnDecl {
+    pub generics: Generics,
+    /// The `self` parameter of a method.
+    pub self_param: Option<SelfParam>,
+    pub params: Vec<Param>,
+    /// `None` if the return type is omitted.
+    pub ret_ty: Option<Ty>,
+    /// `None` for a method of a trait without a default body.
+    pub body: Option<Block>,
+}
+
+/// The `self` receiver of a method, which takes the value the method is
+/// called on.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SelfParam {
+    pub id: NodeId,
+    pub span: Span,
+}
+
+/// `trait Shape { fn area(self) -> f64; }`
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TraitDef {
+    /// The methods of the trait, all of them `ItemKind::Fn`.
+    pub items: Vec<Item>,
+}
+
+/// `impl Shape for Circle { ... }`, or `impl Circle { ... }` for methods
+/// which don't belong to a trait.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ImplDef {
+    pub generics: Generics,
+    pub of_trait: Option<Path>,
+    pub self_ty: Ty,
+    /// The methods of the impl, all of them `ItemKind::Fn`.
+    pub items: Vec<Item>,
+}
+
+/// `struct Point { x: i64, y: i64 }`
+#[derive(Debug, Serialize, Deserialize)]
+pub struct StructDef {
+    pub generics: Generics,
+    pub fields: Vec<FieldDef>,
+}
+
+/// `enum Shape { Circle(f64), Rect { w: f64, h: f64 }, Empty }`
+#[derive(Debug, Serialize, Deserialize)]
+pub struct EnumDef {
+    pub generics: Generics,
+    pub variants: Vec<Variant>,
+}
+
+/// The type parameters of an item, e.g. `<T: Ord, U>`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Generics {
+    pub params: Vec<GenericParam>,
+    /// The span of the angle brackets, or the position where they would go
+    /// if there are none.
+    pub span: Span,
+}
+
+/// `T: Ord + Eq`
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GenericParam {
+    pub id: NodeId,
+    pub ident: Ident,
+    pub bounds: Vec<Path>,
+    pub span: Span,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Variant {
+    pub id: NodeId,
+    /// Outer doc comments ("///") preceding the variant.
+    pub docs: Vec<Doc>,
+    pub ident: Ident,
+    pub data: VariantData,
+    pub span: Span,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub enum VariantData {
+    /// `Rect { w: f64, h: f64 }`
+    Struct(Vec<FieldDef>),
+    /// `Circle(f64)`
+    Tuple(Vec<Ty>),
+    /// `Empty`
+    Unit,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FieldDef {
+    pub id: NodeId,
+    /// Outer doc comments ("///") preceding the field.
+    pub docs: Vec<Doc>,
+    pub ident: Ident,
+    pub ty: Ty,
+    pub span: Span,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Param {
+    pub id: NodeId,
+    pub mutbl: Mutability,
+    pub ident: Ident,
+    pub ty: Ty,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct Ident {
+    /// NFC-normalized name of the identifier.
+    pub name: Symbol,
+    pub span: Span,
+}
+
+/// A possibly qualified name, e.g. `x` or `Shape::Circle`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Path {
+    pub segments: Vec<Ident>,
+    pub span: Span,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Ty {
+    pub id: NodeId,
+    pub kind: TyKind,
+    pub span: Span,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub enum TyKind {
+    I8,
+    I16,
+    I32,
+    I64,
+    I128,
+    U8,
+    U16,
+    U32,
+    U64,
+    U128,
+    Usize,
+    F32,
+    F64,
+    Bool,
+    Char,
+    Str,
+    Void,
+    /// A named type such as a struct or a type parameter, with its generic
+    /// arguments, e.g. `Point` or `Pair<i64, bool>`.
+    Path(Path, Vec<Ty>),
+    /// A trait object, e.g. `dyn Shape`.
+    Dyn(Path),
+    /// A function type, e.g. `fn(i64) -> bool`. The return type is `None`
+    /// if omitted.
+    Fn(Vec<Ty>, Option<Box<Ty>>),
+    /// A tuple type, e.g. `(i64, bool)`, or `(i64,)` with one element.
+    Tuple(Vec<Ty>),
+    /// An array type with its length, e.g. `[i64; 3]`.
+    Array(Box<Ty>, u64),
+    /// A slice type, e.g. `[i64]`, whose values are arrays of any length.
+    Slice(Box<Ty>),
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Block {
+    pub id: NodeId,
+    pub stmts: Vec<Stmt>,
+    pub span: Span,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Stmt {
+    pub id: NodeId,
+    pub attrs: Vec<Attribute>,
+    pub kind: StmtKind,
+    pub span: Span,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub enum StmtKind {
+    /// `let x: i64 = 1;`
+    Let(Local),
+    /// Expression statement terminated by a semicolon.
+    Semi(Expr),
+    /// Expression without a trailing semicolon. It is either the value of
+    /// the block, if it comes last, or a block-like expression such as
+    /// `match`.
+    Expr(Expr),
+}
+
+/// A `let` statement, declaring the variables its pattern binds.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Local {
+    pub id: NodeId,
+    /// A pattern which matches every value, e.g. `x` or `(a, b)`.
+    pub pat: Pat,
+    /// `None` if the type is omitted.
+    pub ty: Option<Ty>,
+    pub init: Expr,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Expr {
+    pub id: NodeId,
+    pub kind: ExprKind,
+    pub span: Span,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
 pub enum ExprKind {
     Literal(LiteralKind),
     Binary(BinOp, Box<Expr>, Box<Expr>),
     Unary(UnOp, Box<Expr>),
+    /// A reference to a variable, function or unit variant, e.g. `x` or
+    /// `Shape::Empty`.
+    Path(Path),
+    /// `f(a, b)`
+    Call(Box<Expr>, Vec<Expr>),
+    /// `receiver.method(a, b)`
+    MethodCall(Box<Expr>, Ident, Vec<Expr>),
+    /// `{ stmts }`
+    Block(Block),
+    /// `match scrutinee { arms }`
+    Match(Box<Expr>, Vec<Arm>),
+    /// `for pat in iter { body }`, which runs the body for each element of
+    /// a range, an array or a slice.
+    ForLoop(Pat, Box<Expr>, Block),
+    /// `start..end` or `start..=end`, or `start..` without an end, which
+    /// can only be an index.
+    Range(Box<Expr>, Option<Box<Expr>>, RangeLimits),
+    /// `place = value`
+    Assign(Box<Expr>, Box<Expr>),
+    /// `place += value`, or another compound assignment, which assigns
+    /// `place + value` to the place, computing the place once.
+    AssignOp(BinOp, Box<Expr>, Box<Expr>),
+    /// `expr.field`, or `expr.0` for the elements of a tuple.
+    Field(Box<Expr>, Ident),
+    /// `(a, b)`, or `(a,)` with one element.
+    Tuple(Vec<Expr>),
+    /// `[a, b, c]`
+    Array(Vec<Expr>),
+    /// `[value; 16]`, an array holding the value that many times.
+    Repeat(Box<Expr>, u64),
+    /// `base[index]`, an element of an array or a slice, or `base[start..end]`,
+    /// a slice of its elements.
+    Index(Box<Expr>, Box<Expr>),
+    /// `expr as i64`, which converts a primitive value to another
+    /// primitive type.
+    Cast(Box<Expr>, Ty),
+    /// `expr?`, which unwraps an `Option` or a `Result`, returning early
+    /// from the function with the `None` or the `Err`.
+    Try(Box<Expr>),
+    /// `Point { x: 1, y: 2 }`
+    Struct(StructExpr),
+    /// `|x: i64| x + offset`
+    Closure(Closure),
+    /// Placeholder for an expression which failed to parse.
+    Err,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct StructExpr {
+    pub path: Path,
+    pub fields: Vec<ExprField>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Closure {
+    pub params: Vec<ClosureParam>,
+    /// The return type, if written. The body is then a block.
+    pub ret_ty: Option<Ty>,
+    pub body: Box<Expr>,
+}
+
+/// `x` or `x: i64` in a closure. The type is inferred if omitted.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ClosureParam {
+    pub id: NodeId,
+    pub mutbl: Mutability,
+    pub ident: Ident,
+    pub ty: Option<Ty>,
+    pub span: Span,
 }
 
-#[derive(Debug)]
+/// `x: 1` in a struct literal.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ExprField {
+    pub ident: Ident,
+    pub expr: Expr,
+    pub span: Span,
+}
+
+/// `pat if guard => body` in a `match` expression.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Arm {
+    pub id: NodeId,
+    pub pat: Pat,
+    pub guard: Option<Expr>,
+    pub body: Expr,
+    pub span: Span,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Pat {
+    pub id: NodeId,
+    pub kind: PatKind,
+    pub span: Span,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub enum PatKind {
+    /// `_`
+    Wild,
+    /// `x` or `mut x`, binding the matched value to a new variable.
+    Ident(Mutability, Ident),
+    /// A literal, possibly negated, e.g. `1`, `-1` or `'a'`.
+    Lit(Box<Expr>),
+    /// A unit variant, e.g. `Shape::Empty`.
+    Path(Path),
+    /// `(a, b)`, or `(a,)` with one element.
+    Tuple(Vec<Pat>),
+    /// `Shape::Circle(r)`
+    TupleStruct(Path, Vec<Pat>),
+    /// `Shape::Rect { w, h: 1.0, .. }`. The flag is set if the pattern ends
+    /// with `..`.
+    Struct(Path, Vec<PatField>, bool),
+    /// `p | q`
+    Or(Vec<Pat>),
+}
+
+/// `h: 1.0` in a struct pattern. The shorthand `w` is `w: w`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PatField {
+    pub ident: Ident,
+    pub pat: Pat,
+    pub span: Span,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
 pub enum LiteralKind {
-    Str(String),
+    /// The string with its escapes already processed.
+    Str(Symbol),
     Char(char),
-    Int(u128),
-    Float(f64),
+    Int(u128, Option<LitSuffix>),
+    /// A float, whose suffix is `f32` or `f64` if any.
+    Float(f64, Option<LitSuffix>),
     Bool(bool),
 }
 
-#[derive(Debug)]
+/// The numeric type a number literal is written with, e.g. `u8` in
+/// `300u8`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LitSuffix {
+    I8,
+    I16,
+    I32,
+    I64,
+    I128,
+    U8,
+    U16,
+    U32,
+    U64,
+    U128,
+    Usize,
+    F32,
+    F64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum BinOp {
     Add,
     Sub,
     Mul,
     Div,
+    Rem,
+    BitAnd,
+    BitOr,
+    BitXor,
+    Shl,
+    Shr,
     Eq,
     Ne,
     Lt,
@@ -44,8 +521,28 @@ pub enum BinOp {
     Ge,
 }
 
-#[derive(Debug)]
+/// Whether a variable can be assigned to after it is initialized.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Mutability {
+    /// `x`, the default.
+    Not,
+    /// `mut x`
+    Mut,
+}
+
+/// Whether a range includes its end.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RangeLimits {
+    /// `start..end`
+    HalfOpen,
+    /// `start..=end`
+    Closed,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum UnOp {
     Not,
     Neg,
+    /// `~`, the bitwise complement of an integer.
+    BitNot,
 }
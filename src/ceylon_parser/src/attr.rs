@@ -0,0 +1,383 @@
+//! Attributes, such as `@inline` or `@deprecated("use bar")`, written before
+//! items and statements.
+//!
+//! Only the attributes of [`BUILTIN_ATTRIBUTES`] exist. The parser reports
+//! attributes which are unknown, malformed or placed on something they
+//! don't apply to, and later passes look them up with [`find_attr`]:
+//!
+//! - `@allow(lint, ...)` silences lints inside the item or statement.
+//! - `@cfg(predicate)` removes the item or statement unless the predicate
+//!   holds, see [`crate::cfg`].
+//! - `@deprecated` and `@deprecated("note")` warn where the item is used.
+//! - `@inline` hints that calls of a function should be inlined.
+//! - `@test` marks a function for the test harness to run.
+
+use super::*;
+use ast::*;
+use ceylon_lexer::LitKind;
+use ceylon_span::Symbol;
+
+/// What an attribute is attached to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Target {
+    /// A function which isn't a method.
+    Fn,
+    /// A function of a trait or an impl.
+    Method,
+    Struct,
+    Enum,
+    Trait,
+    Impl,
+    Mod,
+    Import,
+    Stmt,
+}
+
+impl Target {
+    /// The target of an item of a module.
+    pub fn of_item(item: &Item) -> Target {
+        match item.kind {
+            ItemKind::Fn(_) => Target::Fn,
+            ItemKind::Struct(_) => Target::Struct,
+            ItemKind::Enum(_) => Target::Enum,
+            ItemKind::Trait(_) => Target::Trait,
+            ItemKind::Impl(_) => Target::Impl,
+            ItemKind::Mod(_) => Target::Mod,
+            ItemKind::Import(_) => Target::Import,
+        }
+    }
+
+    fn describe_plural(self) -> &'static str {
+        match self {
+            Target::Fn => "functions",
+            Target::Method => "methods",
+            Target::Struct => "structs",
+            Target::Enum => "enums",
+            Target::Trait => "traits",
+            Target::Impl => "impls",
+            Target::Mod => "modules",
+            Target::Import => "imports",
+            Target::Stmt => "statements",
+        }
+    }
+}
+
+/// The arguments an attribute takes.
+pub struct Template {
+    /// Whether the attribute can be written without arguments.
+    pub word: bool,
+    /// What the attribute takes in parentheses, if anything.
+    pub list: Option<ListArgs>,
+}
+
+pub enum ListArgs {
+    /// A single string, described by the hint, e.g. `"message"`.
+    Str(&'static str),
+    /// One or more names, described by the hint, e.g. `lint, ...`.
+    Words(&'static str),
+    /// A single `@cfg` predicate, described by the hint.
+    Predicate(&'static str),
+}
+
+pub struct BuiltinAttribute {
+    pub name: &'static str,
+    pub template: Template,
+    pub targets: &'static [Target],
+}
+
+pub const BUILTIN_ATTRIBUTES: &[BuiltinAttribute] = &[
+    BuiltinAttribute {
+        name: "allow",
+        template: Template {
+            word: false,
+            list: Some(ListArgs::Words("lint, ...")),
+        },
+        targets: &[
+            Target::Fn,
+            Target::Method,
+            Target::Struct,
+            Target::Enum,
+            Target::Trait,
+            Target::Impl,
+            Target::Mod,
+            Target::Stmt,
+        ],
+    },
+    BuiltinAttribute {
+        name: "cfg",
+        template: Template {
+            word: false,
+            list: Some(ListArgs::Predicate("predicate")),
+        },
+        targets: &[
+            Target::Fn,
+            Target::Method,
+            Target::Struct,
+            Target::Enum,
+            Target::Trait,
+            Target::Impl,
+            Target::Mod,
+            Target::Import,
+            Target::Stmt,
+        ],
+    },
+    BuiltinAttribute {
+        name: "deprecated",
+        template: Template {
+            word: true,
+            list: Some(ListArgs::Str("\"note\"")),
+        },
+        targets: &[Target::Fn, Target::Struct, Target::Enum, Target::Trait],
+    },
+    BuiltinAttribute {
+        name: "inline",
+        template: Template {
+            word: true,
+            list: None,
+        },
+        targets: &[Target::Fn, Target::Method],
+    },
+    BuiltinAttribute {
+        name: "test",
+        template: Template {
+            word: true,
+            list: None,
+        },
+        targets: &[Target::Fn],
+    },
+];
+
+fn builtin_attribute(name: Symbol) -> Option<&'static BuiltinAttribute> {
+    BUILTIN_ATTRIBUTES
+        .iter()
+        .find(|attr| attr.name == name.as_str())
+}
+
+impl Attribute {
+    pub fn name(&self) -> Symbol {
+        self.meta.ident.name
+    }
+
+    /// The arguments in parentheses, which are empty if there are none.
+    pub fn list(&self) -> &[NestedMeta] {
+        match &self.meta.kind {
+            MetaItemKind::List(list) => list,
+            MetaItemKind::Word | MetaItemKind::NameValue(_) => &[],
+        }
+    }
+}
+
+/// Finds the attribute with the given name.
+pub fn find_attr<'a>(attrs: &'a [Attribute], name: &str) -> Option<&'a Attribute> {
+    attrs.iter().find(|attr| attr.name().as_str() == name)
+}
+
+/// The `@deprecated` attribute of an item.
+pub struct Deprecation {
+    pub note: Option<Symbol>,
+}
+
+pub fn deprecation(attrs: &[Attribute]) -> Option<Deprecation> {
+    let attr = find_attr(attrs, "deprecated")?;
+    let note = match attr.list() {
+        [NestedMeta::Str(note, _)] => Some(*note),
+        _ => None,
+    };
+    Some(Deprecation { note })
+}
+
+/// The lints listed by `@allow`.
+pub fn allowed_lints(attrs: &[Attribute]) -> impl Iterator<Item = Symbol> + '_ {
+    attrs
+        .iter()
+        .filter(|attr| attr.name().as_str() == "allow")
+        .flat_map(|attr| attr.list())
+        .filter_map(|nested| match nested {
+            NestedMeta::Meta(meta) => Some(meta.ident.name),
+            NestedMeta::Str(..) => None,
+        })
+}
+
+/// Whether `@allow` lists the lint.
+pub fn is_lint_allowed(attrs: &[Attribute], lint: &str) -> bool {
+    allowed_lints(attrs).any(|name| name.as_str() == lint)
+}
+
+impl Parser<'_> {
+    /// Parses `@name`, `@name = "value"` or `@name(args)`.
+    pub(crate) fn parse_attr(&mut self) -> PResult<Attribute> {
+        let start = self.expect(TokenKind::At)?.span;
+        let meta = self.parse_meta_item()?;
+        Ok(Attribute {
+            span: start.append(meta.span),
+            meta,
+        })
+    }
+
+    /// Parses the attributes before a statement.
+    pub(crate) fn parse_outer_attrs(&mut self) -> PResult<Vec<Attribute>> {
+        let mut attrs = Vec::new();
+        while self.token.kind == TokenKind::At {
+            attrs.push(self.parse_attr()?);
+        }
+        Ok(attrs)
+    }
+
+    fn parse_meta_item(&mut self) -> PResult<MetaItem> {
+        let ident = self.parse_ident()?;
+        let kind = match self.token.kind {
+            TokenKind::OpenParen => {
+                self.bump();
+                let mut list = Vec::new();
+                while self.token.kind != TokenKind::CloseParen {
+                    if let TokenKind::Literal {
+                        kind: LitKind::Str { .. },
+                    } = self.token.kind
+                    {
+                        let (s, span) = self.parse_str()?;
+                        list.push(NestedMeta::Str(s, span));
+                    } else {
+                        list.push(NestedMeta::Meta(self.parse_meta_item()?));
+                    }
+                    if self.token.kind != TokenKind::Comma {
+                        break;
+                    }
+                    self.bump();
+                }
+                self.expect(TokenKind::CloseParen)?;
+                MetaItemKind::List(list)
+            }
+            TokenKind::Eq => {
+                self.bump();
+                let (s, _) = self.parse_str()?;
+                MetaItemKind::NameValue(s)
+            }
+            _ => MetaItemKind::Word,
+        };
+        Ok(MetaItem {
+            ident,
+            kind,
+            span: ident.span.append(self.prev_token.span),
+        })
+    }
+
+    fn parse_str(&mut self) -> PResult<(Symbol, Span)> {
+        let TokenKind::Literal {
+            kind: LitKind::Str { .. },
+        } = self.token.kind
+        else {
+            return Err(self.unexpected("string literal"));
+        };
+        let t = self.token;
+        self.bump();
+        match self.to_ast_literal(t)? {
+            LiteralKind::Str(s) => Ok((s, t.span)),
+            _ => unreachable!(),
+        }
+    }
+
+    /// Reports attributes which are unknown, malformed, repeated, or which
+    /// don't apply to the target.
+    pub(crate) fn check_attrs(&mut self, attrs: &[Attribute], target: Target) {
+        for (i, attr) in attrs.iter().enumerate() {
+            let name = attr.name();
+            let Some(builtin) = builtin_attribute(name) else {
+                self.diagnostics.push(Diagnostic::error(
+                    attr.meta.ident.span,
+                    format!("cannot find attribute `{}`", name),
+                ));
+                continue;
+            };
+            if !is_well_formed(&builtin.template, &attr.meta) {
+                let mut forms = Vec::new();
+                if builtin.template.word {
+                    forms.push(format!("`@{}`", name));
+                }
+                if let Some(
+                    ListArgs::Str(hint) | ListArgs::Words(hint) | ListArgs::Predicate(hint),
+                ) = &builtin.template.list
+                {
+                    forms.push(format!("`@{}({})`", name, hint));
+                }
+                self.diagnostics.push(
+                    Diagnostic::error(attr.span, format!("malformed `{}` attribute", name)).help(
+                        format!("the attribute must be of the form {}", forms.join(" or ")),
+                    ),
+                );
+                continue;
+            }
+            if !builtin.targets.contains(&target) {
+                let targets: Vec<&str> = builtin
+                    .targets
+                    .iter()
+                    .map(|target| target.describe_plural())
+                    .collect();
+                let targets = match &targets[..] {
+                    [init @ .., last] if !init.is_empty() => {
+                        format!("{} and {}", init.join(", "), last)
+                    }
+                    targets => targets.concat(),
+                };
+                self.diagnostics.push(
+                    Diagnostic::error(
+                        attr.span,
+                        format!(
+                            "`@{}` cannot be applied to {}",
+                            name,
+                            target.describe_plural()
+                        ),
+                    )
+                    .note(format!("`@{}` can only be applied to {}", name, targets)),
+                );
+                continue;
+            }
+            // `@allow` may be repeated to list more lints, and `@cfg` to
+            // require more predicates.
+            if !["allow", "cfg"].contains(&name.as_str())
+                && attrs[..i].iter().any(|prev| prev.name() == name)
+            {
+                self.diagnostics.push(Diagnostic::warning(
+                    attr.span,
+                    format!("duplicate attribute `{}`", name),
+                ));
+            }
+            if name.as_str() == "allow" {
+                for nested in attr.list() {
+                    if let NestedMeta::Meta(meta) = nested {
+                        if !lint::LINT_NAMES.contains(&meta.ident.name.as_str()) {
+                            self.diagnostics.push(Diagnostic::warning(
+                                meta.span,
+                                format!("unknown lint: `{}`", meta.ident.name),
+                            ));
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn is_well_formed(template: &Template, meta: &MetaItem) -> bool {
+    match (&meta.kind, &template.list) {
+        (MetaItemKind::Word, _) => template.word,
+        (MetaItemKind::List(list), Some(ListArgs::Str(_))) => {
+            matches!(list[..], [NestedMeta::Str(..)])
+        }
+        (MetaItemKind::List(list), Some(ListArgs::Words(_))) => {
+            !list.is_empty()
+                && list.iter().all(|nested| {
+                    matches!(
+                        nested,
+                        NestedMeta::Meta(MetaItem {
+                            kind: MetaItemKind::Word,
+                            ..
+                        })
+                    )
+                })
+        }
+        (MetaItemKind::List(list), Some(ListArgs::Predicate(_))) => {
+            matches!(list[..], [NestedMeta::Meta(_)])
+        }
+        (MetaItemKind::List(_), None) | (MetaItemKind::NameValue(_), _) => false,
+    }
+}
@@ -0,0 +1,160 @@
+//! Conditional compilation. An item or a statement with `@cfg(predicate)` is
+//! removed from the AST unless the predicate holds for the configuration,
+//! before names are resolved. Predicates are names such as `debug`,
+//! name-value pairs such as `target_os = "linux"`, and `all(...)`,
+//! `any(...)` and `not(...)` of other predicates.
+
+use std::{collections::BTreeSet, fmt};
+
+use super::*;
+use ast::*;
+use mut_visit::MutVisitor;
+
+/// The configuration predicates are checked against: a set of names and
+/// name-value pairs, set by the driver.
+#[derive(Debug, Default, Clone)]
+pub struct Cfg {
+    options: BTreeSet<(String, Option<String>)>,
+}
+
+impl Cfg {
+    pub fn insert(&mut self, name: &str, value: Option<&str>) {
+        self.options
+            .insert((name.to_string(), value.map(str::to_string)));
+    }
+
+    pub fn contains(&self, name: &str, value: Option<&str>) -> bool {
+        self.options
+            .contains(&(name.to_string(), value.map(str::to_string)))
+    }
+
+    /// Whether the `@cfg` attributes allow an item or statement.
+    fn is_enabled(&self, attrs: &[Attribute], diagnostics: &mut Vec<Diagnostic>) -> bool {
+        let mut enabled = true;
+        for attr in attrs.iter().filter(|attr| attr.name().as_str() == "cfg") {
+            // Malformed attributes have been reported by the parser.
+            if let [NestedMeta::Meta(predicate)] = attr.list() {
+                // Every predicate is evaluated so that all of them are checked.
+                enabled &= self.eval(predicate, diagnostics);
+            }
+        }
+        enabled
+    }
+
+    fn eval(&self, predicate: &MetaItem, diagnostics: &mut Vec<Diagnostic>) -> bool {
+        let name = predicate.ident.name.as_str();
+        let list = match &predicate.kind {
+            MetaItemKind::Word => return self.contains(name, None),
+            MetaItemKind::NameValue(value) => return self.contains(name, Some(value.as_str())),
+            MetaItemKind::List(list) => list,
+        };
+        let mut predicates = Vec::new();
+        for nested in list {
+            match nested {
+                NestedMeta::Meta(meta) => predicates.push(meta),
+                NestedMeta::Str(_, span) => diagnostics.push(Diagnostic::error(
+                    *span,
+                    "expected a predicate, found a string",
+                )),
+            }
+        }
+        let mut results = predicates.iter().map(|p| self.eval(p, diagnostics));
+        match name {
+            // Every operand is evaluated so that all of them are checked.
+            "all" => results.fold(true, |all, result| all & result),
+            "any" => results.fold(false, |any, result| any | result),
+            "not" if predicates.len() == 1 => !results.next().unwrap(),
+            "not" => {
+                diagnostics.push(Diagnostic::error(
+                    predicate.span,
+                    "`not` takes exactly one predicate",
+                ));
+                false
+            }
+            _ => {
+                diagnostics.push(
+                    Diagnostic::error(
+                        predicate.ident.span,
+                        format!("invalid predicate `{}`", name),
+                    )
+                    .note("predicates are combined with `all`, `any` and `not`"),
+                );
+                false
+            }
+        }
+    }
+}
+
+/// Prints an option per line, e.g. `target_os="linux"`.
+impl fmt::Display for Cfg {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (name, value) in &self.options {
+            match value {
+                Some(value) => writeln!(f, "{}={:?}", name, value)?,
+                None => writeln!(f, "{}", name)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Parses an option as given on the command line, `name` or
+/// `name="value"`.
+pub fn parse_option(spec: &str) -> Result<(String, Option<String>), String> {
+    let (name, value) = match spec.split_once('=') {
+        Some((name, value)) => {
+            let value = value
+                .strip_prefix('"')
+                .and_then(|value| value.strip_suffix('"'))
+                .filter(|value| !value.contains('"'));
+            match value {
+                Some(value) => (name, Some(value.to_string())),
+                None => return Err(format!("the value of `{}` must be quoted", spec)),
+            }
+        }
+        None => (spec, None),
+    };
+    let mut chars = name.chars();
+    let is_ident = chars.next().is_some_and(ceylon_lexer::is_id_start)
+        && chars.all(ceylon_lexer::is_id_continue);
+    if !is_ident {
+        return Err(format!(
+            "invalid option `{}`, expected `name` or `name=\"value\"`",
+            spec
+        ));
+    }
+    Ok((name.to_string(), value))
+}
+
+/// Removes the items and statements of a module whose `@cfg` predicates
+/// don't hold, including those of its inline modules. Modules in other
+/// files are to be stripped once they are parsed.
+pub fn strip_module(module: &mut Module, cfg: &Cfg) -> Vec<Diagnostic> {
+    let mut strip = StripUnconfigured {
+        cfg,
+        diagnostics: Vec::new(),
+    };
+    strip.visit_module(module);
+    strip.diagnostics
+}
+
+struct StripUnconfigured<'a> {
+    cfg: &'a Cfg,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl MutVisitor for StripUnconfigured<'_> {
+    fn flat_map_item(&mut self, item: Item) -> Vec<Item> {
+        if !self.cfg.is_enabled(&item.attrs, &mut self.diagnostics) {
+            return Vec::new();
+        }
+        mut_visit::walk_flat_map_item(self, item)
+    }
+
+    fn flat_map_stmt(&mut self, stmt: Stmt) -> Vec<Stmt> {
+        if !self.cfg.is_enabled(&stmt.attrs, &mut self.diagnostics) {
+            return Vec::new();
+        }
+        mut_visit::walk_flat_map_stmt(self, stmt)
+    }
+}
@@ -1,173 +1,674 @@
 use super::*;
 use ast::*;
-use ceylon_lexer::LitKind;
+use ceylon_lexer::{KwKind, LitKind};
+use ceylon_span::Symbol;
 
 impl Parser<'_> {
-    pub(crate) fn parse_expression(&mut self) -> Expr {
-        self.parse_operator_expression()
+    pub(crate) fn parse_expression(&mut self) -> PResult<Expr> {
+        self.parse_assign()
+    }
+
+    /// Parses `place = value`, or a compound assignment such as
+    /// `place += value`. Assignment is right associative.
+    fn parse_assign(&mut self) -> PResult<Expr> {
+        let lhs = self.parse_range()?;
+        let op = match self.token.kind {
+            TokenKind::Eq => None,
+            TokenKind::PlusEq => Some(BinOp::Add),
+            TokenKind::MinusEq => Some(BinOp::Sub),
+            TokenKind::StarEq => Some(BinOp::Mul),
+            TokenKind::SlashEq => Some(BinOp::Div),
+            TokenKind::PercentEq => Some(BinOp::Rem),
+            TokenKind::AndEq => Some(BinOp::BitAnd),
+            TokenKind::OrEq => Some(BinOp::BitOr),
+            TokenKind::CaretEq => Some(BinOp::BitXor),
+            TokenKind::ShlEq => Some(BinOp::Shl),
+            TokenKind::ShrEq => Some(BinOp::Shr),
+            _ => return Ok(lhs),
+        };
+        self.bump();
+        let rhs = self.parse_assign()?;
+        let span = lhs.span.append(rhs.span);
+        let kind = match op {
+            Some(op) => ExprKind::AssignOp(op, Box::new(lhs), Box::new(rhs)),
+            None => ExprKind::Assign(Box::new(lhs), Box::new(rhs)),
+        };
+        Ok(self.mk_expr(kind, span))
+    }
+
+    /// Parses `start..end`, `start..=end` or `start..`. Ranges bind more
+    /// loosely than the other operators and don't chain.
+    fn parse_range(&mut self) -> PResult<Expr> {
+        let start = self.parse_operator_expression()?;
+        let limits = match self.token.kind {
+            TokenKind::DotDot => RangeLimits::HalfOpen,
+            TokenKind::DotDotEq => RangeLimits::Closed,
+            _ => return Ok(start),
+        };
+        self.bump();
+        let ends_here = matches!(
+            self.token.kind,
+            TokenKind::CloseBracket
+                | TokenKind::CloseParen
+                | TokenKind::CloseBrace
+                | TokenKind::Comma
+                | TokenKind::Semi
+                | TokenKind::Eof
+        );
+        let end = match limits {
+            RangeLimits::HalfOpen if ends_here => None,
+            _ => Some(Box::new(self.parse_operator_expression()?)),
+        };
+        let span = start.span.append(self.prev_token.span);
+        Ok(self.mk_expr(ExprKind::Range(Box::new(start), end, limits), span))
     }
 
     /// Parse operator expression
     // Operator precedence is as follows:
-    // unary+,-  >  *,/,% > binary+,-  >  <,>,<=,>=  >  ==,!= 
-    fn parse_operator_expression(&mut self) -> Expr {
+    // .field  >  unary+,-  >  as  >  *,/,%  >  binary+,-  >  <<,>>  >  &  >  ^  >  |
+    //   >  <,>,<=,>=  >  ==,!=
+    fn parse_operator_expression(&mut self) -> PResult<Expr> {
         self.parse_equality()
     }
 
-    fn parse_equality(&mut self) -> Expr {
-        let mut expr = self.parse_relational();
-        loop {
-            let binop = match self.token.kind {
-                TokenKind::EqEq => BinOp::Eq,
-                TokenKind::BangEq => BinOp::Ne,
-                _ => return expr,
-            };
+    fn parse_equality(&mut self) -> PResult<Expr> {
+        self.parse_binary(Self::parse_relational, |kind| match kind {
+            TokenKind::EqEq => Some(BinOp::Eq),
+            TokenKind::BangEq => Some(BinOp::Ne),
+            _ => None,
+        })
+    }
+
+    fn parse_relational(&mut self) -> PResult<Expr> {
+        self.parse_binary(Self::parse_bit_or, |kind| match kind {
+            TokenKind::Lt => Some(BinOp::Lt),
+            TokenKind::Gt => Some(BinOp::Gt),
+            TokenKind::LtEq => Some(BinOp::Le),
+            TokenKind::GtEq => Some(BinOp::Ge),
+            _ => None,
+        })
+    }
+
+    fn parse_bit_or(&mut self) -> PResult<Expr> {
+        self.parse_binary(Self::parse_bit_xor, |kind| match kind {
+            TokenKind::Or => Some(BinOp::BitOr),
+            _ => None,
+        })
+    }
+
+    fn parse_bit_xor(&mut self) -> PResult<Expr> {
+        self.parse_binary(Self::parse_bit_and, |kind| match kind {
+            TokenKind::Caret => Some(BinOp::BitXor),
+            _ => None,
+        })
+    }
+
+    fn parse_bit_and(&mut self) -> PResult<Expr> {
+        self.parse_binary(Self::parse_shift, |kind| match kind {
+            TokenKind::And => Some(BinOp::BitAnd),
+            _ => None,
+        })
+    }
+
+    fn parse_shift(&mut self) -> PResult<Expr> {
+        self.parse_binary(Self::parse_add, |kind| match kind {
+            TokenKind::Shl => Some(BinOp::Shl),
+            TokenKind::Shr => Some(BinOp::Shr),
+            _ => None,
+        })
+    }
+
+    fn parse_add(&mut self) -> PResult<Expr> {
+        self.parse_binary(Self::parse_mul, |kind| match kind {
+            TokenKind::Plus => Some(BinOp::Add),
+            TokenKind::Minus => Some(BinOp::Sub),
+            _ => None,
+        })
+    }
+
+    fn parse_mul(&mut self) -> PResult<Expr> {
+        self.parse_binary(Self::parse_cast, |kind| match kind {
+            TokenKind::Star => Some(BinOp::Mul),
+            TokenKind::Slash => Some(BinOp::Div),
+            TokenKind::Percent => Some(BinOp::Rem),
+            _ => None,
+        })
+    }
+
+    /// Parses a left associative chain of the binary operators `binop`
+    /// recognizes, whose operands are parsed by `operand`.
+    fn parse_binary(
+        &mut self,
+        operand: fn(&mut Self) -> PResult<Expr>,
+        binop: fn(TokenKind) -> Option<BinOp>,
+    ) -> PResult<Expr> {
+        let mut expr = operand(self)?;
+        while let Some(binop) = binop(self.token.kind) {
             // Eat a operator
             self.bump();
-            let oprand = self.parse_relational();
+            let oprand = operand(self)?;
             let span = expr.span.append(oprand.span);
-            expr = Expr::new(
+            expr = self.mk_expr(
                 ExprKind::Binary(binop, Box::new(expr), Box::new(oprand)),
                 span,
             );
         }
-        expr
+        Ok(expr)
     }
 
-    fn parse_relational(&mut self) -> Expr {
-        let mut expr = self.parse_add();
-        loop {
-            let binop = match self.token.kind {
-                TokenKind::Lt => BinOp::Lt,
-                TokenKind::Gt => BinOp::Gt,
-                TokenKind::LtEq => BinOp::Le,
-                TokenKind::GtEq => BinOp::Ge,
-                _ => return expr,
-            };
-            // Eat a operator
+    /// Parses `e as T`, which binds tighter than binary operators and looser
+    /// than unary ones, e.g. `-x as u64 * 2` is `((-x) as u64) * 2`.
+    fn parse_cast(&mut self) -> PResult<Expr> {
+        let mut expr = self.parse_unary()?;
+        while self.token.kind == (TokenKind::Keyword { kind: KwKind::As }) {
             self.bump();
-            let oprand = self.parse_add();
-            let span = expr.span.append(oprand.span);
-            expr = Expr::new(
-                ExprKind::Binary(binop, Box::new(expr), Box::new(oprand)),
-                span,
-            );
+            let ty = self.parse_ty()?;
+            let span = expr.span.append(ty.span);
+            expr = self.mk_expr(ExprKind::Cast(Box::new(expr), ty), span);
         }
-        expr
+        Ok(expr)
     }
 
-    fn parse_add(&mut self) -> Expr {
-        let mut expr = self.parse_mul();
+    fn parse_unary(&mut self) -> PResult<Expr> {
+        let unop = match self.token.kind {
+            TokenKind::Plus => {
+                self.bump();
+                return self.parse_postfix();
+            }
+            TokenKind::Minus => UnOp::Neg,
+            TokenKind::Bang => UnOp::Not,
+            TokenKind::Tilde => UnOp::BitNot,
+            _ => return self.parse_postfix(),
+        };
+        let start = self.token.span;
+        // Eat a operator
+        self.bump();
+        let oprand = self.parse_unary()?;
+        let span = start.append(oprand.span);
+        Ok(self.mk_expr(ExprKind::Unary(unop, Box::new(oprand)), span))
+    }
+
+    /// Parses field accesses, calls, indexing and `?`, such as `p.x.y`,
+    /// `f(x)`, `s.area()`, `a[i]` and `parse(s)?`.
+    fn parse_postfix(&mut self) -> PResult<Expr> {
+        let mut expr = self.parse_primary()?;
         loop {
-            let binop = match self.token.kind {
-                TokenKind::Plus => BinOp::Add,
-                TokenKind::Minus => BinOp::Sub,
-                _ => return expr,
+            match self.token.kind {
+                TokenKind::Dot => {
+                    self.bump();
+                    if let TokenKind::Literal { .. } = self.token.kind {
+                        expr = self.parse_tuple_field(expr)?;
+                        continue;
+                    }
+                    let ident = self.parse_ident()?;
+                    if self.token.kind == TokenKind::OpenParen {
+                        self.bump();
+                        let args = self.with_struct_literals(|this| this.parse_call_args())?;
+                        let span = expr.span.append(self.prev_token.span);
+                        let kind = ExprKind::MethodCall(Box::new(expr), ident, args);
+                        expr = self.mk_expr(kind, span);
+                        continue;
+                    }
+                    let span = expr.span.append(ident.span);
+                    expr = self.mk_expr(ExprKind::Field(Box::new(expr), ident), span);
+                }
+                TokenKind::OpenParen => {
+                    self.bump();
+                    let args = self.with_struct_literals(|this| this.parse_call_args())?;
+                    let span = expr.span.append(self.prev_token.span);
+                    expr = self.mk_expr(ExprKind::Call(Box::new(expr), args), span);
+                }
+                TokenKind::OpenBracket => {
+                    self.bump();
+                    let index = self.with_struct_literals(|this| this.parse_expression())?;
+                    let end = self.expect(TokenKind::CloseBracket)?;
+                    let span = expr.span.append(end.span);
+                    expr = self.mk_expr(ExprKind::Index(Box::new(expr), Box::new(index)), span);
+                }
+                TokenKind::Question => {
+                    self.bump();
+                    let span = expr.span.append(self.prev_token.span);
+                    expr = self.mk_expr(ExprKind::Try(Box::new(expr)), span);
+                }
+                _ => return Ok(expr),
+            }
+        }
+    }
+
+    /// Parses the index of a tuple element after the dot, e.g. `0` in `t.0`.
+    /// The lexer reads `t.0.1` as `t`, `.` and the float `0.1`, which is two
+    /// indices.
+    fn parse_tuple_field(&mut self, base: Expr) -> PResult<Expr> {
+        let token = self.token;
+        let text = self.reader.span_to_str(&token.span);
+        let is_index = |s: &str| !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit());
+        let indices: Vec<&str> = text.split('.').collect();
+        if !indices.iter().all(|index| is_index(index)) {
+            return Err(Diagnostic::error(
+                token.span,
+                format!("invalid tuple index `{}`", text),
+            ));
+        }
+        self.bump();
+        let mut expr = base;
+        let mut start = token.span.start_pos;
+        for index in indices {
+            let span = Span {
+                start_pos: start,
+                len: index.len(),
             };
-            // Eat a operator
-            self.bump();
-            let oprand = self.parse_mul();
-            let span = expr.span.append(oprand.span);
-            expr = Expr::new(
-                ExprKind::Binary(binop, Box::new(expr), Box::new(oprand)),
+            start += index.len() + 1;
+            let ident = Ident {
+                name: Symbol::intern(index),
                 span,
-            );
+            };
+            let span = expr.span.append(span);
+            expr = self.mk_expr(ExprKind::Field(Box::new(expr), ident), span);
         }
-        expr
+        Ok(expr)
     }
 
-    fn parse_mul(&mut self) -> Expr {
-        let mut expr = self.parse_unary();
-        loop {
-            let binop = match self.token.kind {
-                TokenKind::Star => BinOp::Mul,
-                TokenKind::Slash => BinOp::Div,
-                _ => return expr,
-            };
-            // Eat a operator
+    /// Parses the arguments of a call after the opening parenthesis.
+    fn parse_call_args(&mut self) -> PResult<Vec<Expr>> {
+        let mut args = Vec::new();
+        while self.token.kind != TokenKind::CloseParen {
+            args.push(self.parse_expression()?);
+            if self.token.kind != TokenKind::Comma {
+                break;
+            }
             self.bump();
-            let oprand = self.parse_unary();
-            let span = expr.span.append(oprand.span);
-            expr = Expr::new(
-                ExprKind::Binary(binop, Box::new(expr), Box::new(oprand)),
-                span,
-            );
         }
-        expr
+        self.expect(TokenKind::CloseParen)?;
+        Ok(args)
     }
 
-    fn parse_unary(&mut self) -> Expr {
+    fn parse_primary(&mut self) -> PResult<Expr> {
+        let expr;
         match self.token.kind {
-            TokenKind::Plus => {
+            TokenKind::Literal { .. } => {
+                let lit = self.to_ast_literal(self.token)?;
+                expr = self.mk_expr(ExprKind::Literal(lit), self.token.span);
+                self.bump();
+            }
+            TokenKind::Keyword {
+                kind: kind @ (KwKind::True | KwKind::False),
+            } => {
+                let lit = LiteralKind::Bool(kind == KwKind::True);
+                expr = self.mk_expr(ExprKind::Literal(lit), self.token.span);
                 self.bump();
             }
-            TokenKind::Minus => {
-                todo!()
+            TokenKind::Ident => {
+                let path = self.parse_path()?;
+                if self.token.kind == TokenKind::OpenBrace && !self.no_struct_literal {
+                    return self.parse_struct_expr(path);
+                }
+                let span = path.span;
+                expr = self.mk_expr(ExprKind::Path(path), span);
+            }
+            TokenKind::OpenParen => return self.with_struct_literals(|this| this.parse_paren()),
+            TokenKind::OpenBracket => return self.with_struct_literals(|this| this.parse_array()),
+            TokenKind::OpenBrace
+            | TokenKind::Keyword {
+                kind: KwKind::Match | KwKind::For,
+            } => {
+                return self.parse_block_like();
             }
-            TokenKind::Bang => {
-                todo!()
+            TokenKind::Or => return self.parse_closure(),
+            _ => return Err(self.unexpected("expression")),
+        }
+        Ok(expr)
+    }
+
+    /// Parses a parenthesized expression or a tuple, e.g. `(a + b)`,
+    /// `(a, b)` or `(a,)`.
+    fn parse_paren(&mut self) -> PResult<Expr> {
+        let start = self.expect(TokenKind::OpenParen)?;
+        let mut exprs = vec![self.parse_expression()?];
+        if self.token.kind == TokenKind::CloseParen {
+            self.bump();
+            return Ok(exprs.pop().unwrap());
+        }
+        self.expect(TokenKind::Comma)?;
+        while self.token.kind != TokenKind::CloseParen {
+            exprs.push(self.parse_expression()?);
+            if self.token.kind != TokenKind::Comma {
+                break;
             }
-            _ => (),
+            self.bump();
         }
-        self.parse_primary()
+        let end = self.expect(TokenKind::CloseParen)?;
+        Ok(self.mk_expr(ExprKind::Tuple(exprs), start.span.append(end.span)))
     }
 
-    fn parse_primary(&mut self) -> Expr {
-        let expr;
-        match self.token.kind {
-            TokenKind::Literal { kind: k } => {
-                expr = self.to_ast_literal(self.token);
+    /// Parses an array, e.g. `[1, 2, 3]`, or a repeat expression, e.g.
+    /// `[0; 16]`.
+    fn parse_array(&mut self) -> PResult<Expr> {
+        let start = self.expect(TokenKind::OpenBracket)?;
+        let mut exprs = Vec::new();
+        if self.token.kind != TokenKind::CloseBracket {
+            let first = self.parse_expression()?;
+            if self.token.kind == TokenKind::Semi {
                 self.bump();
+                let len = self.parse_array_len()?;
+                let end = self.expect(TokenKind::CloseBracket)?;
+                let kind = ExprKind::Repeat(Box::new(first), len);
+                return Ok(self.mk_expr(kind, start.span.append(end.span)));
+            }
+            exprs.push(first);
+            while self.token.kind == TokenKind::Comma {
+                self.bump();
+                if self.token.kind == TokenKind::CloseBracket {
+                    break;
+                }
+                exprs.push(self.parse_expression()?);
             }
-            _ => unimplemented!(),
         }
-        expr
+        let end = self.expect(TokenKind::CloseBracket)?;
+        Ok(self.mk_expr(ExprKind::Array(exprs), start.span.append(end.span)))
     }
 
-    fn to_ast_literal(&self, t: Token) -> Expr {
+    /// Parses a closure, e.g. `|x: i64, y| x + y` or `|| -> i64 { 1 }`. The
+    /// body of a closure with a return type must be a block.
+    fn parse_closure(&mut self) -> PResult<Expr> {
+        let start = self.expect(TokenKind::Or)?;
+        let mut params = Vec::new();
+        while self.token.kind != TokenKind::Or {
+            let start = self.token.span;
+            let mutbl = self.parse_mutability();
+            let ident = self.parse_ident()?;
+            let ty = if self.token.kind == TokenKind::Colon {
+                self.bump();
+                Some(self.parse_ty()?)
+            } else {
+                None
+            };
+            let span = match &ty {
+                Some(ty) => start.append(ty.span),
+                None => start.append(ident.span),
+            };
+            params.push(ClosureParam {
+                id: self.next_node_id(),
+                mutbl,
+                ident,
+                ty,
+                span,
+            });
+            if self.token.kind != TokenKind::Comma {
+                break;
+            }
+            self.bump();
+        }
+        self.expect(TokenKind::Or)?;
+        let ret_ty = if self.token.kind == TokenKind::Arrow {
+            self.bump();
+            Some(self.parse_ty()?)
+        } else {
+            None
+        };
+        let body = if ret_ty.is_some() {
+            if self.token.kind != TokenKind::OpenBrace {
+                return Err(self.unexpected("`{` after the return type of a closure"));
+            }
+            self.parse_block_like()?
+        } else {
+            self.parse_expression()?
+        };
+        let span = start.span.append(body.span);
+        Ok(self.mk_expr(
+            ExprKind::Closure(Closure {
+                params,
+                ret_ty,
+                body: Box::new(body),
+            }),
+            span,
+        ))
+    }
+
+    /// Parses an expression ending with a block, i.e. a block, a `match` or
+    /// a `for` loop.
+    pub(crate) fn parse_block_like(&mut self) -> PResult<Expr> {
+        if self.token.kind == TokenKind::OpenBrace {
+            let block = self.with_struct_literals(|this| this.parse_block())?;
+            let span = block.span;
+            return Ok(self.mk_expr(ExprKind::Block(block), span));
+        }
+        if self.token.kind == (TokenKind::Keyword { kind: KwKind::For }) {
+            return self.parse_for_loop();
+        }
+        let start = self.expect(TokenKind::Keyword {
+            kind: KwKind::Match,
+        })?;
+        // `match x { ... }` must not be read as a struct literal `x { ... }`.
+        let scrutinee = self.without_struct_literals(|this| this.parse_expression())?;
+        self.expect(TokenKind::OpenBrace)?;
+        let mut arms = Vec::new();
+        while self.token.kind != TokenKind::CloseBrace {
+            match self.with_struct_literals(|this| this.parse_arm_with_sep()) {
+                Ok(arm) => arms.push(arm),
+                Err(diag) => {
+                    // Skip the remaining arms rather than the rest of the
+                    // function.
+                    self.diagnostics.push(diag);
+                    self.recover_to_close_brace();
+                }
+            }
+        }
+        let end = self.expect(TokenKind::CloseBrace)?;
+        Ok(self.mk_expr(
+            ExprKind::Match(Box::new(scrutinee), arms),
+            start.span.append(end.span),
+        ))
+    }
+
+    fn parse_for_loop(&mut self) -> PResult<Expr> {
+        let start = self.expect(TokenKind::Keyword { kind: KwKind::For })?;
+        let pat = self.parse_pat()?;
+        self.expect(TokenKind::Keyword { kind: KwKind::In })?;
+        // `for x in xs { ... }` must not be read as a struct literal.
+        let iter = self.without_struct_literals(|this| this.parse_expression())?;
+        let body = self.with_struct_literals(|this| this.parse_block())?;
+        let span = start.span.append(body.span);
+        Ok(self.mk_expr(ExprKind::ForLoop(pat, Box::new(iter), body), span))
+    }
+
+    /// Parses an arm and the comma after it, which is optional after a
+    /// block-like body and after the last arm.
+    fn parse_arm_with_sep(&mut self) -> PResult<Arm> {
+        let arm = self.parse_arm()?;
+        let block_like = is_block_like(&arm.body);
+        if self.token.kind == TokenKind::Comma {
+            self.bump();
+        } else if !block_like && self.token.kind != TokenKind::CloseBrace {
+            return Err(self.unexpected("`,` or `}` after match arm"));
+        }
+        Ok(arm)
+    }
+
+    /// Skips tokens up to the `}` closing the current braces.
+    fn recover_to_close_brace(&mut self) {
+        let mut depth = 0usize;
+        loop {
+            match self.token.kind {
+                TokenKind::Eof => return,
+                TokenKind::CloseBrace if depth == 0 => return,
+                TokenKind::OpenBrace => depth += 1,
+                TokenKind::CloseBrace => depth -= 1,
+                _ => (),
+            }
+            self.bump();
+        }
+    }
+
+    fn parse_arm(&mut self) -> PResult<Arm> {
+        let pat = self.parse_pat()?;
+        let guard = if self.token.kind == (TokenKind::Keyword { kind: KwKind::If }) {
+            self.bump();
+            Some(self.parse_expression()?)
+        } else {
+            None
+        };
+        self.expect(TokenKind::FatArrow)?;
+        let body = self.parse_expression()?;
+        let span = pat.span.append(body.span);
+        Ok(Arm {
+            id: self.next_node_id(),
+            pat,
+            guard,
+            body,
+            span,
+        })
+    }
+
+    /// Parses with struct literals allowed, as they are inside delimiters.
+    fn with_struct_literals<T>(&mut self, f: impl FnOnce(&mut Self) -> T) -> T {
+        let prev = std::mem::replace(&mut self.no_struct_literal, false);
+        let result = f(self);
+        self.no_struct_literal = prev;
+        result
+    }
+
+    /// Parses with struct literals disallowed, where a `{` starts a block.
+    fn without_struct_literals<T>(&mut self, f: impl FnOnce(&mut Self) -> T) -> T {
+        let prev = std::mem::replace(&mut self.no_struct_literal, true);
+        let result = f(self);
+        self.no_struct_literal = prev;
+        result
+    }
+
+    /// Parses the body of a struct literal, e.g. `{ x: 1, y: 2 }` in
+    /// `Point { x: 1, y: 2 }`.
+    fn parse_struct_expr(&mut self, path: Path) -> PResult<Expr> {
+        self.expect(TokenKind::OpenBrace)?;
+        let mut fields = Vec::new();
+        while self.token.kind != TokenKind::CloseBrace {
+            let ident = self.parse_ident()?;
+            self.expect(TokenKind::Colon)?;
+            let expr = self.with_struct_literals(|this| this.parse_expression())?;
+            let span = ident.span.append(expr.span);
+            fields.push(ExprField { ident, expr, span });
+            if self.token.kind != TokenKind::Comma {
+                break;
+            }
+            self.bump();
+        }
+        let end = self.expect(TokenKind::CloseBrace)?.span;
+        let span = path.span.append(end);
+        Ok(self.mk_expr(ExprKind::Struct(StructExpr { path, fields }), span))
+    }
+
+    pub(crate) fn to_ast_literal(&self, t: Token) -> PResult<LiteralKind> {
         debug_assert!(matches!(t.kind, TokenKind::Literal { .. }));
         let lit = match t.kind {
-            TokenKind::Literal { kind: LitKind::Int } => {
-                let s = self.reader.span_to_str(&t.span);
-                let n = s.parse::<u128>().unwrap();
-                LiteralKind::Int(n)
-            }
             TokenKind::Literal {
-                kind: LitKind::Float,
+                kind: kind @ (LitKind::Int | LitKind::Float),
             } => {
                 let s = self.reader.span_to_str(&t.span);
-                let f = s.parse::<f64>().unwrap();
-                LiteralKind::Float(f)
+                // The suffix starts at the first letter.
+                let (digits, written_suffix) =
+                    s.split_at(s.find(|c: char| c.is_alphabetic()).unwrap_or(s.len()));
+                let digits = digits.replace('_', "");
+                let suffix = match written_suffix {
+                    "" => None,
+                    suffix => Some(lit_suffix(suffix).ok_or_else(|| {
+                        Diagnostic::error(
+                            t.span,
+                            format!("invalid suffix `{}` for number literal", suffix),
+                        )
+                        .help("the suffix must be a numeric type, e.g. `u8` or `f32`")
+                    })?),
+                };
+                let is_float = matches!(suffix, Some(LitSuffix::F32 | LitSuffix::F64));
+                if kind == LitKind::Int && !is_float {
+                    let n = digits
+                        .parse::<u128>()
+                        .map_err(|_| Diagnostic::error(t.span, "integer literal is too large"))?;
+                    LiteralKind::Int(n, suffix)
+                } else if suffix.is_none() || is_float {
+                    LiteralKind::Float(digits.parse::<f64>().unwrap(), suffix)
+                } else {
+                    return Err(Diagnostic::error(
+                        t.span,
+                        format!("invalid suffix `{}` for float literal", written_suffix),
+                    ));
+                }
             }
             TokenKind::Literal {
                 kind: LitKind::Char { terminated: termi },
             } => {
                 if !termi {
-                    panic!("Not terminated");
+                    return Err(Diagnostic::error(t.span, "unterminated character literal"));
+                }
+                let s = unescape(self.reader.quoted_to_str(&t.span));
+                let mut chars = s.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(c), None) => LiteralKind::Char(c),
+                    _ => {
+                        return Err(Diagnostic::error(
+                            t.span,
+                            "character literal must contain exactly one character",
+                        ))
+                    }
                 }
-                let s = self.reader.quoted_to_str(&t.span);
-                let c = unescape(s).chars().nth(0).unwrap();
-                LiteralKind::Char(c)
             }
             TokenKind::Literal {
                 kind: LitKind::Str { terminated: termi },
             } => {
                 if !termi {
-                    panic!("Not terminated");
+                    return Err(Diagnostic::error(
+                        t.span,
+                        "unterminated double quote string",
+                    ));
                 }
                 let s = self.reader.quoted_to_str(&t.span);
-                LiteralKind::Str(unescape(s))
+                LiteralKind::Str(Symbol::intern(&unescape(s)))
             }
             _ => unreachable!(),
         };
-        Expr::new(ExprKind::Literal(lit), self.token.span)
+        Ok(lit)
+    }
+
+    pub(crate) fn mk_expr(&mut self, kind: ExprKind, span: Span) -> Expr {
+        Expr {
+            id: self.next_node_id(),
+            kind,
+            span,
+        }
     }
 }
 
+/// Whether an expression ends with a block, so that it needs no `;` or `,`
+/// after it.
+pub(crate) fn is_block_like(expr: &Expr) -> bool {
+    matches!(
+        expr.kind,
+        ExprKind::Block(_) | ExprKind::Match(..) | ExprKind::ForLoop(..)
+    )
+}
+
+fn lit_suffix(s: &str) -> Option<LitSuffix> {
+    let suffix = match s {
+        "i8" => LitSuffix::I8,
+        "i16" => LitSuffix::I16,
+        "i32" => LitSuffix::I32,
+        "i64" => LitSuffix::I64,
+        "i128" => LitSuffix::I128,
+        "u8" => LitSuffix::U8,
+        "u16" => LitSuffix::U16,
+        "u32" => LitSuffix::U32,
+        "u64" => LitSuffix::U64,
+        "u128" => LitSuffix::U128,
+        "usize" => LitSuffix::Usize,
+        "f32" => LitSuffix::F32,
+        "f64" => LitSuffix::F64,
+        _ => return None,
+    };
+    Some(suffix)
+}
+
 fn unescape(s: &str) -> String {
     s.replace("\\n", "\n")
         .replace("\\r", "\r")
-        .replace("\\t", "\\t")
+        .replace("\\t", "\t")
         .replace("\\\"", "\"")
         .replace("\\\'", "\'")
 }
@@ -0,0 +1,829 @@
+use super::*;
+use ast::*;
+use ceylon_lexer::{DocStyle, KwKind};
+use ceylon_span::Symbol;
+use unicode_normalization::UnicodeNormalization;
+
+impl Parser<'_> {
+    pub(crate) fn parse_module(&mut self) -> Module {
+        let start = self.token.span;
+        let docs = self.parse_docs(DocStyle::Inner);
+        let items = self.parse_mod_items(false);
+        let span = start.append(self.token.span);
+        Module { docs, items, span }
+    }
+
+    /// Parses items until the end of the file, or until the `}` closing an
+    /// inline module.
+    fn parse_mod_items(&mut self, inline: bool) -> Vec<Item> {
+        let mut items = Vec::new();
+        loop {
+            match self.token.kind {
+                TokenKind::Eof => break,
+                TokenKind::CloseBrace if inline => break,
+                _ => (),
+            }
+            match self.parse_item() {
+                Ok(item) => {
+                    self.check_attrs(&item.attrs, attr::Target::of_item(&item));
+                    items.push(item);
+                }
+                Err(diag) => {
+                    self.diagnostics.push(diag);
+                    self.recover_to_item(inline);
+                }
+            }
+        }
+        items
+    }
+
+    pub(crate) fn parse_item(&mut self) -> PResult<Item> {
+        // Doc comments and attributes may come in any order.
+        let mut docs = Vec::new();
+        let mut attrs = Vec::new();
+        loop {
+            docs.extend(self.parse_docs(DocStyle::Outer));
+            while self.doc_style() == Some(DocStyle::Inner) {
+                self.diagnostics.push(
+                    Diagnostic::error(self.token.span, "expected outer doc comment")
+                        .note("inner doc comments must be placed at the beginning of the module"),
+                );
+                self.bump();
+            }
+            if self.token.kind != TokenKind::At {
+                break;
+            }
+            attrs.push(self.parse_attr()?);
+        }
+        let start = self.token.span;
+        let vis = if self.token.kind == (TokenKind::Keyword { kind: KwKind::Pub }) {
+            self.bump();
+            Visibility::Public
+        } else {
+            Visibility::Private
+        };
+        match self.token.kind {
+            TokenKind::Keyword { kind: KwKind::Fn } => {
+                self.bump();
+                let ident = self.parse_ident()?;
+                let decl = self.parse_fn_decl()?;
+                let span = start.append(self.prev_token.span);
+                Ok(Item {
+                    id: self.next_node_id(),
+                    docs,
+                    attrs,
+                    vis,
+                    ident,
+                    kind: ItemKind::Fn(decl),
+                    span,
+                })
+            }
+            TokenKind::Keyword {
+                kind: KwKind::Struct,
+            } => {
+                self.bump();
+                let ident = self.parse_ident()?;
+                let def = self.parse_struct_def()?;
+                let span = start.append(self.prev_token.span);
+                Ok(Item {
+                    id: self.next_node_id(),
+                    docs,
+                    attrs,
+                    vis,
+                    ident,
+                    kind: ItemKind::Struct(def),
+                    span,
+                })
+            }
+            TokenKind::Keyword { kind: KwKind::Enum } => {
+                self.bump();
+                let ident = self.parse_ident()?;
+                let def = self.parse_enum_def()?;
+                let span = start.append(self.prev_token.span);
+                Ok(Item {
+                    id: self.next_node_id(),
+                    docs,
+                    attrs,
+                    vis,
+                    ident,
+                    kind: ItemKind::Enum(def),
+                    span,
+                })
+            }
+            TokenKind::Keyword {
+                kind: KwKind::Trait,
+            } => {
+                self.bump();
+                let ident = self.parse_ident()?;
+                let items = self.parse_assoc_items()?;
+                let span = start.append(self.prev_token.span);
+                Ok(Item {
+                    id: self.next_node_id(),
+                    docs,
+                    attrs,
+                    vis,
+                    ident,
+                    kind: ItemKind::Trait(TraitDef { items }),
+                    span,
+                })
+            }
+            TokenKind::Keyword { kind: KwKind::Impl } => {
+                if vis == Visibility::Public {
+                    self.diagnostics.push(
+                        Diagnostic::error(start, "visibility qualifiers are not permitted here")
+                            .note("impls have no name to be used by"),
+                    );
+                }
+                self.bump();
+                let ident = Ident {
+                    name: Symbol::intern(""),
+                    span: start,
+                };
+                let def = self.parse_impl_def()?;
+                let span = start.append(self.prev_token.span);
+                Ok(Item {
+                    id: self.next_node_id(),
+                    docs,
+                    attrs,
+                    vis,
+                    ident,
+                    kind: ItemKind::Impl(def),
+                    span,
+                })
+            }
+            TokenKind::Keyword { kind: KwKind::Mod } => {
+                self.bump();
+                let ident = self.parse_ident()?;
+                let def = if self.token.kind == TokenKind::Semi {
+                    self.bump();
+                    ModDef {
+                        inline: false,
+                        module: None,
+                        package: false,
+                        same_as: None,
+                    }
+                } else {
+                    let open = self.expect(TokenKind::OpenBrace)?;
+                    let docs = self.parse_docs(DocStyle::Inner);
+                    let items = self.parse_mod_items(true);
+                    let close = self.expect(TokenKind::CloseBrace)?;
+                    ModDef {
+                        inline: true,
+                        module: Some(Module {
+                            docs,
+                            items,
+                            span: open.span.append(close.span),
+                        }),
+                        package: false,
+                        same_as: None,
+                    }
+                };
+                let span = start.append(self.prev_token.span);
+                Ok(Item {
+                    id: self.next_node_id(),
+                    docs,
+                    attrs,
+                    vis,
+                    ident,
+                    kind: ItemKind::Mod(def),
+                    span,
+                })
+            }
+            TokenKind::Keyword {
+                kind: KwKind::Import,
+            } => {
+                self.bump();
+                let path = self.parse_path()?;
+                self.expect(TokenKind::Semi)?;
+                let span = start.append(self.prev_token.span);
+                Ok(Item {
+                    id: self.next_node_id(),
+                    docs,
+                    attrs,
+                    vis,
+                    ident: *path.segments.last().unwrap(),
+                    kind: ItemKind::Import(path),
+                    span,
+                })
+            }
+            _ if !docs.is_empty() => Err(self
+                .unexpected("item after doc comment")
+                .help("doc comments must come before what they document")),
+            _ if !attrs.is_empty() => Err(self.unexpected("item after attribute")),
+            _ => Err(self.unexpected("item")),
+        }
+    }
+
+    /// Skips tokens until the start of the next item of the module, or the
+    /// `}` closing an inline module.
+    fn recover_to_item(&mut self, inline: bool) {
+        let mut depth = 0usize;
+        loop {
+            match self.token.kind {
+                TokenKind::Eof => return,
+                TokenKind::Keyword {
+                    kind:
+                        KwKind::Fn
+                        | KwKind::Struct
+                        | KwKind::Enum
+                        | KwKind::Trait
+                        | KwKind::Impl
+                        | KwKind::Pub
+                        | KwKind::Mod
+                        | KwKind::Import,
+                } if depth == 0 => return,
+                TokenKind::At if depth == 0 => return,
+                TokenKind::CloseBrace if depth == 0 && inline => return,
+                TokenKind::OpenBrace => depth += 1,
+                TokenKind::CloseBrace => depth = depth.saturating_sub(1),
+                _ => (),
+            }
+            self.bump();
+        }
+    }
+
+    /// Collects consecutive doc comments of the given style.
+    fn parse_docs(&mut self, style: DocStyle) -> Vec<Doc> {
+        let mut docs = Vec::new();
+        while self.doc_style() == Some(style) {
+            let text = Symbol::intern(self.reader.doc_comment_to_str(&self.token));
+            docs.push(Doc {
+                style,
+                text,
+                span: self.token.span,
+            });
+            self.bump();
+        }
+        docs
+    }
+
+    /// Returns the style of the current token if it is a doc comment.
+    fn doc_style(&self) -> Option<DocStyle> {
+        match self.token.kind {
+            TokenKind::LineComment { doc_style } | TokenKind::BlockComment { doc_style, .. } => {
+                doc_style
+            }
+            _ => None,
+        }
+    }
+
+    fn parse_fn_decl(&mut self) -> PResult<FnDecl> {
+        let generics = self.parse_generics()?;
+        self.expect(TokenKind::OpenParen)?;
+        let self_param = if self.token.kind == TokenKind::Ident
+            && self.reader.span_to_str(&self.token.span) == "self"
+        {
+            let span = self.token.span;
+            self.bump();
+            if self.token.kind == TokenKind::Comma {
+                self.bump();
+            }
+            Some(SelfParam {
+                id: self.next_node_id(),
+                span,
+            })
+        } else {
+            None
+        };
+        let mut params = Vec::new();
+        while self.token.kind != TokenKind::CloseParen {
+            let start = self.token.span;
+            let mutbl = self.parse_mutability();
+            let ident = self.parse_ident()?;
+            self.expect(TokenKind::Colon)?;
+            let ty = self.parse_ty()?;
+            let span = start.append(ty.span);
+            params.push(Param {
+                id: self.next_node_id(),
+                mutbl,
+                ident,
+                ty,
+                span,
+            });
+            if self.token.kind != TokenKind::Comma {
+                break;
+            }
+            self.bump();
+        }
+        self.expect(TokenKind::CloseParen)?;
+
+        let ret_ty = if self.token.kind == TokenKind::Arrow {
+            self.bump();
+            Some(self.parse_ty()?)
+        } else {
+            None
+        };
+        let body = if self.token.kind == TokenKind::Semi {
+            self.bump();
+            None
+        } else {
+            Some(self.parse_block()?)
+        };
+        Ok(FnDecl {
+            generics,
+            self_param,
+            params,
+            ret_ty,
+            body,
+        })
+    }
+
+    /// Parses the rest of an impl after the `impl` keyword, e.g.
+    /// `<T> Shape for Wrap<T> { ... }`.
+    fn parse_impl_def(&mut self) -> PResult<ImplDef> {
+        let generics = self.parse_generics()?;
+        let ty = self.parse_ty()?;
+        let (of_trait, self_ty) = if self.token.kind == (TokenKind::Keyword { kind: KwKind::For }) {
+            self.bump();
+            let path = match ty.kind {
+                TyKind::Path(path, args) if args.is_empty() => path,
+                _ => return Err(Diagnostic::error(ty.span, "expected a trait, found type")),
+            };
+            (Some(path), self.parse_ty()?)
+        } else {
+            (None, ty)
+        };
+        let items = self.parse_assoc_items()?;
+        Ok(ImplDef {
+            generics,
+            of_trait,
+            self_ty,
+            items,
+        })
+    }
+
+    /// Parses the methods of a trait or an impl, e.g. `{ fn area(self); }`.
+    fn parse_assoc_items(&mut self) -> PResult<Vec<Item>> {
+        self.expect(TokenKind::OpenBrace)?;
+        let mut items = Vec::new();
+        while !matches!(self.token.kind, TokenKind::CloseBrace | TokenKind::Eof) {
+            let item = self.parse_item()?;
+            if item.vis == Visibility::Public {
+                // Items start at their `pub`.
+                let span = Span {
+                    start_pos: item.span.start_pos,
+                    len: "pub".len(),
+                };
+                self.diagnostics.push(
+                    Diagnostic::error(span, "visibility qualifiers are not permitted here")
+                        .note("methods are visible wherever their trait or type is"),
+                );
+            }
+            if matches!(item.kind, ItemKind::Fn(_)) {
+                self.check_attrs(&item.attrs, attr::Target::Method);
+                items.push(item);
+            } else {
+                self.diagnostics.push(Diagnostic::error(
+                    item.span,
+                    "only functions are allowed in traits and impls",
+                ));
+            }
+        }
+        self.expect(TokenKind::CloseBrace)?;
+        Ok(items)
+    }
+
+    fn parse_struct_def(&mut self) -> PResult<StructDef> {
+        let generics = self.parse_generics()?;
+        let fields = self.parse_field_defs()?;
+        Ok(StructDef { generics, fields })
+    }
+
+    fn parse_enum_def(&mut self) -> PResult<EnumDef> {
+        let generics = self.parse_generics()?;
+        self.expect(TokenKind::OpenBrace)?;
+        let mut variants = Vec::new();
+        while self.token.kind != TokenKind::CloseBrace {
+            let docs = self.parse_docs(DocStyle::Outer);
+            if !docs.is_empty() && self.token.kind != TokenKind::Ident {
+                return Err(self
+                    .unexpected("variant after doc comment")
+                    .help("doc comments must come before what they document"));
+            }
+            let ident = self.parse_ident()?;
+            let data = match self.token.kind {
+                TokenKind::OpenBrace => VariantData::Struct(self.parse_field_defs()?),
+                TokenKind::OpenParen => {
+                    self.bump();
+                    let mut tys = Vec::new();
+                    while self.token.kind != TokenKind::CloseParen {
+                        tys.push(self.parse_ty()?);
+                        if self.token.kind != TokenKind::Comma {
+                            break;
+                        }
+                        self.bump();
+                    }
+                    self.expect(TokenKind::CloseParen)?;
+                    VariantData::Tuple(tys)
+                }
+                _ => VariantData::Unit,
+            };
+            let span = ident.span.append(self.prev_token.span);
+            variants.push(Variant {
+                id: self.next_node_id(),
+                docs,
+                ident,
+                data,
+                span,
+            });
+            if self.token.kind != TokenKind::Comma {
+                break;
+            }
+            self.bump();
+        }
+        self.expect(TokenKind::CloseBrace)?;
+        Ok(EnumDef { generics, variants })
+    }
+
+    /// Parses `{ x: i64, y: i64 }`.
+    fn parse_field_defs(&mut self) -> PResult<Vec<FieldDef>> {
+        self.expect(TokenKind::OpenBrace)?;
+        let mut fields = Vec::new();
+        while self.token.kind != TokenKind::CloseBrace {
+            let docs = self.parse_docs(DocStyle::Outer);
+            if !docs.is_empty() && self.token.kind != TokenKind::Ident {
+                return Err(self
+                    .unexpected("field after doc comment")
+                    .help("doc comments must come before what they document"));
+            }
+            let ident = self.parse_ident()?;
+            self.expect(TokenKind::Colon)?;
+            let ty = self.parse_ty()?;
+            let span = ident.span.append(ty.span);
+            fields.push(FieldDef {
+                id: self.next_node_id(),
+                docs,
+                ident,
+                ty,
+                span,
+            });
+            if self.token.kind != TokenKind::Comma {
+                break;
+            }
+            self.bump();
+        }
+        self.expect(TokenKind::CloseBrace)?;
+        Ok(fields)
+    }
+
+    pub(crate) fn parse_block(&mut self) -> PResult<Block> {
+        let start = self.expect(TokenKind::OpenBrace)?.span;
+        let mut stmts = Vec::new();
+        while !matches!(self.token.kind, TokenKind::CloseBrace | TokenKind::Eof) {
+            match self.parse_stmt() {
+                Ok(stmt) => stmts.push(stmt),
+                Err(diag) => {
+                    self.diagnostics.push(diag);
+                    self.recover_to_stmt();
+                }
+            }
+        }
+        let end = self.expect(TokenKind::CloseBrace)?.span;
+        Ok(Block {
+            id: self.next_node_id(),
+            stmts,
+            span: start.append(end),
+        })
+    }
+
+    fn parse_stmt(&mut self) -> PResult<Stmt> {
+        let attrs = self.parse_outer_attrs()?;
+        if !attrs.is_empty() && self.token.kind == TokenKind::CloseBrace {
+            return Err(self.unexpected("statement after attribute"));
+        }
+        let mut stmt = self.parse_stmt_without_attrs()?;
+        self.check_attrs(&attrs, attr::Target::Stmt);
+        stmt.attrs = attrs;
+        Ok(stmt)
+    }
+
+    fn parse_stmt_without_attrs(&mut self) -> PResult<Stmt> {
+        if self.doc_style().is_some() {
+            let diag = self
+                .unexpected("statement")
+                .help("doc comments must be followed by an item");
+            self.bump();
+            return Err(diag);
+        }
+        if self.token.kind == (TokenKind::Keyword { kind: KwKind::Let }) {
+            return self.parse_let();
+        }
+        // A block-like expression ends the statement, so that
+        // `match x { ... } -1;` is two statements.
+        let expr = if matches!(
+            self.token.kind,
+            TokenKind::OpenBrace
+                | TokenKind::Keyword {
+                    kind: KwKind::Match | KwKind::For
+                }
+        ) {
+            self.parse_block_like()?
+        } else {
+            self.parse_expression()?
+        };
+        let block_like = expr::is_block_like(&expr);
+        if self.token.kind == TokenKind::CloseBrace
+            || (block_like && self.token.kind != TokenKind::Semi)
+        {
+            let span = expr.span;
+            return Ok(Stmt {
+                id: self.next_node_id(),
+                attrs: Vec::new(),
+                kind: StmtKind::Expr(expr),
+                span,
+            });
+        }
+        let semi = self.expect(TokenKind::Semi)?;
+        let span = expr.span.append(semi.span);
+        Ok(Stmt {
+            id: self.next_node_id(),
+            attrs: Vec::new(),
+            kind: StmtKind::Semi(expr),
+            span,
+        })
+    }
+
+    /// Parses `let x: i64 = 1;` or `let (a, b) = pair;`.
+    fn parse_let(&mut self) -> PResult<Stmt> {
+        let start = self.token.span;
+        self.bump();
+        let pat = self.parse_pat()?;
+        let ty = if self.token.kind == TokenKind::Colon {
+            self.bump();
+            Some(self.parse_ty()?)
+        } else {
+            None
+        };
+        self.expect(TokenKind::Eq)?;
+        let init = self.parse_expression()?;
+        let semi = self.expect(TokenKind::Semi)?;
+        let local = Local {
+            id: self.next_node_id(),
+            pat,
+            ty,
+            init,
+        };
+        Ok(Stmt {
+            id: self.next_node_id(),
+            attrs: Vec::new(),
+            kind: StmtKind::Let(local),
+            span: start.append(semi.span),
+        })
+    }
+
+    /// Skips tokens until the end of the current statement (eating its `;`)
+    /// or the end of the enclosing block.
+    fn recover_to_stmt(&mut self) {
+        let mut depth = 0usize;
+        loop {
+            match self.token.kind {
+                TokenKind::Eof => return,
+                TokenKind::Semi if depth == 0 => {
+                    self.bump();
+                    return;
+                }
+                TokenKind::CloseBrace if depth == 0 => return,
+                TokenKind::OpenBrace => depth += 1,
+                TokenKind::CloseBrace => depth -= 1,
+                _ => (),
+            }
+            self.bump();
+        }
+    }
+
+    pub(crate) fn parse_ty(&mut self) -> PResult<Ty> {
+        let kind = match self.token.kind {
+            TokenKind::Keyword { kind: KwKind::I8 } => TyKind::I8,
+            TokenKind::Keyword { kind: KwKind::I16 } => TyKind::I16,
+            TokenKind::Keyword { kind: KwKind::I32 } => TyKind::I32,
+            TokenKind::Keyword { kind: KwKind::I64 } => TyKind::I64,
+            TokenKind::Keyword { kind: KwKind::I128 } => TyKind::I128,
+            TokenKind::Keyword { kind: KwKind::U8 } => TyKind::U8,
+            TokenKind::Keyword { kind: KwKind::U16 } => TyKind::U16,
+            TokenKind::Keyword { kind: KwKind::U32 } => TyKind::U32,
+            TokenKind::Keyword { kind: KwKind::U64 } => TyKind::U64,
+            TokenKind::Keyword { kind: KwKind::U128 } => TyKind::U128,
+            TokenKind::Keyword {
+                kind: KwKind::Usize,
+            } => TyKind::Usize,
+            TokenKind::Keyword { kind: KwKind::F32 } => TyKind::F32,
+            TokenKind::Keyword { kind: KwKind::F64 } => TyKind::F64,
+            TokenKind::Keyword { kind: KwKind::Bool } => TyKind::Bool,
+            TokenKind::Keyword { kind: KwKind::Char } => TyKind::Char,
+            TokenKind::Keyword { kind: KwKind::Str } => TyKind::Str,
+            TokenKind::Keyword { kind: KwKind::Void } => TyKind::Void,
+            TokenKind::Ident => {
+                let path = self.parse_path()?;
+                let mut span = path.span;
+                let mut args = Vec::new();
+                if self.token.kind == TokenKind::Lt {
+                    self.bump();
+                    while self.token.kind != TokenKind::Gt {
+                        args.push(self.parse_ty()?);
+                        if self.token.kind != TokenKind::Comma {
+                            break;
+                        }
+                        self.bump();
+                    }
+                    span = span.append(self.expect_gt()?.span);
+                }
+                return Ok(Ty {
+                    id: self.next_node_id(),
+                    kind: TyKind::Path(path, args),
+                    span,
+                });
+            }
+            TokenKind::Keyword { kind: KwKind::Dyn } => {
+                let start = self.token.span;
+                self.bump();
+                let path = self.parse_path()?;
+                let span = start.append(path.span);
+                return Ok(Ty {
+                    id: self.next_node_id(),
+                    kind: TyKind::Dyn(path),
+                    span,
+                });
+            }
+            TokenKind::Keyword { kind: KwKind::Fn } => {
+                let start = self.token.span;
+                self.bump();
+                self.expect(TokenKind::OpenParen)?;
+                let mut inputs = Vec::new();
+                while self.token.kind != TokenKind::CloseParen {
+                    inputs.push(self.parse_ty()?);
+                    if self.token.kind != TokenKind::Comma {
+                        break;
+                    }
+                    self.bump();
+                }
+                let mut span = start.append(self.expect(TokenKind::CloseParen)?.span);
+                let output = if self.token.kind == TokenKind::Arrow {
+                    self.bump();
+                    let ty = self.parse_ty()?;
+                    span = span.append(ty.span);
+                    Some(Box::new(ty))
+                } else {
+                    None
+                };
+                return Ok(Ty {
+                    id: self.next_node_id(),
+                    kind: TyKind::Fn(inputs, output),
+                    span,
+                });
+            }
+            // `(T)` is `T` itself, and tuples of one element end with a
+            // comma, e.g. `(T,)`.
+            TokenKind::OpenParen => {
+                let start = self.token.span;
+                self.bump();
+                let mut tys = Vec::new();
+                let mut trailing_comma = false;
+                while self.token.kind != TokenKind::CloseParen {
+                    tys.push(self.parse_ty()?);
+                    trailing_comma = self.token.kind == TokenKind::Comma;
+                    if !trailing_comma {
+                        break;
+                    }
+                    self.bump();
+                }
+                if tys.is_empty() {
+                    return Err(self.unexpected("type"));
+                }
+                let end = self.expect(TokenKind::CloseParen)?;
+                if tys.len() == 1 && !trailing_comma {
+                    return Ok(tys.pop().unwrap());
+                }
+                return Ok(Ty {
+                    id: self.next_node_id(),
+                    kind: TyKind::Tuple(tys),
+                    span: start.append(end.span),
+                });
+            }
+            TokenKind::OpenBracket => {
+                let start = self.token.span;
+                self.bump();
+                let elem = Box::new(self.parse_ty()?);
+                let kind = if self.token.kind == TokenKind::Semi {
+                    self.bump();
+                    TyKind::Array(elem, self.parse_array_len()?)
+                } else {
+                    TyKind::Slice(elem)
+                };
+                let end = self.expect(TokenKind::CloseBracket)?;
+                return Ok(Ty {
+                    id: self.next_node_id(),
+                    kind,
+                    span: start.append(end.span),
+                });
+            }
+            _ => return Err(self.unexpected("type")),
+        };
+        let span = self.token.span;
+        self.bump();
+        Ok(Ty {
+            id: self.next_node_id(),
+            kind,
+            span,
+        })
+    }
+
+    /// Parses the length of an array type or of a repeat expression, an
+    /// integer literal, e.g. `16` in `[0; 16]`.
+    pub(crate) fn parse_array_len(&mut self) -> PResult<u64> {
+        let TokenKind::Literal { .. } = self.token.kind else {
+            return Err(self.unexpected("array length"));
+        };
+        let len = match self.to_ast_literal(self.token)? {
+            LiteralKind::Int(n, None | Some(LitSuffix::Usize)) => u64::try_from(n).ok(),
+            _ => None,
+        };
+        let len = len.ok_or_else(|| {
+            Diagnostic::error(
+                self.token.span,
+                "array length must be an integer literal of type `usize`",
+            )
+        })?;
+        self.bump();
+        Ok(len)
+    }
+
+    /// Parses the type parameters of an item, if any, e.g. `<T: Ord, U>`.
+    fn parse_generics(&mut self) -> PResult<Generics> {
+        if self.token.kind != TokenKind::Lt {
+            let span = Span {
+                start_pos: self.token.span.start_pos,
+                len: 0,
+            };
+            return Ok(Generics {
+                params: Vec::new(),
+                span,
+            });
+        }
+        let start = self.token.span;
+        self.bump();
+        let mut params = Vec::new();
+        while self.token.kind != TokenKind::Gt {
+            let ident = self.parse_ident()?;
+            let mut bounds = Vec::new();
+            if self.token.kind == TokenKind::Colon {
+                self.bump();
+                bounds.push(self.parse_path()?);
+                while self.token.kind == TokenKind::Plus {
+                    self.bump();
+                    bounds.push(self.parse_path()?);
+                }
+            }
+            let span = ident.span.append(self.prev_token.span);
+            params.push(GenericParam {
+                id: self.next_node_id(),
+                ident,
+                bounds,
+                span,
+            });
+            if self.token.kind != TokenKind::Comma {
+                break;
+            }
+            self.bump();
+        }
+        let end = self.expect_gt()?.span;
+        Ok(Generics {
+            params,
+            span: start.append(end),
+        })
+    }
+
+    /// Parses a path such as `Shape::Circle`.
+    pub(crate) fn parse_path(&mut self) -> PResult<Path> {
+        let first = self.parse_ident()?;
+        let mut span = first.span;
+        let mut segments = vec![first];
+        while self.token.kind == TokenKind::PathSep {
+            self.bump();
+            let ident = self.parse_ident()?;
+            span = span.append(ident.span);
+            segments.push(ident);
+        }
+        Ok(Path { segments, span })
+    }
+
+    pub(crate) fn parse_ident(&mut self) -> PResult<Ident> {
+        let t = self.expect(TokenKind::Ident)?;
+        // Identifiers which are canonically equivalent must refer to the same
+        // thing, so they are compared in Normalization Form C.
+        let name: String = self.reader.span_to_str(&t.span).nfc().collect();
+        Ok(Ident {
+            name: Symbol::intern(&name),
+            span: t.span,
+        })
+    }
+
+    /// Eats `mut` if it comes next.
+    pub(crate) fn parse_mutability(&mut self) -> Mutability {
+        if self.token.kind == (TokenKind::Keyword { kind: KwKind::Mut }) {
+            self.bump();
+            Mutability::Mut
+        } else {
+            Mutability::Not
+        }
+    }
+}
@@ -1,34 +1,131 @@
+//! The parser of the Ceylon programming language.
+//!
+//! The entry points are `parse_file`, `parse_expr` and `parse_item`, and
+//! `parse_file_at` for the files of a program with several modules. They
+//! never fail: syntax errors are reported as diagnostics, and the parser
+//! recovers so that as much of the input as possible ends up in the AST.
+//!
+//! ```
+//! let (module, diagnostics) = ceylon_parser::parse_file("fn main() { 1 + 2; }");
+//! assert!(diagnostics.is_empty());
+//! assert_eq!(module.items[0].ident.name.as_str(), "main");
+//! ```
+
 extern crate ceylon_lexer;
 
-mod ast;
+pub mod ast;
+pub mod attr;
+pub mod cfg;
 mod expr;
+mod item;
+pub mod lint;
+pub mod mut_visit;
+pub mod node_map;
+mod pat;
+pub mod serialize;
+pub mod visit;
 
 #[cfg(test)]
 mod tests;
 
+use ceylon_errors::Diagnostic;
 use ceylon_lexer::{Span, StringReader, Token, TokenKind};
 
-pub struct Parser<'a> {
+/// Result of a parsing function. The error is reported by the caller which
+/// knows how to recover from it.
+type PResult<T> = Result<T, Diagnostic>;
+
+pub(crate) struct Parser<'a> {
     /// The current token.
     token: Token,
     /// The previous token.
     prev_token: Token,
 
     reader: StringReader<'a>,
+    /// Set while parsing a `match` scrutinee, where `x {` starts the arms
+    /// rather than a struct literal.
+    no_struct_literal: bool,
+    /// The id assigned to the next AST node.
+    next_node_id: u32,
+    /// Errors reported so far.
+    diagnostics: Vec<Diagnostic>,
 }
 
-// For now parses only expressions
-pub(crate) fn parse(src: &str) -> ast::Expr {
+/// Parses a whole source file.
+pub fn parse_file(src: &str) -> (ast::Module, Vec<Diagnostic>) {
     let mut parser = Parser::new(src);
-    parser.parse_expression()
+    let module = parser.parse_module();
+    (module, parser.diagnostics)
+}
+
+/// Parses a file of a program made of several files. The file starts at
+/// `start_pos` in the source map, and its nodes are numbered from
+/// `next_node_id`, which is advanced past them, so that spans and node ids
+/// are unique in the whole program.
+pub fn parse_file_at(
+    src: &str,
+    start_pos: usize,
+    next_node_id: &mut u32,
+) -> (ast::Module, Vec<Diagnostic>) {
+    let mut parser = Parser::with_start_pos(src, start_pos);
+    parser.next_node_id = *next_node_id;
+    let module = parser.parse_module();
+    *next_node_id = parser.next_node_id;
+    (module, parser.diagnostics)
+}
+
+/// Parses a source consisting of a single expression. If the expression is
+/// malformed, an `ExprKind::Err` node stands in for it.
+pub fn parse_expr(src: &str) -> (ast::Expr, Vec<Diagnostic>) {
+    let mut parser = Parser::new(src);
+    let expr = parser
+        .parse_expression()
+        .and_then(|expr| parser.expect_eof().map(|()| expr))
+        .unwrap_or_else(|diag| {
+            parser.diagnostics.push(diag);
+            let span = Span {
+                start_pos: 0,
+                len: src.len(),
+            };
+            parser.mk_expr(ast::ExprKind::Err, span)
+        });
+    (expr, parser.diagnostics)
+}
+
+/// Parses a source consisting of a single item. `None` is returned if no item
+/// could be parsed.
+pub fn parse_item(src: &str) -> (Option<ast::Item>, Vec<Diagnostic>) {
+    let mut parser = Parser::new(src);
+    let item = parser
+        .parse_item()
+        .and_then(|item| parser.expect_eof().map(|()| item));
+    if let Ok(item) = &item {
+        parser.check_attrs(&item.attrs, attr::Target::of_item(item));
+    }
+    let item = match item {
+        Ok(item) => Some(item),
+        Err(diag) => {
+            parser.diagnostics.push(diag);
+            None
+        }
+    };
+    (item, parser.diagnostics)
 }
 
 impl<'a> Parser<'a> {
-    pub fn new(s: &'a str) -> Self {
-        let mut reader = StringReader::new(s);
+    pub(crate) fn new(s: &'a str) -> Self {
+        Parser::with_start_pos(s, 0)
+    }
 
-        Parser {
-            token: reader.next_token(),
+    fn with_start_pos(s: &'a str, start_pos: usize) -> Self {
+        let mut parser = Parser {
+            token: Token::new(
+                TokenKind::Unknown,
+                Span {
+                    start_pos: 0,
+                    len: 0,
+                },
+            ),
             prev_token: Token::new(
                 TokenKind::Unknown,
                 Span {
@@ -36,12 +133,147 @@ impl<'a> Parser<'a> {
                     len: 0,
                 },
             ),
-            reader,
-        }
+            reader: StringReader::with_start_pos(s, start_pos),
+            no_struct_literal: false,
+            next_node_id: 0,
+            diagnostics: Vec::new(),
+        };
+        parser.token = parser.next_token();
+        parser
     }
 
     fn bump(&mut self) {
         self.prev_token = self.token;
-        self.token = self.reader.next_token();
+        self.token = self.next_token();
+    }
+
+    /// Reads the next token from the reader, reporting tokens which are
+    /// malformed on their own.
+    fn next_token(&mut self) -> Token {
+        loop {
+            let token = self.reader.next_token();
+            match token.kind {
+                TokenKind::BlockComment {
+                    terminated: false, ..
+                } => {
+                    self.diagnostics
+                        .push(Diagnostic::error(token.span, "unterminated block comment"));
+                }
+                TokenKind::Unknown => {
+                    let text = self.reader.span_to_str(&token.span);
+                    let diag = match text.chars().next() {
+                        // Whitespace which isn't Pattern_White_Space.
+                        Some(c) if c.is_whitespace() => Diagnostic::error(
+                            token.span,
+                            "unicode whitespace is not allowed, use a space",
+                        )
+                        .note(format!("found {:?}", c)),
+                        _ => Diagnostic::error(
+                            token.span,
+                            format!("unknown start of token: {:?}", text),
+                        ),
+                    };
+                    self.diagnostics.push(diag);
+                }
+                _ => return token,
+            }
+        }
+    }
+
+    fn next_node_id(&mut self) -> ast::NodeId {
+        let id = ast::NodeId::from_u32(self.next_node_id);
+        self.next_node_id += 1;
+        id
+    }
+
+    /// Eats the current token if it is of the given kind, reports an error otherwise.
+    fn expect(&mut self, kind: TokenKind) -> PResult<Token> {
+        if self.token.kind != kind {
+            return Err(self.unexpected(describe_kind(kind)));
+        }
+        self.bump();
+        Ok(self.prev_token)
+    }
+
+    /// Eats a `>` closing a list of generic arguments or parameters. The
+    /// `>` may be the first character of a longer token, e.g. of `>>` in
+    /// `Option<Option<i64>>`, whose rest then becomes the current token.
+    fn expect_gt(&mut self) -> PResult<Token> {
+        let rest = match self.token.kind {
+            TokenKind::Gt => return self.expect(TokenKind::Gt),
+            TokenKind::Shr => TokenKind::Gt,
+            TokenKind::GtEq => TokenKind::Eq,
+            TokenKind::ShrEq => TokenKind::GtEq,
+            _ => return Err(self.unexpected(describe_kind(TokenKind::Gt))),
+        };
+        let span = self.token.span;
+        let gt = Token::new(
+            TokenKind::Gt,
+            Span {
+                start_pos: span.start_pos,
+                len: 1,
+            },
+        );
+        self.prev_token = gt;
+        self.token = Token::new(
+            rest,
+            Span {
+                start_pos: span.start_pos + 1,
+                len: span.len - 1,
+            },
+        );
+        Ok(gt)
+    }
+
+    fn expect_eof(&mut self) -> PResult<()> {
+        if self.token.kind != TokenKind::Eof {
+            return Err(self.unexpected("end of file"));
+        }
+        Ok(())
+    }
+
+    /// Creates an "expected ..., found ..." error at the current token.
+    fn unexpected(&self, expected: &str) -> Diagnostic {
+        let msg = format!("expected {}, found {}", expected, self.describe_token());
+        Diagnostic::error(self.token.span, msg)
+    }
+
+    /// Describes the current token for error messages, e.g. "`}`".
+    fn describe_token(&self) -> String {
+        match self.token.kind {
+            TokenKind::Eof => "end of file".to_string(),
+            TokenKind::LineComment { .. } | TokenKind::BlockComment { .. } => {
+                "doc comment".to_string()
+            }
+            _ => format!("`{}`", self.reader.span_to_str(&self.token.span)),
+        }
+    }
+}
+
+/// Describes a kind of token for "expected ..." error messages.
+fn describe_kind(kind: TokenKind) -> &'static str {
+    match kind {
+        TokenKind::Ident => "identifier",
+        TokenKind::Semi => "`;`",
+        TokenKind::Comma => "`,`",
+        TokenKind::Colon => "`:`",
+        TokenKind::OpenParen => "`(`",
+        TokenKind::CloseParen => "`)`",
+        TokenKind::OpenBrace => "`{`",
+        TokenKind::CloseBrace => "`}`",
+        TokenKind::OpenBracket => "`[`",
+        TokenKind::CloseBracket => "`]`",
+        TokenKind::Arrow => "`->`",
+        TokenKind::FatArrow => "`=>`",
+        TokenKind::PathSep => "`::`",
+        TokenKind::Eq => "`=`",
+        TokenKind::Lt => "`<`",
+        TokenKind::Gt => "`>`",
+        TokenKind::Or => "`|`",
+        TokenKind::At => "`@`",
+        TokenKind::Keyword {
+            kind: ceylon_lexer::KwKind::In,
+        } => "`in`",
+        _ => "token",
     }
 }
@@ -0,0 +1,289 @@
+//! Lints for suspicious non-ASCII source text.
+
+use std::collections::HashMap;
+
+use super::*;
+use ast::*;
+use unicode_security::skeleton;
+use visit::Visitor;
+
+#[derive(Debug)]
+pub struct Lint {
+    pub kind: LintKind,
+    pub span: Span,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LintKind {
+    /// Two identifiers in the same scope look the same but are different.
+    ConfusableIdents,
+    /// A codepoint which changes the visible direction of text, e.g. "\u{202E}".
+    TextDirectionCodepoint,
+}
+
+impl LintKind {
+    /// The name `@allow` takes to silence the lint.
+    pub fn name(self) -> &'static str {
+        match self {
+            LintKind::ConfusableIdents => "confusable_idents",
+            LintKind::TextDirectionCodepoint => "text_direction_codepoint",
+        }
+    }
+}
+
+/// The lints `@allow` can silence, including those reported by later passes.
+pub const LINT_NAMES: &[&str] = &[
+    "confusable_idents",
+    "deprecated",
+    "text_direction_codepoint",
+    "unreachable_patterns",
+    "unused",
+];
+
+/// Checks a parsed module and the source it was parsed from.
+pub fn check_module(src: &str, module: &Module) -> Vec<Lint> {
+    check_module_at(src, 0, module)
+}
+
+/// Like [`check_module`], for a file starting at `start_pos` in the source
+/// map, as parsed by [`crate::parse_file_at`].
+pub fn check_module_at(src: &str, start_pos: usize, module: &Module) -> Vec<Lint> {
+    let mut lints = Vec::new();
+    check_text_direction_codepoints(src, start_pos, &mut lints);
+    check_idents(module, &mut lints);
+    let mut allowed = Vec::new();
+    collect_allowed(&module.items, &mut allowed);
+    lints.retain(|lint| {
+        !allowed.iter().any(|(name, span)| {
+            *name == lint.kind.name()
+                && span.start_pos <= lint.span.start_pos
+                && lint.span.start_pos < span.start_pos + span.len
+        })
+    });
+    lints
+}
+
+/// Collects the lints allowed by items, with the span they are allowed in.
+fn collect_allowed<'a>(items: &'a [Item], allowed: &mut Vec<(&'a str, Span)>) {
+    for item in items {
+        for name in LINT_NAMES {
+            if attr::is_lint_allowed(&item.attrs, name) {
+                allowed.push((name, item.span));
+            }
+        }
+        match &item.kind {
+            ItemKind::Trait(TraitDef { items }) | ItemKind::Impl(ImplDef { items, .. }) => {
+                collect_allowed(items, allowed)
+            }
+            ItemKind::Mod(ModDef {
+                module: Some(module),
+                ..
+            }) => collect_allowed(&module.items, allowed),
+            _ => (),
+        }
+    }
+}
+
+/// Checks the identifiers of a module and of its inline submodules.
+fn check_idents(module: &Module, lints: &mut Vec<Lint>) {
+    // Impls have no name to be confused with.
+    let module_scope: Vec<&Ident> = module
+        .items
+        .iter()
+        .filter(|item| !matches!(item.kind, ItemKind::Impl(_)))
+        .map(|item| &item.ident)
+        .collect();
+    check_confusable_idents(&module_scope, lints);
+    for item in &module.items {
+        match &item.kind {
+            ItemKind::Fn(decl) => check_fn_idents(&module_scope, decl, lints),
+            ItemKind::Struct(def) => {
+                let fields: Vec<&Ident> = def.fields.iter().map(|field| &field.ident).collect();
+                check_confusable_idents(&fields, lints);
+            }
+            ItemKind::Enum(def) => {
+                let variants: Vec<&Ident> = def.variants.iter().map(|v| &v.ident).collect();
+                check_confusable_idents(&variants, lints);
+            }
+            ItemKind::Trait(TraitDef { items }) | ItemKind::Impl(ImplDef { items, .. }) => {
+                let methods: Vec<&Ident> = items.iter().map(|item| &item.ident).collect();
+                check_confusable_idents(&methods, lints);
+                for item in items {
+                    if let ItemKind::Fn(decl) = &item.kind {
+                        check_fn_idents(&module_scope, decl, lints);
+                    }
+                }
+            }
+            // Modules in other files are checked with their own source.
+            ItemKind::Mod(ModDef {
+                inline: true,
+                module: Some(module),
+                ..
+            }) => check_idents(module, lints),
+            ItemKind::Mod(_) | ItemKind::Import(_) => (),
+        }
+    }
+}
+
+/// Checks the parameters of a function and the variables of its body, each
+/// against the names in scope where it is bound, including the items it can
+/// shadow.
+fn check_fn_idents(module_scope: &[&Ident], decl: &FnDecl, lints: &mut Vec<Lint>) {
+    let mut scopes = LocalScopes {
+        scope: module_scope.to_vec(),
+        lints,
+    };
+    scopes.bind(decl.params.iter().map(|param| &param.ident));
+    if let Some(body) = &decl.body {
+        scopes.visit_block(body);
+    }
+}
+
+/// Walks the body of a function, knowing the names in scope at each point.
+struct LocalScopes<'ast, 'l> {
+    /// The names in scope, the innermost last.
+    scope: Vec<&'ast Ident>,
+    lints: &'l mut Vec<Lint>,
+}
+
+impl<'ast> LocalScopes<'ast, '_> {
+    /// Brings names into scope, reporting those confusable with a name
+    /// already in scope or with each other.
+    fn bind(&mut self, idents: impl IntoIterator<Item = &'ast Ident>) {
+        let first_new = self.scope.len();
+        self.scope.extend(idents);
+        check_confusable_idents_against(&self.scope, &self.scope[first_new..], self.lints);
+    }
+
+    /// Runs `f`, dropping the names it brings into scope afterwards.
+    fn in_scope(&mut self, f: impl FnOnce(&mut Self)) {
+        let len = self.scope.len();
+        f(self);
+        self.scope.truncate(len);
+    }
+}
+
+impl<'ast> Visitor<'ast> for LocalScopes<'ast, '_> {
+    fn visit_block(&mut self, block: &'ast Block) {
+        self.in_scope(|this| visit::walk_block(this, block))
+    }
+
+    fn visit_local(&mut self, local: &'ast Local) {
+        // The variables are not in scope in their own initializer.
+        self.visit_expr(&local.init);
+        self.bind(pat_bindings(&local.pat));
+    }
+
+    fn visit_arm(&mut self, arm: &'ast Arm) {
+        self.in_scope(|this| {
+            this.bind(pat_bindings(&arm.pat));
+            if let Some(guard) = &arm.guard {
+                this.visit_expr(guard);
+            }
+            this.visit_expr(&arm.body);
+        })
+    }
+
+    fn visit_expr(&mut self, expr: &'ast Expr) {
+        match &expr.kind {
+            ExprKind::ForLoop(pat, iter, body) => {
+                self.visit_expr(iter);
+                self.in_scope(|this| {
+                    this.bind(pat_bindings(pat));
+                    this.visit_block(body);
+                })
+            }
+            ExprKind::Closure(closure) => self.in_scope(|this| {
+                this.bind(closure.params.iter().map(|param| &param.ident));
+                this.visit_expr(&closure.body);
+            }),
+            _ => visit::walk_expr(self, expr),
+        }
+    }
+}
+
+/// The variables bound by a pattern.
+fn pat_bindings(pat: &Pat) -> Vec<&Ident> {
+    struct Bindings<'ast>(Vec<&'ast Ident>);
+    impl<'ast> Visitor<'ast> for Bindings<'ast> {
+        fn visit_pat(&mut self, pat: &'ast Pat) {
+            if let PatKind::Ident(_, ident) = &pat.kind {
+                self.0.push(ident);
+            }
+            visit::walk_pat(self, pat)
+        }
+    }
+    let mut bindings = Bindings(Vec::new());
+    bindings.visit_pat(pat);
+    bindings.0
+}
+
+fn check_text_direction_codepoints(src: &str, start_pos: usize, lints: &mut Vec<Lint>) {
+    for (pos, c) in src.char_indices() {
+        if is_text_direction_codepoint(c) {
+            lints.push(Lint {
+                kind: LintKind::TextDirectionCodepoint,
+                span: Span {
+                    start_pos: start_pos + pos,
+                    len: c.len_utf8(),
+                },
+                message: format!(
+                    "unicode codepoint changing visible direction of text present in source: {:?}",
+                    c
+                ),
+            });
+        }
+    }
+}
+
+fn is_text_direction_codepoint(c: char) -> bool {
+    matches!(
+        c,
+        '\u{202A}' // LEFT-TO-RIGHT EMBEDDING
+        | '\u{202B}' // RIGHT-TO-LEFT EMBEDDING
+        | '\u{202C}' // POP DIRECTIONAL FORMATTING
+        | '\u{202D}' // LEFT-TO-RIGHT OVERRIDE
+        | '\u{202E}' // RIGHT-TO-LEFT OVERRIDE
+        | '\u{2066}' // LEFT-TO-RIGHT ISOLATE
+        | '\u{2067}' // RIGHT-TO-LEFT ISOLATE
+        | '\u{2068}' // FIRST STRONG ISOLATE
+        | '\u{2069}' // POP DIRECTIONAL ISOLATE
+    )
+}
+
+fn check_confusable_idents(scope: &[&Ident], lints: &mut Vec<Lint>) {
+    check_confusable_idents_against(scope, scope, lints)
+}
+
+/// Reports identifiers in `new` which are confusable with an earlier
+/// identifier in `scope`. `new` must be a suffix of `scope`.
+fn check_confusable_idents_against(scope: &[&Ident], new: &[&Ident], lints: &mut Vec<Lint>) {
+    let first_new = scope.len() - new.len();
+    let mut skeletons: HashMap<String, &Ident> = HashMap::new();
+    for (i, ident) in scope.iter().enumerate() {
+        let key: String = skeleton(ident.name.as_str()).collect();
+        match skeletons.get(&key) {
+            // ASCII-only identifiers such as "rn" and "m" have the same skeleton,
+            // but they are not what this lint is about.
+            Some(prev)
+                if i >= first_new
+                    && prev.name != ident.name
+                    && !(prev.name.as_str().is_ascii() && ident.name.as_str().is_ascii()) =>
+            {
+                lints.push(Lint {
+                    kind: LintKind::ConfusableIdents,
+                    span: ident.span,
+                    message: format!(
+                        "identifier pair considered confusable between `{}` and `{}`",
+                        prev.name, ident.name
+                    ),
+                });
+            }
+            Some(_) => (),
+            None => {
+                skeletons.insert(key, ident);
+            }
+        }
+    }
+}
@@ -0,0 +1,409 @@
+//! A `MutVisitor` represents an AST modification; it accepts an AST piece and
+//! mutates it in place. Nodes can be replaced wholesale by assigning to them,
+//! and the `flat_map_*` methods can remove a node or expand it into several
+//! nodes, which is what desugaring passes need.
+
+use crate::ast::*;
+
+pub trait MutVisitor: Sized {
+    fn visit_module(&mut self, module: &mut Module) {
+        walk_module(self, module)
+    }
+    fn visit_doc(&mut self, _doc: &mut Doc) {}
+    fn flat_map_item(&mut self, item: Item) -> Vec<Item> {
+        walk_flat_map_item(self, item)
+    }
+    fn visit_item(&mut self, item: &mut Item) {
+        walk_item(self, item)
+    }
+    fn visit_fn_decl(&mut self, decl: &mut FnDecl) {
+        walk_fn_decl(self, decl)
+    }
+    fn visit_struct_def(&mut self, def: &mut StructDef) {
+        walk_struct_def(self, def)
+    }
+    fn visit_enum_def(&mut self, def: &mut EnumDef) {
+        walk_enum_def(self, def)
+    }
+    fn visit_trait_def(&mut self, def: &mut TraitDef) {
+        walk_trait_def(self, def)
+    }
+    fn visit_impl_def(&mut self, def: &mut ImplDef) {
+        walk_impl_def(self, def)
+    }
+    fn visit_variant(&mut self, variant: &mut Variant) {
+        walk_variant(self, variant)
+    }
+    fn visit_field_def(&mut self, field: &mut FieldDef) {
+        walk_field_def(self, field)
+    }
+    fn visit_generics(&mut self, generics: &mut Generics) {
+        walk_generics(self, generics)
+    }
+    fn visit_generic_param(&mut self, param: &mut GenericParam) {
+        walk_generic_param(self, param)
+    }
+    fn visit_param(&mut self, param: &mut Param) {
+        walk_param(self, param)
+    }
+    fn visit_ty(&mut self, ty: &mut Ty) {
+        walk_ty(self, ty)
+    }
+    fn visit_block(&mut self, block: &mut Block) {
+        walk_block(self, block)
+    }
+    fn flat_map_stmt(&mut self, stmt: Stmt) -> Vec<Stmt> {
+        walk_flat_map_stmt(self, stmt)
+    }
+    fn visit_stmt(&mut self, stmt: &mut Stmt) {
+        walk_stmt(self, stmt)
+    }
+    fn visit_local(&mut self, local: &mut Local) {
+        walk_local(self, local)
+    }
+    fn visit_arm(&mut self, arm: &mut Arm) {
+        walk_arm(self, arm)
+    }
+    fn visit_pat(&mut self, pat: &mut Pat) {
+        walk_pat(self, pat)
+    }
+    fn visit_expr(&mut self, expr: &mut Expr) {
+        walk_expr(self, expr)
+    }
+    fn visit_path(&mut self, path: &mut Path) {
+        walk_path(self, path)
+    }
+    fn visit_ident(&mut self, _ident: &mut Ident) {}
+}
+
+pub fn walk_module<T: MutVisitor>(vis: &mut T, module: &mut Module) {
+    for doc in &mut module.docs {
+        vis.visit_doc(doc);
+    }
+    module.items = std::mem::take(&mut module.items)
+        .into_iter()
+        .flat_map(|item| vis.flat_map_item(item))
+        .collect();
+}
+
+pub fn walk_flat_map_item<T: MutVisitor>(vis: &mut T, mut item: Item) -> Vec<Item> {
+    vis.visit_item(&mut item);
+    vec![item]
+}
+
+pub fn walk_item<T: MutVisitor>(vis: &mut T, item: &mut Item) {
+    for doc in &mut item.docs {
+        vis.visit_doc(doc);
+    }
+    vis.visit_ident(&mut item.ident);
+    match &mut item.kind {
+        ItemKind::Fn(decl) => vis.visit_fn_decl(decl),
+        ItemKind::Struct(def) => vis.visit_struct_def(def),
+        ItemKind::Enum(def) => vis.visit_enum_def(def),
+        ItemKind::Trait(def) => vis.visit_trait_def(def),
+        ItemKind::Impl(def) => vis.visit_impl_def(def),
+        ItemKind::Mod(def) => {
+            if let Some(module) = &mut def.module {
+                vis.visit_module(module);
+            }
+        }
+        ItemKind::Import(path) => vis.visit_path(path),
+    }
+}
+
+pub fn walk_fn_decl<T: MutVisitor>(vis: &mut T, decl: &mut FnDecl) {
+    vis.visit_generics(&mut decl.generics);
+    for param in &mut decl.params {
+        vis.visit_param(param);
+    }
+    if let Some(ty) = &mut decl.ret_ty {
+        vis.visit_ty(ty);
+    }
+    if let Some(body) = &mut decl.body {
+        vis.visit_block(body);
+    }
+}
+
+pub fn walk_struct_def<T: MutVisitor>(vis: &mut T, def: &mut StructDef) {
+    vis.visit_generics(&mut def.generics);
+    for field in &mut def.fields {
+        vis.visit_field_def(field);
+    }
+}
+
+pub fn walk_enum_def<T: MutVisitor>(vis: &mut T, def: &mut EnumDef) {
+    vis.visit_generics(&mut def.generics);
+    for variant in &mut def.variants {
+        vis.visit_variant(variant);
+    }
+}
+
+pub fn walk_trait_def<T: MutVisitor>(vis: &mut T, def: &mut TraitDef) {
+    def.items = std::mem::take(&mut def.items)
+        .into_iter()
+        .flat_map(|item| vis.flat_map_item(item))
+        .collect();
+}
+
+pub fn walk_impl_def<T: MutVisitor>(vis: &mut T, def: &mut ImplDef) {
+    vis.visit_generics(&mut def.generics);
+    if let Some(path) = &mut def.of_trait {
+        vis.visit_path(path);
+    }
+    vis.visit_ty(&mut def.self_ty);
+    def.items = std::mem::take(&mut def.items)
+        .into_iter()
+        .flat_map(|item| vis.flat_map_item(item))
+        .collect();
+}
+
+pub fn walk_variant<T: MutVisitor>(vis: &mut T, variant: &mut Variant) {
+    for doc in &mut variant.docs {
+        vis.visit_doc(doc);
+    }
+    vis.visit_ident(&mut variant.ident);
+    match &mut variant.data {
+        VariantData::Struct(fields) => {
+            for field in fields {
+                vis.visit_field_def(field);
+            }
+        }
+        VariantData::Tuple(tys) => {
+            for ty in tys {
+                vis.visit_ty(ty);
+            }
+        }
+        VariantData::Unit => (),
+    }
+}
+
+pub fn walk_field_def<T: MutVisitor>(vis: &mut T, field: &mut FieldDef) {
+    for doc in &mut field.docs {
+        vis.visit_doc(doc);
+    }
+    vis.visit_ident(&mut field.ident);
+    vis.visit_ty(&mut field.ty);
+}
+
+pub fn walk_generics<T: MutVisitor>(vis: &mut T, generics: &mut Generics) {
+    for param in &mut generics.params {
+        vis.visit_generic_param(param);
+    }
+}
+
+pub fn walk_generic_param<T: MutVisitor>(vis: &mut T, param: &mut GenericParam) {
+    vis.visit_ident(&mut param.ident);
+    for bound in &mut param.bounds {
+        vis.visit_path(bound);
+    }
+}
+
+pub fn walk_param<T: MutVisitor>(vis: &mut T, param: &mut Param) {
+    vis.visit_ident(&mut param.ident);
+    vis.visit_ty(&mut param.ty);
+}
+
+pub fn walk_ty<T: MutVisitor>(vis: &mut T, ty: &mut Ty) {
+    match &mut ty.kind {
+        TyKind::Path(path, args) => {
+            vis.visit_path(path);
+            for arg in args {
+                vis.visit_ty(arg);
+            }
+        }
+        TyKind::Dyn(path) => vis.visit_path(path),
+        TyKind::Fn(inputs, output) => {
+            for input in inputs {
+                vis.visit_ty(input);
+            }
+            if let Some(output) = output {
+                vis.visit_ty(output);
+            }
+        }
+        TyKind::Tuple(tys) => {
+            for ty in tys {
+                vis.visit_ty(ty);
+            }
+        }
+        TyKind::Array(ty, _) | TyKind::Slice(ty) => vis.visit_ty(ty),
+        TyKind::I8
+        | TyKind::I16
+        | TyKind::I32
+        | TyKind::I64
+        | TyKind::I128
+        | TyKind::U8
+        | TyKind::U16
+        | TyKind::U32
+        | TyKind::U64
+        | TyKind::U128
+        | TyKind::Usize
+        | TyKind::F32
+        | TyKind::F64
+        | TyKind::Bool
+        | TyKind::Char
+        | TyKind::Str
+        | TyKind::Void => {}
+    }
+}
+
+pub fn walk_block<T: MutVisitor>(vis: &mut T, block: &mut Block) {
+    block.stmts = std::mem::take(&mut block.stmts)
+        .into_iter()
+        .flat_map(|stmt| vis.flat_map_stmt(stmt))
+        .collect();
+}
+
+pub fn walk_flat_map_stmt<T: MutVisitor>(vis: &mut T, mut stmt: Stmt) -> Vec<Stmt> {
+    vis.visit_stmt(&mut stmt);
+    vec![stmt]
+}
+
+pub fn walk_stmt<T: MutVisitor>(vis: &mut T, stmt: &mut Stmt) {
+    match &mut stmt.kind {
+        StmtKind::Let(local) => vis.visit_local(local),
+        StmtKind::Semi(expr) | StmtKind::Expr(expr) => vis.visit_expr(expr),
+    }
+}
+
+pub fn walk_local<T: MutVisitor>(vis: &mut T, local: &mut Local) {
+    vis.visit_pat(&mut local.pat);
+    if let Some(ty) = &mut local.ty {
+        vis.visit_ty(ty);
+    }
+    vis.visit_expr(&mut local.init);
+}
+
+pub fn walk_expr<T: MutVisitor>(vis: &mut T, expr: &mut Expr) {
+    match &mut expr.kind {
+        ExprKind::Literal(_) | ExprKind::Err => (),
+        ExprKind::Binary(_, lhs, rhs) => {
+            vis.visit_expr(lhs);
+            vis.visit_expr(rhs);
+        }
+        ExprKind::Unary(_, operand) => vis.visit_expr(operand),
+        ExprKind::Path(path) => vis.visit_path(path),
+        ExprKind::Call(callee, args) => {
+            vis.visit_expr(callee);
+            for arg in args {
+                vis.visit_expr(arg);
+            }
+        }
+        ExprKind::MethodCall(receiver, ident, args) => {
+            vis.visit_expr(receiver);
+            vis.visit_ident(ident);
+            for arg in args {
+                vis.visit_expr(arg);
+            }
+        }
+        ExprKind::Block(block) => vis.visit_block(block),
+        ExprKind::Match(scrutinee, arms) => {
+            vis.visit_expr(scrutinee);
+            for arm in arms {
+                vis.visit_arm(arm);
+            }
+        }
+        ExprKind::ForLoop(pat, iter, body) => {
+            vis.visit_pat(pat);
+            vis.visit_expr(iter);
+            vis.visit_block(body);
+        }
+        ExprKind::Range(start, end, _) => {
+            vis.visit_expr(start);
+            if let Some(end) = end {
+                vis.visit_expr(end);
+            }
+        }
+        ExprKind::Assign(place, value) | ExprKind::AssignOp(_, place, value) => {
+            vis.visit_expr(place);
+            vis.visit_expr(value);
+        }
+        ExprKind::Field(base, ident) => {
+            vis.visit_expr(base);
+            vis.visit_ident(ident);
+        }
+        ExprKind::Tuple(exprs) | ExprKind::Array(exprs) => {
+            for expr in exprs {
+                vis.visit_expr(expr);
+            }
+        }
+        ExprKind::Repeat(value, _) => vis.visit_expr(value),
+        ExprKind::Index(base, index) => {
+            vis.visit_expr(base);
+            vis.visit_expr(index);
+        }
+        ExprKind::Cast(expr, ty) => {
+            vis.visit_expr(expr);
+            vis.visit_ty(ty);
+        }
+        ExprKind::Try(expr) => vis.visit_expr(expr),
+        ExprKind::Struct(StructExpr { path, fields }) => {
+            vis.visit_path(path);
+            for field in fields {
+                vis.visit_ident(&mut field.ident);
+                vis.visit_expr(&mut field.expr);
+            }
+        }
+        ExprKind::Closure(Closure {
+            params,
+            ret_ty,
+            body,
+        }) => {
+            for param in params {
+                vis.visit_ident(&mut param.ident);
+                if let Some(ty) = &mut param.ty {
+                    vis.visit_ty(ty);
+                }
+            }
+            if let Some(ty) = ret_ty {
+                vis.visit_ty(ty);
+            }
+            vis.visit_expr(body);
+        }
+    }
+}
+
+pub fn walk_arm<T: MutVisitor>(vis: &mut T, arm: &mut Arm) {
+    vis.visit_pat(&mut arm.pat);
+    if let Some(guard) = &mut arm.guard {
+        vis.visit_expr(guard);
+    }
+    vis.visit_expr(&mut arm.body);
+}
+
+pub fn walk_pat<T: MutVisitor>(vis: &mut T, pat: &mut Pat) {
+    match &mut pat.kind {
+        PatKind::Wild => (),
+        PatKind::Ident(_, ident) => vis.visit_ident(ident),
+        PatKind::Lit(expr) => vis.visit_expr(expr),
+        PatKind::Path(path) => vis.visit_path(path),
+        PatKind::Tuple(pats) => {
+            for pat in pats {
+                vis.visit_pat(pat);
+            }
+        }
+        PatKind::TupleStruct(path, pats) => {
+            vis.visit_path(path);
+            for pat in pats {
+                vis.visit_pat(pat);
+            }
+        }
+        PatKind::Struct(path, fields, _) => {
+            vis.visit_path(path);
+            for field in fields {
+                vis.visit_ident(&mut field.ident);
+                vis.visit_pat(&mut field.pat);
+            }
+        }
+        PatKind::Or(pats) => {
+            for pat in pats {
+                vis.visit_pat(pat);
+            }
+        }
+    }
+}
+
+pub fn walk_path<T: MutVisitor>(vis: &mut T, path: &mut Path) {
+    for segment in &mut path.segments {
+        vis.visit_ident(segment);
+    }
+}
@@ -0,0 +1,164 @@
+//! Finding the nodes of a module by position, e.g. the expression under the
+//! cursor of an editor, and by `NodeId`.
+//!
+//! ```
+//! use ceylon_parser::node_map::{Node, NodeMap};
+//!
+//! let (module, _) = ceylon_parser::parse_file("fn f() -> i64 { 1 + 2 }");
+//! let map = NodeMap::new(&module);
+//! let Some(Node::Expr(expr)) = map.node_at(20) else { panic!() };
+//! assert_eq!(expr.span.start_pos, 20);
+//! assert_eq!(map.get(expr.id).unwrap().id(), expr.id);
+//! ```
+
+use std::collections::HashMap;
+
+use super::*;
+use ast::*;
+use visit::Visitor;
+
+/// A node of the AST with a `NodeId` and a span.
+#[derive(Debug, Clone, Copy)]
+pub enum Node<'ast> {
+    Item(&'ast Item),
+    Ty(&'ast Ty),
+    Block(&'ast Block),
+    Stmt(&'ast Stmt),
+    Expr(&'ast Expr),
+    Pat(&'ast Pat),
+}
+
+impl Node<'_> {
+    pub fn id(&self) -> NodeId {
+        match self {
+            Node::Item(item) => item.id,
+            Node::Ty(ty) => ty.id,
+            Node::Block(block) => block.id,
+            Node::Stmt(stmt) => stmt.id,
+            Node::Expr(expr) => expr.id,
+            Node::Pat(pat) => pat.id,
+        }
+    }
+
+    pub fn span(&self) -> Span {
+        match self {
+            Node::Item(item) => item.span,
+            Node::Ty(ty) => ty.span,
+            Node::Block(block) => block.span,
+            Node::Stmt(stmt) => stmt.span,
+            Node::Expr(expr) => expr.span,
+            Node::Pat(pat) => pat.span,
+        }
+    }
+}
+
+/// The nodes of a module, sorted by where they start.
+pub struct NodeMap<'ast> {
+    /// Sorted by start, and outer nodes before the nodes inside them.
+    nodes: Vec<Node<'ast>>,
+    /// The index of the innermost node containing each node, if any.
+    parents: Vec<Option<usize>>,
+    /// The positions where the innermost node containing a position
+    /// changes, sorted, with the index of that node from there on.
+    innermost: Vec<(usize, Option<usize>)>,
+    /// The index of each node in `nodes`.
+    ids: HashMap<NodeId, usize>,
+}
+
+impl<'ast> NodeMap<'ast> {
+    pub fn new(module: &'ast Module) -> Self {
+        let mut collector = Collector(Vec::new());
+        collector.visit_module(module);
+        let mut nodes = collector.0;
+        // The sort is stable, so a node which has the span of its parent
+        // stays after it.
+        nodes.sort_by_key(|node| {
+            let span = node.span();
+            (span.start_pos, std::cmp::Reverse(span.len))
+        });
+        let end = |i: usize| {
+            let span = nodes[i].span();
+            span.start_pos + span.len
+        };
+        // The spans of the AST nest, so the nodes containing a node are
+        // those on the stack when it is reached.
+        let mut parents = Vec::with_capacity(nodes.len());
+        let mut innermost = Vec::new();
+        let mut stack: Vec<usize> = Vec::new();
+        for (i, node) in nodes.iter().enumerate() {
+            let span = node.span();
+            while let Some(&top) = stack.last() {
+                if span.start_pos + span.len <= end(top) && span.start_pos < end(top) {
+                    break;
+                }
+                stack.pop();
+                innermost.push((end(top), stack.last().copied()));
+            }
+            parents.push(stack.last().copied());
+            stack.push(i);
+            innermost.push((span.start_pos, Some(i)));
+        }
+        while let Some(top) = stack.pop() {
+            innermost.push((end(top), stack.last().copied()));
+        }
+        let ids = nodes
+            .iter()
+            .enumerate()
+            .map(|(i, node)| (node.id(), i))
+            .collect();
+        NodeMap {
+            nodes,
+            parents,
+            innermost,
+            ids,
+        }
+    }
+
+    pub fn get(&self, id: NodeId) -> Option<Node<'ast>> {
+        self.ids.get(&id).map(|&i| self.nodes[i])
+    }
+
+    /// Returns the innermost node whose span contains `pos`.
+    pub fn node_at(&self, pos: usize) -> Option<Node<'ast>> {
+        self.nodes_at(pos).next()
+    }
+
+    /// Returns the nodes whose span contains `pos`, innermost first.
+    pub fn nodes_at(&self, pos: usize) -> impl Iterator<Item = Node<'ast>> + '_ {
+        // The last change at or before `pos`.
+        let changes = self.innermost.partition_point(|&(start, _)| start <= pos);
+        let innermost = changes
+            .checked_sub(1)
+            .and_then(|change| self.innermost[change].1);
+        std::iter::successors(innermost, |&i| self.parents[i]).map(|i| self.nodes[i])
+    }
+}
+
+struct Collector<'ast>(Vec<Node<'ast>>);
+
+impl<'ast> Visitor<'ast> for Collector<'ast> {
+    fn visit_item(&mut self, item: &'ast Item) {
+        self.0.push(Node::Item(item));
+        visit::walk_item(self, item)
+    }
+    fn visit_ty(&mut self, ty: &'ast Ty) {
+        self.0.push(Node::Ty(ty));
+        visit::walk_ty(self, ty)
+    }
+    fn visit_block(&mut self, block: &'ast Block) {
+        self.0.push(Node::Block(block));
+        visit::walk_block(self, block)
+    }
+    fn visit_stmt(&mut self, stmt: &'ast Stmt) {
+        self.0.push(Node::Stmt(stmt));
+        visit::walk_stmt(self, stmt)
+    }
+    fn visit_expr(&mut self, expr: &'ast Expr) {
+        self.0.push(Node::Expr(expr));
+        visit::walk_expr(self, expr)
+    }
+    fn visit_pat(&mut self, pat: &'ast Pat) {
+        self.0.push(Node::Pat(pat));
+        visit::walk_pat(self, pat)
+    }
+}
@@ -0,0 +1,168 @@
+use super::*;
+use ast::*;
+use ceylon_lexer::KwKind;
+
+impl Parser<'_> {
+    /// Parses a pattern, possibly with alternatives such as `1 | 2`.
+    pub(crate) fn parse_pat(&mut self) -> PResult<Pat> {
+        let first = self.parse_pat_no_alt()?;
+        if self.token.kind != TokenKind::Or {
+            return Ok(first);
+        }
+        let mut span = first.span;
+        let mut alts = vec![first];
+        while self.token.kind == TokenKind::Or {
+            self.bump();
+            let alt = self.parse_pat_no_alt()?;
+            span = span.append(alt.span);
+            alts.push(alt);
+        }
+        Ok(self.mk_pat(PatKind::Or(alts), span))
+    }
+
+    fn parse_pat_no_alt(&mut self) -> PResult<Pat> {
+        let start = self.token.span;
+        match self.token.kind {
+            TokenKind::OpenParen => self.parse_paren_pat(),
+            TokenKind::Literal { .. }
+            | TokenKind::Keyword {
+                kind: KwKind::True | KwKind::False,
+            } => {
+                let lit = self.parse_lit_expr()?;
+                let span = lit.span;
+                Ok(self.mk_pat(PatKind::Lit(Box::new(lit)), span))
+            }
+            TokenKind::Minus => {
+                self.bump();
+                let lit = self.parse_lit_expr()?;
+                let span = start.append(lit.span);
+                let neg = self.mk_expr(ExprKind::Unary(UnOp::Neg, Box::new(lit)), span);
+                Ok(self.mk_pat(PatKind::Lit(Box::new(neg)), span))
+            }
+            TokenKind::Keyword { kind: KwKind::Mut } => {
+                self.bump();
+                let ident = self.parse_ident()?;
+                let span = start.append(ident.span);
+                Ok(self.mk_pat(PatKind::Ident(Mutability::Mut, ident), span))
+            }
+            TokenKind::Ident if self.reader.span_to_str(&self.token.span) == "_" => {
+                self.bump();
+                Ok(self.mk_pat(PatKind::Wild, start))
+            }
+            TokenKind::Ident => {
+                let path = self.parse_path()?;
+                match self.token.kind {
+                    TokenKind::OpenParen => self.parse_tuple_struct_pat(path),
+                    TokenKind::OpenBrace => self.parse_struct_pat(path),
+                    _ if path.segments.len() == 1 => {
+                        let span = path.span;
+                        let ident = path.segments.into_iter().next().unwrap();
+                        Ok(self.mk_pat(PatKind::Ident(Mutability::Not, ident), span))
+                    }
+                    _ => {
+                        let span = path.span;
+                        Ok(self.mk_pat(PatKind::Path(path), span))
+                    }
+                }
+            }
+            _ => Err(self.unexpected("pattern")),
+        }
+    }
+
+    /// Parses a tuple pattern, or a pattern in parentheses, which is `(a,)`
+    /// rather than `(a)` if it has one element.
+    fn parse_paren_pat(&mut self) -> PResult<Pat> {
+        let start = self.expect(TokenKind::OpenParen)?;
+        let mut pats = vec![self.parse_pat()?];
+        if self.token.kind == TokenKind::CloseParen {
+            self.bump();
+            return Ok(pats.pop().unwrap());
+        }
+        self.expect(TokenKind::Comma)?;
+        while self.token.kind != TokenKind::CloseParen {
+            pats.push(self.parse_pat()?);
+            if self.token.kind != TokenKind::Comma {
+                break;
+            }
+            self.bump();
+        }
+        let end = self.expect(TokenKind::CloseParen)?;
+        Ok(self.mk_pat(PatKind::Tuple(pats), start.span.append(end.span)))
+    }
+
+    /// Parses a literal, or `true` or `false`, as an expression.
+    fn parse_lit_expr(&mut self) -> PResult<Expr> {
+        let lit = match self.token.kind {
+            TokenKind::Literal { .. } => self.to_ast_literal(self.token)?,
+            TokenKind::Keyword {
+                kind: kind @ (KwKind::True | KwKind::False),
+            } => LiteralKind::Bool(kind == KwKind::True),
+            _ => return Err(self.unexpected("literal")),
+        };
+        let span = self.token.span;
+        self.bump();
+        Ok(self.mk_expr(ExprKind::Literal(lit), span))
+    }
+
+    /// Parses `(a, b)` in `Path(a, b)`.
+    fn parse_tuple_struct_pat(&mut self, path: Path) -> PResult<Pat> {
+        self.expect(TokenKind::OpenParen)?;
+        let mut pats = Vec::new();
+        while self.token.kind != TokenKind::CloseParen {
+            pats.push(self.parse_pat()?);
+            if self.token.kind != TokenKind::Comma {
+                break;
+            }
+            self.bump();
+        }
+        let end = self.expect(TokenKind::CloseParen)?.span;
+        let span = path.span.append(end);
+        Ok(self.mk_pat(PatKind::TupleStruct(path, pats), span))
+    }
+
+    /// Parses `{ a, b: pat, .. }` in `Path { a, b: pat, .. }`.
+    fn parse_struct_pat(&mut self, path: Path) -> PResult<Pat> {
+        self.expect(TokenKind::OpenBrace)?;
+        let mut fields = Vec::new();
+        let mut rest = false;
+        while self.token.kind != TokenKind::CloseBrace {
+            if self.token.kind == TokenKind::DotDot {
+                self.bump();
+                rest = true;
+                break;
+            }
+            let start = self.token.span;
+            let mutbl = self.parse_mutability();
+            let ident = self.parse_ident()?;
+            let pat = if mutbl == Mutability::Not && self.token.kind == TokenKind::Colon {
+                self.bump();
+                self.parse_pat()?
+            } else {
+                // The shorthand `a` binds the field to a variable of the same
+                // name, and `mut a` to a mutable one.
+                let binding = Ident {
+                    name: ident.name,
+                    span: ident.span,
+                };
+                self.mk_pat(PatKind::Ident(mutbl, binding), start.append(ident.span))
+            };
+            let span = start.append(pat.span);
+            fields.push(PatField { ident, pat, span });
+            if self.token.kind != TokenKind::Comma {
+                break;
+            }
+            self.bump();
+        }
+        let end = self.expect(TokenKind::CloseBrace)?.span;
+        let span = path.span.append(end);
+        Ok(self.mk_pat(PatKind::Struct(path, fields, rest), span))
+    }
+
+    fn mk_pat(&mut self, kind: PatKind, span: Span) -> Pat {
+        Pat {
+            id: self.next_node_id(),
+            kind,
+            span,
+        }
+    }
+}
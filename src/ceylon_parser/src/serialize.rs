@@ -0,0 +1,87 @@
+//! Serialization of the AST for tools and caches.
+//!
+//! Two formats are supported: JSON, for tooling written in other languages
+//! and for debugging, and a compact binary format for caching parsed modules.
+//! Both wrap the AST in an envelope carrying `SCHEMA_VERSION`, so consumers
+//! can reject data written by an incompatible compiler.
+
+use std::fmt;
+
+use crate::ast::Module;
+use serde::{Deserialize, Serialize};
+
+/// Version of the serialized AST layout. Bump this whenever a change to the
+/// `ast` types changes their serialized form.
+pub const SCHEMA_VERSION: u32 = 21;
+
+#[derive(Serialize)]
+struct Envelope<'a> {
+    schema_version: u32,
+    module: &'a Module,
+}
+
+#[derive(Deserialize)]
+struct OwnedEnvelope {
+    schema_version: u32,
+    module: Module,
+}
+
+#[derive(Debug)]
+pub enum Error {
+    /// The data was written with a different `SCHEMA_VERSION`.
+    SchemaVersion {
+        found: u32,
+    },
+    Json(serde_json::Error),
+    Binary(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::SchemaVersion { found } => write!(
+                f,
+                "unsupported AST schema version {} (expected {})",
+                found, SCHEMA_VERSION
+            ),
+            Error::Json(e) => write!(f, "malformed AST JSON: {}", e),
+            Error::Binary(e) => write!(f, "malformed binary AST: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+fn envelope(module: &Module) -> Envelope<'_> {
+    Envelope {
+        schema_version: SCHEMA_VERSION,
+        module,
+    }
+}
+
+fn open(envelope: OwnedEnvelope) -> Result<Module, Error> {
+    if envelope.schema_version != SCHEMA_VERSION {
+        return Err(Error::SchemaVersion {
+            found: envelope.schema_version,
+        });
+    }
+    Ok(envelope.module)
+}
+
+pub fn to_json(module: &Module) -> String {
+    serde_json::to_string_pretty(&envelope(module)).unwrap()
+}
+
+pub fn from_json(s: &str) -> Result<Module, Error> {
+    open(serde_json::from_str(s).map_err(Error::Json)?)
+}
+
+pub fn to_binary(module: &Module) -> Vec<u8> {
+    bincode::serde::encode_to_vec(envelope(module), bincode::config::standard()).unwrap()
+}
+
+pub fn from_binary(bytes: &[u8]) -> Result<Module, Error> {
+    let (envelope, _) = bincode::serde::decode_from_slice(bytes, bincode::config::standard())
+        .map_err(|e| Error::Binary(e.to_string()))?;
+    open(envelope)
+}
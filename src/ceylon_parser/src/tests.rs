@@ -1,8 +1,22 @@
 use super::*;
+use ast::*;
 use expect_test::{expect, Expect};
 
+fn parse_module(src: &str) -> Module {
+    let (module, diagnostics) = parse_file(src);
+    assert!(diagnostics.is_empty(), "{:?}", diagnostics);
+    module
+}
+
 fn check_parsing(src: &str, expect: Expect) {
-    let actual: String = format!("{:?}\n", parse(src));
+    let (expr, diagnostics) = parse_expr(src);
+    assert!(diagnostics.is_empty(), "{:?}", diagnostics);
+    let actual: String = format!("{:?}\n", expr);
+    expect.assert_eq(&actual)
+}
+
+fn check_module_parsing(src: &str, expect: Expect) {
+    let actual: String = format!("{:#?}\n", parse_module(src));
     expect.assert_eq(&actual)
 }
 
@@ -11,7 +25,7 @@ fn test_parse_integer() {
     check_parsing(
         "123456",
         expect![[r#"
-            Expr { kind: Literal(Int(123456)), span: Span { start_pos: 0, len: 6 } }
+            Expr { id: NodeId(0), kind: Literal(Int(123456, None)), span: Span { start_pos: 0, len: 6 } }
         "#]],
     )
 }
@@ -21,7 +35,23 @@ fn test_parse_float() {
     check_parsing(
         "3.141592",
         expect![[r#"
-            Expr { kind: Literal(Float(3.141592)), span: Span { start_pos: 0, len: 8 } }
+            Expr { id: NodeId(0), kind: Literal(Float(3.141592, None)), span: Span { start_pos: 0, len: 8 } }
+        "#]],
+    )
+}
+
+#[test]
+fn test_parse_escapes() {
+    check_parsing(
+        r#""a\tb\n""#,
+        expect![[r#"
+            Expr { id: NodeId(0), kind: Literal(Str("a\tb\n")), span: Span { start_pos: 0, len: 8 } }
+        "#]],
+    );
+    check_parsing(
+        r"'\t'",
+        expect![[r#"
+            Expr { id: NodeId(0), kind: Literal(Char('\t')), span: Span { start_pos: 0, len: 4 } }
         "#]],
     )
 }
@@ -31,7 +61,1719 @@ fn test_parse_equality() {
     check_parsing(
         "1 + 2 * 3 == 6",
         expect![[r#"
-            Expr { kind: Binary(Eq, Expr { kind: Binary(Add, Expr { kind: Literal(Int(1)), span: Span { start_pos: 0, len: 1 } }, Expr { kind: Binary(Mul, Expr { kind: Literal(Int(2)), span: Span { start_pos: 4, len: 1 } }, Expr { kind: Literal(Int(3)), span: Span { start_pos: 8, len: 1 } }), span: Span { start_pos: 4, len: 5 } }), span: Span { start_pos: 0, len: 9 } }, Expr { kind: Literal(Int(6)), span: Span { start_pos: 13, len: 1 } }), span: Span { start_pos: 0, len: 14 } }
+            Expr { id: NodeId(6), kind: Binary(Eq, Expr { id: NodeId(4), kind: Binary(Add, Expr { id: NodeId(0), kind: Literal(Int(1, None)), span: Span { start_pos: 0, len: 1 } }, Expr { id: NodeId(3), kind: Binary(Mul, Expr { id: NodeId(1), kind: Literal(Int(2, None)), span: Span { start_pos: 4, len: 1 } }, Expr { id: NodeId(2), kind: Literal(Int(3, None)), span: Span { start_pos: 8, len: 1 } }), span: Span { start_pos: 4, len: 5 } }), span: Span { start_pos: 0, len: 9 } }, Expr { id: NodeId(5), kind: Literal(Int(6, None)), span: Span { start_pos: 13, len: 1 } }), span: Span { start_pos: 0, len: 14 } }
+        "#]],
+    )
+}
+
+#[test]
+fn test_parse_doc_comments() {
+    check_module_parsing(
+        "//! Module doc.\n/// Item doc.\n/** Block doc. */\nfn main() -> void { 1; }",
+        expect![[r#"
+            Module {
+                docs: [
+                    Doc {
+                        style: Inner,
+                        text: " Module doc.",
+                        span: Span {
+                            start_pos: 0,
+                            len: 15,
+                        },
+                    },
+                ],
+                items: [
+                    Item {
+                        id: NodeId(4),
+                        docs: [
+                            Doc {
+                                style: Outer,
+                                text: " Item doc.",
+                                span: Span {
+                                    start_pos: 16,
+                                    len: 13,
+                                },
+                            },
+                            Doc {
+                                style: Outer,
+                                text: " Block doc. ",
+                                span: Span {
+                                    start_pos: 30,
+                                    len: 17,
+                                },
+                            },
+                        ],
+                        attrs: [],
+                        vis: Private,
+                        ident: Ident {
+                            name: "main",
+                            span: Span {
+                                start_pos: 51,
+                                len: 4,
+                            },
+                        },
+                        kind: Fn(
+                            FnDecl {
+                                generics: Generics {
+                                    params: [],
+                                    span: Span {
+                                        start_pos: 55,
+                                        len: 0,
+                                    },
+                                },
+                                self_param: None,
+                                params: [],
+                                ret_ty: Some(
+                                    Ty {
+                                        id: NodeId(0),
+                                        kind: Void,
+                                        span: Span {
+                                            start_pos: 61,
+                                            len: 4,
+                                        },
+                                    },
+                                ),
+                                body: Some(
+                                    Block {
+                                        id: NodeId(3),
+                                        stmts: [
+                                            Stmt {
+                                                id: NodeId(2),
+                                                attrs: [],
+                                                kind: Semi(
+                                                    Expr {
+                                                        id: NodeId(1),
+                                                        kind: Literal(
+                                                            Int(
+                                                                1,
+                                                                None,
+                                                            ),
+                                                        ),
+                                                        span: Span {
+                                                            start_pos: 68,
+                                                            len: 1,
+                                                        },
+                                                    },
+                                                ),
+                                                span: Span {
+                                                    start_pos: 68,
+                                                    len: 2,
+                                                },
+                                            },
+                                        ],
+                                        span: Span {
+                                            start_pos: 66,
+                                            len: 6,
+                                        },
+                                    },
+                                ),
+                            },
+                        ),
+                        span: Span {
+                            start_pos: 48,
+                            len: 24,
+                        },
+                    },
+                ],
+                span: Span {
+                    start_pos: 0,
+                    len: 72,
+                },
+            }
+        "#]],
+    )
+}
+
+#[test]
+fn test_parse_field_docs() {
+    let (module, diagnostics) = parse_file(
+        "struct P {\n    /// Across.\n    /** In pixels. */\n    x: i64,\n    y: i64,\n}",
+    );
+    assert!(diagnostics.is_empty(), "{:?}", diagnostics);
+    let ItemKind::Struct(def) = &module.items[0].kind else {
+        unreachable!()
+    };
+    let actual: Vec<String> = def
+        .fields
+        .iter()
+        .map(|field| {
+            let docs: Vec<&str> = field.docs.iter().map(|doc| doc.text.as_str()).collect();
+            format!("{}: {:?}", field.ident.name, docs)
+        })
+        .collect();
+    expect![[r#"
+        x: [" Across.", " In pixels. "]
+        y: []"#]]
+    .assert_eq(&actual.join("\n"));
+    check_diagnostics(
+        "struct P { x: i64, /// Dangling.\n}",
+        expect![[r#"
+            error: expected field after doc comment, found `}`
+             --> test.cy:2:1
+              |
+            2 | }
+              | ^
+              = help: doc comments must come before what they document
+        "#]],
+    );
+}
+
+#[test]
+fn test_parse_variant_docs() {
+    let (module, diagnostics) = parse_file(
+        "enum Shape {\n    /// Round.\n    Circle(f64),\n    /// Square-ish.\n    Rect {\n        /// Width.\n        w: f64,\n        h: f64,\n    },\n    Empty,\n}",
+    );
+    assert!(diagnostics.is_empty(), "{:?}", diagnostics);
+    let ItemKind::Enum(def) = &module.items[0].kind else {
+        unreachable!()
+    };
+    let texts = |docs: &[Doc]| -> Vec<&str> { docs.iter().map(|doc| doc.text.as_str()).collect() };
+    let mut actual = Vec::new();
+    for variant in &def.variants {
+        actual.push(format!(
+            "{}: {:?}",
+            variant.ident.name,
+            texts(&variant.docs)
+        ));
+        if let VariantData::Struct(fields) = &variant.data {
+            for field in fields {
+                actual.push(format!(
+                    "    {}: {:?}",
+                    field.ident.name,
+                    texts(&field.docs)
+                ));
+            }
+        }
+    }
+    expect![[r#"
+        Circle: [" Round."]
+        Rect: [" Square-ish."]
+            w: [" Width."]
+            h: []
+        Empty: []"#]]
+    .assert_eq(&actual.join("\n"));
+    check_diagnostics(
+        "enum E { A, /// Dangling.\n}",
+        expect![[r#"
+        error: expected variant after doc comment, found `}`
+         --> test.cy:2:1
+          |
+        2 | }
+          | ^
+          = help: doc comments must come before what they document
+    "#]],
+    );
+}
+
+fn check_lints(src: &str, expect: Expect) {
+    let module = parse_module(src);
+    let actual: String = lint::check_module(src, &module)
+        .into_iter()
+        .map(|lint| format!("{:?}\n", lint))
+        .collect();
+    expect.assert_eq(&actual)
+}
+
+#[test]
+fn test_nfc_normalized_ident() {
+    // "e" followed by COMBINING ACUTE ACCENT is normalized to "\u{E9}".
+    let module = parse_module("fn cafe\u{301}() {}");
+    assert_eq!(module.items[0].ident.name.as_str(), "caf\u{E9}");
+}
+
+#[test]
+fn test_lint_confusable_idents() {
+    check_lints(
+        // The second "a" is CYRILLIC SMALL LETTER A.
+        "fn a() {} fn f(\u{430}: i64, m: i64, rn: i64) {}",
+        expect![[r#"
+            Lint { kind: ConfusableIdents, span: Span { start_pos: 15, len: 2 }, message: "identifier pair considered confusable between `a` and `а`" }
+        "#]],
+    );
+    // Variables are checked against the names in scope where they are
+    // bound, which ends with their block, closure or match arm.
+    check_lints(
+        "fn f(x: i64) {\n\
+         \x20   let a = 1;\n\
+         \x20   let \u{430} = 2;\n\
+         \x20   { let \u{445} = 3; }\n\
+         \x20   let g = |p, \u{440}| p;\n\
+         \x20   match x { (\u{441}, c) => c, _ => 0 };\n\
+         \x20   for e in 0..2 { let \u{435} = e; }\n\
+         \x20   { let o = 1; } { let \u{43e} = 2; }\n\
+         }",
+        expect![[r#"
+            Lint { kind: ConfusableIdents, span: Span { start_pos: 38, len: 2 }, message: "identifier pair considered confusable between `a` and `а`" }
+            Lint { kind: ConfusableIdents, span: Span { start_pos: 56, len: 2 }, message: "identifier pair considered confusable between `x` and `х`" }
+            Lint { kind: ConfusableIdents, span: Span { start_pos: 82, len: 2 }, message: "identifier pair considered confusable between `p` and `р`" }
+            Lint { kind: ConfusableIdents, span: Span { start_pos: 108, len: 1 }, message: "identifier pair considered confusable between `с` and `c`" }
+            Lint { kind: ConfusableIdents, span: Span { start_pos: 151, len: 2 }, message: "identifier pair considered confusable between `e` and `е`" }
+        "#]],
+    );
+}
+
+#[test]
+fn test_lint_text_direction_codepoint() {
+    check_lints(
+        "/* \u{202E} */ fn f() {}",
+        expect![[r#"
+            Lint { kind: TextDirectionCodepoint, span: Span { start_pos: 3, len: 3 }, message: "unicode codepoint changing visible direction of text present in source: '\\u{202e}'" }
+        "#]],
+    )
+}
+
+#[test]
+fn test_lint_file_at() {
+    // Spans are those of the file in the source map, as with `parse_file_at`.
+    let src = "/* \u{202E} */ fn a() {} fn \u{430}() {}";
+    let (module, diagnostics) = parse_file_at(src, 100, &mut 0);
+    assert!(diagnostics.is_empty());
+    let actual: String = lint::check_module_at(src, 100, &module)
+        .into_iter()
+        .map(|lint| format!("{:?}\n", lint))
+        .collect();
+    expect![[r#"
+        Lint { kind: TextDirectionCodepoint, span: Span { start_pos: 103, len: 3 }, message: "unicode codepoint changing visible direction of text present in source: '\\u{202e}'" }
+        Lint { kind: ConfusableIdents, span: Span { start_pos: 123, len: 2 }, message: "identifier pair considered confusable between `a` and `а`" }
+    "#]].assert_eq(&actual);
+}
+
+#[test]
+fn test_node_map() {
+    use node_map::{Node, NodeMap};
+
+    let src = "fn f(x: i64) -> i64 { let y = x; match y { 0 => 1, n => n + 2 } }";
+    let module = parse_module(src);
+    let map = NodeMap::new(&module);
+    let describe = |node: Node| {
+        let kind = match node {
+            Node::Item(_) => "item",
+            Node::Ty(_) => "type",
+            Node::Block(_) => "block",
+            Node::Stmt(_) => "statement",
+            Node::Expr(_) => "expression",
+            Node::Pat(_) => "pattern",
+        };
+        let span = node.span();
+        format!(
+            "{} `{}`",
+            kind,
+            &src[span.start_pos..span.start_pos + span.len]
+        )
+    };
+    // On `n` of `n + 2`, on `i64`, on `fn`, after the `match` and on the
+    // `;` ending the `let`.
+    let actual: String = [56, 8, 0, 64, 31]
+        .into_iter()
+        .map(|pos| {
+            let nodes: Vec<String> = map.nodes_at(pos).map(describe).collect();
+            format!("{}\n", nodes.join(" < "))
+        })
+        .collect();
+    expect![[r#"
+        expression `n` < expression `n + 2` < expression `match y { 0 => 1, n => n + 2 }` < statement `match y { 0 => 1, n => n + 2 }` < block `{ let y = x; match y { 0 => 1, n => n + 2 } }` < item `fn f(x: i64) -> i64 { let y = x; match y { 0 => 1, n => n + 2 } }`
+        type `i64` < item `fn f(x: i64) -> i64 { let y = x; match y { 0 => 1, n => n + 2 } }`
+        item `fn f(x: i64) -> i64 { let y = x; match y { 0 => 1, n => n + 2 } }`
+        block `{ let y = x; match y { 0 => 1, n => n + 2 } }` < item `fn f(x: i64) -> i64 { let y = x; match y { 0 => 1, n => n + 2 } }`
+        statement `let y = x;` < block `{ let y = x; match y { 0 => 1, n => n + 2 } }` < item `fn f(x: i64) -> i64 { let y = x; match y { 0 => 1, n => n + 2 } }`
+    "#]].assert_eq(&actual);
+    let node = map.node_at(56).unwrap();
+    assert_eq!(map.get(node.id()).unwrap().id(), node.id());
+    assert!(map.node_at(src.len()).is_none());
+}
+
+#[test]
+fn test_visitor() {
+    struct LiteralCounter(usize);
+
+    impl<'ast> visit::Visitor<'ast> for LiteralCounter {
+        fn visit_expr(&mut self, expr: &'ast Expr) {
+            if let ExprKind::Literal(_) = expr.kind {
+                self.0 += 1;
+            }
+            visit::walk_expr(self, expr)
+        }
+    }
+
+    let module = parse_module("fn f() { 1 + 2 * 3; 4; } fn g() { 5 == 6; }");
+    let mut counter = LiteralCounter(0);
+    visit::Visitor::visit_module(&mut counter, &module);
+    assert_eq!(counter.0, 6);
+}
+
+#[test]
+fn test_mut_visitor() {
+    /// Removes statements consisting of a lone literal and doubles integers.
+    struct Rewriter;
+
+    impl mut_visit::MutVisitor for Rewriter {
+        fn flat_map_stmt(&mut self, stmt: Stmt) -> Vec<Stmt> {
+            match &stmt.kind {
+                StmtKind::Semi(Expr {
+                    kind: ExprKind::Literal(_),
+                    ..
+                }) => vec![],
+                _ => mut_visit::walk_flat_map_stmt(self, stmt),
+            }
+        }
+
+        fn visit_expr(&mut self, expr: &mut Expr) {
+            if let ExprKind::Literal(LiteralKind::Int(n, _)) = &mut expr.kind {
+                *n *= 2;
+            }
+            mut_visit::walk_expr(self, expr)
+        }
+    }
+
+    let mut module = parse_module("fn f() { 1; 2 + 3; }");
+    mut_visit::MutVisitor::visit_module(&mut Rewriter, &mut module);
+    let ItemKind::Fn(decl) = &module.items[0].kind else {
+        unreachable!()
+    };
+    let actual = format!("{:?}\n", decl.body.as_ref().unwrap().stmts);
+    expect![[r#"
+        [Stmt { id: NodeId(5), attrs: [], kind: Semi(Expr { id: NodeId(4), kind: Binary(Add, Expr { id: NodeId(2), kind: Literal(Int(4, None)), span: Span { start_pos: 12, len: 1 } }, Expr { id: NodeId(3), kind: Literal(Int(6, None)), span: Span { start_pos: 16, len: 1 } }), span: Span { start_pos: 12, len: 5 } }), span: Span { start_pos: 12, len: 6 } }]
+    "#]]
+    .assert_eq(&actual);
+}
+
+#[test]
+fn test_serialize_json() {
+    let module = parse_module("/// Doc.\nfn f(x: i64) { 1 + 2; }");
+    let json = serialize::to_json(&module);
+    expect![[r#"
+        {
+          "schema_version": 21,
+          "module": {
+            "docs": [],
+            "items": [
+              {
+                "id": 7,
+                "docs": [
+                  {
+                    "style": "Outer",
+                    "text": " Doc.",
+                    "span": {
+                      "start_pos": 0,
+                      "len": 8
+                    }
+                  }
+                ],
+                "attrs": [],
+                "vis": "Private",
+                "ident": {
+                  "name": "f",
+                  "span": {
+                    "start_pos": 12,
+                    "len": 1
+                  }
+                },
+                "kind": {
+                  "Fn": {
+                    "generics": {
+                      "params": [],
+                      "span": {
+                        "start_pos": 13,
+                        "len": 0
+                      }
+                    },
+                    "self_param": null,
+                    "params": [
+                      {
+                        "id": 1,
+                        "mutbl": "Not",
+                        "ident": {
+                          "name": "x",
+                          "span": {
+                            "start_pos": 14,
+                            "len": 1
+                          }
+                        },
+                        "ty": {
+                          "id": 0,
+                          "kind": "I64",
+                          "span": {
+                            "start_pos": 17,
+                            "len": 3
+                          }
+                        },
+                        "span": {
+                          "start_pos": 14,
+                          "len": 6
+                        }
+                      }
+                    ],
+                    "ret_ty": null,
+                    "body": {
+                      "id": 6,
+                      "stmts": [
+                        {
+                          "id": 5,
+                          "attrs": [],
+                          "kind": {
+                            "Semi": {
+                              "id": 4,
+                              "kind": {
+                                "Binary": [
+                                  "Add",
+                                  {
+                                    "id": 2,
+                                    "kind": {
+                                      "Literal": {
+                                        "Int": [
+                                          1,
+                                          null
+                                        ]
+                                      }
+                                    },
+                                    "span": {
+                                      "start_pos": 24,
+                                      "len": 1
+                                    }
+                                  },
+                                  {
+                                    "id": 3,
+                                    "kind": {
+                                      "Literal": {
+                                        "Int": [
+                                          2,
+                                          null
+                                        ]
+                                      }
+                                    },
+                                    "span": {
+                                      "start_pos": 28,
+                                      "len": 1
+                                    }
+                                  }
+                                ]
+                              },
+                              "span": {
+                                "start_pos": 24,
+                                "len": 5
+                              }
+                            }
+                          },
+                          "span": {
+                            "start_pos": 24,
+                            "len": 6
+                          }
+                        }
+                      ],
+                      "span": {
+                        "start_pos": 22,
+                        "len": 10
+                      }
+                    }
+                  }
+                },
+                "span": {
+                  "start_pos": 9,
+                  "len": 23
+                }
+              }
+            ],
+            "span": {
+              "start_pos": 0,
+              "len": 32
+            }
+          }
+        }"#]]
+    .assert_eq(&json);
+    let roundtrip = serialize::from_json(&json).unwrap();
+    assert_eq!(format!("{:?}", roundtrip), format!("{:?}", module));
+}
+
+#[test]
+fn test_serialize_binary() {
+    let module = parse_module("fn f() { \"abc\"; 'c'; 3.14 == 0.5; }");
+    let bytes = serialize::to_binary(&module);
+    let roundtrip = serialize::from_binary(&bytes).unwrap();
+    assert_eq!(format!("{:?}", roundtrip), format!("{:?}", module));
+}
+
+#[test]
+fn test_serialize_schema_version() {
+    let json = r#"{ "schema_version": 0, "module": { "docs": [], "items": [], "span": { "start_pos": 0, "len": 0 } } }"#;
+    assert!(matches!(
+        serialize::from_json(json),
+        Err(serialize::Error::SchemaVersion { found: 0 })
+    ));
+}
+
+fn check_diagnostics(src: &str, expect: Expect) {
+    let (_, diagnostics) = parse_file(src);
+    let actual: String = diagnostics
+        .iter()
+        .map(|diag| diag.render(src, "test.cy"))
+        .collect();
+    expect.assert_eq(&actual)
+}
+
+#[test]
+fn test_unknown_tokens() {
+    check_diagnostics(
+        "fn f() {\u{00A0}1 \u{20AC} 2\u{3000}}",
+        expect![[r#"
+            error: unicode whitespace is not allowed, use a space
+             --> test.cy:1:9
+              |
+            1 | fn f() { 1 € 2　}
+              |         ^
+              = note: found '\u{a0}'
+            error: unknown start of token: "€"
+             --> test.cy:1:13
+              |
+            1 | fn f() { 1 € 2　}
+              |            ^
+            error: expected `;`, found `2`
+             --> test.cy:1:17
+              |
+            1 | fn f() { 1 € 2　}
+              |              ^
+            error: unicode whitespace is not allowed, use a space
+             --> test.cy:1:18
+              |
+            1 | fn f() { 1 € 2　}
+              |               ^
+              = note: found '\u{3000}'
+        "#]],
+    )
+}
+
+#[test]
+fn test_parse_unary() {
+    check_parsing(
+        "-1 == !2",
+        expect![[r#"
+            Expr { id: NodeId(4), kind: Binary(Eq, Expr { id: NodeId(1), kind: Unary(Neg, Expr { id: NodeId(0), kind: Literal(Int(1, None)), span: Span { start_pos: 1, len: 1 } }), span: Span { start_pos: 0, len: 2 } }, Expr { id: NodeId(3), kind: Unary(Not, Expr { id: NodeId(2), kind: Literal(Int(2, None)), span: Span { start_pos: 7, len: 1 } }), span: Span { start_pos: 6, len: 2 } }), span: Span { start_pos: 0, len: 8 } }
+        "#]],
+    )
+}
+
+#[test]
+fn test_parse_expr_trailing_tokens() {
+    let (expr, diagnostics) = parse_expr("1 2");
+    assert!(matches!(expr.kind, ExprKind::Err));
+    assert_eq!(diagnostics[0].message, "expected end of file, found `2`");
+}
+
+#[test]
+fn test_parse_item() {
+    let (item, diagnostics) = parse_item("fn f() {}");
+    assert!(item.is_some() && diagnostics.is_empty());
+    let (item, diagnostics) = parse_item("fn () {}");
+    assert!(item.is_none());
+    assert_eq!(diagnostics[0].message, "expected identifier, found `(`");
+}
+
+#[test]
+fn test_recovery() {
+    check_diagnostics(
+        "fn f() { 1 +; 2 } fn g( {}\nfn h() { 'ab'; 1; }\n/* x",
+        expect![[r#"
+            error: expected expression, found `;`
+             --> test.cy:1:13
+              |
+            1 | fn f() { 1 +; 2 } fn g( {}
+              |             ^
+            error: expected identifier, found `{`
+             --> test.cy:1:25
+              |
+            1 | fn f() { 1 +; 2 } fn g( {}
+              |                         ^
+            error: character literal must contain exactly one character
+             --> test.cy:2:10
+              |
+            2 | fn h() { 'ab'; 1; }
+              |          ^^^^
+            error: unterminated block comment
+             --> test.cy:3:1
+              |
+            3 | /* x
+              | ^^^^
+        "#]],
+    )
+}
+
+#[test]
+fn test_misplaced_doc_comments() {
+    check_diagnostics(
+        "fn f() { /// a\n 1; }\n//! b\nfn g() {}\n/// c",
+        expect![[r#"
+            error: expected statement, found doc comment
+             --> test.cy:1:10
+              |
+            1 | fn f() { /// a
+              |          ^^^^^
+              = help: doc comments must be followed by an item
+            error: expected outer doc comment
+             --> test.cy:3:1
+              |
+            3 | //! b
+              | ^^^^^
+              = note: inner doc comments must be placed at the beginning of the module
+            error: expected item after doc comment, found end of file
+             --> test.cy:5:6
+              |
+            5 | /// c
+              |      ^
+              = help: doc comments must come before what they document
+        "#]],
+    )
+}
+
+#[test]
+fn test_parse_struct() {
+    check_module_parsing(
+        "struct Point { x: i64, y: Point, }",
+        expect![[r#"
+            Module {
+                docs: [],
+                items: [
+                    Item {
+                        id: NodeId(4),
+                        docs: [],
+                        attrs: [],
+                        vis: Private,
+                        ident: Ident {
+                            name: "Point",
+                            span: Span {
+                                start_pos: 7,
+                                len: 5,
+                            },
+                        },
+                        kind: Struct(
+                            StructDef {
+                                generics: Generics {
+                                    params: [],
+                                    span: Span {
+                                        start_pos: 13,
+                                        len: 0,
+                                    },
+                                },
+                                fields: [
+                                    FieldDef {
+                                        id: NodeId(1),
+                                        docs: [],
+                                        ident: Ident {
+                                            name: "x",
+                                            span: Span {
+                                                start_pos: 15,
+                                                len: 1,
+                                            },
+                                        },
+                                        ty: Ty {
+                                            id: NodeId(0),
+                                            kind: I64,
+                                            span: Span {
+                                                start_pos: 18,
+                                                len: 3,
+                                            },
+                                        },
+                                        span: Span {
+                                            start_pos: 15,
+                                            len: 6,
+                                        },
+                                    },
+                                    FieldDef {
+                                        id: NodeId(3),
+                                        docs: [],
+                                        ident: Ident {
+                                            name: "y",
+                                            span: Span {
+                                                start_pos: 23,
+                                                len: 1,
+                                            },
+                                        },
+                                        ty: Ty {
+                                            id: NodeId(2),
+                                            kind: Path(
+                                                Path {
+                                                    segments: [
+                                                        Ident {
+                                                            name: "Point",
+                                                            span: Span {
+                                                                start_pos: 26,
+                                                                len: 5,
+                                                            },
+                                                        },
+                                                    ],
+                                                    span: Span {
+                                                        start_pos: 26,
+                                                        len: 5,
+                                                    },
+                                                },
+                                                [],
+                                            ),
+                                            span: Span {
+                                                start_pos: 26,
+                                                len: 5,
+                                            },
+                                        },
+                                        span: Span {
+                                            start_pos: 23,
+                                            len: 8,
+                                        },
+                                    },
+                                ],
+                            },
+                        ),
+                        span: Span {
+                            start_pos: 0,
+                            len: 34,
+                        },
+                    },
+                ],
+                span: Span {
+                    start_pos: 0,
+                    len: 34,
+                },
+            }
+        "#]],
+    )
+}
+
+#[test]
+fn test_parse_struct_expr() {
+    check_parsing(
+        "Point { x: 1, y: true }",
+        expect![[r#"
+            Expr { id: NodeId(2), kind: Struct(StructExpr { path: Path { segments: [Ident { name: "Point", span: Span { start_pos: 0, len: 5 } }], span: Span { start_pos: 0, len: 5 } }, fields: [ExprField { ident: Ident { name: "x", span: Span { start_pos: 8, len: 1 } }, expr: Expr { id: NodeId(0), kind: Literal(Int(1, None)), span: Span { start_pos: 11, len: 1 } }, span: Span { start_pos: 8, len: 4 } }, ExprField { ident: Ident { name: "y", span: Span { start_pos: 14, len: 1 } }, expr: Expr { id: NodeId(1), kind: Literal(Bool(true)), span: Span { start_pos: 17, len: 4 } }, span: Span { start_pos: 14, len: 7 } }] }), span: Span { start_pos: 0, len: 23 } }
+        "#]],
+    )
+}
+
+#[test]
+fn test_parse_field_assign() {
+    check_parsing(
+        "a.b.c = d = 1",
+        expect![[r#"
+            Expr { id: NodeId(6), kind: Assign(Expr { id: NodeId(2), kind: Field(Expr { id: NodeId(1), kind: Field(Expr { id: NodeId(0), kind: Path(Path { segments: [Ident { name: "a", span: Span { start_pos: 0, len: 1 } }], span: Span { start_pos: 0, len: 1 } }), span: Span { start_pos: 0, len: 1 } }, Ident { name: "b", span: Span { start_pos: 2, len: 1 } }), span: Span { start_pos: 0, len: 3 } }, Ident { name: "c", span: Span { start_pos: 4, len: 1 } }), span: Span { start_pos: 0, len: 5 } }, Expr { id: NodeId(5), kind: Assign(Expr { id: NodeId(3), kind: Path(Path { segments: [Ident { name: "d", span: Span { start_pos: 8, len: 1 } }], span: Span { start_pos: 8, len: 1 } }), span: Span { start_pos: 8, len: 1 } }, Expr { id: NodeId(4), kind: Literal(Int(1, None)), span: Span { start_pos: 12, len: 1 } }), span: Span { start_pos: 8, len: 5 } }), span: Span { start_pos: 0, len: 13 } }
+        "#]],
+    )
+}
+
+#[test]
+fn test_parse_let() {
+    check_module_parsing(
+        "fn f() { let p: Point = q; }",
+        expect![[r#"
+            Module {
+                docs: [],
+                items: [
+                    Item {
+                        id: NodeId(6),
+                        docs: [],
+                        attrs: [],
+                        vis: Private,
+                        ident: Ident {
+                            name: "f",
+                            span: Span {
+                                start_pos: 3,
+                                len: 1,
+                            },
+                        },
+                        kind: Fn(
+                            FnDecl {
+                                generics: Generics {
+                                    params: [],
+                                    span: Span {
+                                        start_pos: 4,
+                                        len: 0,
+                                    },
+                                },
+                                self_param: None,
+                                params: [],
+                                ret_ty: None,
+                                body: Some(
+                                    Block {
+                                        id: NodeId(5),
+                                        stmts: [
+                                            Stmt {
+                                                id: NodeId(4),
+                                                attrs: [],
+                                                kind: Let(
+                                                    Local {
+                                                        id: NodeId(3),
+                                                        pat: Pat {
+                                                            id: NodeId(0),
+                                                            kind: Ident(
+                                                                Not,
+                                                                Ident {
+                                                                    name: "p",
+                                                                    span: Span {
+                                                                        start_pos: 13,
+                                                                        len: 1,
+                                                                    },
+                                                                },
+                                                            ),
+                                                            span: Span {
+                                                                start_pos: 13,
+                                                                len: 1,
+                                                            },
+                                                        },
+                                                        ty: Some(
+                                                            Ty {
+                                                                id: NodeId(1),
+                                                                kind: Path(
+                                                                    Path {
+                                                                        segments: [
+                                                                            Ident {
+                                                                                name: "Point",
+                                                                                span: Span {
+                                                                                    start_pos: 16,
+                                                                                    len: 5,
+                                                                                },
+                                                                            },
+                                                                        ],
+                                                                        span: Span {
+                                                                            start_pos: 16,
+                                                                            len: 5,
+                                                                        },
+                                                                    },
+                                                                    [],
+                                                                ),
+                                                                span: Span {
+                                                                    start_pos: 16,
+                                                                    len: 5,
+                                                                },
+                                                            },
+                                                        ),
+                                                        init: Expr {
+                                                            id: NodeId(2),
+                                                            kind: Path(
+                                                                Path {
+                                                                    segments: [
+                                                                        Ident {
+                                                                            name: "q",
+                                                                            span: Span {
+                                                                                start_pos: 24,
+                                                                                len: 1,
+                                                                            },
+                                                                        },
+                                                                    ],
+                                                                    span: Span {
+                                                                        start_pos: 24,
+                                                                        len: 1,
+                                                                    },
+                                                                },
+                                                            ),
+                                                            span: Span {
+                                                                start_pos: 24,
+                                                                len: 1,
+                                                            },
+                                                        },
+                                                    },
+                                                ),
+                                                span: Span {
+                                                    start_pos: 9,
+                                                    len: 17,
+                                                },
+                                            },
+                                        ],
+                                        span: Span {
+                                            start_pos: 7,
+                                            len: 21,
+                                        },
+                                    },
+                                ),
+                            },
+                        ),
+                        span: Span {
+                            start_pos: 0,
+                            len: 28,
+                        },
+                    },
+                ],
+                span: Span {
+                    start_pos: 0,
+                    len: 28,
+                },
+            }
+        "#]],
+    )
+}
+
+#[test]
+fn test_struct_recovery() {
+    check_diagnostics(
+        "struct P { x i64 }\nfn main() { let = 1; P { x: 1 }; }",
+        expect![[r#"
+            error: expected `:`, found `i64`
+             --> test.cy:1:14
+              |
+            1 | struct P { x i64 }
+              |              ^^^
+            error: expected pattern, found `=`
+             --> test.cy:2:17
+              |
+            2 | fn main() { let = 1; P { x: 1 }; }
+              |                 ^
+        "#]],
+    )
+}
+
+#[test]
+fn test_parse_enum() {
+    let module = parse_module("enum Shape { Circle(f64), Rect { w: f64, h: f64 }, Dot }");
+    let ItemKind::Enum(def) = &module.items[0].kind else {
+        unreachable!()
+    };
+    let variants: Vec<String> = def
+        .variants
+        .iter()
+        .map(|variant| format!("{} {:?}", variant.ident.name, variant.data))
+        .collect();
+    expect![[r#"
+        Circle Tuple([Ty { id: NodeId(0), kind: F64, span: Span { start_pos: 20, len: 3 } }])
+        Rect Struct([FieldDef { id: NodeId(3), docs: [], ident: Ident { name: "w", span: Span { start_pos: 33, len: 1 } }, ty: Ty { id: NodeId(2), kind: F64, span: Span { start_pos: 36, len: 3 } }, span: Span { start_pos: 33, len: 6 } }, FieldDef { id: NodeId(5), docs: [], ident: Ident { name: "h", span: Span { start_pos: 41, len: 1 } }, ty: Ty { id: NodeId(4), kind: F64, span: Span { start_pos: 44, len: 3 } }, span: Span { start_pos: 41, len: 6 } }])
+        Dot Unit"#]].assert_eq(&variants.join("\n"));
+}
+
+#[test]
+fn test_parse_match() {
+    check_parsing(
+        "match s { Shape::Circle(r) if r > 0.0 => r, _ => { 0.0 } }",
+        expect![[r#"
+            Expr { id: NodeId(14), kind: Match(Expr { id: NodeId(0), kind: Path(Path { segments: [Ident { name: "s", span: Span { start_pos: 6, len: 1 } }], span: Span { start_pos: 6, len: 1 } }), span: Span { start_pos: 6, len: 1 } }, [Arm { id: NodeId(7), pat: Pat { id: NodeId(2), kind: TupleStruct(Path { segments: [Ident { name: "Shape", span: Span { start_pos: 10, len: 5 } }, Ident { name: "Circle", span: Span { start_pos: 17, len: 6 } }], span: Span { start_pos: 10, len: 13 } }, [Pat { id: NodeId(1), kind: Ident(Not, Ident { name: "r", span: Span { start_pos: 24, len: 1 } }), span: Span { start_pos: 24, len: 1 } }]), span: Span { start_pos: 10, len: 16 } }, guard: Some(Expr { id: NodeId(5), kind: Binary(Gt, Expr { id: NodeId(3), kind: Path(Path { segments: [Ident { name: "r", span: Span { start_pos: 30, len: 1 } }], span: Span { start_pos: 30, len: 1 } }), span: Span { start_pos: 30, len: 1 } }, Expr { id: NodeId(4), kind: Literal(Float(0.0, None)), span: Span { start_pos: 34, len: 3 } }), span: Span { start_pos: 30, len: 7 } }), body: Expr { id: NodeId(6), kind: Path(Path { segments: [Ident { name: "r", span: Span { start_pos: 41, len: 1 } }], span: Span { start_pos: 41, len: 1 } }), span: Span { start_pos: 41, len: 1 } }, span: Span { start_pos: 10, len: 32 } }, Arm { id: NodeId(13), pat: Pat { id: NodeId(8), kind: Wild, span: Span { start_pos: 44, len: 1 } }, guard: None, body: Expr { id: NodeId(12), kind: Block(Block { id: NodeId(11), stmts: [Stmt { id: NodeId(10), attrs: [], kind: Expr(Expr { id: NodeId(9), kind: Literal(Float(0.0, None)), span: Span { start_pos: 51, len: 3 } }), span: Span { start_pos: 51, len: 3 } }], span: Span { start_pos: 49, len: 7 } }), span: Span { start_pos: 49, len: 7 } }, span: Span { start_pos: 44, len: 12 } }]), span: Span { start_pos: 0, len: 58 } }
+        "#]],
+    )
+}
+
+#[test]
+fn test_parse_patterns() {
+    let (expr, diagnostics) = parse_expr(
+        "match x { 1 | -2 => a, 'c' | \"s\" | true => a, P { x, y: _, .. } => a, A::B(C::D, e) => a, (f) => a }",
+    );
+    assert!(diagnostics.is_empty(), "{:?}", diagnostics);
+    let ExprKind::Match(_, arms) = &expr.kind else {
+        unreachable!()
+    };
+    let pats: Vec<String> = arms
+        .iter()
+        .map(|arm| format!("{:?}", arm.pat.kind))
+        .collect();
+    expect![[r#"
+        Or([Pat { id: NodeId(2), kind: Lit(Expr { id: NodeId(1), kind: Literal(Int(1, None)), span: Span { start_pos: 10, len: 1 } }), span: Span { start_pos: 10, len: 1 } }, Pat { id: NodeId(5), kind: Lit(Expr { id: NodeId(4), kind: Unary(Neg, Expr { id: NodeId(3), kind: Literal(Int(2, None)), span: Span { start_pos: 15, len: 1 } }), span: Span { start_pos: 14, len: 2 } }), span: Span { start_pos: 14, len: 2 } }])
+        Or([Pat { id: NodeId(10), kind: Lit(Expr { id: NodeId(9), kind: Literal(Char('c')), span: Span { start_pos: 23, len: 3 } }), span: Span { start_pos: 23, len: 3 } }, Pat { id: NodeId(12), kind: Lit(Expr { id: NodeId(11), kind: Literal(Str("s")), span: Span { start_pos: 29, len: 3 } }), span: Span { start_pos: 29, len: 3 } }, Pat { id: NodeId(14), kind: Lit(Expr { id: NodeId(13), kind: Literal(Bool(true)), span: Span { start_pos: 35, len: 4 } }), span: Span { start_pos: 35, len: 4 } }])
+        Struct(Path { segments: [Ident { name: "P", span: Span { start_pos: 46, len: 1 } }], span: Span { start_pos: 46, len: 1 } }, [PatField { ident: Ident { name: "x", span: Span { start_pos: 50, len: 1 } }, pat: Pat { id: NodeId(18), kind: Ident(Not, Ident { name: "x", span: Span { start_pos: 50, len: 1 } }), span: Span { start_pos: 50, len: 1 } }, span: Span { start_pos: 50, len: 1 } }, PatField { ident: Ident { name: "y", span: Span { start_pos: 53, len: 1 } }, pat: Pat { id: NodeId(19), kind: Wild, span: Span { start_pos: 56, len: 1 } }, span: Span { start_pos: 53, len: 4 } }], true)
+        TupleStruct(Path { segments: [Ident { name: "A", span: Span { start_pos: 70, len: 1 } }, Ident { name: "B", span: Span { start_pos: 73, len: 1 } }], span: Span { start_pos: 70, len: 4 } }, [Pat { id: NodeId(23), kind: Path(Path { segments: [Ident { name: "C", span: Span { start_pos: 75, len: 1 } }, Ident { name: "D", span: Span { start_pos: 78, len: 1 } }], span: Span { start_pos: 75, len: 4 } }), span: Span { start_pos: 75, len: 4 } }, Pat { id: NodeId(24), kind: Ident(Not, Ident { name: "e", span: Span { start_pos: 81, len: 1 } }), span: Span { start_pos: 81, len: 1 } }])
+        Ident(Not, Ident { name: "f", span: Span { start_pos: 91, len: 1 } })"#]].assert_eq(&pats.join("\n"));
+}
+
+#[test]
+fn test_match_recovery() {
+    check_diagnostics(
+        r#"
+fn f() -> i64 {
+    match x { _ => {} }
+    { 3 }
+}
+fn g() { match x { A => 1 B => 2 } }
+fn h() { enum }
+"#,
+        expect![[r#"
+            error: expected `,` or `}` after match arm, found `B`
+             --> test.cy:6:27
+              |
+            6 | fn g() { match x { A => 1 B => 2 } }
+              |                           ^
+            error: expected expression, found `enum`
+             --> test.cy:7:10
+              |
+            7 | fn h() { enum }
+              |          ^^^^
+        "#]],
+    )
+}
+
+#[test]
+fn test_parse_generics() {
+    let module = parse_module(
+        "fn max<T: Ord>(a: T, b: T) -> T { a }\nstruct Pair<A, B: Eq + Ord> { a: A, b: Pair<A, i64> }",
+    );
+    let generics: Vec<String> = module
+        .items
+        .iter()
+        .map(|item| match &item.kind {
+            ItemKind::Fn(decl) => format!("{:?}", decl.generics.params),
+            ItemKind::Struct(def) => format!("{:?}", def.generics.params),
+            _ => unreachable!(),
+        })
+        .collect();
+    expect![[r#"
+        [GenericParam { id: NodeId(0), ident: Ident { name: "T", span: Span { start_pos: 7, len: 1 } }, bounds: [Path { segments: [Ident { name: "Ord", span: Span { start_pos: 10, len: 3 } }], span: Span { start_pos: 10, len: 3 } }], span: Span { start_pos: 7, len: 6 } }]
+        [GenericParam { id: NodeId(10), ident: Ident { name: "A", span: Span { start_pos: 50, len: 1 } }, bounds: [], span: Span { start_pos: 50, len: 1 } }, GenericParam { id: NodeId(11), ident: Ident { name: "B", span: Span { start_pos: 53, len: 1 } }, bounds: [Path { segments: [Ident { name: "Eq", span: Span { start_pos: 56, len: 2 } }], span: Span { start_pos: 56, len: 2 } }, Path { segments: [Ident { name: "Ord", span: Span { start_pos: 61, len: 3 } }], span: Span { start_pos: 61, len: 3 } }], span: Span { start_pos: 53, len: 11 } }]"#]].assert_eq(&generics.join("\n"));
+    let ItemKind::Struct(def) = &module.items[1].kind else {
+        unreachable!()
+    };
+    expect![[r#"Path(Path { segments: [Ident { name: "Pair", span: Span { start_pos: 77, len: 4 } }], span: Span { start_pos: 77, len: 4 } }, [Ty { id: NodeId(14), kind: Path(Path { segments: [Ident { name: "A", span: Span { start_pos: 82, len: 1 } }], span: Span { start_pos: 82, len: 1 } }, []), span: Span { start_pos: 82, len: 1 } }, Ty { id: NodeId(15), kind: I64, span: Span { start_pos: 85, len: 3 } }])"#]].assert_eq(&format!("{:?}", def.fields[1].ty.kind));
+}
+
+#[test]
+fn test_generics_recovery() {
+    check_diagnostics(
+        "fn f<T: >() {}\nstruct S<T U> {}",
+        expect![[r#"
+            error: expected identifier, found `>`
+             --> test.cy:1:9
+              |
+            1 | fn f<T: >() {}
+              |         ^
+            error: expected `>`, found `U`
+             --> test.cy:2:12
+              |
+            2 | struct S<T U> {}
+              |            ^
+        "#]],
+    )
+}
+
+#[test]
+fn test_parse_method_call() {
+    check_parsing(
+        "a.area(1).b",
+        expect![[r#"
+            Expr { id: NodeId(3), kind: Field(Expr { id: NodeId(2), kind: MethodCall(Expr { id: NodeId(0), kind: Path(Path { segments: [Ident { name: "a", span: Span { start_pos: 0, len: 1 } }], span: Span { start_pos: 0, len: 1 } }), span: Span { start_pos: 0, len: 1 } }, Ident { name: "area", span: Span { start_pos: 2, len: 4 } }, [Expr { id: NodeId(1), kind: Literal(Int(1, None)), span: Span { start_pos: 7, len: 1 } }]), span: Span { start_pos: 0, len: 9 } }, Ident { name: "b", span: Span { start_pos: 10, len: 1 } }), span: Span { start_pos: 0, len: 11 } }
+        "#]],
+    )
+}
+
+#[test]
+fn test_parse_traits() {
+    let module = parse_module(
+        "trait Shape { fn area(self) -> f64; fn name(self, x: i64) { x } }\n\
+         impl<T: Eq> Shape for Pair<T> { fn area(self) -> f64 { 1.0 } }\n\
+         impl Circle { fn new() -> Circle { Circle {} } }\n\
+         fn f(s: dyn Shape) {}",
+    );
+    let items: Vec<String> = module
+        .items
+        .iter()
+        .map(|item| match &item.kind {
+            ItemKind::Trait(def) => format!("trait {:?}", def.items),
+            ItemKind::Impl(def) => format!(
+                "impl {:?} {:?} {:?} {:?}",
+                def.generics.params.len(),
+                def.of_trait,
+                def.self_ty.kind,
+                def.items
+                    .iter()
+                    .map(|item| item.ident.name)
+                    .collect::<Vec<_>>()
+            ),
+            ItemKind::Fn(decl) => format!("fn {:?}", decl.params[0].ty.kind),
+            _ => unreachable!(),
+        })
+        .collect();
+    expect![[r#"
+        trait [Item { id: NodeId(2), docs: [], attrs: [], vis: Private, ident: Ident { name: "area", span: Span { start_pos: 17, len: 4 } }, kind: Fn(FnDecl { generics: Generics { params: [], span: Span { start_pos: 21, len: 0 } }, self_param: Some(SelfParam { id: NodeId(0), span: Span { start_pos: 22, len: 4 } }), params: [], ret_ty: Some(Ty { id: NodeId(1), kind: F64, span: Span { start_pos: 31, len: 3 } }), body: None }), span: Span { start_pos: 14, len: 21 } }, Item { id: NodeId(9), docs: [], attrs: [], vis: Private, ident: Ident { name: "name", span: Span { start_pos: 39, len: 4 } }, kind: Fn(FnDecl { generics: Generics { params: [], span: Span { start_pos: 43, len: 0 } }, self_param: Some(SelfParam { id: NodeId(3), span: Span { start_pos: 44, len: 4 } }), params: [Param { id: NodeId(5), mutbl: Not, ident: Ident { name: "x", span: Span { start_pos: 50, len: 1 } }, ty: Ty { id: NodeId(4), kind: I64, span: Span { start_pos: 53, len: 3 } }, span: Span { start_pos: 50, len: 6 } }], ret_ty: None, body: Some(Block { id: NodeId(8), stmts: [Stmt { id: NodeId(7), attrs: [], kind: Expr(Expr { id: NodeId(6), kind: Path(Path { segments: [Ident { name: "x", span: Span { start_pos: 60, len: 1 } }], span: Span { start_pos: 60, len: 1 } }), span: Span { start_pos: 60, len: 1 } }), span: Span { start_pos: 60, len: 1 } }], span: Span { start_pos: 58, len: 5 } }) }), span: Span { start_pos: 36, len: 27 } }]
+        impl 1 Some(Path { segments: [Ident { name: "Shape", span: Span { start_pos: 78, len: 5 } }], span: Span { start_pos: 78, len: 5 } }) Path(Path { segments: [Ident { name: "Pair", span: Span { start_pos: 88, len: 4 } }], span: Span { start_pos: 88, len: 4 } }, [Ty { id: NodeId(13), kind: Path(Path { segments: [Ident { name: "T", span: Span { start_pos: 93, len: 1 } }], span: Span { start_pos: 93, len: 1 } }, []), span: Span { start_pos: 93, len: 1 } }]) ["area"]
+        impl 0 None Path(Path { segments: [Ident { name: "Circle", span: Span { start_pos: 134, len: 6 } }], span: Span { start_pos: 134, len: 6 } }, []) ["new"]
+        fn Dyn(Path { segments: [Ident { name: "Shape", span: Span { start_pos: 190, len: 5 } }], span: Span { start_pos: 190, len: 5 } })"#]].assert_eq(&items.join("\n"));
+}
+
+#[test]
+fn test_trait_recovery() {
+    check_diagnostics(
+        "trait T { struct S {} fn f(); }\nimpl Pair<i64> for S {}\nfn g();",
+        expect![[r#"
+            error: only functions are allowed in traits and impls
+             --> test.cy:1:11
+              |
+            1 | trait T { struct S {} fn f(); }
+              |           ^^^^^^^^^^^
+            error: expected a trait, found type
+             --> test.cy:2:6
+              |
+            2 | impl Pair<i64> for S {}
+              |      ^^^^^^^^^
+        "#]],
+    )
+}
+
+#[test]
+fn test_parse_closure() {
+    check_parsing(
+        "|x: i64, y| x + y",
+        expect![[r#"
+            Expr { id: NodeId(6), kind: Closure(Closure { params: [ClosureParam { id: NodeId(1), mutbl: Not, ident: Ident { name: "x", span: Span { start_pos: 1, len: 1 } }, ty: Some(Ty { id: NodeId(0), kind: I64, span: Span { start_pos: 4, len: 3 } }), span: Span { start_pos: 1, len: 6 } }, ClosureParam { id: NodeId(2), mutbl: Not, ident: Ident { name: "y", span: Span { start_pos: 9, len: 1 } }, ty: None, span: Span { start_pos: 9, len: 1 } }], ret_ty: None, body: Expr { id: NodeId(5), kind: Binary(Add, Expr { id: NodeId(3), kind: Path(Path { segments: [Ident { name: "x", span: Span { start_pos: 12, len: 1 } }], span: Span { start_pos: 12, len: 1 } }), span: Span { start_pos: 12, len: 1 } }, Expr { id: NodeId(4), kind: Path(Path { segments: [Ident { name: "y", span: Span { start_pos: 16, len: 1 } }], span: Span { start_pos: 16, len: 1 } }), span: Span { start_pos: 16, len: 1 } }), span: Span { start_pos: 12, len: 5 } } }), span: Span { start_pos: 0, len: 17 } }
+        "#]],
+    );
+    check_parsing(
+        "|| -> i64 { 1 }",
+        expect![[r#"
+            Expr { id: NodeId(5), kind: Closure(Closure { params: [], ret_ty: Some(Ty { id: NodeId(0), kind: I64, span: Span { start_pos: 6, len: 3 } }), body: Expr { id: NodeId(4), kind: Block(Block { id: NodeId(3), stmts: [Stmt { id: NodeId(2), attrs: [], kind: Expr(Expr { id: NodeId(1), kind: Literal(Int(1, None)), span: Span { start_pos: 12, len: 1 } }), span: Span { start_pos: 12, len: 1 } }], span: Span { start_pos: 10, len: 5 } }), span: Span { start_pos: 10, len: 5 } } }), span: Span { start_pos: 0, len: 15 } }
         "#]],
+    );
+}
+
+#[test]
+fn test_parse_fn_ty() {
+    let module = parse_module("fn apply(f: fn(i64, bool) -> i64, g: fn()) {}");
+    let ItemKind::Fn(decl) = &module.items[0].kind else {
+        unreachable!()
+    };
+    let tys: Vec<String> = decl
+        .params
+        .iter()
+        .map(|param| format!("{:?}", param.ty.kind))
+        .collect();
+    expect![[r#"
+        Fn([Ty { id: NodeId(0), kind: I64, span: Span { start_pos: 15, len: 3 } }, Ty { id: NodeId(1), kind: Bool, span: Span { start_pos: 20, len: 4 } }], Some(Ty { id: NodeId(2), kind: I64, span: Span { start_pos: 29, len: 3 } }))
+        Fn([], None)"#]].assert_eq(&tys.join("\n"));
+}
+
+#[test]
+fn test_closure_recovery() {
+    check_diagnostics(
+        "fn f() { |x| -> i64 x }",
+        expect![[r#"
+        error: expected `{` after the return type of a closure, found `x`
+         --> test.cy:1:21
+          |
+        1 | fn f() { |x| -> i64 x }
+          |                     ^
+    "#]],
     )
 }
+
+#[test]
+fn test_parse_modules() {
+    let module = parse_module(
+        "mod math;\n\
+         pub mod shapes { pub struct Circle {} fn helper() {} }\n\
+         import math::sqrt;\n\
+         pub import shapes::Circle;",
+    );
+    fn describe(items: &[Item]) -> Vec<String> {
+        items
+            .iter()
+            .map(|item| {
+                let kind = match &item.kind {
+                    ItemKind::Mod(def) => match &def.module {
+                        Some(module) => format!("mod {{ {} }}", describe(&module.items).join(", ")),
+                        None => "mod;".to_string(),
+                    },
+                    ItemKind::Import(path) => format!("import {:?}", path.segments),
+                    ItemKind::Struct(_) => "struct".to_string(),
+                    ItemKind::Fn(_) => "fn".to_string(),
+                    _ => unreachable!(),
+                };
+                format!("{:?} {} {}", item.vis, item.ident.name, kind)
+            })
+            .collect()
+    }
+    expect![[r#"
+        Private math mod;
+        Public shapes mod { Public Circle struct, Private helper fn }
+        Private sqrt import [Ident { name: "math", span: Span { start_pos: 72, len: 4 } }, Ident { name: "sqrt", span: Span { start_pos: 78, len: 4 } }]
+        Public Circle import [Ident { name: "shapes", span: Span { start_pos: 95, len: 6 } }, Ident { name: "Circle", span: Span { start_pos: 103, len: 6 } }]"#]].assert_eq(&describe(&module.items).join("\n"));
+}
+
+#[test]
+fn test_parse_file_at() {
+    let mut next_node_id = 10;
+    let (module, diagnostics) = parse_file_at("fn f() {}", 100, &mut next_node_id);
+    assert!(diagnostics.is_empty());
+    let item = &module.items[0];
+    assert_eq!((item.span.start_pos, item.ident.span.start_pos), (100, 103));
+    assert!(item.id.as_u32() >= 10 && next_node_id > item.id.as_u32());
+}
+
+#[test]
+fn test_module_recovery() {
+    check_diagnostics(
+        "mod m { fn f( }\nmod n { pub impl T {} impl S { pub fn g() {} } }\nimport a::;",
+        expect![[r#"
+            error: expected identifier, found `}`
+             --> test.cy:1:15
+              |
+            1 | mod m { fn f( }
+              |               ^
+            error: visibility qualifiers are not permitted here
+             --> test.cy:2:9
+              |
+            2 | mod n { pub impl T {} impl S { pub fn g() {} } }
+              |         ^^^
+              = note: impls have no name to be used by
+            error: visibility qualifiers are not permitted here
+             --> test.cy:2:32
+              |
+            2 | mod n { pub impl T {} impl S { pub fn g() {} } }
+              |                                ^^^
+              = note: methods are visible wherever their trait or type is
+            error: expected identifier, found `;`
+             --> test.cy:3:11
+              |
+            3 | import a::;
+              |           ^
+        "#]],
+    )
+}
+
+#[test]
+fn test_parse_attributes() {
+    let module = parse_module(
+        "@inline\n/// Doc.\n@allow(confusable_idents, text_direction_codepoint)\nfn f() {\n\
+         @allow(unreachable_patterns) let x = 1;\n}\n@deprecated(\"use g\") struct S {}",
+    );
+    fn describe(attrs: &[Attribute]) -> String {
+        attrs
+            .iter()
+            .map(|attr| {
+                let args: Vec<String> = attr
+                    .list()
+                    .iter()
+                    .map(|nested| match nested {
+                        NestedMeta::Meta(meta) => meta.ident.name.to_string(),
+                        NestedMeta::Str(s, _) => format!("{:?}", s.as_str()),
+                    })
+                    .collect();
+                format!("{}({})", attr.name(), args.join(", "))
+            })
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+    let ItemKind::Fn(decl) = &module.items[0].kind else {
+        unreachable!()
+    };
+    let stmt = &decl.body.as_ref().unwrap().stmts[0];
+    let actual = format!(
+        "{}\n{}\n{}\n{}",
+        describe(&module.items[0].attrs),
+        module.items[0].docs.len(),
+        describe(&stmt.attrs),
+        describe(&module.items[1].attrs),
+    );
+    expect![[r#"
+        inline() allow(confusable_idents, text_direction_codepoint)
+        1
+        allow(unreachable_patterns)
+        deprecated("use g")"#]]
+    .assert_eq(&actual);
+    assert!(attr::deprecation(&module.items[1].attrs).is_some());
+    assert!(attr::is_lint_allowed(
+        &module.items[0].attrs,
+        "confusable_idents"
+    ));
+}
+
+#[test]
+fn test_attribute_errors() {
+    check_diagnostics(
+        "@inlined fn f() {}\n@test struct S {}\n@deprecated(use_f) @allow fn g() {}\n\
+         @inline @inline fn h() { @allow(no_such_lint) 1; @test }\nimpl S { @test fn m() {} }\n@inline",
+        expect![[r#"
+            error: cannot find attribute `inlined`
+             --> test.cy:1:2
+              |
+            1 | @inlined fn f() {}
+              |  ^^^^^^^
+            error: `@test` cannot be applied to structs
+             --> test.cy:2:1
+              |
+            2 | @test struct S {}
+              | ^^^^^
+              = note: `@test` can only be applied to functions
+            error: malformed `deprecated` attribute
+             --> test.cy:3:1
+              |
+            3 | @deprecated(use_f) @allow fn g() {}
+              | ^^^^^^^^^^^^^^^^^^
+              = help: the attribute must be of the form `@deprecated` or `@deprecated("note")`
+            error: malformed `allow` attribute
+             --> test.cy:3:20
+              |
+            3 | @deprecated(use_f) @allow fn g() {}
+              |                    ^^^^^^
+              = help: the attribute must be of the form `@allow(lint, ...)`
+            warning: unknown lint: `no_such_lint`
+             --> test.cy:4:33
+              |
+            4 | @inline @inline fn h() { @allow(no_such_lint) 1; @test }
+              |                                 ^^^^^^^^^^^^
+            error: expected statement after attribute, found `}`
+             --> test.cy:4:56
+              |
+            4 | @inline @inline fn h() { @allow(no_such_lint) 1; @test }
+              |                                                        ^
+            warning: duplicate attribute `inline`
+             --> test.cy:4:9
+              |
+            4 | @inline @inline fn h() { @allow(no_such_lint) 1; @test }
+              |         ^^^^^^^
+            error: `@test` cannot be applied to methods
+             --> test.cy:5:10
+              |
+            5 | impl S { @test fn m() {} }
+              |          ^^^^^
+              = note: `@test` can only be applied to functions
+            error: expected item after attribute, found end of file
+             --> test.cy:6:8
+              |
+            6 | @inline
+              |        ^
+        "#]],
+    );
+    check_diagnostics(
+        "@inline struct T {}\n@deprecated impl T {}\n@allow(unused) fn k() {}",
+        expect![[r#"
+            error: `@inline` cannot be applied to structs
+             --> test.cy:1:1
+              |
+            1 | @inline struct T {}
+              | ^^^^^^^
+              = note: `@inline` can only be applied to functions and methods
+            error: `@deprecated` cannot be applied to impls
+             --> test.cy:2:1
+              |
+            2 | @deprecated impl T {}
+              | ^^^^^^^^^^^
+              = note: `@deprecated` can only be applied to functions, structs, enums and traits
+        "#]],
+    );
+}
+
+#[test]
+fn test_lint_allow() {
+    check_lints(
+        "@allow(confusable_idents)\nfn a() {}\nfn f(\u{430}: i64) {}\n\
+         @allow(confusable_idents) fn g(\u{430}: i64) {}",
+        expect![[r#"
+            Lint { kind: ConfusableIdents, span: Span { start_pos: 41, len: 2 }, message: "identifier pair considered confusable between `a` and `а`" }
+        "#]],
+    )
+}
+
+/// Strips `src` with a configuration of `debug`, `target_os="linux"` and
+/// `feature="net"`, printing the diagnostics and the remaining items, with
+/// the number of statements of each function.
+fn check_cfg(src: &str, expect: Expect) {
+    fn print_items(items: &[Item], indent: usize, out: &mut String) {
+        for item in items {
+            let pad = "    ".repeat(indent);
+            match &item.kind {
+                ItemKind::Fn(FnDecl {
+                    body: Some(body), ..
+                }) => out.push_str(&format!(
+                    "{}fn {}: {} statements\n",
+                    pad,
+                    item.ident.name,
+                    body.stmts.len()
+                )),
+                ItemKind::Mod(ModDef {
+                    module: Some(module),
+                    ..
+                }) => {
+                    out.push_str(&format!("{}mod {}\n", pad, item.ident.name));
+                    print_items(&module.items, indent + 1, out);
+                }
+                ItemKind::Impl(def) => {
+                    out.push_str(&format!("{}impl\n", pad));
+                    print_items(&def.items, indent + 1, out);
+                }
+                _ => out.push_str(&format!("{}{}\n", pad, item.ident.name)),
+            }
+        }
+    }
+    let mut module = parse_module(src);
+    let mut config = cfg::Cfg::default();
+    config.insert("debug", None);
+    config.insert("target_os", Some("linux"));
+    config.insert("feature", Some("net"));
+    let mut actual: String = cfg::strip_module(&mut module, &config)
+        .iter()
+        .map(|diag| diag.render(src, "test.cy"))
+        .collect();
+    print_items(&module.items, 0, &mut actual);
+    expect.assert_eq(&actual)
+}
+
+#[test]
+fn test_cfg() {
+    check_cfg(
+        "@cfg(debug) fn a() {}\n@cfg(not(debug)) fn b() {}\n\
+         @cfg(target_os = \"windows\") struct C {}\n\
+         @cfg(all(target_os = \"linux\", feature = \"net\")) struct D {}\n\
+         @cfg(any(feature = \"gui\", not(any()))) mod m { @cfg(all()) fn e() {} fn f() {} }\n\
+         impl D { @cfg(feature = \"gui\") fn g() {} fn h() {} }\n\
+         fn i() { @cfg(debug) 1; @cfg(release) 2; @cfg(debug) @cfg(release) 3; 4 }",
+        expect![[r#"
+            fn a: 0 statements
+            D
+            mod m
+                fn e: 0 statements
+                fn f: 0 statements
+            impl
+                fn h: 0 statements
+            fn i: 2 statements
+        "#]],
+    );
+    check_cfg(
+        "@cfg(nor(debug)) fn a() {}\n@cfg(not(debug, test)) fn b() {}\n\
+         @cfg(any(\"debug\")) fn c() {}",
+        expect![[r#"
+            error: invalid predicate `nor`
+             --> test.cy:1:6
+              |
+            1 | @cfg(nor(debug)) fn a() {}
+              |      ^^^
+              = note: predicates are combined with `all`, `any` and `not`
+            error: `not` takes exactly one predicate
+             --> test.cy:2:6
+              |
+            2 | @cfg(not(debug, test)) fn b() {}
+              |      ^^^^^^^^^^^^^^^^
+            error: expected a predicate, found a string
+             --> test.cy:3:10
+              |
+            3 | @cfg(any("debug")) fn c() {}
+              |          ^^^^^^^
+        "#]],
+    );
+    check_diagnostics(
+        "@cfg fn a() {}\n@cfg(debug, test) fn b() {}",
+        expect![[r#"
+            error: malformed `cfg` attribute
+             --> test.cy:1:1
+              |
+            1 | @cfg fn a() {}
+              | ^^^^
+              = help: the attribute must be of the form `@cfg(predicate)`
+            error: malformed `cfg` attribute
+             --> test.cy:2:1
+              |
+            2 | @cfg(debug, test) fn b() {}
+              | ^^^^^^^^^^^^^^^^^
+              = help: the attribute must be of the form `@cfg(predicate)`
+        "#]],
+    );
+}
+
+#[test]
+fn test_parse_cfg_option() {
+    let options: Vec<_> = [
+        "debug",
+        "feature=\"net\"",
+        "feature=net",
+        "1x",
+        "a=\"b\"c\"",
+    ]
+    .iter()
+    .map(|spec| cfg::parse_option(spec))
+    .collect();
+    expect![[r#"
+        [
+            Ok(
+                (
+                    "debug",
+                    None,
+                ),
+            ),
+            Ok(
+                (
+                    "feature",
+                    Some(
+                        "net",
+                    ),
+                ),
+            ),
+            Err(
+                "the value of `feature=net` must be quoted",
+            ),
+            Err(
+                "invalid option `1x`, expected `name` or `name=\"value\"`",
+            ),
+            Err(
+                "the value of `a=\"b\"c\"` must be quoted",
+            ),
+        ]
+    "#]]
+    .assert_debug_eq(&options);
+}
+
+#[test]
+fn test_parse_try() {
+    check_parsing(
+        "-f(x)?.y?",
+        expect![[r#"
+            Expr { id: NodeId(6), kind: Unary(Neg, Expr { id: NodeId(5), kind: Try(Expr { id: NodeId(4), kind: Field(Expr { id: NodeId(3), kind: Try(Expr { id: NodeId(2), kind: Call(Expr { id: NodeId(0), kind: Path(Path { segments: [Ident { name: "f", span: Span { start_pos: 1, len: 1 } }], span: Span { start_pos: 1, len: 1 } }), span: Span { start_pos: 1, len: 1 } }, [Expr { id: NodeId(1), kind: Path(Path { segments: [Ident { name: "x", span: Span { start_pos: 3, len: 1 } }], span: Span { start_pos: 3, len: 1 } }), span: Span { start_pos: 3, len: 1 } }]), span: Span { start_pos: 1, len: 4 } }), span: Span { start_pos: 1, len: 5 } }, Ident { name: "y", span: Span { start_pos: 7, len: 1 } }), span: Span { start_pos: 1, len: 7 } }), span: Span { start_pos: 1, len: 8 } }), span: Span { start_pos: 0, len: 9 } }
+        "#]],
+    );
+}
+
+#[test]
+fn test_parse_cast() {
+    check_parsing(
+        "-x as u64 * ~y",
+        expect![[r#"
+        Expr { id: NodeId(6), kind: Binary(Mul, Expr { id: NodeId(3), kind: Cast(Expr { id: NodeId(1), kind: Unary(Neg, Expr { id: NodeId(0), kind: Path(Path { segments: [Ident { name: "x", span: Span { start_pos: 1, len: 1 } }], span: Span { start_pos: 1, len: 1 } }), span: Span { start_pos: 1, len: 1 } }), span: Span { start_pos: 0, len: 2 } }, Ty { id: NodeId(2), kind: U64, span: Span { start_pos: 6, len: 3 } }), span: Span { start_pos: 0, len: 9 } }, Expr { id: NodeId(5), kind: Unary(BitNot, Expr { id: NodeId(4), kind: Path(Path { segments: [Ident { name: "y", span: Span { start_pos: 13, len: 1 } }], span: Span { start_pos: 13, len: 1 } }), span: Span { start_pos: 13, len: 1 } }), span: Span { start_pos: 12, len: 2 } }), span: Span { start_pos: 0, len: 14 } }
+    "#]],
+    );
+}
+
+#[test]
+fn test_parse_literal_suffix() {
+    check_parsing(
+        "300u8 + 1_000 + 2f32 + 1.5f64",
+        expect![[r#"
+        Expr { id: NodeId(6), kind: Binary(Add, Expr { id: NodeId(4), kind: Binary(Add, Expr { id: NodeId(2), kind: Binary(Add, Expr { id: NodeId(0), kind: Literal(Int(300, Some(U8))), span: Span { start_pos: 0, len: 5 } }, Expr { id: NodeId(1), kind: Literal(Int(1000, None)), span: Span { start_pos: 8, len: 5 } }), span: Span { start_pos: 0, len: 13 } }, Expr { id: NodeId(3), kind: Literal(Float(2.0, Some(F32))), span: Span { start_pos: 16, len: 4 } }), span: Span { start_pos: 0, len: 20 } }, Expr { id: NodeId(5), kind: Literal(Float(1.5, Some(F64))), span: Span { start_pos: 23, len: 6 } }), span: Span { start_pos: 0, len: 29 } }
+    "#]],
+    );
+    check_diagnostics(
+        "fn f() -> void { 1x; 1.5u8; 1_u8; }",
+        expect![[r#"
+            error: invalid suffix `x` for number literal
+             --> test.cy:1:18
+              |
+            1 | fn f() -> void { 1x; 1.5u8; 1_u8; }
+              |                  ^^
+              = help: the suffix must be a numeric type, e.g. `u8` or `f32`
+            error: invalid suffix `u8` for float literal
+             --> test.cy:1:22
+              |
+            1 | fn f() -> void { 1x; 1.5u8; 1_u8; }
+              |                      ^^^^^
+        "#]],
+    );
+}
+
+#[test]
+fn test_parse_arrays_and_tuples() {
+    check_parsing(
+        "[(1, a[i]).0; 2]",
+        expect![[r#"
+            Expr { id: NodeId(6), kind: Repeat(Expr { id: NodeId(5), kind: Field(Expr { id: NodeId(4), kind: Tuple([Expr { id: NodeId(0), kind: Literal(Int(1, None)), span: Span { start_pos: 2, len: 1 } }, Expr { id: NodeId(3), kind: Index(Expr { id: NodeId(1), kind: Path(Path { segments: [Ident { name: "a", span: Span { start_pos: 5, len: 1 } }], span: Span { start_pos: 5, len: 1 } }), span: Span { start_pos: 5, len: 1 } }, Expr { id: NodeId(2), kind: Path(Path { segments: [Ident { name: "i", span: Span { start_pos: 7, len: 1 } }], span: Span { start_pos: 7, len: 1 } }), span: Span { start_pos: 7, len: 1 } }), span: Span { start_pos: 5, len: 4 } }]), span: Span { start_pos: 1, len: 9 } }, Ident { name: "0", span: Span { start_pos: 11, len: 1 } }), span: Span { start_pos: 1, len: 11 } }, 2), span: Span { start_pos: 0, len: 16 } }
+        "#]],
+    );
+    check_parsing(
+        "t.0.1 + [x,]",
+        expect![[r#"
+        Expr { id: NodeId(5), kind: Binary(Add, Expr { id: NodeId(2), kind: Field(Expr { id: NodeId(1), kind: Field(Expr { id: NodeId(0), kind: Path(Path { segments: [Ident { name: "t", span: Span { start_pos: 0, len: 1 } }], span: Span { start_pos: 0, len: 1 } }), span: Span { start_pos: 0, len: 1 } }, Ident { name: "0", span: Span { start_pos: 2, len: 1 } }), span: Span { start_pos: 0, len: 3 } }, Ident { name: "1", span: Span { start_pos: 4, len: 1 } }), span: Span { start_pos: 0, len: 5 } }, Expr { id: NodeId(4), kind: Array([Expr { id: NodeId(3), kind: Path(Path { segments: [Ident { name: "x", span: Span { start_pos: 9, len: 1 } }], span: Span { start_pos: 9, len: 1 } }), span: Span { start_pos: 9, len: 1 } }]), span: Span { start_pos: 8, len: 4 } }), span: Span { start_pos: 0, len: 12 } }
+    "#]],
+    );
+    check_diagnostics(
+        "fn f(a: [i64; n]) {}\nfn g() { [0; 1.5]; }\nfn h() { t.0x; }",
+        expect![[r#"
+            error: expected array length, found `n`
+             --> test.cy:1:15
+              |
+            1 | fn f(a: [i64; n]) {}
+              |               ^
+            error: array length must be an integer literal of type `usize`
+             --> test.cy:2:14
+              |
+            2 | fn g() { [0; 1.5]; }
+              |              ^^^
+            error: invalid tuple index `0x`
+             --> test.cy:3:12
+              |
+            3 | fn h() { t.0x; }
+              |            ^^
+        "#]],
+    );
+}
+
+#[test]
+fn test_parse_tuple_patterns() {
+    let (expr, diagnostics) =
+        parse_expr("match x { (a,) => a, (0, (b), _) => a, (P(c), (d, e)) => a }");
+    assert!(diagnostics.is_empty(), "{:?}", diagnostics);
+    let ExprKind::Match(_, arms) = &expr.kind else {
+        unreachable!()
+    };
+    let pats: Vec<String> = arms
+        .iter()
+        .map(|arm| format!("{:?}", arm.pat.kind))
+        .collect();
+    expect![[r#"
+        Tuple([Pat { id: NodeId(1), kind: Ident(Not, Ident { name: "a", span: Span { start_pos: 11, len: 1 } }), span: Span { start_pos: 11, len: 1 } }])
+        Tuple([Pat { id: NodeId(6), kind: Lit(Expr { id: NodeId(5), kind: Literal(Int(0, None)), span: Span { start_pos: 22, len: 1 } }), span: Span { start_pos: 22, len: 1 } }, Pat { id: NodeId(7), kind: Ident(Not, Ident { name: "b", span: Span { start_pos: 26, len: 1 } }), span: Span { start_pos: 26, len: 1 } }, Pat { id: NodeId(8), kind: Wild, span: Span { start_pos: 30, len: 1 } }])
+        Tuple([Pat { id: NodeId(13), kind: TupleStruct(Path { segments: [Ident { name: "P", span: Span { start_pos: 40, len: 1 } }], span: Span { start_pos: 40, len: 1 } }, [Pat { id: NodeId(12), kind: Ident(Not, Ident { name: "c", span: Span { start_pos: 42, len: 1 } }), span: Span { start_pos: 42, len: 1 } }]), span: Span { start_pos: 40, len: 4 } }, Pat { id: NodeId(16), kind: Tuple([Pat { id: NodeId(14), kind: Ident(Not, Ident { name: "d", span: Span { start_pos: 47, len: 1 } }), span: Span { start_pos: 47, len: 1 } }, Pat { id: NodeId(15), kind: Ident(Not, Ident { name: "e", span: Span { start_pos: 50, len: 1 } }), span: Span { start_pos: 50, len: 1 } }]), span: Span { start_pos: 46, len: 6 } }])"#]].assert_eq(&pats.join("\n"));
+    check_diagnostics(
+        "fn f() { let (a, mut b) = t; let (c, d = t; }",
+        expect![[r#"
+            error: expected `)`, found `=`
+             --> test.cy:1:40
+              |
+            1 | fn f() { let (a, mut b) = t; let (c, d = t; }
+              |                                        ^
+        "#]],
+    );
+}
+
+#[test]
+fn test_parse_ranges_and_for_loops() {
+    check_parsing(
+        "0..n + 1",
+        expect![[r#"
+            Expr { id: NodeId(4), kind: Range(Expr { id: NodeId(0), kind: Literal(Int(0, None)), span: Span { start_pos: 0, len: 1 } }, Some(Expr { id: NodeId(3), kind: Binary(Add, Expr { id: NodeId(1), kind: Path(Path { segments: [Ident { name: "n", span: Span { start_pos: 3, len: 1 } }], span: Span { start_pos: 3, len: 1 } }), span: Span { start_pos: 3, len: 1 } }, Expr { id: NodeId(2), kind: Literal(Int(1, None)), span: Span { start_pos: 7, len: 1 } }), span: Span { start_pos: 3, len: 5 } }), HalfOpen), span: Span { start_pos: 0, len: 8 } }
+        "#]],
+    );
+    check_parsing(
+        "a..=b",
+        expect![[r#"
+            Expr { id: NodeId(2), kind: Range(Expr { id: NodeId(0), kind: Path(Path { segments: [Ident { name: "a", span: Span { start_pos: 0, len: 1 } }], span: Span { start_pos: 0, len: 1 } }), span: Span { start_pos: 0, len: 1 } }, Some(Expr { id: NodeId(1), kind: Path(Path { segments: [Ident { name: "b", span: Span { start_pos: 4, len: 1 } }], span: Span { start_pos: 4, len: 1 } }), span: Span { start_pos: 4, len: 1 } }), Closed), span: Span { start_pos: 0, len: 5 } }
+        "#]],
+    );
+    check_parsing(
+        "for (i) in 0..n { g(i); }",
+        expect![[r#"
+            Expr { id: NodeId(9), kind: ForLoop(Pat { id: NodeId(0), kind: Ident(Not, Ident { name: "i", span: Span { start_pos: 5, len: 1 } }), span: Span { start_pos: 5, len: 1 } }, Expr { id: NodeId(3), kind: Range(Expr { id: NodeId(1), kind: Literal(Int(0, None)), span: Span { start_pos: 11, len: 1 } }, Some(Expr { id: NodeId(2), kind: Path(Path { segments: [Ident { name: "n", span: Span { start_pos: 14, len: 1 } }], span: Span { start_pos: 14, len: 1 } }), span: Span { start_pos: 14, len: 1 } }), HalfOpen), span: Span { start_pos: 11, len: 4 } }, Block { id: NodeId(8), stmts: [Stmt { id: NodeId(7), attrs: [], kind: Semi(Expr { id: NodeId(6), kind: Call(Expr { id: NodeId(4), kind: Path(Path { segments: [Ident { name: "g", span: Span { start_pos: 18, len: 1 } }], span: Span { start_pos: 18, len: 1 } }), span: Span { start_pos: 18, len: 1 } }, [Expr { id: NodeId(5), kind: Path(Path { segments: [Ident { name: "i", span: Span { start_pos: 20, len: 1 } }], span: Span { start_pos: 20, len: 1 } }), span: Span { start_pos: 20, len: 1 } }]), span: Span { start_pos: 18, len: 4 } }), span: Span { start_pos: 18, len: 5 } }], span: Span { start_pos: 16, len: 9 } }), span: Span { start_pos: 0, len: 25 } }
+        "#]],
+    );
+    check_parsing(
+        "a[1..][0..=n]",
+        expect![[r#"
+            Expr { id: NodeId(7), kind: Index(Expr { id: NodeId(3), kind: Index(Expr { id: NodeId(0), kind: Path(Path { segments: [Ident { name: "a", span: Span { start_pos: 0, len: 1 } }], span: Span { start_pos: 0, len: 1 } }), span: Span { start_pos: 0, len: 1 } }, Expr { id: NodeId(2), kind: Range(Expr { id: NodeId(1), kind: Literal(Int(1, None)), span: Span { start_pos: 2, len: 1 } }, None, HalfOpen), span: Span { start_pos: 2, len: 3 } }), span: Span { start_pos: 0, len: 6 } }, Expr { id: NodeId(6), kind: Range(Expr { id: NodeId(4), kind: Literal(Int(0, None)), span: Span { start_pos: 7, len: 1 } }, Some(Expr { id: NodeId(5), kind: Path(Path { segments: [Ident { name: "n", span: Span { start_pos: 11, len: 1 } }], span: Span { start_pos: 11, len: 1 } }), span: Span { start_pos: 11, len: 1 } }), Closed), span: Span { start_pos: 7, len: 5 } }), span: Span { start_pos: 0, len: 13 } }
+        "#]],
+    );
+    check_diagnostics(
+        "fn f() { for i 0..1 {} }\nfn g() { 0..1..2; }\nfn h() { a[1..=]; }",
+        expect![[r#"
+            error: expected `in`, found `0`
+             --> test.cy:1:16
+              |
+            1 | fn f() { for i 0..1 {} }
+              |                ^
+            error: expected `;`, found `..`
+             --> test.cy:2:14
+              |
+            2 | fn g() { 0..1..2; }
+              |              ^^
+            error: expected expression, found `]`
+             --> test.cy:3:16
+              |
+            3 | fn h() { a[1..=]; }
+              |                ^
+        "#]],
+    );
+}
+
+#[test]
+fn test_parse_bitwise_operators() {
+    check_parsing(
+        "a | b ^ c & d << 1 + e % 2 == f",
+        expect![[r#"
+            Expr { id: NodeId(14), kind: Binary(Eq, Expr { id: NodeId(12), kind: Binary(BitOr, Expr { id: NodeId(0), kind: Path(Path { segments: [Ident { name: "a", span: Span { start_pos: 0, len: 1 } }], span: Span { start_pos: 0, len: 1 } }), span: Span { start_pos: 0, len: 1 } }, Expr { id: NodeId(11), kind: Binary(BitXor, Expr { id: NodeId(1), kind: Path(Path { segments: [Ident { name: "b", span: Span { start_pos: 4, len: 1 } }], span: Span { start_pos: 4, len: 1 } }), span: Span { start_pos: 4, len: 1 } }, Expr { id: NodeId(10), kind: Binary(BitAnd, Expr { id: NodeId(2), kind: Path(Path { segments: [Ident { name: "c", span: Span { start_pos: 8, len: 1 } }], span: Span { start_pos: 8, len: 1 } }), span: Span { start_pos: 8, len: 1 } }, Expr { id: NodeId(9), kind: Binary(Shl, Expr { id: NodeId(3), kind: Path(Path { segments: [Ident { name: "d", span: Span { start_pos: 12, len: 1 } }], span: Span { start_pos: 12, len: 1 } }), span: Span { start_pos: 12, len: 1 } }, Expr { id: NodeId(8), kind: Binary(Add, Expr { id: NodeId(4), kind: Literal(Int(1, None)), span: Span { start_pos: 17, len: 1 } }, Expr { id: NodeId(7), kind: Binary(Rem, Expr { id: NodeId(5), kind: Path(Path { segments: [Ident { name: "e", span: Span { start_pos: 21, len: 1 } }], span: Span { start_pos: 21, len: 1 } }), span: Span { start_pos: 21, len: 1 } }, Expr { id: NodeId(6), kind: Literal(Int(2, None)), span: Span { start_pos: 25, len: 1 } }), span: Span { start_pos: 21, len: 5 } }), span: Span { start_pos: 17, len: 9 } }), span: Span { start_pos: 12, len: 14 } }), span: Span { start_pos: 8, len: 18 } }), span: Span { start_pos: 4, len: 22 } }), span: Span { start_pos: 0, len: 26 } }, Expr { id: NodeId(13), kind: Path(Path { segments: [Ident { name: "f", span: Span { start_pos: 30, len: 1 } }], span: Span { start_pos: 30, len: 1 } }), span: Span { start_pos: 30, len: 1 } }), span: Span { start_pos: 0, len: 31 } }
+        "#]],
+    );
+}
+
+#[test]
+fn test_parse_compound_assignment_and_mut() {
+    check_parsing(
+        "a[i].x += b >> 1",
+        expect![[r#"
+            Expr { id: NodeId(7), kind: AssignOp(Add, Expr { id: NodeId(3), kind: Field(Expr { id: NodeId(2), kind: Index(Expr { id: NodeId(0), kind: Path(Path { segments: [Ident { name: "a", span: Span { start_pos: 0, len: 1 } }], span: Span { start_pos: 0, len: 1 } }), span: Span { start_pos: 0, len: 1 } }, Expr { id: NodeId(1), kind: Path(Path { segments: [Ident { name: "i", span: Span { start_pos: 2, len: 1 } }], span: Span { start_pos: 2, len: 1 } }), span: Span { start_pos: 2, len: 1 } }), span: Span { start_pos: 0, len: 4 } }, Ident { name: "x", span: Span { start_pos: 5, len: 1 } }), span: Span { start_pos: 0, len: 6 } }, Expr { id: NodeId(6), kind: Binary(Shr, Expr { id: NodeId(4), kind: Path(Path { segments: [Ident { name: "b", span: Span { start_pos: 10, len: 1 } }], span: Span { start_pos: 10, len: 1 } }), span: Span { start_pos: 10, len: 1 } }, Expr { id: NodeId(5), kind: Literal(Int(1, None)), span: Span { start_pos: 15, len: 1 } }), span: Span { start_pos: 10, len: 6 } }), span: Span { start_pos: 0, len: 16 } }
+        "#]],
+    );
+    check_parsing(
+        "{ let mut x: Option<Option<i64>>= f(|mut y| y); for mut z in x {} }",
+        expect![[r#"
+            Expr { id: NodeId(17), kind: Block(Block { id: NodeId(16), stmts: [Stmt { id: NodeId(10), attrs: [], kind: Let(Local { id: NodeId(9), pat: Pat { id: NodeId(0), kind: Ident(Mut, Ident { name: "x", span: Span { start_pos: 10, len: 1 } }), span: Span { start_pos: 6, len: 5 } }, ty: Some(Ty { id: NodeId(3), kind: Path(Path { segments: [Ident { name: "Option", span: Span { start_pos: 13, len: 6 } }], span: Span { start_pos: 13, len: 6 } }, [Ty { id: NodeId(2), kind: Path(Path { segments: [Ident { name: "Option", span: Span { start_pos: 20, len: 6 } }], span: Span { start_pos: 20, len: 6 } }, [Ty { id: NodeId(1), kind: I64, span: Span { start_pos: 27, len: 3 } }]), span: Span { start_pos: 20, len: 11 } }]), span: Span { start_pos: 13, len: 19 } }), init: Expr { id: NodeId(8), kind: Call(Expr { id: NodeId(4), kind: Path(Path { segments: [Ident { name: "f", span: Span { start_pos: 34, len: 1 } }], span: Span { start_pos: 34, len: 1 } }), span: Span { start_pos: 34, len: 1 } }, [Expr { id: NodeId(7), kind: Closure(Closure { params: [ClosureParam { id: NodeId(5), mutbl: Mut, ident: Ident { name: "y", span: Span { start_pos: 41, len: 1 } }, ty: None, span: Span { start_pos: 37, len: 5 } }], ret_ty: None, body: Expr { id: NodeId(6), kind: Path(Path { segments: [Ident { name: "y", span: Span { start_pos: 44, len: 1 } }], span: Span { start_pos: 44, len: 1 } }), span: Span { start_pos: 44, len: 1 } } }), span: Span { start_pos: 36, len: 9 } }]), span: Span { start_pos: 34, len: 12 } } }), span: Span { start_pos: 2, len: 45 } }, Stmt { id: NodeId(15), attrs: [], kind: Expr(Expr { id: NodeId(14), kind: ForLoop(Pat { id: NodeId(11), kind: Ident(Mut, Ident { name: "z", span: Span { start_pos: 56, len: 1 } }), span: Span { start_pos: 52, len: 5 } }, Expr { id: NodeId(12), kind: Path(Path { segments: [Ident { name: "x", span: Span { start_pos: 61, len: 1 } }], span: Span { start_pos: 61, len: 1 } }), span: Span { start_pos: 61, len: 1 } }, Block { id: NodeId(13), stmts: [], span: Span { start_pos: 63, len: 2 } }), span: Span { start_pos: 48, len: 17 } }), span: Span { start_pos: 48, len: 17 } }], span: Span { start_pos: 0, len: 67 } }), span: Span { start_pos: 0, len: 67 } }
+        "#]],
+    );
+}
@@ -0,0 +1,389 @@
+//! AST walker. Each overridden visit method has full control over what
+//! happens with its node: it can do its own traversal of the node's children,
+//! call `visit::walk_*` to apply the default traversal algorithm, or prevent
+//! deeper traversal by doing nothing.
+
+use crate::ast::*;
+
+pub trait Visitor<'ast>: Sized {
+    fn visit_module(&mut self, module: &'ast Module) {
+        walk_module(self, module)
+    }
+    fn visit_doc(&mut self, _doc: &'ast Doc) {}
+    fn visit_item(&mut self, item: &'ast Item) {
+        walk_item(self, item)
+    }
+    fn visit_fn_decl(&mut self, decl: &'ast FnDecl) {
+        walk_fn_decl(self, decl)
+    }
+    fn visit_struct_def(&mut self, def: &'ast StructDef) {
+        walk_struct_def(self, def)
+    }
+    fn visit_enum_def(&mut self, def: &'ast EnumDef) {
+        walk_enum_def(self, def)
+    }
+    fn visit_trait_def(&mut self, def: &'ast TraitDef) {
+        walk_trait_def(self, def)
+    }
+    fn visit_impl_def(&mut self, def: &'ast ImplDef) {
+        walk_impl_def(self, def)
+    }
+    fn visit_variant(&mut self, variant: &'ast Variant) {
+        walk_variant(self, variant)
+    }
+    fn visit_field_def(&mut self, field: &'ast FieldDef) {
+        walk_field_def(self, field)
+    }
+    fn visit_generics(&mut self, generics: &'ast Generics) {
+        walk_generics(self, generics)
+    }
+    fn visit_generic_param(&mut self, param: &'ast GenericParam) {
+        walk_generic_param(self, param)
+    }
+    fn visit_param(&mut self, param: &'ast Param) {
+        walk_param(self, param)
+    }
+    fn visit_ty(&mut self, ty: &'ast Ty) {
+        walk_ty(self, ty)
+    }
+    fn visit_block(&mut self, block: &'ast Block) {
+        walk_block(self, block)
+    }
+    fn visit_stmt(&mut self, stmt: &'ast Stmt) {
+        walk_stmt(self, stmt)
+    }
+    fn visit_local(&mut self, local: &'ast Local) {
+        walk_local(self, local)
+    }
+    fn visit_arm(&mut self, arm: &'ast Arm) {
+        walk_arm(self, arm)
+    }
+    fn visit_pat(&mut self, pat: &'ast Pat) {
+        walk_pat(self, pat)
+    }
+    fn visit_expr(&mut self, expr: &'ast Expr) {
+        walk_expr(self, expr)
+    }
+    fn visit_path(&mut self, path: &'ast Path) {
+        walk_path(self, path)
+    }
+    fn visit_ident(&mut self, _ident: &'ast Ident) {}
+}
+
+pub fn walk_module<'a, V: Visitor<'a>>(visitor: &mut V, module: &'a Module) {
+    for doc in &module.docs {
+        visitor.visit_doc(doc);
+    }
+    for item in &module.items {
+        visitor.visit_item(item);
+    }
+}
+
+pub fn walk_item<'a, V: Visitor<'a>>(visitor: &mut V, item: &'a Item) {
+    for doc in &item.docs {
+        visitor.visit_doc(doc);
+    }
+    visitor.visit_ident(&item.ident);
+    match &item.kind {
+        ItemKind::Fn(decl) => visitor.visit_fn_decl(decl),
+        ItemKind::Struct(def) => visitor.visit_struct_def(def),
+        ItemKind::Enum(def) => visitor.visit_enum_def(def),
+        ItemKind::Trait(def) => visitor.visit_trait_def(def),
+        ItemKind::Impl(def) => visitor.visit_impl_def(def),
+        ItemKind::Mod(def) => {
+            if let Some(module) = &def.module {
+                visitor.visit_module(module);
+            }
+        }
+        ItemKind::Import(path) => visitor.visit_path(path),
+    }
+}
+
+pub fn walk_fn_decl<'a, V: Visitor<'a>>(visitor: &mut V, decl: &'a FnDecl) {
+    visitor.visit_generics(&decl.generics);
+    for param in &decl.params {
+        visitor.visit_param(param);
+    }
+    if let Some(ty) = &decl.ret_ty {
+        visitor.visit_ty(ty);
+    }
+    if let Some(body) = &decl.body {
+        visitor.visit_block(body);
+    }
+}
+
+pub fn walk_struct_def<'a, V: Visitor<'a>>(visitor: &mut V, def: &'a StructDef) {
+    visitor.visit_generics(&def.generics);
+    for field in &def.fields {
+        visitor.visit_field_def(field);
+    }
+}
+
+pub fn walk_enum_def<'a, V: Visitor<'a>>(visitor: &mut V, def: &'a EnumDef) {
+    visitor.visit_generics(&def.generics);
+    for variant in &def.variants {
+        visitor.visit_variant(variant);
+    }
+}
+
+pub fn walk_trait_def<'a, V: Visitor<'a>>(visitor: &mut V, def: &'a TraitDef) {
+    for item in &def.items {
+        visitor.visit_item(item);
+    }
+}
+
+pub fn walk_impl_def<'a, V: Visitor<'a>>(visitor: &mut V, def: &'a ImplDef) {
+    visitor.visit_generics(&def.generics);
+    if let Some(path) = &def.of_trait {
+        visitor.visit_path(path);
+    }
+    visitor.visit_ty(&def.self_ty);
+    for item in &def.items {
+        visitor.visit_item(item);
+    }
+}
+
+pub fn walk_variant<'a, V: Visitor<'a>>(visitor: &mut V, variant: &'a Variant) {
+    for doc in &variant.docs {
+        visitor.visit_doc(doc);
+    }
+    visitor.visit_ident(&variant.ident);
+    match &variant.data {
+        VariantData::Struct(fields) => {
+            for field in fields {
+                visitor.visit_field_def(field);
+            }
+        }
+        VariantData::Tuple(tys) => {
+            for ty in tys {
+                visitor.visit_ty(ty);
+            }
+        }
+        VariantData::Unit => (),
+    }
+}
+
+pub fn walk_field_def<'a, V: Visitor<'a>>(visitor: &mut V, field: &'a FieldDef) {
+    for doc in &field.docs {
+        visitor.visit_doc(doc);
+    }
+    visitor.visit_ident(&field.ident);
+    visitor.visit_ty(&field.ty);
+}
+
+pub fn walk_generics<'a, V: Visitor<'a>>(visitor: &mut V, generics: &'a Generics) {
+    for param in &generics.params {
+        visitor.visit_generic_param(param);
+    }
+}
+
+pub fn walk_generic_param<'a, V: Visitor<'a>>(visitor: &mut V, param: &'a GenericParam) {
+    visitor.visit_ident(&param.ident);
+    for bound in &param.bounds {
+        visitor.visit_path(bound);
+    }
+}
+
+pub fn walk_param<'a, V: Visitor<'a>>(visitor: &mut V, param: &'a Param) {
+    visitor.visit_ident(&param.ident);
+    visitor.visit_ty(&param.ty);
+}
+
+pub fn walk_ty<'a, V: Visitor<'a>>(visitor: &mut V, ty: &'a Ty) {
+    match &ty.kind {
+        TyKind::Path(path, args) => {
+            visitor.visit_path(path);
+            for arg in args {
+                visitor.visit_ty(arg);
+            }
+        }
+        TyKind::Dyn(path) => visitor.visit_path(path),
+        TyKind::Fn(inputs, output) => {
+            for input in inputs {
+                visitor.visit_ty(input);
+            }
+            if let Some(output) = output {
+                visitor.visit_ty(output);
+            }
+        }
+        TyKind::Tuple(tys) => {
+            for ty in tys {
+                visitor.visit_ty(ty);
+            }
+        }
+        TyKind::Array(ty, _) | TyKind::Slice(ty) => visitor.visit_ty(ty),
+        TyKind::I8
+        | TyKind::I16
+        | TyKind::I32
+        | TyKind::I64
+        | TyKind::I128
+        | TyKind::U8
+        | TyKind::U16
+        | TyKind::U32
+        | TyKind::U64
+        | TyKind::U128
+        | TyKind::Usize
+        | TyKind::F32
+        | TyKind::F64
+        | TyKind::Bool
+        | TyKind::Char
+        | TyKind::Str
+        | TyKind::Void => {}
+    }
+}
+
+pub fn walk_block<'a, V: Visitor<'a>>(visitor: &mut V, block: &'a Block) {
+    for stmt in &block.stmts {
+        visitor.visit_stmt(stmt);
+    }
+}
+
+pub fn walk_stmt<'a, V: Visitor<'a>>(visitor: &mut V, stmt: &'a Stmt) {
+    match &stmt.kind {
+        StmtKind::Let(local) => visitor.visit_local(local),
+        StmtKind::Semi(expr) | StmtKind::Expr(expr) => visitor.visit_expr(expr),
+    }
+}
+
+pub fn walk_local<'a, V: Visitor<'a>>(visitor: &mut V, local: &'a Local) {
+    visitor.visit_pat(&local.pat);
+    if let Some(ty) = &local.ty {
+        visitor.visit_ty(ty);
+    }
+    visitor.visit_expr(&local.init);
+}
+
+pub fn walk_expr<'a, V: Visitor<'a>>(visitor: &mut V, expr: &'a Expr) {
+    match &expr.kind {
+        ExprKind::Literal(_) | ExprKind::Err => (),
+        ExprKind::Binary(_, lhs, rhs) => {
+            visitor.visit_expr(lhs);
+            visitor.visit_expr(rhs);
+        }
+        ExprKind::Unary(_, operand) => visitor.visit_expr(operand),
+        ExprKind::Path(path) => visitor.visit_path(path),
+        ExprKind::Call(callee, args) => {
+            visitor.visit_expr(callee);
+            for arg in args {
+                visitor.visit_expr(arg);
+            }
+        }
+        ExprKind::MethodCall(receiver, ident, args) => {
+            visitor.visit_expr(receiver);
+            visitor.visit_ident(ident);
+            for arg in args {
+                visitor.visit_expr(arg);
+            }
+        }
+        ExprKind::Block(block) => visitor.visit_block(block),
+        ExprKind::Match(scrutinee, arms) => {
+            visitor.visit_expr(scrutinee);
+            for arm in arms {
+                visitor.visit_arm(arm);
+            }
+        }
+        ExprKind::ForLoop(pat, iter, body) => {
+            visitor.visit_pat(pat);
+            visitor.visit_expr(iter);
+            visitor.visit_block(body);
+        }
+        ExprKind::Range(start, end, _) => {
+            visitor.visit_expr(start);
+            if let Some(end) = end {
+                visitor.visit_expr(end);
+            }
+        }
+        ExprKind::Assign(place, value) | ExprKind::AssignOp(_, place, value) => {
+            visitor.visit_expr(place);
+            visitor.visit_expr(value);
+        }
+        ExprKind::Field(base, ident) => {
+            visitor.visit_expr(base);
+            visitor.visit_ident(ident);
+        }
+        ExprKind::Tuple(exprs) | ExprKind::Array(exprs) => {
+            for expr in exprs {
+                visitor.visit_expr(expr);
+            }
+        }
+        ExprKind::Repeat(value, _) => visitor.visit_expr(value),
+        ExprKind::Index(base, index) => {
+            visitor.visit_expr(base);
+            visitor.visit_expr(index);
+        }
+        ExprKind::Cast(expr, ty) => {
+            visitor.visit_expr(expr);
+            visitor.visit_ty(ty);
+        }
+        ExprKind::Try(expr) => visitor.visit_expr(expr),
+        ExprKind::Struct(StructExpr { path, fields }) => {
+            visitor.visit_path(path);
+            for field in fields {
+                visitor.visit_ident(&field.ident);
+                visitor.visit_expr(&field.expr);
+            }
+        }
+        ExprKind::Closure(Closure {
+            params,
+            ret_ty,
+            body,
+        }) => {
+            for param in params {
+                visitor.visit_ident(&param.ident);
+                if let Some(ty) = &param.ty {
+                    visitor.visit_ty(ty);
+                }
+            }
+            if let Some(ty) = ret_ty {
+                visitor.visit_ty(ty);
+            }
+            visitor.visit_expr(body);
+        }
+    }
+}
+
+pub fn walk_arm<'a, V: Visitor<'a>>(visitor: &mut V, arm: &'a Arm) {
+    visitor.visit_pat(&arm.pat);
+    if let Some(guard) = &arm.guard {
+        visitor.visit_expr(guard);
+    }
+    visitor.visit_expr(&arm.body);
+}
+
+pub fn walk_pat<'a, V: Visitor<'a>>(visitor: &mut V, pat: &'a Pat) {
+    match &pat.kind {
+        PatKind::Wild => (),
+        PatKind::Ident(_, ident) => visitor.visit_ident(ident),
+        PatKind::Lit(expr) => visitor.visit_expr(expr),
+        PatKind::Path(path) => visitor.visit_path(path),
+        PatKind::Tuple(pats) => {
+            for pat in pats {
+                visitor.visit_pat(pat);
+            }
+        }
+        PatKind::TupleStruct(path, pats) => {
+            visitor.visit_path(path);
+            for pat in pats {
+                visitor.visit_pat(pat);
+            }
+        }
+        PatKind::Struct(path, fields, _) => {
+            visitor.visit_path(path);
+            for field in fields {
+                visitor.visit_ident(&field.ident);
+                visitor.visit_pat(&field.pat);
+            }
+        }
+        PatKind::Or(pats) => {
+            for pat in pats {
+                visitor.visit_pat(pat);
+            }
+        }
+    }
+}
+
+pub fn walk_path<'a, V: Visitor<'a>>(visitor: &mut V, path: &'a Path) {
+    for segment in &path.segments {
+        visitor.visit_ident(segment);
+    }
+}
@@ -0,0 +1,69 @@
+pub mod symbol;
+
+#[cfg(test)]
+mod tests;
+
+pub use symbol::{kw, Symbol};
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+pub struct Span {
+    pub start_pos: usize,
+    pub len: usize,
+}
+
+impl Span {
+    pub fn append(&self, s: Span) -> Self {
+        let start_pos = std::cmp::min(self.start_pos, s.start_pos);
+        let end_pos = std::cmp::max(self.start_pos + self.len, s.start_pos + s.len);
+        Span {
+            start_pos,
+            len: end_pos - start_pos,
+        }
+    }
+}
+
+/// The source files of a program. Each file occupies its own range of
+/// positions, so a span identifies the file it is in.
+#[derive(Debug, Default)]
+pub struct SourceMap {
+    files: Vec<SourceFile>,
+}
+
+#[derive(Debug)]
+pub struct SourceFile {
+    /// The path of the file, as shown in diagnostics.
+    pub name: String,
+    pub src: String,
+    /// Position of the first byte of the file.
+    pub start_pos: usize,
+}
+
+impl SourceMap {
+    /// Adds a file after the files added so far.
+    pub fn add_file(&mut self, name: String, src: String) -> &SourceFile {
+        // Files are separated by one position, so that the end of a file is
+        // not the start of the next one.
+        let start_pos = self
+            .files
+            .last()
+            .map_or(0, |file| file.start_pos + file.src.len() + 1);
+        self.files.push(SourceFile {
+            name,
+            src,
+            start_pos,
+        });
+        self.files.last().unwrap()
+    }
+
+    /// Returns the file containing a position.
+    pub fn lookup_file(&self, pos: usize) -> &SourceFile {
+        let index = self.files.partition_point(|file| file.start_pos <= pos);
+        &self.files[index - 1]
+    }
+
+    pub fn files(&self) -> &[SourceFile] {
+        &self.files
+    }
+}
@@ -0,0 +1,154 @@
+//! Interning of identifiers, keywords and string literals.
+//!
+//! A `Symbol` is an index into a global table of strings, so comparing and
+//! hashing symbols is as cheap as comparing and hashing integers.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Mutex, OnceLock};
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Symbol(u32);
+
+impl Symbol {
+    /// Maps a string to its interned representation.
+    pub fn intern(s: &str) -> Self {
+        with_interner(|interner| interner.intern(s))
+    }
+
+    /// Returns the string this symbol was interned from.
+    pub fn as_str(&self) -> &'static str {
+        with_interner(|interner| interner.get(*self))
+    }
+
+    pub fn as_u32(self) -> u32 {
+        self.0
+    }
+
+    /// True if the symbol is one of the keywords in `kw`.
+    pub fn is_keyword(self) -> bool {
+        (self.0 as usize) < KEYWORDS.len()
+    }
+}
+
+impl fmt::Debug for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self.as_str(), f)
+    }
+}
+
+/// Symbols are serialized as their strings, since the indices are only
+/// meaningful within one process.
+impl Serialize for Symbol {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for Symbol {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = <std::borrow::Cow<'de, str>>::deserialize(deserializer)?;
+        Ok(Symbol::intern(&s))
+    }
+}
+
+impl fmt::Display for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self.as_str(), f)
+    }
+}
+
+macro_rules! keywords {
+    ($($name:ident: $string:literal,)*) => {
+        /// Keywords, which are pre-interned so that they have fixed symbols.
+        #[allow(non_upper_case_globals)]
+        pub mod kw {
+            use super::Symbol;
+            keywords!(@consts 0u32; $($name,)*);
+        }
+
+        const KEYWORDS: &[&str] = &[$($string,)*];
+    };
+    (@consts $index:expr; $name:ident, $($rest:ident,)*) => {
+        pub const $name: Symbol = Symbol($index);
+        keywords!(@consts $index + 1u32; $($rest,)*);
+    };
+    (@consts $index:expr;) => {};
+}
+
+keywords! {
+    I64: "i64",
+    U64: "u64",
+    Bool: "bool",
+    Char: "char",
+    Void: "void",
+    Str: "str",
+    If: "if",
+    Fn: "fn",
+    Struct: "struct",
+    Let: "let",
+    True: "true",
+    False: "false",
+    Enum: "enum",
+    Match: "match",
+    F64: "f64",
+    Trait: "trait",
+    Impl: "impl",
+    For: "for",
+    Dyn: "dyn",
+    Pub: "pub",
+    Mod: "mod",
+    Import: "import",
+    As: "as",
+    I8: "i8",
+    I16: "i16",
+    I32: "i32",
+    I128: "i128",
+    U8: "u8",
+    U16: "u16",
+    U32: "u32",
+    U128: "u128",
+    Usize: "usize",
+    F32: "f32",
+    In: "in",
+    Mut: "mut",
+}
+
+struct Interner {
+    names: HashMap<&'static str, Symbol>,
+    strings: Vec<&'static str>,
+}
+
+impl Interner {
+    fn prefill(init: &[&'static str]) -> Self {
+        Interner {
+            names: init.iter().copied().zip((0..).map(Symbol)).collect(),
+            strings: init.to_vec(),
+        }
+    }
+
+    fn intern(&mut self, s: &str) -> Symbol {
+        if let Some(&sym) = self.names.get(s) {
+            return sym;
+        }
+        let sym = Symbol(self.strings.len() as u32);
+        // Interned strings live as long as the compiler, so they are leaked
+        // to be handed out as `&'static str`.
+        let s: &'static str = Box::leak(s.into());
+        self.strings.push(s);
+        self.names.insert(s, sym);
+        sym
+    }
+
+    fn get(&self, sym: Symbol) -> &'static str {
+        self.strings[sym.0 as usize]
+    }
+}
+
+fn with_interner<R>(f: impl FnOnce(&mut Interner) -> R) -> R {
+    static INTERNER: OnceLock<Mutex<Interner>> = OnceLock::new();
+    let interner = INTERNER.get_or_init(|| Mutex::new(Interner::prefill(KEYWORDS)));
+    f(&mut interner.lock().unwrap())
+}
@@ -0,0 +1,32 @@
+use super::symbol::*;
+
+#[test]
+fn test_intern() {
+    let foo = Symbol::intern("foo");
+    assert_eq!(foo, Symbol::intern("foo"));
+    assert_ne!(foo, Symbol::intern("bar"));
+    assert_eq!(foo.as_str(), "foo");
+    assert!(!foo.is_keyword());
+}
+
+#[test]
+fn test_keywords() {
+    assert_eq!(Symbol::intern("fn"), kw::Fn);
+    assert_eq!(kw::I64.as_str(), "i64");
+    assert!(kw::If.is_keyword());
+}
+
+#[test]
+fn test_source_map() {
+    let mut source_map = super::SourceMap::default();
+    let a = source_map
+        .add_file("a.cy".to_string(), "fn a() {}".to_string())
+        .start_pos;
+    let b = source_map
+        .add_file("b.cy".to_string(), "fn b() {}".to_string())
+        .start_pos;
+    assert_eq!((a, b), (0, 10));
+    assert_eq!(source_map.lookup_file(9).name, "a.cy");
+    assert_eq!(source_map.lookup_file(10).name, "b.cy");
+    assert_eq!(source_map.lookup_file(19).name, "b.cy");
+}
@@ -0,0 +1,1954 @@
+//! Type checking of function bodies.
+
+use std::collections::{HashMap, HashSet};
+
+use super::*;
+use crate::collect::{path_to_string, plural};
+use crate::infer::InferCtxt;
+use crate::layout::{self, LayoutError};
+use crate::resolve::{ModId, PathRes};
+use crate::ty::{CtorKind, FnContainer, ImplDef, TraitId};
+use ceylon_span::Span;
+
+struct FnCtxt<'a> {
+    tcx: &'a Tcx,
+    /// The module the function is in, whose scope paths are resolved in.
+    scope: ModId,
+    /// The type parameters of the function being checked.
+    generics: &'a Generics,
+    /// The lints silenced by `@allow` on the function, the items and
+    /// modules containing it, and the statements being checked.
+    allowed_lints: Vec<Symbol>,
+    /// Variables in scope, innermost scope last.
+    scopes: Vec<HashMap<Symbol, NodeId>>,
+    /// The variables declared `mut`. The others, and `self`, can't be
+    /// assigned to once initialized.
+    mutable: HashSet<NodeId>,
+    /// Where the parameters of the function and its closures are declared.
+    params: HashMap<NodeId, Span>,
+    /// The return type of the function or closure being checked, which `?`
+    /// returns from.
+    ret_ty: Ty,
+    infcx: InferCtxt,
+    /// Bounds which generic arguments must satisfy. They are checked once
+    /// the arguments have been inferred.
+    obligations: Vec<Obligation>,
+    /// The nodes whose types or generic arguments were recorded while
+    /// checking this function, to be resolved at the end.
+    written: Vec<NodeId>,
+    /// The integer literals, whose values are checked to fit in their types
+    /// once these are inferred.
+    int_lits: Vec<IntLit>,
+    /// The types written in this function and those of its array
+    /// expressions, which are checked to be small enough to be stored once
+    /// they are inferred.
+    sized_tys: Vec<(Ty, Span)>,
+    results: &'a mut TypeckResults,
+    diagnostics: &'a mut Vec<Diagnostic>,
+}
+
+struct Obligation {
+    ty: Ty,
+    bound: TraitId,
+    span: Span,
+    cause: ObligationCause,
+}
+
+struct IntLit {
+    id: NodeId,
+    value: u128,
+    /// Whether the literal is the operand of `-`, which is then part of it.
+    negated: bool,
+    span: Span,
+}
+
+/// Why a type must satisfy a bound.
+enum ObligationCause {
+    /// The item declaring the bound is used.
+    Item(Symbol),
+    /// A value of the type is turned into a `dyn` value.
+    DynCoercion,
+}
+
+pub(crate) fn check_fn(
+    tcx: &Tcx,
+    scope: ModId,
+    fn_id: FnId,
+    decl: &ast::FnDecl,
+    allowed_lints: Vec<Symbol>,
+    results: &mut TypeckResults,
+    diagnostics: &mut Vec<Diagnostic>,
+) {
+    let sig = tcx.fn_sig(fn_id);
+    let errors = diagnostics.len();
+    let mut fcx = FnCtxt {
+        tcx,
+        scope,
+        generics: &sig.generics,
+        allowed_lints,
+        scopes: vec![HashMap::new()],
+        mutable: HashSet::new(),
+        params: HashMap::new(),
+        ret_ty: sig.output.clone(),
+        infcx: InferCtxt::default(),
+        obligations: Vec::new(),
+        written: Vec::new(),
+        int_lits: Vec::new(),
+        sized_tys: Vec::new(),
+        results,
+        diagnostics,
+    };
+    let mut inputs = sig.inputs.iter();
+    // Free functions don't take the `self` they may declare.
+    if let Some(self_param) = decl.self_param.as_ref().filter(|_| sig.has_self) {
+        let ty = inputs.next().unwrap().clone();
+        fcx.write_ty(self_param.id, ty);
+        fcx.scopes[0].insert(Symbol::intern("self"), self_param.id);
+        fcx.params.insert(self_param.id, self_param.span);
+    }
+    for (param, ty) in decl.params.iter().zip(inputs) {
+        fcx.sized_tys.push((ty.clone(), param.ty.span));
+        fcx.declare(param.mutbl, &param.ident, param.id, ty.clone());
+        fcx.params.insert(param.id, param.ident.span);
+    }
+    if let Some(ty) = &decl.ret_ty {
+        fcx.sized_tys.push((sig.output.clone(), ty.span));
+    }
+    // Methods of traits may have no body, which the collection reports
+    // elsewhere.
+    let Some(body) = &decl.body else {
+        return;
+    };
+    let ty = fcx.check_block(body, Some(&sig.output));
+    fcx.demand_eq(body.span, &sig.output, &ty);
+    // Unknown types are most likely caused by the errors, if any.
+    let report_unknown = fcx.diagnostics.len() == errors;
+    fcx.writeback(report_unknown);
+}
+
+impl FnCtxt<'_> {
+    fn error(&mut self, span: Span, message: impl Into<String>) {
+        self.diagnostics.push(Diagnostic::error(span, message));
+    }
+
+    fn lower_ty(&mut self, ty: &ast::Ty) -> Ty {
+        let lowered = self
+            .tcx
+            .lower_ty(self.scope, ty, self.generics, self.diagnostics);
+        self.sized_tys.push((lowered.clone(), ty.span));
+        lowered
+    }
+
+    fn write_ty(&mut self, id: NodeId, ty: Ty) {
+        self.results.node_types.insert(id, ty);
+        self.written.push(id);
+    }
+
+    fn declare(&mut self, mutbl: ast::Mutability, ident: &ast::Ident, id: NodeId, ty: Ty) {
+        self.write_ty(id, ty);
+        if mutbl == ast::Mutability::Mut {
+            self.mutable.insert(id);
+        }
+        self.scopes.last_mut().unwrap().insert(ident.name, id);
+    }
+
+    /// Creates a variable for each type parameter of an item used at `span`,
+    /// and requires them to satisfy the bounds of the parameters.
+    fn fresh_args(&mut self, generics: &Generics, span: Span, item: Symbol) -> Vec<Ty> {
+        let args: Vec<Ty> = generics
+            .params
+            .iter()
+            .map(|_| self.infcx.next_var(false, span))
+            .collect();
+        for (arg, param) in args.iter().zip(&generics.params) {
+            for &bound in &param.bounds {
+                self.obligations.push(Obligation {
+                    ty: arg.clone(),
+                    bound,
+                    span,
+                    cause: ObligationCause::Item(item),
+                });
+            }
+        }
+        args
+    }
+
+    /// Resolves a type whose structure must be known at this point, e.g. the
+    /// type of the base of a field access. If it is still unknown, reports an
+    /// error and returns `Ty::Error`.
+    fn structurally_resolve(&mut self, span: Span, ty: &Ty) -> Ty {
+        let ty = self.infcx.shallow_resolve(ty);
+        if let Ty::Infer(InferTy::TyVar(index)) = ty {
+            self.error(span, "type annotations needed");
+            // Don't report the variable again.
+            self.infcx.vars[index as usize].value = Some(Ty::Error);
+            return Ty::Error;
+        }
+        ty
+    }
+
+    /// Resolves the types recorded for this function now that every variable
+    /// which can be inferred is known, and checks the bounds of generic
+    /// arguments.
+    fn writeback(&mut self, report_unknown: bool) {
+        // Variables created for the same expression are reported once.
+        let mut reported: Vec<usize> = Vec::new();
+        for i in 0..self.infcx.vars.len() {
+            let var = &self.infcx.vars[i];
+            if var.value.is_some() {
+                continue;
+            }
+            // Integer variables fall back to `i64`.
+            if var.int {
+                self.infcx.vars[i].value = Some(Ty::I64);
+                continue;
+            }
+            let span = var.span;
+            if report_unknown && !reported.contains(&span.start_pos) {
+                self.error(span, "type annotations needed");
+                reported.push(span.start_pos);
+            }
+            self.infcx.vars[i].value = Some(Ty::Error);
+        }
+        for id in std::mem::take(&mut self.written) {
+            if let Some(ty) = self.results.node_types.get(&id) {
+                let ty = self.infcx.resolve(ty);
+                self.results.node_types.insert(id, ty);
+            }
+            if let Some(args) = self.results.node_args.get(&id) {
+                let args = args.iter().map(|arg| self.infcx.resolve(arg)).collect();
+                self.results.node_args.insert(id, args);
+            }
+        }
+        for lit in std::mem::take(&mut self.int_lits) {
+            let ty = &self.results.node_types[&lit.id];
+            let Some((min, max)) = ty.int_range() else {
+                continue;
+            };
+            let fits = if lit.negated {
+                lit.value <= min.unsigned_abs()
+            } else {
+                lit.value <= max
+            };
+            if !fits {
+                let ty = self.tcx.ty_to_string(ty);
+                let sign = if lit.negated { "-" } else { "" };
+                self.diagnostics.push(
+                    Diagnostic::error(lit.span, format!("literal out of range for `{}`", ty))
+                        .note(format!(
+                            "the literal `{}{}` does not fit into the type `{}` whose range is `{}..={}`",
+                            sign, lit.value, ty, min, max
+                        )),
+                );
+            }
+        }
+        for (ty, span) in std::mem::take(&mut self.sized_tys) {
+            let ty = self.infcx.resolve(&ty);
+            if self.tcx.layout_of(&ty) == Err(LayoutError::TooBig) {
+                self.diagnostics.push(layout::too_big(self.tcx, span, &ty));
+            }
+        }
+        for obligation in std::mem::take(&mut self.obligations) {
+            let ty = self.infcx.resolve(&obligation.ty);
+            if !self.tcx.satisfies(&ty, obligation.bound, self.generics) {
+                let note = match obligation.cause {
+                    ObligationCause::Item(item) => format!("required by a bound in `{}`", item),
+                    ObligationCause::DynCoercion => format!(
+                        "required for the cast from `{}` to `dyn {}`",
+                        self.tcx.ty_to_string(&ty),
+                        self.tcx.trait_def(obligation.bound).name
+                    ),
+                };
+                let diag = self
+                    .tcx
+                    .unsatisfied_bound(obligation.span, &ty, obligation.bound, note);
+                self.diagnostics.push(diag);
+            }
+        }
+    }
+
+    fn lookup(&self, name: Symbol) -> Option<NodeId> {
+        self.scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.get(&name).copied())
+    }
+
+    /// Reports an error unless `found` is `expected`. Returns whether the
+    /// types are compatible.
+    fn demand_eq(&mut self, span: Span, expected: &Ty, found: &Ty) -> bool {
+        if self.infcx.unify(expected, found) {
+            return true;
+        }
+        let diag = Diagnostic::error(span, "mismatched types").note(format!(
+            "expected `{}`, found `{}`",
+            self.ty_to_string(expected),
+            self.ty_to_string(found)
+        ));
+        self.diagnostics.push(diag);
+        false
+    }
+
+    /// Checks a block, whose value must be of type `expected` if any, and
+    /// returns its type.
+    fn check_block(&mut self, block: &ast::Block, expected: Option<&Ty>) -> Ty {
+        self.scopes.push(HashMap::new());
+        let mut ty = Ty::Void;
+        for (i, stmt) in block.stmts.iter().enumerate() {
+            let outer_lints = self.allowed_lints.len();
+            self.allowed_lints.extend(attr::allowed_lints(&stmt.attrs));
+            match &stmt.kind {
+                ast::StmtKind::Let(local) => {
+                    let ty = local.ty.as_ref().map(|ty| self.lower_ty(ty));
+                    let init_ty = self.check_expr_with(&local.init, ty.as_ref());
+                    let ty = ty.unwrap_or(init_ty);
+                    // The variables are not in scope in their own initializer.
+                    // They are bound in a scope of their own first, so that
+                    // they may shadow the ones of the block.
+                    self.scopes.push(HashMap::new());
+                    self.check_pat(&local.pat, &ty);
+                    let bound = self.scopes.pop().unwrap();
+                    self.scopes.last_mut().unwrap().extend(bound);
+                    let ty = self.infcx.resolve(&ty);
+                    usefulness::check_irrefutable(
+                        self.tcx,
+                        self.results,
+                        &local.pat,
+                        &ty,
+                        "local binding",
+                        self.diagnostics,
+                    );
+                }
+                ast::StmtKind::Semi(expr) => {
+                    self.check_expr(expr);
+                }
+                ast::StmtKind::Expr(expr) if i == block.stmts.len() - 1 => {
+                    ty = self.check_expr_with(expr, expected);
+                }
+                ast::StmtKind::Expr(expr) => {
+                    self.check_expr_with(expr, Some(&Ty::Void));
+                }
+            }
+            self.allowed_lints.truncate(outer_lints);
+        }
+        self.scopes.pop();
+        ty
+    }
+
+    fn check_expr(&mut self, expr: &ast::Expr) -> Ty {
+        self.check_expr_with(expr, None)
+    }
+
+    /// Checks an expression whose type must be `expected`, if any. If it
+    /// isn't, the error is reported here and `Ty::Error` is returned.
+    fn check_expr_with(&mut self, expr: &ast::Expr, expected: Option<&Ty>) -> Ty {
+        let ty = self.check_expr_kind(expr, expected);
+        self.write_ty(expr.id, ty.clone());
+        match expected {
+            Some(expected) if self.coerce_to_dyn(expr, expected, &ty) => expected.clone(),
+            Some(expected) if self.coerce_to_slice(expr, expected, &ty) => expected.clone(),
+            Some(expected) if !self.demand_eq(expr.span, expected, &ty) => Ty::Error,
+            _ => ty,
+        }
+    }
+
+    /// Turns the value of an expression into a `dyn` value if one is
+    /// expected and the value has another type. Returns whether it does.
+    fn coerce_to_dyn(&mut self, expr: &ast::Expr, expected: &Ty, found: &Ty) -> bool {
+        let Ty::Dyn(trait_id) = self.infcx.shallow_resolve(expected) else {
+            return false;
+        };
+        if let Ty::Dyn(_) | Ty::Infer(InferTy::TyVar(_)) | Ty::Error =
+            self.infcx.shallow_resolve(found)
+        {
+            return false;
+        }
+        self.results.dyn_coercions.insert(expr.id, trait_id);
+        self.obligations.push(Obligation {
+            ty: found.clone(),
+            bound: trait_id,
+            span: expr.span,
+            cause: ObligationCause::DynCoercion,
+        });
+        true
+    }
+
+    /// Turns the value of an expression into a slice if one is expected and
+    /// the value is an array. Returns whether it does.
+    fn coerce_to_slice(&mut self, expr: &ast::Expr, expected: &Ty, found: &Ty) -> bool {
+        let Ty::Slice(elem) = self.infcx.shallow_resolve(expected) else {
+            return false;
+        };
+        let Ty::Array(found_elem, _) = self.infcx.shallow_resolve(found) else {
+            return false;
+        };
+        // Otherwise the mismatch of the array and the slice is reported.
+        if !self.infcx.unify(&elem, &found_elem) {
+            return false;
+        }
+        self.results.unsize_coercions.insert(expr.id);
+        true
+    }
+
+    fn check_expr_kind(&mut self, expr: &ast::Expr, expected: Option<&Ty>) -> Ty {
+        match &expr.kind {
+            ast::ExprKind::Literal(lit) => match lit {
+                // Integer literals have the type of their suffix, or take the
+                // type the context expects, and are inferred from their uses
+                // otherwise.
+                ast::LiteralKind::Int(value, suffix) => {
+                    self.int_lits.push(IntLit {
+                        id: expr.id,
+                        value: *value,
+                        negated: false,
+                        span: expr.span,
+                    });
+                    match (suffix, expected.map(|ty| self.infcx.shallow_resolve(ty))) {
+                        (Some(suffix), _) => suffix_ty(*suffix),
+                        (None, Some(ty)) if ty.is_integral() => ty,
+                        (None, None | Some(Ty::Infer(InferTy::TyVar(_)))) => {
+                            self.infcx.next_var(true, expr.span)
+                        }
+                        (None, Some(_)) => Ty::I64,
+                    }
+                }
+                // Float literals are `f32` only if written or expected so.
+                ast::LiteralKind::Float(value, suffix) => {
+                    let ty = match (suffix, expected.map(|ty| self.infcx.shallow_resolve(ty))) {
+                        (Some(suffix), _) => suffix_ty(*suffix),
+                        (None, Some(Ty::F32)) => Ty::F32,
+                        (None, _) => Ty::F64,
+                    };
+                    // The literals are finite, but may round to infinity.
+                    let max = match ty {
+                        Ty::F32 if (*value as f32).is_infinite() => Some(format!("{:e}", f32::MAX)),
+                        Ty::F64 if value.is_infinite() => Some(format!("{:e}", f64::MAX)),
+                        _ => None,
+                    };
+                    if let Some(max) = max {
+                        let ty = self.tcx.ty_to_string(&ty);
+                        self.diagnostics.push(
+                            Diagnostic::error(
+                                expr.span,
+                                format!("literal out of range for `{}`", ty),
+                            )
+                            .note(format!("the largest finite `{}` is `{}`", ty, max)),
+                        );
+                    }
+                    ty
+                }
+                ast::LiteralKind::Str(_) => Ty::Str,
+                ast::LiteralKind::Char(_) => Ty::Char,
+                ast::LiteralKind::Bool(_) => Ty::Bool,
+            },
+            ast::ExprKind::Binary(op, lhs, rhs) => self.check_binary(expr, op, lhs, rhs),
+            ast::ExprKind::Unary(op, operand) => {
+                let ty = self.check_expr_with(operand, expected);
+                let ty = self.structurally_resolve(operand.span, &ty);
+                // Unsigned integers can't be negated, which is reported below.
+                if *op == ast::UnOp::Neg && ty.is_signed() {
+                    if let Some(lit) = self.int_lits.iter_mut().find(|lit| lit.id == operand.id) {
+                        lit.negated = true;
+                        lit.span = expr.span;
+                    }
+                }
+                let ok = match op {
+                    ast::UnOp::Neg => self.check_operator(expr, self.tcx.neg_method(), &ty),
+                    ast::UnOp::Not => ty == Ty::Bool,
+                    ast::UnOp::BitNot => ty.is_integral(),
+                };
+                if ty == Ty::Error || ok {
+                    ty
+                } else {
+                    let name = match op {
+                        ast::UnOp::Neg => "-",
+                        ast::UnOp::Not => "!",
+                        ast::UnOp::BitNot => "~",
+                    };
+                    let message = format!(
+                        "cannot apply unary operator `{}` to type `{}`",
+                        name,
+                        self.ty_to_string(&ty)
+                    );
+                    match op {
+                        ast::UnOp::BitNot => self.error(expr.span, message),
+                        _ => self.operator_error(expr.span, message, &ty, self.tcx.lang.neg),
+                    }
+                    Ty::Error
+                }
+            }
+            ast::ExprKind::Path(path) => {
+                let Some(res) = self.resolve_path(path, "value", true) else {
+                    return Ty::Error;
+                };
+                self.results.path_res.insert(expr.id, res);
+                match res {
+                    Res::Local(id) => self.results.node_types[&id].clone(),
+                    // A function used as a value.
+                    Res::Fn(id) => {
+                        let sig = self.tcx.fn_sig(id);
+                        let args = self.fresh_args(&sig.generics, expr.span, sig.name);
+                        let inputs = sig.inputs.iter().map(|ty| ty.subst(&args)).collect();
+                        let output = sig.output.subst(&args);
+                        if !args.is_empty() {
+                            self.results.node_args.insert(expr.id, args);
+                        }
+                        Ty::Fn(inputs, Box::new(output))
+                    }
+                    Res::Variant(adt, index)
+                        if self.tcx.adt(adt).variants[index].ctor_kind == CtorKind::Const =>
+                    {
+                        let def = self.tcx.adt(adt);
+                        Ty::Adt(adt, self.fresh_args(&def.generics, expr.span, def.name))
+                    }
+                    _ => {
+                        let descr = self.describe_res(res);
+                        self.error(
+                            path.span,
+                            format!("expected value, found {} `{}`", descr, path_to_string(path)),
+                        );
+                        Ty::Error
+                    }
+                }
+            }
+            ast::ExprKind::Call(callee, args) => self.check_call(callee, args),
+            ast::ExprKind::MethodCall(receiver, ident, args) => {
+                self.check_method_call(expr, receiver, ident, args)
+            }
+            ast::ExprKind::Block(block) => self.check_block(block, expected),
+            ast::ExprKind::Match(scrutinee, arms) => {
+                self.check_match(expr.span, scrutinee, arms, expected)
+            }
+            ast::ExprKind::Assign(place, value) => {
+                let ty = self.check_expr(place);
+                self.check_expr_with(value, Some(&ty));
+                self.check_assignable(place);
+                Ty::Void
+            }
+            ast::ExprKind::AssignOp(op, place, value) => {
+                let lhs_ty = self.check_expr(place);
+                let rhs_ty = self.check_expr_with(value, Some(&lhs_ty));
+                self.check_binop(expr, op, place.span, &lhs_ty, &rhs_ty, true);
+                self.check_assignable(place);
+                Ty::Void
+            }
+            ast::ExprKind::Field(base, ident) => {
+                let base_ty = self.check_expr(base);
+                match self.structurally_resolve(base.span, &base_ty) {
+                    Ty::Adt(id, args) if !self.tcx.adt(id).is_enum() => {
+                        let variant = self.tcx.adt(id).non_enum_variant();
+                        match variant.field_index(ident.name) {
+                            Some(index) => {
+                                self.results.field_indices.insert(expr.id, index);
+                                variant.fields[index].ty.subst(&args)
+                            }
+                            None => {
+                                self.error(
+                                    ident.span,
+                                    format!(
+                                        "no field `{}` on type `{}`",
+                                        ident.name,
+                                        self.tcx.adt(id).name
+                                    ),
+                                );
+                                Ty::Error
+                            }
+                        }
+                    }
+                    Ty::Tuple(elems) => {
+                        let index = ident.name.as_str().parse::<usize>().ok();
+                        match index.filter(|&index| index < elems.len()) {
+                            Some(index) => {
+                                self.results.field_indices.insert(expr.id, index);
+                                elems[index].clone()
+                            }
+                            None => {
+                                self.error(
+                                    ident.span,
+                                    format!(
+                                        "no field `{}` on type `{}`",
+                                        ident.name,
+                                        self.ty_to_string(&Ty::Tuple(elems))
+                                    ),
+                                );
+                                Ty::Error
+                            }
+                        }
+                    }
+                    Ty::Error => Ty::Error,
+                    ty => {
+                        self.error(
+                            ident.span,
+                            format!(
+                                "no field `{}` on type `{}`",
+                                ident.name,
+                                self.ty_to_string(&ty)
+                            ),
+                        );
+                        Ty::Error
+                    }
+                }
+            }
+            ast::ExprKind::Tuple(elems) => {
+                let expected = match expected.map(|ty| self.infcx.shallow_resolve(ty)) {
+                    Some(Ty::Tuple(tys)) if tys.len() == elems.len() => Some(tys),
+                    _ => None,
+                };
+                let tys = elems
+                    .iter()
+                    .enumerate()
+                    .map(|(i, elem)| {
+                        let expected = expected.as_ref().map(|tys| &tys[i]);
+                        self.check_expr_with(elem, expected)
+                    })
+                    .collect();
+                Ty::Tuple(tys)
+            }
+            ast::ExprKind::Array(elems) => {
+                let elem_ty = self.expected_elem_ty(expected, expr.span);
+                for elem in elems {
+                    self.check_expr_with(elem, Some(&elem_ty));
+                }
+                Ty::Array(Box::new(elem_ty), elems.len() as u64)
+            }
+            ast::ExprKind::Repeat(elem, len) => {
+                let elem_ty = self.expected_elem_ty(expected, expr.span);
+                self.check_expr_with(elem, Some(&elem_ty));
+                let ty = Ty::Array(Box::new(elem_ty), *len);
+                self.sized_tys.push((ty.clone(), expr.span));
+                ty
+            }
+            ast::ExprKind::Index(base, index) => self.check_index(base, index),
+            ast::ExprKind::Range(start, end, limits) => {
+                let id = match limits {
+                    ast::RangeLimits::HalfOpen => self.tcx.lang.range,
+                    ast::RangeLimits::Closed => self.tcx.lang.range_inclusive,
+                };
+                let expected = match expected.map(|ty| self.infcx.shallow_resolve(ty)) {
+                    Some(Ty::Adt(found, args)) if found == id => Some(args[0].clone()),
+                    _ => None,
+                };
+                let start_ty = self.check_expr_with(start, expected.as_ref());
+                let Some(end) = end else {
+                    self.diagnostics.push(
+                        Diagnostic::error(expr.span, "a range without an end must be an index")
+                            .help("`base[start..]` is the slice of the elements from `start` on"),
+                    );
+                    return Ty::Error;
+                };
+                self.check_expr_with(end, Some(&start_ty));
+                Ty::Adt(id, vec![start_ty])
+            }
+            ast::ExprKind::ForLoop(pat, iter, body) => self.check_for_loop(pat, iter, body),
+            ast::ExprKind::Struct(lit) => self.check_struct_expr(expr, lit),
+            ast::ExprKind::Closure(closure) => self.check_closure(closure, expected),
+            ast::ExprKind::Cast(operand, ty) => self.check_cast(expr, operand, ty),
+            ast::ExprKind::Try(operand) => self.check_try(expr, operand),
+            ast::ExprKind::Err => Ty::Error,
+        }
+    }
+
+    /// Reports the left-hand side of an assignment unless it is a place
+    /// inside a variable declared `mut`.
+    fn check_assignable(&mut self, place: &ast::Expr) {
+        if !is_place_expr(place, self.results) {
+            self.diagnostics.push(
+                Diagnostic::error(place.span, "invalid left-hand side of assignment")
+                    .note("only variables, their fields and their elements can be assigned to"),
+            );
+            return;
+        }
+        let mut base = place;
+        while let ast::ExprKind::Field(inner, _) | ast::ExprKind::Index(inner, _) = &base.kind {
+            base = inner;
+        }
+        let ast::ExprKind::Path(path) = &base.kind else {
+            return;
+        };
+        let Some(&Res::Local(id)) = self.results.path_res.get(&base.id) else {
+            return;
+        };
+        if self.mutable.contains(&id) {
+            return;
+        }
+        let name = path_to_string(path);
+        let param = self.params.get(&id).copied();
+        let message = match (base.id == place.id, param) {
+            (true, Some(_)) => format!("cannot assign to immutable parameter `{}`", name),
+            (true, None) => format!("cannot assign twice to immutable variable `{}`", name),
+            (false, Some(_)) => {
+                format!("cannot assign to a part of immutable parameter `{}`", name)
+            }
+            (false, None) => {
+                format!("cannot assign to a part of immutable variable `{}`", name)
+            }
+        };
+        let mut diagnostic = Diagnostic::error(place.span, message);
+        if let Some(span) = param {
+            diagnostic = diagnostic.span_note(span, format!("`{}` is declared here", name));
+        }
+        // `self` can't be declared `mut`.
+        if name != "self" {
+            diagnostic = diagnostic.help(format!(
+                "consider adding `mut` to the declaration of `{0}`: `mut {0}`",
+                name
+            ));
+        }
+        self.diagnostics.push(diagnostic);
+    }
+
+    /// The element type of an array literal: the one of the array or the
+    /// slice expected, or a new variable.
+    fn expected_elem_ty(&mut self, expected: Option<&Ty>, span: Span) -> Ty {
+        match expected.map(|ty| self.infcx.shallow_resolve(ty)) {
+            Some(Ty::Array(elem, _) | Ty::Slice(elem)) => *elem,
+            _ => self.infcx.next_var(false, span),
+        }
+    }
+
+    /// Checks `base[index]`. Indices into arrays which are known to be out
+    /// of bounds are reported here; the others are checked at run time.
+    fn check_index(&mut self, base: &ast::Expr, index: &ast::Expr) -> Ty {
+        let base_ty = self.check_expr(base);
+        let base_ty = self.structurally_resolve(base.span, &base_ty);
+        // `base[start..end]` is a slice of the elements in the range, which
+        // may also be a value built elsewhere.
+        let is_range = match &index.kind {
+            ast::ExprKind::Range(start, end, _) => {
+                self.check_expr_with(start, Some(&Ty::Usize));
+                if let Some(end) = end {
+                    self.check_expr_with(end, Some(&Ty::Usize));
+                }
+                true
+            }
+            _ => {
+                let index_ty = self.check_expr(index);
+                let lang = self.tcx.lang;
+                match self.infcx.shallow_resolve(&index_ty) {
+                    Ty::Adt(id, args) if id == lang.range || id == lang.range_inclusive => {
+                        self.demand_eq(index.span, &Ty::Usize, &args[0]);
+                        true
+                    }
+                    _ => {
+                        self.demand_eq(index.span, &Ty::Usize, &index_ty);
+                        false
+                    }
+                }
+            }
+        };
+        match base_ty {
+            Ty::Array(elem, _) | Ty::Slice(elem) if is_range => Ty::Slice(elem),
+            Ty::Array(elem, len) => {
+                if let ast::ExprKind::Literal(ast::LiteralKind::Int(value, _)) = index.kind {
+                    if value >= u128::from(len) {
+                        self.error(
+                            index.span,
+                            format!(
+                                "index out of bounds: the len is {} but the index is {}",
+                                len, value
+                            ),
+                        );
+                    }
+                }
+                *elem
+            }
+            Ty::Slice(elem) => *elem,
+            Ty::Error => Ty::Error,
+            ty => {
+                self.error(
+                    base.span,
+                    format!(
+                        "cannot index into a value of type `{}`",
+                        self.ty_to_string(&ty)
+                    ),
+                );
+                Ty::Error
+            }
+        }
+    }
+
+    /// Checks `for pat in iter { body }`. Ranges of integers yield their
+    /// integers, and arrays and slices their elements.
+    fn check_for_loop(&mut self, pat: &ast::Pat, iter: &ast::Expr, body: &ast::Block) -> Ty {
+        let iter_ty = self.check_expr(iter);
+        let iter_ty = self.structurally_resolve(iter.span, &iter_ty);
+        let lang = self.tcx.lang;
+        let item_ty = match &iter_ty {
+            Ty::Adt(id, args) if *id == lang.range || *id == lang.range_inclusive => {
+                match self.structurally_resolve(iter.span, &args[0]) {
+                    ty if ty.is_integral() || ty == Ty::Error => Some(ty),
+                    _ => None,
+                }
+            }
+            Ty::Array(elem, _) | Ty::Slice(elem) => Some((**elem).clone()),
+            Ty::Error => Some(Ty::Error),
+            _ => None,
+        };
+        let item_ty = item_ty.unwrap_or_else(|| {
+            self.diagnostics.push(
+                Diagnostic::error(
+                    iter.span,
+                    format!("`{}` is not an iterator", self.ty_to_string(&iter_ty)),
+                )
+                .note("only ranges of integers, arrays and slices can be iterated over"),
+            );
+            Ty::Error
+        });
+        self.scopes.push(HashMap::new());
+        self.check_pat(pat, &item_ty);
+        self.check_block(body, Some(&Ty::Void));
+        self.scopes.pop();
+        let item_ty = self.infcx.resolve(&item_ty);
+        usefulness::check_irrefutable(
+            self.tcx,
+            self.results,
+            pat,
+            &item_ty,
+            "`for` loop binding",
+            self.diagnostics,
+        );
+        Ty::Void
+    }
+
+    /// Resolves a path in an expression or a pattern, reporting an error if
+    /// it cannot be resolved. `what` describes what is expected, e.g.
+    /// "value". Local variables are only considered if `locals` is set.
+    fn resolve_path(&mut self, path: &ast::Path, what: &str, locals: bool) -> Option<Res> {
+        if let [ident] = &path.segments[..] {
+            if let Some(id) = self.lookup(ident.name).filter(|_| locals) {
+                return Some(Res::Local(id));
+            }
+        }
+        match self
+            .tcx
+            .resolve_path_prefix(self.scope, path, self.diagnostics)
+        {
+            PathRes::Def(def, []) => match def {
+                Def::Fn(id) => Some(Res::Fn(id)),
+                Def::Adt(id) if !self.tcx.adt(id).is_enum() => Some(Res::Variant(id, 0)),
+                Def::Adt(_) | Def::Trait(_) | Def::Mod(_) => {
+                    self.error(
+                        path.span,
+                        format!(
+                            "expected {}, found {} `{}`",
+                            what,
+                            self.tcx.describe_def(def),
+                            path_to_string(path)
+                        ),
+                    );
+                    None
+                }
+                Def::Impl(_) => unreachable!("impls have no name"),
+            },
+            // A variant or an associated function of the type the path
+            // names, e.g. `Shape::Circle`.
+            PathRes::Def(def, [last]) => {
+                let first = &path.segments[path.segments.len() - 2];
+                match def {
+                    Def::Adt(id) => {
+                        let adt = self.tcx.adt(id);
+                        if let Some(index) = adt.variant_index(last.name).filter(|_| adt.is_enum())
+                        {
+                            return Some(Res::Variant(id, index));
+                        }
+                        let tcx = self.tcx;
+                        let fns: Vec<FnId> = tcx
+                            .impls
+                            .iter()
+                            .filter(|imp| {
+                                imp.trait_ref.is_none()
+                                    && matches!(imp.self_ty, Ty::Adt(self_id, _) if self_id == id)
+                            })
+                            .flat_map(|imp| imp.methods.iter().copied())
+                            .filter(|&method| tcx.fn_sig(method).name == last.name)
+                            .collect();
+                        match fns[..] {
+                            [method] => Some(Res::Fn(method)),
+                            [] if adt.is_enum() => {
+                                self.error(
+                                    last.span,
+                                    format!(
+                                        "no variant named `{}` in enum `{}`",
+                                        last.name, first.name
+                                    ),
+                                );
+                                None
+                            }
+                            [] => {
+                                self.error(
+                                    last.span,
+                                    format!(
+                                "no function or associated item named `{}` found for struct `{}`",
+                                last.name, first.name
+                            ),
+                                );
+                                None
+                            }
+                            _ => {
+                                self.multiple_applicable_items(last.span, &fns);
+                                None
+                            }
+                        }
+                    }
+                    Def::Trait(id) => match self.tcx.trait_method(id, last.name) {
+                        Some(method) => Some(Res::Fn(method)),
+                        None => {
+                            self.error(
+                                last.span,
+                                format!(
+                                    "cannot find method `{}` in trait `{}`",
+                                    last.name, first.name
+                                ),
+                            );
+                            None
+                        }
+                    },
+                    _ => {
+                        self.error(
+                            first.span,
+                            format!("failed to resolve: `{}` is not a type", first.name),
+                        );
+                        None
+                    }
+                }
+            }
+            PathRes::Def(..) => {
+                self.error(
+                    path.span,
+                    format!(
+                        "cannot find {} `{}` in this scope",
+                        what,
+                        path_to_string(path)
+                    ),
+                );
+                None
+            }
+            PathRes::Undeclared => {
+                let first = &path.segments[0];
+                let diag = if path.segments.len() == 1 {
+                    let diag = Diagnostic::error(
+                        first.span,
+                        format!("cannot find {} `{}` in this scope", what, first.name),
+                    );
+                    match self.tcx.import_suggestion(self.scope, first.name) {
+                        Some(help) => diag.help(help),
+                        None => diag,
+                    }
+                } else {
+                    Diagnostic::error(
+                        first.span,
+                        format!(
+                            "failed to resolve: use of undeclared type or module `{}`",
+                            first.name
+                        ),
+                    )
+                };
+                self.diagnostics.push(diag);
+                None
+            }
+            PathRes::Failed => None,
+        }
+    }
+
+    /// Describes what a path resolves to for error messages, e.g. "tuple
+    /// variant".
+    fn describe_res(&self, res: Res) -> &'static str {
+        match res {
+            Res::Local(_) => "local variable",
+            Res::Fn(_) => "function",
+            Res::Variant(adt, _) if !self.tcx.adt(adt).is_enum() => "struct",
+            Res::Variant(adt, index) => match self.tcx.adt(adt).variants[index].ctor_kind {
+                CtorKind::Struct => "struct variant",
+                CtorKind::Fn => "tuple variant",
+                CtorKind::Const => "unit variant",
+            },
+        }
+    }
+
+    fn check_call(&mut self, callee: &ast::Expr, args: &[ast::Expr]) -> Ty {
+        let res = match &callee.kind {
+            ast::ExprKind::Path(path) => {
+                let res = self.resolve_path(path, "function", true);
+                if let Some(res) = res {
+                    self.results.path_res.insert(callee.id, res);
+                }
+                res
+            }
+            _ => return self.check_indirect_call(callee, args),
+        };
+        let (what, inputs, output) = match res {
+            Some(Res::Fn(id)) => {
+                let sig = self.tcx.fn_sig(id);
+                let args = self.fresh_args(&sig.generics, callee.span, sig.name);
+                let inputs: Vec<Ty> = sig.inputs.iter().map(|ty| ty.subst(&args)).collect();
+                let output = sig.output.subst(&args);
+                if !args.is_empty() {
+                    self.results.node_args.insert(callee.id, args);
+                    self.written.push(callee.id);
+                }
+                ("function", inputs, output)
+            }
+            Some(Res::Variant(adt, index))
+                if self.tcx.adt(adt).variants[index].ctor_kind == CtorKind::Fn =>
+            {
+                let def = self.tcx.adt(adt);
+                let args = self.fresh_args(&def.generics, callee.span, def.name);
+                let variant = &def.variants[index];
+                let inputs = variant.fields.iter().map(|f| f.ty.subst(&args)).collect();
+                ("enum variant", inputs, Ty::Adt(adt, args))
+            }
+            // A variable holding a function value.
+            Some(Res::Local(_)) => return self.check_indirect_call(callee, args),
+            res => {
+                if let (Some(res), ast::ExprKind::Path(path)) = (res, &callee.kind) {
+                    let descr = self.describe_res(res);
+                    self.error(
+                        callee.span,
+                        format!(
+                            "expected function, found {} `{}`",
+                            descr,
+                            path_to_string(path)
+                        ),
+                    );
+                }
+                for arg in args {
+                    self.check_expr(arg);
+                }
+                return Ty::Error;
+            }
+        };
+        self.check_args(callee.span, what, &inputs, args);
+        output
+    }
+
+    /// Checks a call of a function value, e.g. `make_adder(1)(2)`.
+    fn check_indirect_call(&mut self, callee: &ast::Expr, args: &[ast::Expr]) -> Ty {
+        let ty = self.check_expr(callee);
+        match self.structurally_resolve(callee.span, &ty) {
+            Ty::Fn(inputs, output) => {
+                self.check_args(callee.span, "function", &inputs, args);
+                *output
+            }
+            ty => {
+                if ty != Ty::Error {
+                    self.error(
+                        callee.span,
+                        format!("expected function, found `{}`", self.ty_to_string(&ty)),
+                    );
+                }
+                for arg in args {
+                    self.check_expr(arg);
+                }
+                Ty::Error
+            }
+        }
+    }
+
+    /// Checks a closure. Parameters without a type take the one the context
+    /// expects, if it expects a function, and are inferred otherwise.
+    fn check_closure(&mut self, closure: &ast::Closure, expected: Option<&Ty>) -> Ty {
+        let (expected_inputs, expected_output) =
+            match expected.map(|ty| self.infcx.shallow_resolve(ty)) {
+                Some(Ty::Fn(inputs, output)) if inputs.len() == closure.params.len() => {
+                    (Some(inputs), Some(*output))
+                }
+                _ => (None, None),
+            };
+        self.scopes.push(HashMap::new());
+        let mut inputs = Vec::new();
+        for (i, param) in closure.params.iter().enumerate() {
+            let ty = match (&param.ty, &expected_inputs) {
+                (Some(ty), _) => self.lower_ty(ty),
+                (None, Some(expected)) => expected[i].clone(),
+                (None, None) => self.infcx.next_var(false, param.span),
+            };
+            self.declare(param.mutbl, &param.ident, param.id, ty.clone());
+            self.params.insert(param.id, param.ident.span);
+            inputs.push(ty);
+        }
+        let output = match (&closure.ret_ty, expected_output) {
+            (Some(ty), _) => self.lower_ty(ty),
+            (None, Some(expected)) => expected,
+            (None, None) => self.infcx.next_var(false, closure.body.span),
+        };
+        let outer_ret_ty = std::mem::replace(&mut self.ret_ty, output.clone());
+        self.check_expr_with(&closure.body, Some(&output));
+        self.ret_ty = outer_ret_ty;
+        self.scopes.pop();
+        Ty::Fn(inputs, Box::new(output))
+    }
+
+    /// Checks `operand as ty`, which must be one of the conversions of
+    /// [`Ty::can_cast_to`].
+    fn check_cast(&mut self, expr: &ast::Expr, operand: &ast::Expr, ty: &ast::Ty) -> Ty {
+        let to = self.lower_ty(ty);
+        let from = self.check_expr(operand);
+        let from = self.structurally_resolve(operand.span, &from);
+        // Integer literals are not inferred from the target, but fall back to
+        // `i64` unless inferred otherwise, e.g. `-1 as u64` is `u64::MAX`.
+        if from == Ty::Error || to == Ty::Error || from == to {
+            return to;
+        }
+        if !from.can_cast_to(&to) {
+            let primitive = from.is_primitive() && to.is_primitive();
+            let (from, to) = (self.tcx.ty_to_string(&from), self.tcx.ty_to_string(&to));
+            let diag = if primitive {
+                Diagnostic::error(
+                    expr.span,
+                    format!("casting `{}` as `{}` is invalid", from, to),
+                )
+            } else {
+                Diagnostic::error(
+                    expr.span,
+                    format!("non-primitive cast: `{}` as `{}`", from, to),
+                )
+                .note("only primitive types can be cast to other types")
+            };
+            self.diagnostics.push(diag);
+        }
+        to
+    }
+
+    /// Checks `operand?`, which evaluates to the value in a `Some` or an `Ok`
+    /// and returns a `None` or an `Err` from the enclosing function, whose
+    /// return type must be an `Option` or a `Result` with the same error
+    /// type respectively.
+    fn check_try(&mut self, expr: &ast::Expr, operand: &ast::Expr) -> Ty {
+        let ty = self.check_expr(operand);
+        let lang = self.tcx.lang;
+        let (value_ty, ret_ty, kind) = match self.structurally_resolve(operand.span, &ty) {
+            Ty::Adt(id, args) if id == lang.option => {
+                let ret_ty = Ty::Adt(id, vec![self.infcx.next_var(false, expr.span)]);
+                (args[0].clone(), ret_ty, "an `Option`")
+            }
+            Ty::Adt(id, args) if id == lang.result => {
+                let ok_ty = self.infcx.next_var(false, expr.span);
+                let ret_ty = Ty::Adt(id, vec![ok_ty, args[1].clone()]);
+                (args[0].clone(), ret_ty, "a `Result`")
+            }
+            Ty::Error => return Ty::Error,
+            ty => {
+                let mut diag = Diagnostic::error(
+                    expr.span,
+                    "the `?` operator can only be applied to values of type `Option` or `Result`",
+                )
+                .note(format!("found `{}`", self.ty_to_string(&ty)));
+                if let Some(note) = self.tcx.shadowed_lang_adt_note(&ty) {
+                    diag = diag.note(note);
+                }
+                self.diagnostics.push(diag);
+                return Ty::Error;
+            }
+        };
+        if !self.infcx.unify(&self.ret_ty, &ret_ty) {
+            let found = self.infcx.resolve(&self.ret_ty);
+            let mut diag = match (&found, &ret_ty) {
+                (Ty::Adt(found_id, found_args), Ty::Adt(id, args))
+                    if found_id == id && *id == lang.result =>
+                {
+                    Diagnostic::error(
+                        expr.span,
+                        "`?` cannot return the error of a `Result` with another error type",
+                    )
+                    .note(format!(
+                        "the error type is `{}`, but the function returns errors of type `{}`",
+                        self.ty_to_string(&args[1]),
+                        self.tcx.ty_to_string(&found_args[1])
+                    ))
+                }
+                _ => Diagnostic::error(
+                    expr.span,
+                    format!(
+                        "the `?` operator on {} can only be used in a function that returns \
+                         {}",
+                        kind, kind
+                    ),
+                )
+                .note(format!(
+                    "the function returns `{}`",
+                    self.tcx.ty_to_string(&found)
+                )),
+            };
+            if let Some(note) = self.tcx.shadowed_lang_adt_note(&found) {
+                diag = diag.note(note);
+            }
+            self.diagnostics.push(diag);
+        }
+        value_ty
+    }
+
+    /// Checks the arguments of a call of a function taking `inputs`.
+    fn check_args(&mut self, span: Span, what: &str, inputs: &[Ty], args: &[ast::Expr]) {
+        if inputs.len() != args.len() {
+            self.error(
+                span,
+                format!(
+                    "this {} takes {} but {} {} supplied",
+                    what,
+                    plural(inputs.len(), "argument"),
+                    plural(args.len(), "argument"),
+                    if args.len() == 1 { "was" } else { "were" }
+                ),
+            );
+        }
+        for (i, arg) in args.iter().enumerate() {
+            self.check_expr_with(arg, inputs.get(i));
+        }
+    }
+
+    /// Checks `receiver.method(args)`. The receiver is passed as `self`.
+    fn check_method_call(
+        &mut self,
+        expr: &ast::Expr,
+        receiver: &ast::Expr,
+        ident: &ast::Ident,
+        args: &[ast::Expr],
+    ) -> Ty {
+        let receiver_ty = self.check_expr(receiver);
+        let receiver_ty = self.structurally_resolve(receiver.span, &receiver_ty);
+        let Some(method) = self.lookup_method(&receiver_ty, ident) else {
+            for arg in args {
+                self.check_expr(arg);
+            }
+            return Ty::Error;
+        };
+        self.results.path_res.insert(expr.id, Res::Fn(method));
+        let sig = self.tcx.fn_sig(method);
+        let fn_args = self.fresh_args(&sig.generics, ident.span, sig.name);
+        let inputs: Vec<Ty> = sig.inputs.iter().map(|ty| ty.subst(&fn_args)).collect();
+        let output = sig.output.subst(&fn_args);
+        if !fn_args.is_empty() {
+            self.results.node_args.insert(expr.id, fn_args);
+            self.written.push(expr.id);
+        }
+        // Values are passed by value, so the receiver is `self` itself.
+        self.demand_eq(receiver.span, &inputs[0], &receiver_ty);
+        self.check_args(ident.span, "method", &inputs[1..], args);
+        output
+    }
+
+    /// Finds the method a method call on a value of type `ty` calls. Methods
+    /// of inherent impls are preferred over those of traits.
+    fn lookup_method(&mut self, ty: &Ty, ident: &ast::Ident) -> Option<FnId> {
+        if *ty == Ty::Error {
+            return None;
+        }
+        let tcx = self.tcx;
+        let mut candidates: Vec<FnId> = Vec::new();
+        for imp in tcx.impls.iter().filter(|imp| imp.trait_ref.is_none()) {
+            if self.impl_may_apply(imp, ty) {
+                candidates.extend(
+                    imp.methods
+                        .iter()
+                        .copied()
+                        .filter(|&method| tcx.fn_sig(method).name == ident.name),
+                );
+            }
+        }
+        let mut unimplemented = Vec::new();
+        if candidates.is_empty() {
+            for i in 0..tcx.traits.len() {
+                let trait_id = TraitId::from_usize(i);
+                let Some(method) = tcx.trait_method(trait_id, ident.name) else {
+                    continue;
+                };
+                if self.may_implement(ty, trait_id) {
+                    candidates.push(method);
+                } else {
+                    unimplemented.push(trait_id);
+                }
+            }
+        }
+        match candidates[..] {
+            [method] if tcx.fn_sig(method).has_self => Some(method),
+            [_] => {
+                self.diagnostics.push(
+                    Diagnostic::error(ident.span, self.no_method_message(ty, ident)).note(format!(
+                        "`{}` is an associated function, not a method",
+                        ident.name
+                    )),
+                );
+                None
+            }
+            [] => {
+                let mut diag = Diagnostic::error(ident.span, self.no_method_message(ty, ident));
+                if let Some(&trait_id) = unimplemented.first() {
+                    diag = diag.help(format!(
+                        "the trait `{}` defines an item `{}`, perhaps you need to implement it",
+                        tcx.trait_def(trait_id).name,
+                        ident.name
+                    ));
+                }
+                self.diagnostics.push(diag);
+                None
+            }
+            _ => {
+                self.multiple_applicable_items(ident.span, &candidates);
+                None
+            }
+        }
+    }
+
+    /// Formats a type for a diagnostic, with what is inferred of it so far.
+    fn ty_to_string(&self, ty: &Ty) -> String {
+        self.tcx.ty_to_string(&self.infcx.resolve(ty))
+    }
+
+    fn no_method_message(&self, ty: &Ty, ident: &ast::Ident) -> String {
+        format!(
+            "no method named `{}` found for type `{}` in the current scope",
+            ident.name,
+            self.ty_to_string(ty)
+        )
+    }
+
+    fn multiple_applicable_items(&mut self, span: Span, candidates: &[FnId]) {
+        let candidates: Vec<String> = candidates
+            .iter()
+            .map(|&method| match self.tcx.fn_sig(method).container {
+                FnContainer::Trait(id) => format!("the trait `{}`", self.tcx.trait_def(id).name),
+                FnContainer::Impl(id) => format!(
+                    "an impl for `{}`",
+                    self.tcx.ty_to_string(&self.tcx.impl_def(id).self_ty)
+                ),
+                FnContainer::Free => unreachable!(),
+            })
+            .collect();
+        self.diagnostics.push(
+            Diagnostic::error(span, "multiple applicable items in scope").note(format!(
+                "candidates are defined in {}",
+                candidates.join(" and ")
+            )),
+        );
+    }
+
+    /// Whether the self type of an impl can be unified with `ty`.
+    fn impl_may_apply(&mut self, imp: &ImplDef, ty: &Ty) -> bool {
+        if imp.self_ty == Ty::Error {
+            return false;
+        }
+        self.infcx.probe(|infcx| {
+            let args: Vec<Ty> = imp
+                .generics
+                .params
+                .iter()
+                .map(|param| infcx.next_var(false, param.span))
+                .collect();
+            infcx.unify(&imp.self_ty.subst(&args), ty)
+        })
+    }
+
+    /// Whether `ty` may implement a trait, depending on what its inference
+    /// variables turn out to be.
+    fn may_implement(&mut self, ty: &Ty, trait_id: TraitId) -> bool {
+        match ty {
+            Ty::Param(_) | Ty::Dyn(_) => self.tcx.satisfies(ty, trait_id, self.generics),
+            ty if self.tcx.has_builtin_impl(trait_id, ty) => true,
+            _ => {
+                let tcx = self.tcx;
+                tcx.impls
+                    .iter()
+                    .filter(|imp| imp.trait_ref == Some(trait_id))
+                    .any(|imp| self.impl_may_apply(imp, ty))
+            }
+        }
+    }
+
+    fn check_match(
+        &mut self,
+        span: Span,
+        scrutinee: &ast::Expr,
+        arms: &[ast::Arm],
+        expected: Option<&Ty>,
+    ) -> Ty {
+        let scrutinee_ty = self.check_expr(scrutinee);
+        let mut ty = expected.cloned();
+        for arm in arms {
+            self.scopes.push(HashMap::new());
+            self.check_pat(&arm.pat, &scrutinee_ty);
+            if let Some(guard) = &arm.guard {
+                self.check_expr_with(guard, Some(&Ty::Bool));
+            }
+            let arm_ty = self.check_expr_with(&arm.body, ty.as_ref());
+            // The first arm decides the type of the others.
+            if ty.is_none() && arm_ty != Ty::Error {
+                ty = Some(arm_ty);
+            }
+            self.scopes.pop();
+        }
+        let scrutinee_ty = self.infcx.resolve(&scrutinee_ty);
+        let lint_unreachable = !self
+            .allowed_lints
+            .contains(&Symbol::intern("unreachable_patterns"));
+        usefulness::check_match(
+            self.tcx,
+            self.results,
+            span,
+            &scrutinee_ty,
+            arms,
+            lint_unreachable,
+            self.diagnostics,
+        );
+        ty.unwrap_or(Ty::Void)
+    }
+
+    /// Checks a pattern matching values of type `expected`, declaring the
+    /// variables it binds.
+    fn check_pat(&mut self, pat: &ast::Pat, expected: &Ty) {
+        let ok = self.check_pat_kind(pat, expected);
+        let ty = if ok { expected.clone() } else { Ty::Error };
+        self.write_ty(pat.id, ty);
+    }
+
+    fn check_pat_kind(&mut self, pat: &ast::Pat, expected: &Ty) -> bool {
+        match &pat.kind {
+            ast::PatKind::Wild => true,
+            ast::PatKind::Ident(mutbl, ident) => {
+                if self.scopes.last().unwrap().contains_key(&ident.name) {
+                    self.error(
+                        ident.span,
+                        format!(
+                            "identifier `{}` is bound more than once in the same pattern",
+                            ident.name
+                        ),
+                    );
+                }
+                self.declare(*mutbl, ident, pat.id, expected.clone());
+                true
+            }
+            ast::PatKind::Lit(expr) => {
+                if is_float_lit(expr) {
+                    self.error(
+                        expr.span,
+                        "floating-point literals cannot be used in patterns",
+                    );
+                    return false;
+                }
+                self.check_expr_with(expr, Some(expected)) != Ty::Error
+            }
+            ast::PatKind::Path(path) => {
+                let Some((adt, _)) = self.resolve_pat_path(pat, path, CtorKind::Const) else {
+                    return false;
+                };
+                let def = self.tcx.adt(adt);
+                let args = self.fresh_args(&def.generics, pat.span, def.name);
+                self.demand_eq(pat.span, expected, &Ty::Adt(adt, args))
+            }
+            ast::PatKind::Tuple(pats) => {
+                let tys = match self.infcx.shallow_resolve(expected) {
+                    Ty::Tuple(tys) if tys.len() == pats.len() => tys,
+                    Ty::Tuple(tys) => {
+                        self.diagnostics.push(
+                            Diagnostic::error(pat.span, "mismatched types").note(format!(
+                                "expected a tuple with {}, found one with {}",
+                                plural(tys.len(), "element"),
+                                plural(pats.len(), "element")
+                            )),
+                        );
+                        for pat in pats {
+                            self.check_pat(pat, &Ty::Error);
+                        }
+                        return false;
+                    }
+                    Ty::Error => vec![Ty::Error; pats.len()],
+                    _ => {
+                        let tys: Vec<Ty> = pats
+                            .iter()
+                            .map(|pat| self.infcx.next_var(false, pat.span))
+                            .collect();
+                        if !self.demand_eq(pat.span, expected, &Ty::Tuple(tys.clone())) {
+                            for pat in pats {
+                                self.check_pat(pat, &Ty::Error);
+                            }
+                            return false;
+                        }
+                        tys
+                    }
+                };
+                for (pat, ty) in pats.iter().zip(&tys) {
+                    self.check_pat(pat, ty);
+                }
+                true
+            }
+            ast::PatKind::TupleStruct(path, pats) => {
+                let Some((adt, index)) = self.resolve_pat_path(pat, path, CtorKind::Fn) else {
+                    for pat in pats {
+                        self.check_pat(pat, &Ty::Error);
+                    }
+                    return false;
+                };
+                let def = self.tcx.adt(adt);
+                let args = self.fresh_args(&def.generics, pat.span, def.name);
+                let mut ok = self.demand_eq(pat.span, expected, &Ty::Adt(adt, args.clone()));
+                let fields = &def.variants[index].fields;
+                if pats.len() != fields.len() {
+                    self.error(
+                        pat.span,
+                        format!(
+                            "this pattern has {}, but the corresponding tuple variant has {}",
+                            plural(pats.len(), "field"),
+                            plural(fields.len(), "field")
+                        ),
+                    );
+                    ok = false;
+                }
+                for (i, pat) in pats.iter().enumerate() {
+                    let ty = fields.get(i).map_or(Ty::Error, |f| f.ty.subst(&args));
+                    self.check_pat(pat, &ty);
+                }
+                ok
+            }
+            ast::PatKind::Struct(path, fields, rest) => {
+                let Some((adt, index)) = self.resolve_pat_path(pat, path, CtorKind::Struct) else {
+                    for field in fields {
+                        self.check_pat(&field.pat, &Ty::Error);
+                    }
+                    return false;
+                };
+                let def = self.tcx.adt(adt);
+                let args = self.fresh_args(&def.generics, pat.span, def.name);
+                let mut ok = self.demand_eq(pat.span, expected, &Ty::Adt(adt, args.clone()));
+                let variant = &def.variants[index];
+                let mut seen = vec![false; variant.fields.len()];
+                for field in fields {
+                    let Some(i) = variant.field_index(field.ident.name) else {
+                        let kind = if self.tcx.adt(adt).is_enum() {
+                            "variant"
+                        } else {
+                            "struct"
+                        };
+                        self.error(
+                            field.ident.span,
+                            format!(
+                                "{} `{}` does not have a field named `{}`",
+                                kind,
+                                path_to_string(path),
+                                field.ident.name
+                            ),
+                        );
+                        self.check_pat(&field.pat, &Ty::Error);
+                        ok = false;
+                        continue;
+                    };
+                    if seen[i] {
+                        self.error(
+                            field.ident.span,
+                            format!(
+                                "field `{}` bound multiple times in the pattern",
+                                field.ident.name
+                            ),
+                        );
+                        ok = false;
+                    }
+                    seen[i] = true;
+                    self.check_pat(&field.pat, &variant.fields[i].ty.subst(&args));
+                }
+                let missing = missing_fields(&variant.fields, &seen);
+                if !*rest && !missing.is_empty() {
+                    let s = if missing.contains(',') { "s" } else { "" };
+                    self.diagnostics.push(
+                        Diagnostic::error(
+                            pat.span,
+                            format!("pattern does not mention field{} {}", s, missing),
+                        )
+                        .help("ignore the remaining fields with `..`"),
+                    );
+                    ok = false;
+                }
+                ok
+            }
+            ast::PatKind::Or(alts) => {
+                // The first alternative declares the variables, the others
+                // must bind the same ones with the same types.
+                self.check_pat(&alts[0], expected);
+                let mut first = Vec::new();
+                bindings(&alts[0], &mut first);
+                let mut ok = true;
+                for alt in &alts[1..] {
+                    self.scopes.push(HashMap::new());
+                    self.check_pat(alt, expected);
+                    self.scopes.pop();
+                    let mut bound = Vec::new();
+                    bindings(alt, &mut bound);
+                    for (ident, id) in &bound {
+                        match first.iter().find(|(first, _)| first.name == ident.name) {
+                            Some((_, first_id)) => {
+                                if self.mutable.contains(id) != self.mutable.contains(first_id) {
+                                    self.error(
+                                        ident.span,
+                                        format!(
+                                            "variable `{}` is bound with different mutability in different patterns",
+                                            ident.name
+                                        ),
+                                    );
+                                    ok = false;
+                                }
+                                self.results.binding_aliases.insert(*id, *first_id);
+                                let expected = self.results.node_types[first_id].clone();
+                                let found = self.results.node_types[id].clone();
+                                ok &= self.demand_eq(ident.span, &expected, &found);
+                            }
+                            None => {
+                                self.error(
+                                    ident.span,
+                                    format!(
+                                        "variable `{}` is not bound in all patterns",
+                                        ident.name
+                                    ),
+                                );
+                                ok = false;
+                            }
+                        }
+                    }
+                    for (ident, _) in &first {
+                        if !bound.iter().any(|(bound, _)| bound.name == ident.name) {
+                            self.error(
+                                alt.span,
+                                format!("variable `{}` is not bound in all patterns", ident.name),
+                            );
+                            ok = false;
+                        }
+                    }
+                }
+                ok
+            }
+        }
+    }
+
+    /// Resolves the path of a pattern, which must be a struct or a variant
+    /// constructed the given way.
+    fn resolve_pat_path(
+        &mut self,
+        pat: &ast::Pat,
+        path: &ast::Path,
+        ctor_kind: CtorKind,
+    ) -> Option<(AdtId, usize)> {
+        let what = match ctor_kind {
+            CtorKind::Struct => "struct or struct variant",
+            CtorKind::Fn => "tuple variant",
+            CtorKind::Const => "unit variant",
+        };
+        let res = self.resolve_path(path, what, false)?;
+        match res {
+            Res::Variant(adt, index)
+                if self.tcx.adt(adt).variants[index].ctor_kind == ctor_kind =>
+            {
+                self.results.path_res.insert(pat.id, res);
+                Some((adt, index))
+            }
+            _ => {
+                let descr = self.describe_res(res);
+                self.error(
+                    path.span,
+                    format!(
+                        "expected {}, found {} `{}`",
+                        what,
+                        descr,
+                        path_to_string(path)
+                    ),
+                );
+                None
+            }
+        }
+    }
+
+    fn check_binary(
+        &mut self,
+        expr: &ast::Expr,
+        op: &ast::BinOp,
+        lhs: &ast::Expr,
+        rhs: &ast::Expr,
+    ) -> Ty {
+        // Check the operand which isn't a number literal without a suffix
+        // first, so that the type of `1 + x` is the type of `x`.
+        let is_untyped_lit = matches!(
+            lhs.kind,
+            ast::ExprKind::Literal(
+                ast::LiteralKind::Int(_, None) | ast::LiteralKind::Float(_, None)
+            )
+        );
+        let (lhs_ty, rhs_ty) = if is_untyped_lit {
+            let rhs_ty = self.check_expr(rhs);
+            let lhs_ty = self.check_expr_with(lhs, Some(&rhs_ty));
+            (lhs_ty, rhs_ty)
+        } else {
+            let lhs_ty = self.check_expr(lhs);
+            let rhs_ty = self.check_expr_with(rhs, Some(&lhs_ty));
+            (lhs_ty, rhs_ty)
+        };
+        self.check_binop(expr, op, lhs.span, &lhs_ty, &rhs_ty, false)
+    }
+
+    /// Checks that `op` applies to operands of types `lhs_ty` and `rhs_ty`
+    /// and returns the type of the result. `assign` is set for compound
+    /// assignments such as `a += b`.
+    fn check_binop(
+        &mut self,
+        expr: &ast::Expr,
+        op: &ast::BinOp,
+        lhs_span: Span,
+        lhs_ty: &Ty,
+        rhs_ty: &Ty,
+        assign: bool,
+    ) -> Ty {
+        let lhs_ty = self.structurally_resolve(lhs_span, lhs_ty);
+        let rhs_ty = self.infcx.resolve(rhs_ty);
+        if lhs_ty == Ty::Error || rhs_ty == Ty::Error || lhs_ty != rhs_ty {
+            // The mismatch, if any, has been reported already.
+            return if is_comparison(op) {
+                Ty::Bool
+            } else {
+                Ty::Error
+            };
+        }
+        let method = self.tcx.binop_method(*op);
+        if !self.check_operator(expr, method, &lhs_ty) {
+            let message = if assign {
+                format!(
+                    "binary assignment operation `{}=` cannot be applied to type `{}`",
+                    binop_to_str(op),
+                    self.ty_to_string(&lhs_ty)
+                )
+            } else {
+                format!(
+                    "binary operation `{}` cannot be applied to type `{}`",
+                    binop_to_str(op),
+                    self.ty_to_string(&lhs_ty)
+                )
+            };
+            let FnContainer::Trait(trait_id) = self.tcx.fn_sig(method).container else {
+                unreachable!()
+            };
+            self.operator_error(expr.span, message, &lhs_ty, trait_id);
+            return Ty::Error;
+        }
+        if is_comparison(op) {
+            Ty::Bool
+        } else {
+            lhs_ty
+        }
+    }
+
+    /// Checks that the operands of an operator, of type `ty`, implement the
+    /// trait of `method`. Unless they are primitive, the operator calls the
+    /// method, which is recorded like for a method call.
+    fn check_operator(&mut self, expr: &ast::Expr, method: FnId, ty: &Ty) -> bool {
+        let FnContainer::Trait(trait_id) = self.tcx.fn_sig(method).container else {
+            unreachable!("operators call methods of built-in traits")
+        };
+        if !self.tcx.satisfies(ty, trait_id, self.generics) {
+            return false;
+        }
+        // Primitive operands use the operators of the IR.
+        if matches!(
+            ty,
+            Ty::Adt(..) | Ty::Param(_) | Ty::Dyn(_) | Ty::Tuple(_) | Ty::Array(..) | Ty::Slice(_)
+        ) {
+            self.results.path_res.insert(expr.id, Res::Fn(method));
+            self.results.node_args.insert(expr.id, vec![ty.clone()]);
+            self.written.push(expr.id);
+        }
+        true
+    }
+
+    /// Reports an operator applied to a type which doesn't implement its
+    /// trait.
+    fn operator_error(&mut self, span: Span, message: String, ty: &Ty, trait_id: TraitId) {
+        let name = self.tcx.trait_def(trait_id).name;
+        let diag = Diagnostic::error(span, message);
+        let diag = match ty {
+            Ty::Param(param) => diag.help(format!(
+                "consider restricting type parameter `{}` with trait `{}`",
+                param.name, name
+            )),
+            Ty::Adt(..) => diag.note(format!(
+                "an implementation of `{}` might be missing for `{}`",
+                name,
+                self.tcx.ty_to_string(ty)
+            )),
+            _ => diag,
+        };
+        self.diagnostics.push(diag);
+    }
+
+    fn check_struct_expr(&mut self, expr: &ast::Expr, lit: &ast::StructExpr) -> Ty {
+        let res = self.resolve_path(&lit.path, "struct", false);
+        let variant = match res {
+            Some(Res::Variant(adt, index))
+                if self.tcx.adt(adt).variants[index].ctor_kind == CtorKind::Struct =>
+            {
+                self.results
+                    .path_res
+                    .insert(expr.id, Res::Variant(adt, index));
+                Some((adt, index))
+            }
+            Some(res) => {
+                let descr = self.describe_res(res);
+                self.error(
+                    lit.path.span,
+                    format!(
+                        "expected struct, found {} `{}`",
+                        descr,
+                        path_to_string(&lit.path)
+                    ),
+                );
+                None
+            }
+            None => None,
+        };
+        let Some((adt, index)) = variant else {
+            for field in &lit.fields {
+                self.check_expr(&field.expr);
+            }
+            return Ty::Error;
+        };
+
+        let name = path_to_string(&lit.path);
+        let def = self.tcx.adt(adt);
+        let args = self.fresh_args(&def.generics, expr.span, def.name);
+        let variant = &def.variants[index];
+        let mut seen = vec![false; variant.fields.len()];
+        for field in &lit.fields {
+            let Some(i) = variant.field_index(field.ident.name) else {
+                let kind = if self.tcx.adt(adt).is_enum() {
+                    "variant"
+                } else {
+                    "struct"
+                };
+                self.error(
+                    field.ident.span,
+                    format!(
+                        "{} `{}` has no field named `{}`",
+                        kind, name, field.ident.name
+                    ),
+                );
+                self.check_expr(&field.expr);
+                continue;
+            };
+            if seen[i] {
+                self.error(
+                    field.ident.span,
+                    format!("field `{}` specified more than once", field.ident.name),
+                );
+            }
+            seen[i] = true;
+            self.check_expr_with(&field.expr, Some(&variant.fields[i].ty.subst(&args)));
+        }
+        let missing = missing_fields(&variant.fields, &seen);
+        if !missing.is_empty() {
+            let s = if missing.contains(',') { "s" } else { "" };
+            self.error(
+                expr.span,
+                format!(
+                    "missing field{} {} in initializer of `{}`",
+                    s, missing, name
+                ),
+            );
+        }
+        Ty::Adt(adt, args)
+    }
+}
+
+/// Lists the fields which haven't been seen, e.g. "`x`, `y`".
+fn missing_fields(fields: &[crate::ty::FieldDef], seen: &[bool]) -> String {
+    let missing: Vec<String> = fields
+        .iter()
+        .zip(seen)
+        .filter(|(_, seen)| !**seen)
+        .map(|(field, _)| format!("`{}`", field.name))
+        .collect();
+    missing.join(", ")
+}
+
+/// Collects the variables bound by a pattern.
+fn bindings<'a>(pat: &'a ast::Pat, out: &mut Vec<(&'a ast::Ident, NodeId)>) {
+    match &pat.kind {
+        ast::PatKind::Ident(_, ident) => out.push((ident, pat.id)),
+        ast::PatKind::Wild | ast::PatKind::Lit(_) | ast::PatKind::Path(_) => (),
+        ast::PatKind::Tuple(pats) | ast::PatKind::TupleStruct(_, pats) => {
+            pats.iter().for_each(|pat| bindings(pat, out))
+        }
+        ast::PatKind::Struct(_, fields, _) => {
+            fields.iter().for_each(|field| bindings(&field.pat, out))
+        }
+        // Alternatives bind the same variables.
+        ast::PatKind::Or(alts) => bindings(&alts[0], out),
+    }
+}
+
+fn suffix_ty(suffix: ast::LitSuffix) -> Ty {
+    match suffix {
+        ast::LitSuffix::I8 => Ty::I8,
+        ast::LitSuffix::I16 => Ty::I16,
+        ast::LitSuffix::I32 => Ty::I32,
+        ast::LitSuffix::I64 => Ty::I64,
+        ast::LitSuffix::I128 => Ty::I128,
+        ast::LitSuffix::U8 => Ty::U8,
+        ast::LitSuffix::U16 => Ty::U16,
+        ast::LitSuffix::U32 => Ty::U32,
+        ast::LitSuffix::U64 => Ty::U64,
+        ast::LitSuffix::U128 => Ty::U128,
+        ast::LitSuffix::Usize => Ty::Usize,
+        ast::LitSuffix::F32 => Ty::F32,
+        ast::LitSuffix::F64 => Ty::F64,
+    }
+}
+
+fn is_float_lit(expr: &ast::Expr) -> bool {
+    match &expr.kind {
+        ast::ExprKind::Literal(ast::LiteralKind::Float(..)) => true,
+        ast::ExprKind::Unary(_, operand) => is_float_lit(operand),
+        _ => false,
+    }
+}
+
+/// Whether an expression denotes a memory location which can be assigned to.
+fn is_place_expr(expr: &ast::Expr, results: &TypeckResults) -> bool {
+    match &expr.kind {
+        // Paths which failed to resolve have been reported already.
+        ast::ExprKind::Path(_) => !matches!(
+            results.path_res.get(&expr.id),
+            Some(Res::Fn(_) | Res::Variant(..))
+        ),
+        ast::ExprKind::Err => true,
+        // A slice of an array is a new value.
+        ast::ExprKind::Index(_, index) if matches!(index.kind, ast::ExprKind::Range(..)) => false,
+        ast::ExprKind::Field(base, _) | ast::ExprKind::Index(base, _) => {
+            is_place_expr(base, results)
+        }
+        _ => false,
+    }
+}
+
+fn is_comparison(op: &ast::BinOp) -> bool {
+    matches!(
+        op,
+        ast::BinOp::Eq
+            | ast::BinOp::Ne
+            | ast::BinOp::Lt
+            | ast::BinOp::Gt
+            | ast::BinOp::Le
+            | ast::BinOp::Ge
+    )
+}
+
+fn binop_to_str(op: &ast::BinOp) -> &'static str {
+    match op {
+        ast::BinOp::Add => "+",
+        ast::BinOp::Sub => "-",
+        ast::BinOp::Mul => "*",
+        ast::BinOp::Div => "/",
+        ast::BinOp::Rem => "%",
+        ast::BinOp::BitAnd => "&",
+        ast::BinOp::BitOr => "|",
+        ast::BinOp::BitXor => "^",
+        ast::BinOp::Shl => "<<",
+        ast::BinOp::Shr => ">>",
+        ast::BinOp::Eq => "==",
+        ast::BinOp::Ne => "!=",
+        ast::BinOp::Lt => "<",
+        ast::BinOp::Gt => ">",
+        ast::BinOp::Le => "<=",
+        ast::BinOp::Ge => ">=",
+    }
+}
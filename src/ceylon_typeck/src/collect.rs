@@ -0,0 +1,913 @@
+//! Collects the items of a module: struct, enum and trait definitions, impl
+//! blocks, function signatures and layouts.
+
+use std::collections::HashMap;
+
+use super::*;
+use crate::resolve::{ModId, ModuleDef, PathRes};
+use crate::ty::{
+    AdtKind, CtorKind, FieldDef, FnContainer, GenericParamDef, ImplDef, ImplId, ParamTy, TraitDef,
+    TraitId, VariantDef,
+};
+use ceylon_span::Span;
+
+/// Collects the items of a module and of its submodules, returning them with
+/// the module each of them is in.
+pub(crate) fn collect_items<'ast>(
+    module: &'ast ast::Module,
+    diagnostics: &mut Vec<Diagnostic>,
+) -> (Tcx, Vec<(ModId, &'ast ast::Item)>) {
+    let mut fns = Vec::new();
+    let mut traits = Vec::new();
+    let mut adts = Vec::new();
+    let lang = LangItems::define(&mut traits, &mut fns, &mut adts);
+    let mut tcx = Tcx {
+        adts,
+        fns,
+        traits,
+        impls: Vec::new(),
+        lang,
+        modules: vec![ModuleDef {
+            name: Symbol::intern("crate"),
+            parent: None,
+            package: ModId::ROOT,
+            allowed_lints: Vec::new(),
+            bindings: HashMap::new(),
+        }],
+        item_defs: HashMap::new(),
+        deprecations: HashMap::new(),
+        allowed_spans: resolve::collect_allowed_spans(module),
+        tests: Vec::new(),
+        recursive_adts: Vec::new(),
+        typeck_results: TypeckResults::default(),
+    };
+
+    // Names are collected first so that items can refer to items defined
+    // after them, or in other modules.
+    let mut items = Vec::new();
+    resolve::collect_module(&mut tcx, ModId::ROOT, module, &mut items, diagnostics);
+    resolve::resolve_imports(&mut tcx, &items, diagnostics);
+
+    // Then the generics, whose bounds may name any trait.
+    for &(scope, item) in &items {
+        let (generics, def) = match &item.kind {
+            ast::ItemKind::Fn(decl) => (&decl.generics, tcx.item_def(item.id)),
+            ast::ItemKind::Struct(def) => (&def.generics, tcx.item_def(item.id)),
+            ast::ItemKind::Enum(def) => (&def.generics, tcx.item_def(item.id)),
+            ast::ItemKind::Impl(def) => (&def.generics, tcx.item_def(item.id)),
+            ast::ItemKind::Trait(_) | ast::ItemKind::Mod(_) | ast::ItemKind::Import(_) => continue,
+        };
+        let generics = tcx.lower_generics(scope, generics, diagnostics);
+        match def {
+            Def::Fn(id) => tcx.fns[id.as_usize()].generics = generics,
+            Def::Adt(id) => tcx.adts[id.as_usize()].generics = generics,
+            Def::Impl(id) => tcx.impls[id.as_usize()].generics = generics,
+            Def::Trait(_) | Def::Mod(_) => unreachable!(),
+        }
+    }
+
+    // Then the headers of impls, which bounds of types in signatures are
+    // checked against.
+    for &(scope, item) in &items {
+        if let ast::ItemKind::Impl(def) = &item.kind {
+            let Def::Impl(id) = tcx.item_def(item.id) else {
+                unreachable!()
+            };
+            tcx.collect_impl_header(scope, id, def, diagnostics);
+        }
+    }
+    for &(_, item) in &items {
+        if let ast::ItemKind::Impl(def) = &item.kind {
+            let imp = tcx.impl_def(tcx.impl_id(item.id));
+            tcx.check_ty_bounds(&def.self_ty, &imp.self_ty, &imp.generics, diagnostics);
+        }
+    }
+
+    for &(scope, item) in &items {
+        if let ast::ItemKind::Mod(_) | ast::ItemKind::Import(_) = item.kind {
+            continue;
+        }
+        match (&item.kind, tcx.item_def(item.id)) {
+            (ast::ItemKind::Fn(decl), Def::Fn(id)) => {
+                if let Some(self_param) = &decl.self_param {
+                    diagnostics.push(Diagnostic::error(
+                        self_param.span,
+                        "`self` parameter is only allowed in associated functions",
+                    ));
+                }
+                if decl.body.is_none() {
+                    diagnostics.push(Diagnostic::error(item.span, "free function without a body"));
+                }
+                tcx.collect_fn_sig(scope, id, decl, None, diagnostics);
+                if attr::find_attr(&item.attrs, "test").is_some() {
+                    if !decl.generics.params.is_empty() {
+                        diagnostics.push(Diagnostic::error(
+                            decl.generics.span,
+                            "test functions must not be generic",
+                        ));
+                    } else if !decl.params.is_empty() {
+                        diagnostics.push(Diagnostic::error(
+                            item.ident.span,
+                            "test functions must not take arguments",
+                        ));
+                    } else {
+                        tcx.tests.push((scope, id));
+                    }
+                }
+            }
+            (ast::ItemKind::Struct(def), Def::Adt(id)) => {
+                let generics = &tcx.adts[id.as_usize()].generics;
+                let variant = VariantDef {
+                    name: item.ident.name,
+                    ctor_kind: CtorKind::Struct,
+                    fields: tcx.lower_field_defs(scope, &def.fields, generics, diagnostics),
+                    span: item.span,
+                };
+                tcx.adts[id.as_usize()].variants = vec![variant];
+            }
+            (ast::ItemKind::Enum(def), Def::Adt(id)) => {
+                let generics = &tcx.adts[id.as_usize()].generics;
+                let mut variants: Vec<VariantDef> = Vec::new();
+                for variant in &def.variants {
+                    if variants.iter().any(|v| v.name == variant.ident.name) {
+                        diagnostics.push(Diagnostic::error(
+                            variant.ident.span,
+                            format!(
+                                "the name `{}` is defined multiple times",
+                                variant.ident.name
+                            ),
+                        ));
+                        continue;
+                    }
+                    let (ctor_kind, fields) = match &variant.data {
+                        ast::VariantData::Struct(fields) => {
+                            let fields = tcx.lower_field_defs(scope, fields, generics, diagnostics);
+                            (CtorKind::Struct, fields)
+                        }
+                        ast::VariantData::Tuple(tys) => {
+                            let fields = tys
+                                .iter()
+                                .enumerate()
+                                .map(|(i, ty)| FieldDef {
+                                    name: Symbol::intern(&i.to_string()),
+                                    ty: tcx.lower_ty(scope, ty, generics, diagnostics),
+                                    span: ty.span,
+                                })
+                                .collect();
+                            (CtorKind::Fn, fields)
+                        }
+                        ast::VariantData::Unit => (CtorKind::Const, Vec::new()),
+                    };
+                    variants.push(VariantDef {
+                        name: variant.ident.name,
+                        ctor_kind,
+                        fields,
+                        span: variant.span,
+                    });
+                }
+                tcx.adts[id.as_usize()].variants = variants;
+            }
+            (ast::ItemKind::Trait(def), Def::Trait(id)) => {
+                let methods =
+                    tcx.collect_methods(scope, FnContainer::Trait(id), &def.items, diagnostics);
+                tcx.traits[id.as_usize()].methods = methods;
+            }
+            (ast::ItemKind::Impl(def), Def::Impl(id)) => {
+                let methods =
+                    tcx.collect_methods(scope, FnContainer::Impl(id), &def.items, diagnostics);
+                tcx.impls[id.as_usize()].methods = methods;
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    for &(_, item) in &items {
+        if let ast::ItemKind::Impl(def) = &item.kind {
+            tcx.check_impl_items(tcx.impl_id(item.id), def, diagnostics);
+        }
+    }
+    traits::check_coherence(&tcx, diagnostics);
+    traits::check_dyn_tys(&tcx, module, diagnostics);
+    tcx.recursive_adts = layout::find_recursive_adts(&tcx, diagnostics);
+    layout::check_adt_sizes(&tcx, diagnostics);
+    (tcx, items)
+}
+
+impl Tcx {
+    /// Creates the definition of an item, to be filled in by the passes
+    /// after the names are known.
+    pub(crate) fn create_def(&mut self, item: &ast::Item) -> Def {
+        match &item.kind {
+            ast::ItemKind::Fn(_) => {
+                self.fns.push(FnSig {
+                    name: item.ident.name,
+                    container: FnContainer::Free,
+                    generics: Generics::default(),
+                    inputs: Vec::new(),
+                    output: Ty::Void,
+                    has_self: false,
+                    has_body: true,
+                    inline: attr::find_attr(&item.attrs, "inline").is_some(),
+                    span: item.span,
+                });
+                Def::Fn(FnId::from_usize(self.fns.len() - 1))
+            }
+            ast::ItemKind::Struct(_) | ast::ItemKind::Enum(_) => {
+                let kind = match &item.kind {
+                    ast::ItemKind::Enum(_) => AdtKind::Enum,
+                    _ => AdtKind::Struct,
+                };
+                self.adts.push(AdtDef {
+                    name: item.ident.name,
+                    kind,
+                    generics: Generics::default(),
+                    variants: Vec::new(),
+                    span: item.span,
+                });
+                Def::Adt(AdtId::from_usize(self.adts.len() - 1))
+            }
+            ast::ItemKind::Trait(_) => {
+                self.traits.push(TraitDef {
+                    name: item.ident.name,
+                    methods: Vec::new(),
+                    span: item.span,
+                });
+                Def::Trait(TraitId::from_usize(self.traits.len() - 1))
+            }
+            ast::ItemKind::Impl(_) => {
+                self.impls.push(ImplDef {
+                    generics: Generics::default(),
+                    trait_ref: None,
+                    self_ty: Ty::Error,
+                    methods: Vec::new(),
+                    span: item.span,
+                });
+                Def::Impl(ImplId::from_usize(self.impls.len() - 1))
+            }
+            ast::ItemKind::Mod(_) | ast::ItemKind::Import(_) => {
+                unreachable!("modules and imports are defined by the resolver")
+            }
+        }
+    }
+
+    fn impl_id(&self, item: NodeId) -> ImplId {
+        let Def::Impl(id) = self.item_def(item) else {
+            unreachable!()
+        };
+        id
+    }
+
+    /// Resolves the trait and the self type of an impl.
+    fn collect_impl_header(
+        &mut self,
+        scope: ModId,
+        id: ImplId,
+        def: &ast::ImplDef,
+        diagnostics: &mut Vec<Diagnostic>,
+    ) {
+        let trait_ref = def
+            .of_trait
+            .as_ref()
+            .and_then(|path| self.resolve_trait(scope, path, diagnostics));
+        let generics = &self.impls[id.as_usize()].generics;
+        let self_ty = self.lower_ty_unchecked(scope, &def.self_ty, generics, diagnostics);
+        if def.of_trait.is_none() {
+            let kind = match self_ty {
+                Ty::Param(_) => Some("a type parameter"),
+                Ty::Adt(..) | Ty::Dyn(_) | Ty::Error => None,
+                _ => Some("primitive types"),
+            };
+            if let Some(kind) = kind {
+                diagnostics.push(
+                    Diagnostic::error(
+                        def.self_ty.span,
+                        format!("cannot define inherent `impl` for {}", kind),
+                    )
+                    .help("define a trait and implement it for the type instead"),
+                );
+            }
+        }
+        // The operators of primitive types are built in, and so is the
+        // length of arrays and slices.
+        let builtin = trait_ref.is_some_and(|id| {
+            self.lang.is_lang_trait(id)
+                && (self_ty.is_primitive() || self.has_builtin_impl(id, &self_ty))
+        });
+        if builtin {
+            diagnostics.push(
+                Diagnostic::error(
+                    def.self_ty.span,
+                    "only traits defined in the current module can be implemented for primitive types",
+                )
+                .note(format!(
+                    "`{}` implements the built-in traits already",
+                    self.ty_to_string(&self_ty)
+                )),
+            );
+        }
+        // Every parameter must be determined by the self type, so that the
+        // arguments of an impl can be found from the type using it.
+        for (i, param) in generics.params.iter().enumerate() {
+            let mut used = false;
+            self_ty.walk(&mut |ty| used |= matches!(ty, Ty::Param(p) if p.index as usize == i));
+            if !used && self_ty != Ty::Error {
+                diagnostics.push(Diagnostic::error(
+                    param.span,
+                    format!(
+                        "the type parameter `{}` is not constrained by the impl self type",
+                        param.name
+                    ),
+                ));
+            }
+        }
+        let imp = &mut self.impls[id.as_usize()];
+        imp.trait_ref = trait_ref;
+        imp.generics.self_ty = Some(self_ty.clone());
+        imp.self_ty = self_ty;
+    }
+
+    /// Resolves the name of a trait, e.g. in a bound.
+    fn resolve_trait(
+        &self,
+        scope: ModId,
+        path: &ast::Path,
+        diagnostics: &mut Vec<Diagnostic>,
+    ) -> Option<TraitId> {
+        match self.resolve_path_prefix(scope, path, diagnostics) {
+            PathRes::Def(Def::Trait(id), []) => Some(id),
+            PathRes::Def(def, []) => {
+                diagnostics.push(Diagnostic::error(
+                    path.span,
+                    format!(
+                        "expected trait, found {} `{}`",
+                        self.describe_def(def),
+                        path_to_string(path)
+                    ),
+                ));
+                None
+            }
+            PathRes::Def(..) | PathRes::Undeclared => {
+                let mut diag = Diagnostic::error(
+                    path.span,
+                    format!("cannot find trait `{}` in this scope", path_to_string(path)),
+                );
+                if let [ident] = &path.segments[..] {
+                    if let Some(help) = self.import_suggestion(scope, ident.name) {
+                        diag = diag.help(help);
+                    }
+                }
+                diagnostics.push(diag);
+                None
+            }
+            PathRes::Failed => None,
+        }
+    }
+
+    /// Describes what an item is for error messages, e.g. "struct".
+    pub(crate) fn describe_def(&self, def: Def) -> &'static str {
+        match def {
+            Def::Adt(id) if self.adt(id).is_enum() => "enum",
+            Def::Adt(_) => "struct",
+            Def::Fn(_) => "function",
+            Def::Trait(_) => "trait",
+            Def::Impl(_) => "impl",
+            Def::Mod(_) => "module",
+        }
+    }
+
+    /// Collects the methods of a trait or an impl.
+    fn collect_methods(
+        &mut self,
+        scope: ModId,
+        container: FnContainer,
+        items: &[ast::Item],
+        diagnostics: &mut Vec<Diagnostic>,
+    ) -> Vec<FnId> {
+        let mut methods: Vec<FnId> = Vec::new();
+        for item in items {
+            let ast::ItemKind::Fn(decl) = &item.kind else {
+                // The parser reports these.
+                continue;
+            };
+            if methods
+                .iter()
+                .any(|&id| self.fn_sig(id).name == item.ident.name)
+            {
+                diagnostics.push(Diagnostic::error(
+                    item.ident.span,
+                    format!("duplicate definitions with name `{}`", item.ident.name),
+                ));
+            }
+            if decl.body.is_none() && matches!(container, FnContainer::Impl(_)) {
+                diagnostics.push(Diagnostic::error(
+                    item.span,
+                    "associated function in `impl` without body",
+                ));
+            }
+            let mut generics = match container {
+                FnContainer::Trait(id) => {
+                    let param = Ty::Param(ParamTy {
+                        index: 0,
+                        name: Symbol::intern("Self"),
+                    });
+                    Generics {
+                        params: vec![GenericParamDef {
+                            name: Symbol::intern("Self"),
+                            bounds: vec![id],
+                            span: self.trait_def(id).span,
+                        }],
+                        self_ty: Some(param),
+                    }
+                }
+                FnContainer::Impl(id) => {
+                    let imp = self.impl_def(id);
+                    Generics {
+                        params: imp.generics.params.clone(),
+                        self_ty: imp.generics.self_ty.clone(),
+                    }
+                }
+                FnContainer::Free => unreachable!(),
+            };
+            let own = self.lower_generics(scope, &decl.generics, diagnostics);
+            generics.params.extend(own.params);
+            self.fns.push(FnSig {
+                name: item.ident.name,
+                container,
+                generics,
+                inputs: Vec::new(),
+                output: Ty::Void,
+                has_self: decl.self_param.is_some(),
+                has_body: decl.body.is_some(),
+                inline: attr::find_attr(&item.attrs, "inline").is_some(),
+                span: item.span,
+            });
+            let id = FnId::from_usize(self.fns.len() - 1);
+            self.item_defs.insert(item.id, Def::Fn(id));
+            let self_ty = self.fns[id.as_usize()].generics.self_ty.clone();
+            self.collect_fn_sig(scope, id, decl, self_ty, diagnostics);
+            methods.push(id);
+        }
+        methods
+    }
+
+    /// Lowers the parameter and return types of a function. `self_ty` is the
+    /// type of `self` for methods.
+    fn collect_fn_sig(
+        &mut self,
+        scope: ModId,
+        id: FnId,
+        decl: &ast::FnDecl,
+        self_ty: Option<Ty>,
+        diagnostics: &mut Vec<Diagnostic>,
+    ) {
+        let generics = &self.fns[id.as_usize()].generics;
+        let receiver = decl.self_param.as_ref().and(self_ty);
+        let inputs = receiver
+            .into_iter()
+            .chain(
+                decl.params
+                    .iter()
+                    .map(|param| self.lower_ty(scope, &param.ty, generics, diagnostics)),
+            )
+            .collect();
+        let output = match &decl.ret_ty {
+            Some(ty) => self.lower_ty(scope, ty, generics, diagnostics),
+            None => Ty::Void,
+        };
+        let sig = &mut self.fns[id.as_usize()];
+        sig.inputs = inputs;
+        sig.output = output;
+    }
+
+    /// Checks that an impl of a trait defines the methods of the trait with
+    /// the right signatures.
+    fn check_impl_items(&self, id: ImplId, def: &ast::ImplDef, diagnostics: &mut Vec<Diagnostic>) {
+        let imp = self.impl_def(id);
+        let Some(trait_id) = imp.trait_ref else {
+            return;
+        };
+        let trait_def = self.trait_def(trait_id);
+        for (&method, item) in imp.methods.iter().zip(&def.items) {
+            let sig = self.fn_sig(method);
+            let Some(trait_method) = self.trait_method(trait_id, sig.name) else {
+                diagnostics.push(Diagnostic::error(
+                    item.ident.span,
+                    format!(
+                        "method `{}` is not a member of trait `{}`",
+                        sig.name, trait_def.name
+                    ),
+                ));
+                continue;
+            };
+            // The trait's method with `Self` replaced by the self type of
+            // the impl, and its own parameters by those of the impl's method.
+            let parent = imp.generics.params.len();
+            let trait_sig = self.fn_sig(trait_method);
+            let mut args = vec![imp.self_ty.clone()];
+            args.extend(sig.generics.identity_args().into_iter().skip(parent));
+            let expected_inputs: Vec<Ty> =
+                trait_sig.inputs.iter().map(|ty| ty.subst(&args)).collect();
+            let expected_output = trait_sig.output.subst(&args);
+            let compatible = args.len() == trait_sig.generics.params.len()
+                && sig.has_self == trait_sig.has_self
+                && sig.inputs == expected_inputs
+                && sig.output == expected_output;
+            if !compatible {
+                diagnostics.push(
+                    Diagnostic::error(
+                        item.ident.span,
+                        format!(
+                            "method `{}` has an incompatible type for trait `{}`",
+                            sig.name, trait_def.name
+                        ),
+                    )
+                    .note(format!(
+                        "expected `{}`, found `{}`",
+                        self.fn_ty_to_string(&expected_inputs, &expected_output),
+                        self.fn_ty_to_string(&sig.inputs, &sig.output)
+                    )),
+                );
+            }
+        }
+        if trait_id == self.lang.ord && !self.satisfies(&imp.self_ty, self.lang.eq, &imp.generics) {
+            diagnostics.push(self.unsatisfied_bound(
+                def.self_ty.span,
+                &imp.self_ty,
+                self.lang.eq,
+                "required by the trait `Ord`".to_string(),
+            ));
+        }
+        let missing: Vec<String> = trait_def
+            .methods
+            .iter()
+            .map(|&method| self.fn_sig(method))
+            .filter(|method| !method.has_body)
+            .filter(|method| {
+                !imp.methods
+                    .iter()
+                    .any(|&id| self.fn_sig(id).name == method.name)
+            })
+            .map(|method| format!("`{}`", method.name))
+            .collect();
+        if !missing.is_empty() {
+            diagnostics.push(Diagnostic::error(
+                def.self_ty.span,
+                format!(
+                    "not all trait items implemented, missing: {}",
+                    missing.join(", ")
+                ),
+            ));
+        }
+    }
+
+    /// Formats the type of a function, e.g. `fn(Circle, i64) -> f64`.
+    fn fn_ty_to_string(&self, inputs: &[Ty], output: &Ty) -> String {
+        let inputs: Vec<String> = inputs.iter().map(|ty| self.ty_to_string(ty)).collect();
+        match output {
+            Ty::Void => format!("fn({})", inputs.join(", ")),
+            output => format!("fn({}) -> {}", inputs.join(", "), self.ty_to_string(output)),
+        }
+    }
+
+    fn lower_field_defs(
+        &self,
+        scope: ModId,
+        fields: &[ast::FieldDef],
+        generics: &Generics,
+        diagnostics: &mut Vec<Diagnostic>,
+    ) -> Vec<FieldDef> {
+        let mut lowered: Vec<FieldDef> = Vec::new();
+        for field in fields {
+            if lowered.iter().any(|f| f.name == field.ident.name) {
+                diagnostics.push(Diagnostic::error(
+                    field.ident.span,
+                    format!("field `{}` is already declared", field.ident.name),
+                ));
+                continue;
+            }
+            lowered.push(FieldDef {
+                name: field.ident.name,
+                ty: self.lower_ty(scope, &field.ty, generics, diagnostics),
+                span: field.span,
+            });
+        }
+        lowered
+    }
+
+    /// Resolves a type written in the source, in module `scope` and an item
+    /// declaring the type parameters `generics`, and checks the bounds of its
+    /// generic arguments.
+    pub(crate) fn lower_ty(
+        &self,
+        scope: ModId,
+        ty: &ast::Ty,
+        generics: &Generics,
+        diagnostics: &mut Vec<Diagnostic>,
+    ) -> Ty {
+        let lowered = self.lower_ty_unchecked(scope, ty, generics, diagnostics);
+        self.check_ty_bounds(ty, &lowered, generics, diagnostics);
+        lowered
+    }
+
+    /// Resolves a type without checking bounds, which needs the impls.
+    fn lower_ty_unchecked(
+        &self,
+        scope: ModId,
+        ty: &ast::Ty,
+        generics: &Generics,
+        diagnostics: &mut Vec<Diagnostic>,
+    ) -> Ty {
+        match &ty.kind {
+            ast::TyKind::I8 => Ty::I8,
+            ast::TyKind::I16 => Ty::I16,
+            ast::TyKind::I32 => Ty::I32,
+            ast::TyKind::I64 => Ty::I64,
+            ast::TyKind::I128 => Ty::I128,
+            ast::TyKind::U8 => Ty::U8,
+            ast::TyKind::U16 => Ty::U16,
+            ast::TyKind::U32 => Ty::U32,
+            ast::TyKind::U64 => Ty::U64,
+            ast::TyKind::U128 => Ty::U128,
+            ast::TyKind::Usize => Ty::Usize,
+            ast::TyKind::F32 => Ty::F32,
+            ast::TyKind::F64 => Ty::F64,
+            ast::TyKind::Bool => Ty::Bool,
+            ast::TyKind::Char => Ty::Char,
+            ast::TyKind::Str => Ty::Str,
+            ast::TyKind::Void => Ty::Void,
+            ast::TyKind::Dyn(path) => match self.resolve_trait(scope, path, diagnostics) {
+                Some(id) => Ty::Dyn(id),
+                None => Ty::Error,
+            },
+            ast::TyKind::Fn(inputs, output) => Ty::Fn(
+                inputs
+                    .iter()
+                    .map(|ty| self.lower_ty_unchecked(scope, ty, generics, diagnostics))
+                    .collect(),
+                Box::new(match output {
+                    Some(output) => self.lower_ty_unchecked(scope, output, generics, diagnostics),
+                    None => Ty::Void,
+                }),
+            ),
+            ast::TyKind::Tuple(tys) => Ty::Tuple(
+                tys.iter()
+                    .map(|ty| self.lower_ty_unchecked(scope, ty, generics, diagnostics))
+                    .collect(),
+            ),
+            ast::TyKind::Array(ty, len) => Ty::Array(
+                Box::new(self.lower_ty_unchecked(scope, ty, generics, diagnostics)),
+                *len,
+            ),
+            ast::TyKind::Slice(ty) => Ty::Slice(Box::new(self.lower_ty_unchecked(
+                scope,
+                ty,
+                generics,
+                diagnostics,
+            ))),
+            ast::TyKind::Path(path, args) => {
+                if let [ident] = &path.segments[..] {
+                    let param = generics
+                        .params
+                        .iter()
+                        .position(|param| param.name == ident.name)
+                        .map(|index| {
+                            Ty::Param(ParamTy {
+                                index: index as u32,
+                                name: ident.name,
+                            })
+                        });
+                    let self_ty = generics
+                        .self_ty
+                        .clone()
+                        .filter(|_| ident.name.as_str() == "Self");
+                    if let Some(ty) = self_ty.or(param) {
+                        if !args.is_empty() {
+                            diagnostics.push(Diagnostic::error(
+                                args[0].span,
+                                format!(
+                                    "type arguments are not allowed on type parameter `{}`",
+                                    ident.name
+                                ),
+                            ));
+                            return Ty::Error;
+                        }
+                        return ty;
+                    }
+                }
+                match self.resolve_path_prefix(scope, path, diagnostics) {
+                    PathRes::Def(Def::Adt(id), []) => {
+                        self.lower_adt_ty(scope, ty, id, args, generics, diagnostics)
+                    }
+                    PathRes::Def(Def::Trait(_), []) => {
+                        let span = path.span;
+                        let path = path_to_string(path);
+                        diagnostics.push(
+                            Diagnostic::error(
+                                span,
+                                format!("expected type, found trait `{}`", path),
+                            )
+                            .help(format!(
+                                "use `dyn {}` for a value of any type implementing the trait",
+                                path
+                            )),
+                        );
+                        Ty::Error
+                    }
+                    PathRes::Def(def @ (Def::Fn(_) | Def::Mod(_)), []) => {
+                        diagnostics.push(Diagnostic::error(
+                            path.span,
+                            format!(
+                                "expected type, found {} `{}`",
+                                self.describe_def(def),
+                                path_to_string(path)
+                            ),
+                        ));
+                        Ty::Error
+                    }
+                    PathRes::Def(Def::Impl(_), []) => unreachable!("impls have no name"),
+                    PathRes::Def(..) | PathRes::Undeclared => {
+                        let mut diag = Diagnostic::error(
+                            path.span,
+                            format!("cannot find type `{}` in this scope", path_to_string(path)),
+                        );
+                        if let [ident] = &path.segments[..] {
+                            if let Some(help) = self.import_suggestion(scope, ident.name) {
+                                diag = diag.help(help);
+                            }
+                        }
+                        diagnostics.push(diag);
+                        Ty::Error
+                    }
+                    PathRes::Failed => Ty::Error,
+                }
+            }
+        }
+    }
+
+    /// Resolves a struct or an enum with generic arguments, e.g.
+    /// `Pair<i64, T>`.
+    fn lower_adt_ty(
+        &self,
+        scope: ModId,
+        ty: &ast::Ty,
+        id: AdtId,
+        args: &[ast::Ty],
+        generics: &Generics,
+        diagnostics: &mut Vec<Diagnostic>,
+    ) -> Ty {
+        let adt = self.adt(id);
+        let kind = if adt.is_enum() { "enum" } else { "struct" };
+        let expected = adt.generics.params.len();
+        if args.is_empty() && expected > 0 {
+            diagnostics.push(
+                Diagnostic::error(
+                    ty.span,
+                    format!("missing generics for {} `{}`", kind, adt.name),
+                )
+                .help(format!(
+                    "add the missing generic arguments, e.g. `{}<{}>`",
+                    adt.name,
+                    vec!["_"; expected].join(", ")
+                )),
+            );
+            return Ty::Error;
+        }
+        if args.len() != expected {
+            let span = args.first().map_or(ty.span, |arg| arg.span);
+            diagnostics.push(Diagnostic::error(
+                span,
+                format!(
+                    "{} takes {} but {} {} supplied",
+                    kind,
+                    plural(expected, "generic argument"),
+                    plural(args.len(), "generic argument"),
+                    if args.len() == 1 { "was" } else { "were" }
+                ),
+            ));
+            return Ty::Error;
+        }
+        let args: Vec<Ty> = args
+            .iter()
+            .map(|arg| self.lower_ty_unchecked(scope, arg, generics, diagnostics))
+            .collect();
+        Ty::Adt(id, args)
+    }
+
+    /// Checks that the generic arguments in a type satisfy the bounds of
+    /// the ADTs they are passed to. `lowered` is `ty` resolved.
+    fn check_ty_bounds(
+        &self,
+        ty: &ast::Ty,
+        lowered: &Ty,
+        generics: &Generics,
+        diagnostics: &mut Vec<Diagnostic>,
+    ) {
+        let (ast_args, id, args) = match (&ty.kind, lowered) {
+            (ast::TyKind::Fn(ast_inputs, ast_output), Ty::Fn(inputs, output)) => {
+                for (ast_input, input) in ast_inputs.iter().zip(inputs) {
+                    self.check_ty_bounds(ast_input, input, generics, diagnostics);
+                }
+                if let Some(ast_output) = ast_output {
+                    self.check_ty_bounds(ast_output, output, generics, diagnostics);
+                }
+                return;
+            }
+            (ast::TyKind::Tuple(ast_tys), Ty::Tuple(tys)) => {
+                for (ast_ty, ty) in ast_tys.iter().zip(tys) {
+                    self.check_ty_bounds(ast_ty, ty, generics, diagnostics);
+                }
+                return;
+            }
+            (ast::TyKind::Array(ast_ty, _), Ty::Array(ty, _))
+            | (ast::TyKind::Slice(ast_ty), Ty::Slice(ty)) => {
+                return self.check_ty_bounds(ast_ty, ty, generics, diagnostics);
+            }
+            (ast::TyKind::Path(_, ast_args), Ty::Adt(id, args)) => (ast_args, id, args),
+            _ => return,
+        };
+        let adt = self.adt(*id);
+        for ((ast_arg, arg), param) in ast_args.iter().zip(args).zip(&adt.generics.params) {
+            for &bound in &param.bounds {
+                if !self.satisfies(arg, bound, generics) {
+                    diagnostics.push(self.unsatisfied_bound(
+                        ast_arg.span,
+                        arg,
+                        bound,
+                        format!("required by a bound in `{}`", adt.name),
+                    ));
+                }
+            }
+            self.check_ty_bounds(ast_arg, arg, generics, diagnostics);
+        }
+    }
+
+    /// Reports that `ty` lacks a bound, with a note explaining why it is
+    /// required.
+    pub(crate) fn unsatisfied_bound(
+        &self,
+        span: Span,
+        ty: &Ty,
+        bound: TraitId,
+        note: String,
+    ) -> Diagnostic {
+        Diagnostic::error(
+            span,
+            format!(
+                "the trait bound `{}: {}` is not satisfied",
+                self.ty_to_string(ty),
+                self.trait_def(bound).name
+            ),
+        )
+        .note(note)
+    }
+
+    fn lower_generics(
+        &self,
+        scope: ModId,
+        generics: &ast::Generics,
+        diagnostics: &mut Vec<Diagnostic>,
+    ) -> Generics {
+        let mut params: Vec<GenericParamDef> = Vec::new();
+        for param in &generics.params {
+            if params.iter().any(|p| p.name == param.ident.name) {
+                diagnostics.push(Diagnostic::error(
+                    param.ident.span,
+                    format!(
+                        "the name `{}` is already used for a generic parameter",
+                        param.ident.name
+                    ),
+                ));
+            }
+            let mut bounds = Vec::new();
+            for path in &param.bounds {
+                if let Some(id) = self.resolve_trait(scope, path, diagnostics) {
+                    bounds.push(id);
+                }
+            }
+            params.push(GenericParamDef {
+                name: param.ident.name,
+                bounds,
+                span: param.span,
+            });
+        }
+        Generics {
+            params,
+            self_ty: None,
+        }
+    }
+}
+
+/// Formats a count of things, e.g. "1 argument" or "2 arguments".
+pub(crate) fn plural(n: usize, thing: &str) -> String {
+    if n == 1 {
+        format!("{} {}", n, thing)
+    } else {
+        format!("{} {}s", n, thing)
+    }
+}
+
+/// Formats a path the way it is written in the source, e.g. `Shape::Circle`.
+pub(crate) fn path_to_string(path: &ast::Path) -> String {
+    let segments: Vec<&str> = path.segments.iter().map(|s| s.name.as_str()).collect();
+    segments.join("::")
+}
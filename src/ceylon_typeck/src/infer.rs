@@ -0,0 +1,128 @@
+//! Inference variables and unification, shared by the checking of function
+//! bodies and the matching of impls.
+
+use super::*;
+use ceylon_span::Span;
+
+#[derive(Default)]
+pub(crate) struct InferCtxt {
+    /// The inference variables created so far, indexed by their number.
+    pub(crate) vars: Vec<VarDef>,
+}
+
+#[derive(Clone)]
+pub(crate) struct VarDef {
+    /// Whether the variable can only be an integer type.
+    pub(crate) int: bool,
+    pub(crate) value: Option<Ty>,
+    /// The expression whose type the variable stands for.
+    pub(crate) span: Span,
+}
+
+impl InferCtxt {
+    pub(crate) fn next_var(&mut self, int: bool, span: Span) -> Ty {
+        let index = self.vars.len() as u32;
+        self.vars.push(VarDef {
+            int,
+            value: None,
+            span,
+        });
+        Ty::Infer(if int {
+            InferTy::IntVar(index)
+        } else {
+            InferTy::TyVar(index)
+        })
+    }
+
+    /// Runs `f`, then undoes the assignments it made, e.g. to find out
+    /// whether types could be unified without unifying them.
+    pub(crate) fn probe<R>(&mut self, f: impl FnOnce(&mut Self) -> R) -> R {
+        let vars = self.vars.clone();
+        let result = f(self);
+        self.vars = vars;
+        result
+    }
+
+    /// Replaces a type by the value of its variable, if it is a variable
+    /// which has one.
+    pub(crate) fn shallow_resolve(&self, ty: &Ty) -> Ty {
+        match ty {
+            Ty::Infer(InferTy::TyVar(index) | InferTy::IntVar(index)) => {
+                match &self.vars[*index as usize].value {
+                    Some(value) => self.shallow_resolve(value),
+                    None => ty.clone(),
+                }
+            }
+            ty => ty.clone(),
+        }
+    }
+
+    /// Replaces every variable with a value in a type.
+    pub(crate) fn resolve(&self, ty: &Ty) -> Ty {
+        match self.shallow_resolve(ty) {
+            Ty::Adt(id, args) => Ty::Adt(id, args.iter().map(|arg| self.resolve(arg)).collect()),
+            Ty::Fn(inputs, output) => Ty::Fn(
+                inputs.iter().map(|ty| self.resolve(ty)).collect(),
+                Box::new(self.resolve(&output)),
+            ),
+            Ty::Tuple(tys) => Ty::Tuple(tys.iter().map(|ty| self.resolve(ty)).collect()),
+            Ty::Array(ty, len) => Ty::Array(Box::new(self.resolve(&ty)), len),
+            Ty::Slice(ty) => Ty::Slice(Box::new(self.resolve(&ty))),
+            ty => ty,
+        }
+    }
+
+    /// Makes two types equal by assigning variables. Returns whether this is
+    /// possible.
+    pub(crate) fn unify(&mut self, a: &Ty, b: &Ty) -> bool {
+        let (a, b) = (self.shallow_resolve(a), self.shallow_resolve(b));
+        match (&a, &b) {
+            _ if a == b => true,
+            (Ty::Error, _) | (_, Ty::Error) => true,
+            // Bind the more general variable, so that an integer variable
+            // stays one.
+            (Ty::Infer(var @ InferTy::TyVar(_)), ty) | (ty, Ty::Infer(var)) => {
+                self.bind(*var, ty.clone())
+            }
+            (Ty::Infer(var), ty) => self.bind(*var, ty.clone()),
+            (Ty::Adt(a_id, a_args), Ty::Adt(b_id, b_args)) if a_id == b_id => a_args
+                .iter()
+                .zip(b_args)
+                .fold(true, |ok, (a, b)| self.unify(a, b) && ok),
+            (Ty::Fn(a_inputs, a_output), Ty::Fn(b_inputs, b_output))
+                if a_inputs.len() == b_inputs.len() =>
+            {
+                let inputs = a_inputs
+                    .iter()
+                    .zip(b_inputs)
+                    .fold(true, |ok, (a, b)| self.unify(a, b) && ok);
+                self.unify(a_output, b_output) && inputs
+            }
+            (Ty::Tuple(a_tys), Ty::Tuple(b_tys)) if a_tys.len() == b_tys.len() => a_tys
+                .iter()
+                .zip(b_tys)
+                .fold(true, |ok, (a, b)| self.unify(a, b) && ok),
+            (Ty::Array(a_ty, a_len), Ty::Array(b_ty, b_len)) if a_len == b_len => {
+                self.unify(a_ty, b_ty)
+            }
+            (Ty::Slice(a_ty), Ty::Slice(b_ty)) => self.unify(a_ty, b_ty),
+            _ => false,
+        }
+    }
+
+    fn bind(&mut self, var: InferTy, ty: Ty) -> bool {
+        let index = match var {
+            InferTy::TyVar(index) => index,
+            InferTy::IntVar(index) if ty.is_integral() => index,
+            InferTy::IntVar(_) => return false,
+        };
+        let mut occurs = false;
+        self.resolve(&ty)
+            .walk(&mut |ty| occurs |= *ty == Ty::Infer(var));
+        if occurs {
+            return false;
+        }
+        self.vars[index as usize].value = Some(ty);
+        true
+    }
+}
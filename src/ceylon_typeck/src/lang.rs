@@ -0,0 +1,355 @@
+//! The traits built into the language, which overload the operators, the
+//! enums `Option` and `Result`, which `?` unwraps, and the structs `Range`
+//! and `RangeInclusive`, the values of `a..b` and `a..=b`.
+//!
+//! Primitive types implement the traits without an impl, their operators
+//! being those of the IR. Integers also have the methods of `Wrapping` and
+//! `Checked`, e.g. `x.checked_add(1)`, and arrays and slices the method of
+//! `Len`, e.g. `a.len()`. Other types get an operator by implementing its
+//! trait, e.g. `impl Add for Point { fn add(self, rhs: Point) -> Point { ... } }`,
+//! and `a + b` then calls `Add::add(a, b)`.
+
+use super::*;
+use crate::ty::{AdtKind, CtorKind, FieldDef, FnContainer, GenericParamDef, ParamTy, VariantDef};
+use ceylon_span::Span;
+
+/// The built-in traits.
+#[derive(Debug, Clone, Copy)]
+pub struct LangItems {
+    /// `fn eq(self, other: Self) -> bool`, used by `==` and `!=`.
+    pub eq: TraitId,
+    /// `fn lt(self, other: Self) -> bool`, used by `<`, `>`, `<=` and `>=`.
+    /// Types implementing it must implement `Eq` too.
+    pub ord: TraitId,
+    /// `fn add(self, rhs: Self) -> Self`, used by `+`.
+    pub add: TraitId,
+    /// `fn sub(self, rhs: Self) -> Self`, used by `-`.
+    pub sub: TraitId,
+    /// `fn mul(self, rhs: Self) -> Self`, used by `*`.
+    pub mul: TraitId,
+    /// `fn div(self, rhs: Self) -> Self`, used by `/`.
+    pub div: TraitId,
+    /// `fn rem(self, rhs: Self) -> Self`, used by `%`.
+    pub rem: TraitId,
+    /// `fn bitand(self, rhs: Self) -> Self`, used by `&`.
+    pub bit_and: TraitId,
+    /// `fn bitor(self, rhs: Self) -> Self`, used by `|`.
+    pub bit_or: TraitId,
+    /// `fn bitxor(self, rhs: Self) -> Self`, used by `^`.
+    pub bit_xor: TraitId,
+    /// `fn shl(self, rhs: Self) -> Self`, used by `<<`. The shift amount
+    /// has the type of the value shifted.
+    pub shl: TraitId,
+    /// `fn shr(self, rhs: Self) -> Self`, used by `>>`, which is an
+    /// arithmetic shift for signed integers.
+    pub shr: TraitId,
+    /// `fn neg(self) -> Self`, used by unary `-`.
+    pub neg: TraitId,
+    /// `fn wrapping_add(self, rhs: Self) -> Self`, and `wrapping_sub` and
+    /// `wrapping_mul`, which wrap around at the bounds of the type instead
+    /// of overflowing.
+    pub wrapping: TraitId,
+    /// `fn checked_add(self, rhs: Self) -> Option<Self>`, and `checked_sub`,
+    /// `checked_mul` and `checked_div`, which return `None` instead of
+    /// overflowing or dividing by zero.
+    pub checked: TraitId,
+    /// `fn len(self) -> usize`, the number of elements of an array or a
+    /// slice.
+    pub len: TraitId,
+    /// `enum Option<T> { Some(T), None }`
+    pub option: AdtId,
+    /// `enum Result<T, E> { Ok(T), Err(E) }`
+    pub result: AdtId,
+    /// `struct Range<T> { start: T, end: T }`, the value of `start..end`.
+    pub range: AdtId,
+    /// `struct RangeInclusive<T> { start: T, end: T }`, the value of
+    /// `start..=end`.
+    pub range_inclusive: AdtId,
+}
+
+/// Built-in items are not written anywhere, so their spans are empty.
+const BUILTIN_SPAN: Span = Span {
+    start_pos: 0,
+    len: 0,
+};
+
+impl LangItems {
+    /// Defines the built-in traits and their methods, and the built-in
+    /// enums and structs.
+    pub(crate) fn define(
+        traits: &mut Vec<TraitDef>,
+        fns: &mut Vec<FnSig>,
+        adts: &mut Vec<AdtDef>,
+    ) -> LangItems {
+        let self_ty = Ty::Param(ParamTy {
+            index: 0,
+            name: Symbol::intern("Self"),
+        });
+        // Traits whose methods take `arity` values of type `Self` and return
+        // `output`, or `Self`.
+        let mut define = |name: &str, methods: &[&str], arity: usize, output: Option<Ty>| {
+            let trait_id = TraitId::from_usize(traits.len());
+            let mut ids = Vec::new();
+            for method in methods {
+                fns.push(FnSig {
+                    name: Symbol::intern(method),
+                    container: FnContainer::Trait(trait_id),
+                    generics: Generics {
+                        params: vec![GenericParamDef {
+                            name: Symbol::intern("Self"),
+                            bounds: vec![trait_id],
+                            span: BUILTIN_SPAN,
+                        }],
+                        self_ty: Some(self_ty.clone()),
+                    },
+                    inputs: vec![self_ty.clone(); arity],
+                    output: output.clone().unwrap_or(self_ty.clone()),
+                    has_self: true,
+                    has_body: false,
+                    inline: false,
+                    span: BUILTIN_SPAN,
+                });
+                ids.push(FnId::from_usize(fns.len() - 1));
+            }
+            traits.push(TraitDef {
+                name: Symbol::intern(name),
+                methods: ids,
+                span: BUILTIN_SPAN,
+            });
+            trait_id
+        };
+        // Enums whose variants each hold one value of a type parameter, or
+        // none.
+        let mut define_enum = |name: &str, params: &[&str], variants: &[(&str, Option<u32>)]| {
+            adts.push(AdtDef {
+                name: Symbol::intern(name),
+                kind: AdtKind::Enum,
+                generics: Generics {
+                    params: params
+                        .iter()
+                        .map(|param| GenericParamDef {
+                            name: Symbol::intern(param),
+                            bounds: Vec::new(),
+                            span: BUILTIN_SPAN,
+                        })
+                        .collect(),
+                    self_ty: None,
+                },
+                variants: variants
+                    .iter()
+                    .map(|&(name, field)| VariantDef {
+                        name: Symbol::intern(name),
+                        ctor_kind: match field {
+                            Some(_) => CtorKind::Fn,
+                            None => CtorKind::Const,
+                        },
+                        fields: field
+                            .map(|index| FieldDef {
+                                name: Symbol::intern("0"),
+                                ty: Ty::Param(ParamTy {
+                                    index,
+                                    name: Symbol::intern(params[index as usize]),
+                                }),
+                                span: BUILTIN_SPAN,
+                            })
+                            .into_iter()
+                            .collect(),
+                        span: BUILTIN_SPAN,
+                    })
+                    .collect(),
+                span: BUILTIN_SPAN,
+            });
+            AdtId::from_usize(adts.len() - 1)
+        };
+        let option = define_enum("Option", &["T"], &[("Some", Some(0)), ("None", None)]);
+        let result = define_enum("Result", &["T", "E"], &[("Ok", Some(0)), ("Err", Some(1))]);
+        // Structs with one type parameter `T`, whose fields are all of type
+        // `T`.
+        let mut define_struct = |name: &str, fields: &[&str]| {
+            let param = Ty::Param(ParamTy {
+                index: 0,
+                name: Symbol::intern("T"),
+            });
+            adts.push(AdtDef {
+                name: Symbol::intern(name),
+                kind: AdtKind::Struct,
+                generics: Generics {
+                    params: vec![GenericParamDef {
+                        name: Symbol::intern("T"),
+                        bounds: Vec::new(),
+                        span: BUILTIN_SPAN,
+                    }],
+                    self_ty: None,
+                },
+                variants: vec![VariantDef {
+                    name: Symbol::intern(name),
+                    ctor_kind: CtorKind::Struct,
+                    fields: fields
+                        .iter()
+                        .map(|field| FieldDef {
+                            name: Symbol::intern(field),
+                            ty: param.clone(),
+                            span: BUILTIN_SPAN,
+                        })
+                        .collect(),
+                    span: BUILTIN_SPAN,
+                }],
+                span: BUILTIN_SPAN,
+            });
+            AdtId::from_usize(adts.len() - 1)
+        };
+        let range = define_struct("Range", &["start", "end"]);
+        let range_inclusive = define_struct("RangeInclusive", &["start", "end"]);
+        let checked_output = Ty::Adt(option, vec![self_ty.clone()]);
+        LangItems {
+            eq: define("Eq", &["eq"], 2, Some(Ty::Bool)),
+            ord: define("Ord", &["lt"], 2, Some(Ty::Bool)),
+            add: define("Add", &["add"], 2, None),
+            sub: define("Sub", &["sub"], 2, None),
+            mul: define("Mul", &["mul"], 2, None),
+            div: define("Div", &["div"], 2, None),
+            rem: define("Rem", &["rem"], 2, None),
+            bit_and: define("BitAnd", &["bitand"], 2, None),
+            bit_or: define("BitOr", &["bitor"], 2, None),
+            bit_xor: define("BitXor", &["bitxor"], 2, None),
+            shl: define("Shl", &["shl"], 2, None),
+            shr: define("Shr", &["shr"], 2, None),
+            neg: define("Neg", &["neg"], 1, None),
+            wrapping: define(
+                "Wrapping",
+                &["wrapping_add", "wrapping_sub", "wrapping_mul"],
+                2,
+                None,
+            ),
+            checked: define(
+                "Checked",
+                &["checked_add", "checked_sub", "checked_mul", "checked_div"],
+                2,
+                Some(checked_output),
+            ),
+            len: define("Len", &["len"], 1, Some(Ty::Usize)),
+            option,
+            result,
+            range,
+            range_inclusive,
+        }
+    }
+
+    fn all(&self) -> [TraitId; 16] {
+        [
+            self.eq,
+            self.ord,
+            self.add,
+            self.sub,
+            self.mul,
+            self.div,
+            self.rem,
+            self.bit_and,
+            self.bit_or,
+            self.bit_xor,
+            self.shl,
+            self.shr,
+            self.neg,
+            self.wrapping,
+            self.checked,
+            self.len,
+        ]
+    }
+
+    pub fn is_lang_trait(&self, id: TraitId) -> bool {
+        self.all().contains(&id)
+    }
+}
+
+impl Tcx {
+    /// Returns the built-in trait with the given name.
+    pub(crate) fn lang_trait(&self, name: Symbol) -> Option<TraitId> {
+        self.lang
+            .all()
+            .into_iter()
+            .find(|&id| self.trait_def(id).name == name)
+    }
+
+    /// Returns the built-in enum or struct with the given name.
+    pub(crate) fn lang_adt(&self, name: Symbol) -> Option<AdtId> {
+        let lang = &self.lang;
+        [lang.option, lang.result, lang.range, lang.range_inclusive]
+            .into_iter()
+            .find(|&id| self.adt(id).name == name)
+    }
+
+    /// Explains that a type is an ADT of the program with the name of a
+    /// built-in one, e.g. an `enum Option` of its own, if it is.
+    pub(crate) fn shadowed_lang_adt_note(&self, ty: &Ty) -> Option<String> {
+        let &Ty::Adt(id, _) = ty else { return None };
+        let name = self.adt(id).name;
+        let lang_id = self.lang_adt(name)?;
+        (lang_id != id).then(|| {
+            format!(
+                "`{}` is the {} `{}`, not the built-in `{}`",
+                name,
+                self.describe_def(Def::Adt(id)),
+                self.adt_path(id),
+                name
+            )
+        })
+    }
+
+    /// Whether a type parameter declared with the bound `declared` also has
+    /// the bound `bound`.
+    pub(crate) fn implies(&self, declared: TraitId, bound: TraitId) -> bool {
+        declared == bound || (declared == self.lang.ord && bound == self.lang.eq)
+    }
+
+    /// Whether a type implements a built-in trait without an impl.
+    pub(crate) fn has_builtin_impl(&self, id: TraitId, ty: &Ty) -> bool {
+        let lang = &self.lang;
+        if id == lang.len {
+            matches!(ty, Ty::Array(..) | Ty::Slice(_))
+        } else if !ty.is_primitive() {
+            false
+        } else if id == lang.eq {
+            true
+        } else if id == lang.ord {
+            ty.is_numeric() || *ty == Ty::Char
+        } else if [lang.add, lang.sub, lang.mul, lang.div, lang.rem].contains(&id) {
+            ty.is_numeric()
+        } else if [lang.bit_and, lang.bit_or, lang.bit_xor].contains(&id) {
+            ty.is_integral() || *ty == Ty::Bool
+        } else if id == lang.shl || id == lang.shr {
+            ty.is_integral()
+        } else if id == lang.neg {
+            ty.is_signed()
+        } else if id == lang.wrapping || id == lang.checked {
+            ty.is_integral()
+        } else {
+            false
+        }
+    }
+
+    /// Returns the method a binary operator calls. `!=` negates the result
+    /// of `Eq::eq`, and `>`, `<=` and `>=` are written with `Ord::lt`.
+    pub fn binop_method(&self, op: ast::BinOp) -> FnId {
+        let lang = &self.lang;
+        let trait_id = match op {
+            ast::BinOp::Add => lang.add,
+            ast::BinOp::Sub => lang.sub,
+            ast::BinOp::Mul => lang.mul,
+            ast::BinOp::Div => lang.div,
+            ast::BinOp::Rem => lang.rem,
+            ast::BinOp::BitAnd => lang.bit_and,
+            ast::BinOp::BitOr => lang.bit_or,
+            ast::BinOp::BitXor => lang.bit_xor,
+            ast::BinOp::Shl => lang.shl,
+            ast::BinOp::Shr => lang.shr,
+            ast::BinOp::Eq | ast::BinOp::Ne => lang.eq,
+            ast::BinOp::Lt | ast::BinOp::Gt | ast::BinOp::Le | ast::BinOp::Ge => lang.ord,
+        };
+        self.trait_def(trait_id).methods[0]
+    }
+
+    /// Returns the method unary `-` calls.
+    pub fn neg_method(&self) -> FnId {
+        self.trait_def(self.lang.neg).methods[0]
+    }
+}
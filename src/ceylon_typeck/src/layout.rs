@@ -0,0 +1,374 @@
+//! Memory layout of types: size, alignment and field offsets. The driver
+//! prints them with `--emit=layout`. The type checker finds the ADTs which
+//! would be infinitely large with `find_recursive_adts` first, since those
+//! have no layout, and then reports the types too big to be stored.
+//!
+//! Struct fields are laid out in declaration order, each at the first offset
+//! satisfying its alignment, like C does. The size of a struct is rounded up
+//! to its alignment so that arrays of it keep every element aligned.
+//!
+//! An enum starts with a tag holding the index of its variant, in the
+//! smallest unsigned integer able to hold every index. The fields of each
+//! variant follow the tag as if they were a struct starting with it, and the
+//! enum is as large as its largest variant.
+//!
+//! Tuples are laid out like structs. The elements of an array follow each
+//! other, and need no padding since sizes are multiples of alignments.
+
+use super::*;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Layout {
+    /// Size in bytes, a multiple of `align`.
+    pub size: u64,
+    /// Alignment in bytes, a power of two.
+    pub align: u64,
+    /// Offset of each field of a struct in declaration order. Empty for
+    /// other types.
+    pub field_offsets: Vec<u64>,
+    /// The layout of each variant of an enum.
+    pub variants: Option<Variants>,
+}
+
+/// Why a type has no layout.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LayoutError {
+    /// The type depends on type parameters, or on an error which has
+    /// already been reported.
+    Unknown,
+    /// The size of the type exceeds [`MAX_SIZE`].
+    TooBig,
+}
+
+/// The size of the largest value, in bytes. Offsets within a value fit in
+/// an `i64` as well as in a `u64`.
+pub const MAX_SIZE: u64 = i64::MAX as u64;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Variants {
+    /// Size of the tag at offset 0. Zero for an enum without variants.
+    pub tag_size: u64,
+    /// Layout of each variant, including the tag. Only the field offsets of
+    /// these are interesting, the size of the enum is that of the largest.
+    pub variants: Vec<Layout>,
+}
+
+impl Layout {
+    fn scalar(size: u64) -> Layout {
+        Layout {
+            size,
+            align: size,
+            field_offsets: Vec::new(),
+            variants: None,
+        }
+    }
+
+    /// Lays out fields in order, inserting padding where needed.
+    fn record(fields: &[Layout]) -> Result<Layout, LayoutError> {
+        let mut offset = 0;
+        let mut align = 1;
+        let mut field_offsets = Vec::with_capacity(fields.len());
+        for field in fields {
+            offset = align_to(offset, field.align)?;
+            field_offsets.push(offset);
+            offset = checked_size(offset.checked_add(field.size))?;
+            align = align.max(field.align);
+        }
+        Ok(Layout {
+            size: align_to(offset, align)?,
+            align,
+            field_offsets,
+            variants: None,
+        })
+    }
+
+    /// Lays out the variants of an enum, each a list of fields.
+    fn tagged_union(variants: &[Vec<Layout>]) -> Result<Layout, LayoutError> {
+        let tag_size = match variants.len() {
+            0 => {
+                return Ok(Layout {
+                    size: 0,
+                    align: 1,
+                    field_offsets: Vec::new(),
+                    variants: Some(Variants {
+                        tag_size: 0,
+                        variants: Vec::new(),
+                    }),
+                })
+            }
+            n if n <= 1 << 8 => 1,
+            n if n <= 1 << 16 => 2,
+            _ => 4,
+        };
+        let mut size = 0;
+        let mut align = 1;
+        let variants: Vec<Layout> = variants
+            .iter()
+            .map(|fields| {
+                let mut tagged = vec![Layout::scalar(tag_size)];
+                tagged.extend(fields.iter().cloned());
+                let mut layout = Layout::record(&tagged)?;
+                layout.field_offsets.remove(0);
+                size = size.max(layout.size);
+                align = align.max(layout.align);
+                Ok(layout)
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Layout {
+            size: align_to(size, align)?,
+            align,
+            field_offsets: Vec::new(),
+            variants: Some(Variants { tag_size, variants }),
+        })
+    }
+}
+
+/// Rounds `offset` up to a multiple of `align`, a power of two.
+fn align_to(offset: u64, align: u64) -> Result<u64, LayoutError> {
+    checked_size(offset.checked_add(align - 1).map(|end| end & !(align - 1)))
+}
+
+/// Returns `size` unless the computation overflowed or the result exceeds
+/// [`MAX_SIZE`].
+fn checked_size(size: Option<u64>) -> Result<u64, LayoutError> {
+    size.filter(|&size| size <= MAX_SIZE)
+        .ok_or(LayoutError::TooBig)
+}
+
+impl Tcx {
+    /// Returns the layout of a type. A type which depends on type parameters
+    /// or on an error has none, nor does one too big to be stored.
+    pub fn layout_of(&self, ty: &Ty) -> Result<Layout, LayoutError> {
+        let (id, args) = match ty {
+            Ty::Adt(id, args) => (id, args),
+            Ty::Tuple(tys) => {
+                let fields = tys
+                    .iter()
+                    .map(|ty| self.layout_of(ty))
+                    .collect::<Result<Vec<Layout>, _>>()?;
+                return Layout::record(&fields);
+            }
+            Ty::Array(ty, len) => {
+                let elem = self.layout_of(ty)?;
+                return Ok(Layout {
+                    size: checked_size(elem.size.checked_mul(*len))?,
+                    align: elem.align,
+                    field_offsets: Vec::new(),
+                    variants: None,
+                });
+            }
+            ty => return primitive_layout(ty).ok_or(LayoutError::Unknown),
+        };
+        if self.recursive_adts[id.as_usize()] {
+            return Err(LayoutError::Unknown);
+        }
+        let adt = self.adt(*id);
+        let variants = adt
+            .variants
+            .iter()
+            .map(|variant| {
+                variant
+                    .fields
+                    .iter()
+                    .map(|field| self.layout_of(&field.ty.subst(args)))
+                    .collect::<Result<Vec<Layout>, _>>()
+            })
+            .collect::<Result<Vec<Vec<Layout>>, _>>()?;
+        if adt.is_enum() {
+            Layout::tagged_union(&variants)
+        } else {
+            Layout::record(&variants[0])
+        }
+    }
+}
+
+fn primitive_layout(ty: &Ty) -> Option<Layout> {
+    let layout = match ty {
+        Ty::I8 | Ty::U8 => Layout::scalar(1),
+        Ty::I16 | Ty::U16 => Layout::scalar(2),
+        Ty::I32 | Ty::U32 | Ty::F32 => Layout::scalar(4),
+        Ty::I64 | Ty::U64 | Ty::Usize | Ty::F64 => Layout::scalar(8),
+        Ty::I128 | Ty::U128 => Layout::scalar(16),
+        Ty::Bool => Layout::scalar(1),
+        Ty::Char => Layout::scalar(4),
+        // A pointer to the bytes or the elements, and their number.
+        Ty::Str | Ty::Slice(_) => Layout {
+            size: 16,
+            align: 8,
+            field_offsets: Vec::new(),
+            variants: None,
+        },
+        // A pointer to the value and one to the vtable of its type. A
+        // function value is a pointer to the code and one to the captured
+        // variables.
+        Ty::Dyn(_) | Ty::Fn(..) => Layout {
+            size: 16,
+            align: 8,
+            field_offsets: Vec::new(),
+            variants: None,
+        },
+        Ty::Void => Layout {
+            size: 0,
+            align: 1,
+            field_offsets: Vec::new(),
+            variants: None,
+        },
+        Ty::Adt(..) | Ty::Tuple(_) | Ty::Array(..) | Ty::Param(_) | Ty::Infer(_) | Ty::Error => {
+            return None
+        }
+    };
+    Some(layout)
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum State {
+    NotStarted,
+    InProgress,
+    Done,
+}
+
+struct RecursionCx<'a> {
+    tcx: &'a Tcx,
+    states: Vec<State>,
+    /// ADTs which contain themselves.
+    recursive: Vec<bool>,
+    diagnostics: &'a mut Vec<Diagnostic>,
+}
+
+/// Finds the structs and enums which contain themselves and hence would be
+/// infinitely large, and reports them.
+///
+/// An ADT passed as a generic argument to a field type counts as contained,
+/// since without indirection every type parameter is stored by value. This
+/// also catches types which would grow with each instantiation, such as
+/// `struct S<T> { s: S<Pair<T, T>> }`.
+pub(crate) fn find_recursive_adts(tcx: &Tcx, diagnostics: &mut Vec<Diagnostic>) -> Vec<bool> {
+    let mut cx = RecursionCx {
+        tcx,
+        states: vec![State::NotStarted; tcx.adts.len()],
+        recursive: vec![false; tcx.adts.len()],
+        diagnostics,
+    };
+    for i in 0..tcx.adts.len() {
+        cx.visit_adt(AdtId::from_usize(i));
+    }
+    cx.recursive
+}
+
+impl RecursionCx<'_> {
+    fn visit_adt(&mut self, id: AdtId) {
+        let i = id.as_usize();
+        match self.states[i] {
+            State::Done => return,
+            State::InProgress => {
+                if !self.recursive[i] {
+                    self.recursive[i] = true;
+                    let adt = self.tcx.adt(id);
+                    self.diagnostics.push(
+                        Diagnostic::error(
+                            adt.span,
+                            format!("recursive type `{}` has infinite size", adt.name),
+                        )
+                        .note("a type cannot contain itself, directly or through other types"),
+                    );
+                }
+                return;
+            }
+            State::NotStarted => (),
+        }
+        self.states[i] = State::InProgress;
+        let mut contained = Vec::new();
+        for variant in &self.tcx.adt(id).variants {
+            for field in &variant.fields {
+                contained_adts(&field.ty, &mut |id| contained.push(id));
+            }
+        }
+        for field_id in contained {
+            self.visit_adt(field_id);
+            // An ADT containing a recursive one has no layout either.
+            if self.recursive[field_id.as_usize()] {
+                self.recursive[i] = true;
+            }
+        }
+        self.states[i] = State::Done;
+    }
+}
+
+/// Reports the fields of structs and enums whose types are too big to be
+/// stored, and the non-generic ones whose fields are too big together.
+///
+/// A field whose type is too big because of the definition of another ADT is
+/// reported there, e.g. the field `big` of `struct S { big: Big }`.
+pub(crate) fn check_adt_sizes(tcx: &Tcx, diagnostics: &mut Vec<Diagnostic>) {
+    for (i, adt) in tcx.adts.iter().enumerate() {
+        if tcx.recursive_adts[i] {
+            continue;
+        }
+        let mut big_fields = false;
+        for field in adt.variants.iter().flat_map(|variant| &variant.fields) {
+            if tcx.layout_of(&field.ty) != Err(LayoutError::TooBig) {
+                continue;
+            }
+            big_fields = true;
+            if !is_big_adt(tcx, &field.ty) {
+                diagnostics.push(too_big(tcx, field.span, &field.ty));
+            }
+        }
+        let ty = Ty::Adt(AdtId::from_usize(i), Vec::new());
+        if adt.generics.params.is_empty()
+            && !big_fields
+            && tcx.layout_of(&ty) == Err(LayoutError::TooBig)
+        {
+            diagnostics.push(too_big(tcx, adt.span, &ty));
+        }
+    }
+}
+
+/// Whether `ty` is too big because it stores an ADT which is too big with
+/// arguments which are not.
+fn is_big_adt(tcx: &Tcx, ty: &Ty) -> bool {
+    match ty {
+        Ty::Adt(_, args) => {
+            if args.iter().any(|arg| is_big_adt(tcx, arg)) {
+                return true;
+            }
+            args.iter()
+                .all(|arg| tcx.layout_of(arg) != Err(LayoutError::TooBig))
+                && tcx.layout_of(ty) == Err(LayoutError::TooBig)
+        }
+        Ty::Tuple(tys) => tys.iter().any(|ty| is_big_adt(tcx, ty)),
+        Ty::Array(ty, _) => is_big_adt(tcx, ty),
+        _ => false,
+    }
+}
+
+/// The error for a value of type `ty` at `span`, which can't be stored.
+pub(crate) fn too_big(tcx: &Tcx, span: Span, ty: &Ty) -> Diagnostic {
+    Diagnostic::error(
+        span,
+        format!("the type `{}` is too big", tcx.ty_to_string(ty)),
+    )
+    .note(format!("a value can take at most {} bytes", MAX_SIZE))
+}
+
+/// Calls `f` on the ADTs stored inline in a value of type `ty`. A slice, a
+/// string, a function value or a trait object only points to its contents,
+/// which hence don't count, so that e.g. `struct Cb { f: fn(Cb) -> i64 }`
+/// and `struct Node { kids: [Node] }` have a size.
+fn contained_adts(ty: &Ty, f: &mut impl FnMut(AdtId)) {
+    match ty {
+        Ty::Adt(id, args) => {
+            f(*id);
+            for arg in args {
+                contained_adts(arg, f);
+            }
+        }
+        Ty::Tuple(tys) => {
+            for ty in tys {
+                contained_adts(ty, f);
+            }
+        }
+        Ty::Array(ty, _) => contained_adts(ty, f),
+        _ => {}
+    }
+}
@@ -0,0 +1,239 @@
+//! Name resolution and type checking of a parsed module.
+//!
+//! `check_module` collects the items of the module and of its submodules
+//! into a `Tcx` (type context), then checks the body of every function. The results are kept in
+//! side tables keyed by `NodeId` so that later passes don't need to repeat
+//! the work.
+//!
+//! ```
+//! let (module, _) = ceylon_parser::parse_file("struct P { x: i64 } fn f(p: P) { p.x; }");
+//! let (tcx, diagnostics) = ceylon_typeck::check_module(&module);
+//! assert!(diagnostics.is_empty());
+//! assert_eq!(tcx.adts.last().unwrap().non_enum_variant().fields.len(), 1);
+//! ```
+
+mod check;
+mod collect;
+mod infer;
+pub mod lang;
+pub mod layout;
+pub mod resolve;
+pub mod traits;
+pub mod ty;
+mod usefulness;
+
+#[cfg(test)]
+mod tests;
+
+use std::collections::{HashMap, HashSet};
+
+use ceylon_errors::Diagnostic;
+use ceylon_parser::ast::{self, NodeId};
+use ceylon_parser::attr;
+use ceylon_span::{Span, Symbol};
+use lang::LangItems;
+use resolve::{ModId, ModuleDef};
+use ty::{AdtDef, AdtId, FnId, FnSig, Generics, ImplDef, ImplId, InferTy, TraitDef, TraitId, Ty};
+
+/// Everything known about a module after type checking.
+pub struct Tcx {
+    pub adts: Vec<AdtDef>,
+    /// Every function, including the methods of traits and impls.
+    pub fns: Vec<FnSig>,
+    pub traits: Vec<TraitDef>,
+    pub impls: Vec<ImplDef>,
+    pub lang: LangItems,
+    /// The root module first, then its submodules.
+    pub modules: Vec<ModuleDef>,
+    /// What each item defines, by the id of the item.
+    item_defs: HashMap<NodeId, Def>,
+    /// The items marked `@deprecated`, with their notes, which uses of them
+    /// are warned about.
+    deprecations: HashMap<Def, Option<Symbol>>,
+    /// The lints silenced by `@allow` on items and statements, with the
+    /// span of the item or statement.
+    allowed_spans: Vec<(Symbol, Span)>,
+    /// The functions marked `@test` with the module they are in, for the
+    /// test harness to run.
+    pub tests: Vec<(ModId, FnId)>,
+    /// Whether each ADT contains itself, or another ADT which does, so that
+    /// it has no layout.
+    recursive_adts: Vec<bool>,
+    pub typeck_results: TypeckResults,
+}
+
+/// What an item name refers to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Def {
+    Adt(AdtId),
+    Fn(FnId),
+    Trait(TraitId),
+    /// An impl block. These have no name, so only `Tcx::item_def` returns
+    /// them.
+    Impl(ImplId),
+    Mod(ModId),
+}
+
+#[derive(Debug, Default)]
+pub struct TypeckResults {
+    /// Type of every expression, parameter and local variable.
+    pub node_types: HashMap<NodeId, Ty>,
+    /// What each path refers to, keyed by the id of the path expression,
+    /// struct literal or pattern containing it. Method calls resolve to the
+    /// method they call.
+    pub path_res: HashMap<NodeId, Res>,
+    /// Index of the field accessed by each field expression.
+    pub field_indices: HashMap<NodeId, usize>,
+    /// Generic arguments of each call to a generic function, keyed by the id
+    /// of the callee path or of the method call.
+    pub node_args: HashMap<NodeId, Vec<Ty>>,
+    /// Expressions whose value is turned into a `dyn` value of the trait.
+    /// Their type in `node_types` is the type before the conversion.
+    pub dyn_coercions: HashMap<NodeId, TraitId>,
+    /// Expressions whose value is an array turned into a slice of its
+    /// elements. Their type in `node_types` is the type of the array.
+    pub unsize_coercions: HashSet<NodeId>,
+    /// Maps each binding in the second and later alternatives of an
+    /// or-pattern to the binding in the first alternative, which is the
+    /// variable that paths resolve to.
+    pub binding_aliases: HashMap<NodeId, NodeId>,
+}
+
+/// The resolution of a path expression.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Res {
+    /// A parameter or a local variable, identified by the id of its
+    /// declaration.
+    Local(NodeId),
+    Fn(FnId),
+    /// A struct or a variant of an enum, identified by its index.
+    Variant(AdtId, usize),
+}
+
+/// Resolves and type checks a module.
+pub fn check_module(module: &ast::Module) -> (Tcx, Vec<Diagnostic>) {
+    let mut diagnostics = Vec::new();
+    let (mut tcx, items) = collect::collect_items(module, &mut diagnostics);
+    let mut results = TypeckResults::default();
+    for (scope, item) in items {
+        let mut allowed_lints = tcx.module(scope).allowed_lints.clone();
+        allowed_lints.extend(attr::allowed_lints(&item.attrs));
+        let items = match &item.kind {
+            ast::ItemKind::Fn(_) => std::slice::from_ref(item),
+            ast::ItemKind::Trait(def) => &def.items[..],
+            ast::ItemKind::Impl(def) => &def.items[..],
+            ast::ItemKind::Struct(_)
+            | ast::ItemKind::Enum(_)
+            | ast::ItemKind::Mod(_)
+            | ast::ItemKind::Import(_) => continue,
+        };
+        for item in items {
+            let (ast::ItemKind::Fn(decl), Def::Fn(fn_id)) = (&item.kind, tcx.item_def(item.id))
+            else {
+                unreachable!()
+            };
+            let mut allowed_lints = allowed_lints.clone();
+            allowed_lints.extend(attr::allowed_lints(&item.attrs));
+            check::check_fn(
+                &tcx,
+                scope,
+                fn_id,
+                decl,
+                allowed_lints,
+                &mut results,
+                &mut diagnostics,
+            );
+        }
+    }
+    tcx.typeck_results = results;
+    (tcx, diagnostics)
+}
+
+impl Tcx {
+    /// Looks up a name in the scope of the root module.
+    pub fn def(&self, name: Symbol) -> Option<Def> {
+        self.resolve_name(ModId::ROOT, name)
+    }
+
+    /// Returns what an item of a module defines.
+    pub fn item_def(&self, id: NodeId) -> Def {
+        self.item_defs[&id]
+    }
+
+    pub fn adt(&self, id: AdtId) -> &AdtDef {
+        &self.adts[id.as_usize()]
+    }
+
+    pub fn fn_sig(&self, id: FnId) -> &FnSig {
+        &self.fns[id.as_usize()]
+    }
+
+    /// Whether a type implements a trait, where `generics` declares the
+    /// type parameters in scope.
+    pub fn satisfies(&self, ty: &Ty, bound: TraitId, generics: &Generics) -> bool {
+        match ty {
+            Ty::Param(param) => {
+                generics.params[param.index as usize]
+                    .bounds
+                    .iter()
+                    .any(|&declared| self.implies(declared, bound))
+                    || self.find_impl(bound, ty, generics).is_some()
+            }
+            Ty::Dyn(id) => *id == bound,
+            Ty::Error => true,
+            // Integer variables fall back to `i64`.
+            Ty::Infer(InferTy::IntVar(_)) => self.satisfies(&Ty::I64, bound, generics),
+            Ty::Infer(InferTy::TyVar(_)) => false,
+            ty => self.has_builtin_impl(bound, ty) || self.find_impl(bound, ty, generics).is_some(),
+        }
+    }
+
+    /// Formats a type the way it is written in the source, e.g. `Point`.
+    pub fn ty_to_string(&self, ty: &Ty) -> String {
+        match ty {
+            Ty::I8 => "i8".to_string(),
+            Ty::I16 => "i16".to_string(),
+            Ty::I32 => "i32".to_string(),
+            Ty::I64 => "i64".to_string(),
+            Ty::I128 => "i128".to_string(),
+            Ty::U8 => "u8".to_string(),
+            Ty::U16 => "u16".to_string(),
+            Ty::U32 => "u32".to_string(),
+            Ty::U64 => "u64".to_string(),
+            Ty::U128 => "u128".to_string(),
+            Ty::Usize => "usize".to_string(),
+            Ty::F32 => "f32".to_string(),
+            Ty::F64 => "f64".to_string(),
+            Ty::Bool => "bool".to_string(),
+            Ty::Char => "char".to_string(),
+            Ty::Str => "str".to_string(),
+            Ty::Void => "void".to_string(),
+            Ty::Adt(id, args) if args.is_empty() => self.adt(*id).name.to_string(),
+            Ty::Adt(id, args) => {
+                let args: Vec<String> = args.iter().map(|arg| self.ty_to_string(arg)).collect();
+                format!("{}<{}>", self.adt(*id).name, args.join(", "))
+            }
+            Ty::Param(param) => param.name.to_string(),
+            Ty::Dyn(id) => format!("dyn {}", self.trait_def(*id).name),
+            Ty::Fn(inputs, output) => {
+                let inputs: Vec<String> = inputs.iter().map(|ty| self.ty_to_string(ty)).collect();
+                match **output {
+                    Ty::Void => format!("fn({})", inputs.join(", ")),
+                    _ => format!("fn({}) -> {}", inputs.join(", "), self.ty_to_string(output)),
+                }
+            }
+            Ty::Tuple(tys) => {
+                let tys: Vec<String> = tys.iter().map(|ty| self.ty_to_string(ty)).collect();
+                match &tys[..] {
+                    [ty] => format!("({},)", ty),
+                    _ => format!("({})", tys.join(", ")),
+                }
+            }
+            Ty::Array(ty, len) => format!("[{}; {}]", self.ty_to_string(ty), len),
+            Ty::Slice(ty) => format!("[{}]", self.ty_to_string(ty)),
+            Ty::Infer(InferTy::TyVar(_)) => "_".to_string(),
+            Ty::Infer(InferTy::IntVar(_)) => "{integer}".to_string(),
+            Ty::Error => "{error}".to_string(),
+        }
+    }
+}
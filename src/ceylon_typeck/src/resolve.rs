@@ -0,0 +1,547 @@
+//! Modules: the names each of them defines and imports, and the resolution
+//! of paths through them.
+//!
+//! Every module has its own scope. It contains the items of the module, its
+//! submodules and what it imports, but not the items of the modules around
+//! it. Import paths start at the root module of the package, while other
+//! paths start in the scope of the module they are written in. Items are
+//! private unless marked `pub`: a private item can only be named in its
+//! module and the submodules of it.
+//!
+//! The imports between modules must not form a cycle, so that the modules
+//! of a program can be understood one after the other.
+
+use std::collections::{hash_map::Entry, HashSet};
+
+use super::*;
+use ast::Visibility;
+use ceylon_parser::visit::{self, Visitor};
+use ceylon_span::Span;
+
+/// Index of a module in `Tcx::modules`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ModId(u32);
+
+impl ModId {
+    /// The module of the file the program starts with.
+    pub const ROOT: ModId = ModId(0);
+
+    pub fn from_usize(n: usize) -> Self {
+        ModId(n as u32)
+    }
+
+    pub fn as_usize(self) -> usize {
+        self.0 as usize
+    }
+}
+
+#[derive(Debug)]
+pub struct ModuleDef {
+    /// The name of the module. The root module is named `crate`.
+    pub name: Symbol,
+    /// `None` for the root module.
+    pub parent: Option<ModId>,
+    /// The root module of the package the module is in, which its import
+    /// paths start at.
+    pub package: ModId,
+    /// The lints silenced by `@allow` on the module or the modules
+    /// containing it.
+    pub allowed_lints: Vec<Symbol>,
+    /// The names in scope in the module: its items and its imports.
+    pub bindings: HashMap<Symbol, Binding>,
+}
+
+/// What a name in the scope of a module refers to.
+#[derive(Debug, Clone, Copy)]
+pub struct Binding {
+    pub def: Def,
+    pub vis: Visibility,
+    /// Whether the name is imported rather than defined by the module.
+    pub import: bool,
+    pub span: Span,
+}
+
+/// What a path names, as far as it can be resolved through modules.
+pub(crate) enum PathRes<'p> {
+    /// The first segment of the path is not in scope.
+    Undeclared,
+    /// A segment after a module was not found in it, which was reported.
+    Failed,
+    /// What the path names after its modules, and the segments after that,
+    /// e.g. the enum and the variant of `shapes::Shape::Circle`.
+    Def(Def, &'p [ast::Ident]),
+}
+
+impl Tcx {
+    pub fn module(&self, id: ModId) -> &ModuleDef {
+        &self.modules[id.as_usize()]
+    }
+
+    /// Looks up a name in the scope of a module. The built-in traits and
+    /// enums are in scope everywhere, unless a binding of the module shadows
+    /// them.
+    pub fn resolve_name(&self, module: ModId, name: Symbol) -> Option<Def> {
+        self.module(module)
+            .bindings
+            .get(&name)
+            .map(|binding| binding.def)
+            .or_else(|| self.lang_trait(name).map(Def::Trait))
+            .or_else(|| self.lang_adt(name).map(Def::Adt))
+    }
+
+    /// Formats the path of a module from the root, e.g. `shapes::round`.
+    pub fn module_path(&self, id: ModId) -> String {
+        let mut names = Vec::new();
+        let mut module = Some(id);
+        while let Some(id) = module.filter(|&id| id != ModId::ROOT) {
+            names.push(self.module(id).name.as_str());
+            module = self.module(id).parent;
+        }
+        if names.is_empty() {
+            return "crate".to_string();
+        }
+        names.reverse();
+        names.join("::")
+    }
+
+    /// Formats the path of an ADT from the root, e.g. `shapes::Circle`. The
+    /// built-in ADTs are in no module, so only their name is given.
+    pub fn adt_path(&self, id: AdtId) -> String {
+        let name = self.adt(id).name;
+        let owner = self.modules.iter().position(|module| {
+            module
+                .bindings
+                .get(&name)
+                .is_some_and(|binding| !binding.import && binding.def == Def::Adt(id))
+        });
+        match owner {
+            Some(owner) => format!("{}::{}", self.module_path(ModId::from_usize(owner)), name),
+            None => name.to_string(),
+        }
+    }
+
+    /// Whether `module` is `ancestor` or one of its submodules, at any depth.
+    fn is_descendant(&self, mut module: ModId, ancestor: ModId) -> bool {
+        loop {
+            if module == ancestor {
+                return true;
+            }
+            match self.module(module).parent {
+                Some(parent) => module = parent,
+                None => return false,
+            }
+        }
+    }
+
+    /// Whether a binding of module `owner` can be named from module `from`.
+    fn is_accessible(&self, binding: &Binding, owner: ModId, from: ModId) -> bool {
+        binding.vis == Visibility::Public || self.is_descendant(from, owner)
+    }
+
+    /// Resolves a path written in `module` through the modules it names.
+    pub(crate) fn resolve_path_prefix<'p>(
+        &self,
+        module: ModId,
+        path: &'p ast::Path,
+        diagnostics: &mut Vec<Diagnostic>,
+    ) -> PathRes<'p> {
+        let [first, rest @ ..] = &path.segments[..] else {
+            unreachable!("paths have at least one segment")
+        };
+        let Some(mut def) = self.resolve_name(module, first.name) else {
+            return PathRes::Undeclared;
+        };
+        let mut rest = rest;
+        while let (Def::Mod(owner), [ident, after @ ..]) = (def, rest) {
+            let Some(binding) = self.module(owner).bindings.get(&ident.name) else {
+                diagnostics.push(Diagnostic::error(
+                    ident.span,
+                    format!(
+                        "cannot find `{}` in module `{}`",
+                        ident.name,
+                        self.module_path(owner)
+                    ),
+                ));
+                return PathRes::Failed;
+            };
+            if !self.is_accessible(binding, owner, module) {
+                diagnostics.push(self.private_item(ident, binding.def));
+            }
+            def = binding.def;
+            rest = after;
+        }
+        let ident = &path.segments[path.segments.len() - rest.len() - 1];
+        if let Some(note) = self
+            .deprecations
+            .get(&def)
+            .filter(|_| !self.is_lint_allowed(module, "deprecated", ident.span))
+        {
+            let mut diag = Diagnostic::warning(
+                ident.span,
+                format!(
+                    "use of deprecated {} `{}`",
+                    self.describe_def(def),
+                    ident.name
+                ),
+            );
+            if let Some(note) = note {
+                diag = diag.note(note.to_string());
+            }
+            diagnostics.push(diag);
+        }
+        PathRes::Def(def, rest)
+    }
+
+    /// Whether `@allow` silences a lint at a span of `module`, on the span's
+    /// item or statement, or on the module.
+    fn is_lint_allowed(&self, module: ModId, lint: &str, span: Span) -> bool {
+        let by_module = self.module(module).allowed_lints.iter();
+        let by_span = self.allowed_spans.iter().filter_map(|&(name, allowed)| {
+            let inside = allowed.start_pos <= span.start_pos
+                && span.start_pos < allowed.start_pos + allowed.len;
+            inside.then_some(name)
+        });
+        by_module
+            .copied()
+            .chain(by_span)
+            .any(|name| name.as_str() == lint)
+    }
+
+    fn private_item(&self, ident: &ast::Ident, def: Def) -> Diagnostic {
+        Diagnostic::error(
+            ident.span,
+            format!("{} `{}` is private", self.describe_def(def), ident.name),
+        )
+        .note("items without `pub` can only be used in their module and its submodules")
+    }
+
+    /// Suggests importing an item of another module named `name`, when a
+    /// name is not in scope in `module`.
+    pub(crate) fn import_suggestion(&self, module: ModId, name: Symbol) -> Option<String> {
+        self.modules.iter().enumerate().find_map(|(i, other)| {
+            let owner = ModId::from_usize(i);
+            let binding = other.bindings.get(&name)?;
+            let found = owner != module
+                && !binding.import
+                && !matches!(binding.def, Def::Mod(_))
+                && self.is_accessible(binding, owner, module);
+            let path = match owner {
+                ModId::ROOT => name.to_string(),
+                _ => format!("{}::{}", self.module_path(owner), name),
+            };
+            found.then(|| format!("consider importing it: `import {};`", path))
+        })
+    }
+}
+
+/// Collects the lints silenced by `@allow` on the items and statements of a
+/// module, with the span they are silenced in.
+pub(crate) fn collect_allowed_spans(module: &ast::Module) -> Vec<(Symbol, Span)> {
+    struct AllowedSpans(Vec<(Symbol, Span)>);
+
+    impl<'ast> Visitor<'ast> for AllowedSpans {
+        fn visit_item(&mut self, item: &'ast ast::Item) {
+            let lints = attr::allowed_lints(&item.attrs);
+            self.0.extend(lints.map(|name| (name, item.span)));
+            visit::walk_item(self, item)
+        }
+        fn visit_stmt(&mut self, stmt: &'ast ast::Stmt) {
+            let lints = attr::allowed_lints(&stmt.attrs);
+            self.0.extend(lints.map(|name| (name, stmt.span)));
+            visit::walk_stmt(self, stmt)
+        }
+    }
+
+    let mut visitor = AllowedSpans(Vec::new());
+    visitor.visit_module(module);
+    visitor.0
+}
+
+/// Defines the names of the items of a module and of its submodules, and
+/// returns every item with the module it is in. Imports are bound later by
+/// `resolve_imports`, once every module has its items.
+pub(crate) fn collect_module<'ast>(
+    tcx: &mut Tcx,
+    id: ModId,
+    module: &'ast ast::Module,
+    items: &mut Vec<(ModId, &'ast ast::Item)>,
+    diagnostics: &mut Vec<Diagnostic>,
+) {
+    for item in &module.items {
+        items.push((id, item));
+        let def = match &item.kind {
+            // A package added by several dependencies is the same module
+            // everywhere, the one collected where it was first added.
+            ast::ItemKind::Mod(ast::ModDef {
+                same_as: Some(first),
+                ..
+            }) => tcx.item_def(*first),
+            ast::ItemKind::Mod(def) => {
+                let child = ModId::from_usize(tcx.modules.len());
+                let package = match def.package {
+                    true => child,
+                    false => tcx.module(id).package,
+                };
+                let mut allowed_lints = tcx.module(id).allowed_lints.clone();
+                allowed_lints.extend(attr::allowed_lints(&item.attrs));
+                tcx.modules.push(ModuleDef {
+                    name: item.ident.name,
+                    parent: Some(id),
+                    package,
+                    allowed_lints,
+                    bindings: HashMap::new(),
+                });
+                match &def.module {
+                    Some(module) => collect_module(tcx, child, module, items, diagnostics),
+                    None => diagnostics.push(
+                        Diagnostic::error(
+                            item.span,
+                            format!("file not found for module `{}`", item.ident.name),
+                        )
+                        .note("the files of modules are loaded by the driver"),
+                    ),
+                }
+                Def::Mod(child)
+            }
+            ast::ItemKind::Import(_) => continue,
+            _ => tcx.create_def(item),
+        };
+        if let Some(deprecation) = attr::deprecation(&item.attrs) {
+            tcx.deprecations.insert(def, deprecation.note);
+        }
+        tcx.item_defs.insert(item.id, def);
+        // Impls have no name to be referred to by.
+        if !matches!(def, Def::Impl(_)) {
+            define(tcx, id, item, def, false, diagnostics);
+        }
+    }
+}
+
+/// Binds the name of an item or an import in the scope of a module.
+fn define(
+    tcx: &mut Tcx,
+    module: ModId,
+    item: &ast::Item,
+    def: Def,
+    import: bool,
+    diagnostics: &mut Vec<Diagnostic>,
+) {
+    let binding = Binding {
+        def,
+        vis: item.vis,
+        import,
+        span: item.span,
+    };
+    match tcx.modules[module.as_usize()]
+        .bindings
+        .entry(item.ident.name)
+    {
+        Entry::Occupied(_) => diagnostics.push(Diagnostic::error(
+            item.ident.span,
+            format!("the name `{}` is defined multiple times", item.ident.name),
+        )),
+        Entry::Vacant(entry) => {
+            entry.insert(binding);
+        }
+    }
+}
+
+/// How an import path resolved.
+enum ImportRes {
+    /// The item, and the module the import depends on for it.
+    Resolved(Def, ModId),
+    /// A segment is bound by an import which isn't resolved yet.
+    Blocked,
+    Failed(Diagnostic),
+}
+
+/// Binds the imports of every module, then checks that modules don't import
+/// each other in a cycle.
+pub(crate) fn resolve_imports(
+    tcx: &mut Tcx,
+    items: &[(ModId, &ast::Item)],
+    diagnostics: &mut Vec<Diagnostic>,
+) {
+    let mut pending: Vec<(ModId, &ast::Item)> = items
+        .iter()
+        .copied()
+        .filter(|(_, item)| matches!(item.kind, ast::ItemKind::Import(_)))
+        .collect();
+    // The modules each module imports from, with the import.
+    let mut edges: Vec<Vec<(ModId, Span)>> = vec![Vec::new(); tcx.modules.len()];
+    // Imports may name what other imports bind, so they are resolved until
+    // no more can be.
+    loop {
+        let unresolved: HashSet<(ModId, Symbol)> = pending
+            .iter()
+            .map(|(module, item)| (*module, item.ident.name))
+            .collect();
+        let before = pending.len();
+        let mut blocked = Vec::new();
+        for (module, item) in pending {
+            let ast::ItemKind::Import(path) = &item.kind else {
+                unreachable!()
+            };
+            match resolve_import(tcx, module, item, path, &unresolved) {
+                ImportRes::Resolved(def, target) => {
+                    if target != module {
+                        edges[module.as_usize()].push((target, item.span));
+                    }
+                    define(tcx, module, item, def, true, diagnostics);
+                }
+                ImportRes::Blocked => blocked.push((module, item)),
+                ImportRes::Failed(diag) => diagnostics.push(diag),
+            }
+        }
+        pending = blocked;
+        if pending.len() == before || pending.is_empty() {
+            break;
+        }
+    }
+    // The rest import each other.
+    for (_, item) in pending {
+        let ast::ItemKind::Import(path) = &item.kind else {
+            unreachable!()
+        };
+        diagnostics.push(
+            Diagnostic::error(
+                item.span,
+                format!("unresolved import `{}`", collect::path_to_string(path)),
+            )
+            .note("the imports re-export each other in a cycle"),
+        );
+    }
+    check_import_cycles(tcx, &edges, diagnostics);
+}
+
+fn resolve_import(
+    tcx: &Tcx,
+    module: ModId,
+    item: &ast::Item,
+    path: &ast::Path,
+    unresolved: &HashSet<(ModId, Symbol)>,
+) -> ImportRes {
+    let mut owner = tcx.module(module).package;
+    for (i, ident) in path.segments.iter().enumerate() {
+        let Some(binding) = tcx.module(owner).bindings.get(&ident.name) else {
+            if unresolved.contains(&(owner, ident.name)) {
+                return ImportRes::Blocked;
+            }
+            let place = match owner {
+                ModId::ROOT => "the root module".to_string(),
+                _ if tcx.module(owner).package == owner => {
+                    format!("the root module of package `{}`", tcx.module(owner).name)
+                }
+                _ => format!("module `{}`", tcx.module_path(owner)),
+            };
+            return ImportRes::Failed(
+                Diagnostic::error(
+                    ident.span,
+                    format!("unresolved import `{}`", collect::path_to_string(path)),
+                )
+                .note(format!("there is no `{}` in {}", ident.name, place)),
+            );
+        };
+        if !tcx.is_accessible(binding, owner, module) {
+            return ImportRes::Failed(tcx.private_item(ident, binding.def));
+        }
+        if i == path.segments.len() - 1 {
+            if item.vis == Visibility::Public && binding.vis == Visibility::Private {
+                return ImportRes::Failed(
+                    Diagnostic::error(
+                        ident.span,
+                        format!(
+                            "{} `{}` is private, and cannot be re-exported",
+                            tcx.describe_def(binding.def),
+                            ident.name
+                        ),
+                    )
+                    .help(format!("make `{}` public, or remove the `pub`", ident.name)),
+                );
+            }
+            // Importing a module depends on that module, not on the one
+            // defining it.
+            let target = match binding.def {
+                Def::Mod(id) => id,
+                _ => owner,
+            };
+            return ImportRes::Resolved(binding.def, target);
+        }
+        match binding.def {
+            Def::Mod(id) => owner = id,
+            def => {
+                return ImportRes::Failed(Diagnostic::error(
+                    ident.span,
+                    format!(
+                        "expected module, found {} `{}`",
+                        tcx.describe_def(def),
+                        ident.name
+                    ),
+                ))
+            }
+        }
+    }
+    unreachable!("paths have at least one segment")
+}
+
+/// Reports the imports which close a cycle of modules importing each other.
+fn check_import_cycles(tcx: &Tcx, edges: &[Vec<(ModId, Span)>], diagnostics: &mut Vec<Diagnostic>) {
+    #[derive(Clone, Copy, PartialEq)]
+    enum State {
+        Unvisited,
+        /// On the path from the module the search started at.
+        Active,
+        Done,
+    }
+
+    fn visit(
+        tcx: &Tcx,
+        module: ModId,
+        edges: &[Vec<(ModId, Span)>],
+        states: &mut [State],
+        stack: &mut Vec<ModId>,
+        diagnostics: &mut Vec<Diagnostic>,
+    ) {
+        states[module.as_usize()] = State::Active;
+        stack.push(module);
+        for &(target, span) in &edges[module.as_usize()] {
+            match states[target.as_usize()] {
+                State::Unvisited => visit(tcx, target, edges, states, stack, diagnostics),
+                State::Active => {
+                    let start = stack.iter().position(|&m| m == target).unwrap();
+                    let cycle: Vec<String> = stack[start..]
+                        .iter()
+                        .chain([&target])
+                        .map(|&m| format!("`{}`", tcx.module_path(m)))
+                        .collect();
+                    diagnostics.push(
+                        Diagnostic::error(span, "import cycle not allowed")
+                            .note(format!(
+                                "the modules import each other: {}",
+                                cycle.join(" -> ")
+                            ))
+                            .help("move the items they share into a module of their own"),
+                    );
+                }
+                State::Done => (),
+            }
+        }
+        stack.pop();
+        states[module.as_usize()] = State::Done;
+    }
+
+    let mut states = vec![State::Unvisited; edges.len()];
+    for i in 0..edges.len() {
+        if states[i] == State::Unvisited {
+            let module = ModId::from_usize(i);
+            visit(
+                tcx,
+                module,
+                edges,
+                &mut states,
+                &mut Vec::new(),
+                diagnostics,
+            );
+        }
+    }
+}
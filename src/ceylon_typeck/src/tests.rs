@@ -0,0 +1,1940 @@
+use super::*;
+use ceylon_parser::visit::{self, Visitor};
+use expect_test::{expect, Expect};
+
+fn check(src: &str) -> (ast::Module, Tcx, Vec<Diagnostic>) {
+    let (module, diagnostics) = ceylon_parser::parse_file(src);
+    assert!(diagnostics.is_empty(), "{:?}", diagnostics);
+    let (tcx, diagnostics) = check_module(&module);
+    (module, tcx, diagnostics)
+}
+
+fn check_diagnostics(src: &str, expect: Expect) {
+    let (_, _, diagnostics) = check(src);
+    let actual: String = diagnostics
+        .iter()
+        .map(|diag| diag.render(src, "test.cy"))
+        .collect();
+    expect.assert_eq(&actual)
+}
+
+/// Prints the type of every variable declared by a `let` statement.
+fn check_local_types(src: &str, expect: Expect) {
+    struct Locals<'a> {
+        tcx: &'a Tcx,
+        out: String,
+        in_let: bool,
+    }
+    impl<'ast> Visitor<'ast> for Locals<'_> {
+        fn visit_local(&mut self, local: &'ast ast::Local) {
+            self.in_let = true;
+            self.visit_pat(&local.pat);
+            self.in_let = false;
+            if let Some(ty) = &local.ty {
+                self.visit_ty(ty);
+            }
+            self.visit_expr(&local.init);
+        }
+        fn visit_pat(&mut self, pat: &'ast ast::Pat) {
+            if let (true, ast::PatKind::Ident(_, ident)) = (self.in_let, &pat.kind) {
+                let ty = &self.tcx.typeck_results.node_types[&pat.id];
+                self.out += &format!("{}: {}\n", ident.name, self.tcx.ty_to_string(ty));
+            }
+            visit::walk_pat(self, pat)
+        }
+    }
+
+    let (module, tcx, diagnostics) = check(src);
+    assert!(diagnostics.is_empty(), "{:?}", diagnostics);
+    let mut locals = Locals {
+        tcx: &tcx,
+        out: String::new(),
+        in_let: false,
+    };
+    locals.visit_module(&module);
+    expect.assert_eq(&locals.out)
+}
+
+fn check_layouts(src: &str, expect: Expect) {
+    let (_, tcx, diagnostics) = check(src);
+    assert!(diagnostics.is_empty(), "{:?}", diagnostics);
+    let mut actual = String::new();
+    for (i, adt) in tcx.adts.iter().enumerate() {
+        // Generic ADTs only have layouts once instantiated.
+        if !adt.generics.params.is_empty() {
+            continue;
+        }
+        let layout = tcx
+            .layout_of(&Ty::Adt(AdtId::from_usize(i), vec![]))
+            .unwrap();
+        actual += &format!(
+            "{}: size {}, align {}, offsets {:?}\n",
+            adt.name, layout.size, layout.align, layout.field_offsets
+        );
+        if let Some(variants) = &layout.variants {
+            actual += &format!("    tag size {}\n", variants.tag_size);
+            for (variant, layout) in adt.variants.iter().zip(&variants.variants) {
+                actual += &format!("    {}: offsets {:?}\n", variant.name, layout.field_offsets);
+            }
+        }
+    }
+    expect.assert_eq(&actual)
+}
+
+#[test]
+fn test_local_types() {
+    check_local_types(
+        r#"
+struct Point { x: u64, y: u64 }
+struct Line { from: Point, to: Point }
+fn f(l: Line) {
+    let a = l.from;
+    let b = a.x + 1;
+    let c: u64 = 2;
+    let d = Point { x: b, y: c } .y == 3;
+    let e = 'e';
+}
+"#,
+        expect![[r#"
+            a: Point
+            b: u64
+            c: u64
+            d: bool
+            e: char
+        "#]],
+    )
+}
+
+#[test]
+fn test_field_assign() {
+    check_diagnostics(
+        r#"
+struct Point { x: i64, y: i64 }
+struct Line { from: Point, to: Point }
+fn f(mut l: Line, mut p: Point) {
+    l.from.x = 1;
+    l.to = p;
+    p = Point { x: 1, y: 2 };
+}
+"#,
+        expect![[""]],
+    )
+}
+
+#[test]
+fn test_layout() {
+    check_layouts(
+        r#"
+struct Empty {}
+struct Point { x: i64, y: i64 }
+struct Padded { a: bool, b: i64, c: bool }
+struct Small { a: bool, b: char }
+struct Nested { tag: char, p: Padded, name: str }
+"#,
+        expect![[r#"
+            Empty: size 0, align 1, offsets []
+            Point: size 16, align 8, offsets [0, 8]
+            Padded: size 24, align 8, offsets [0, 8, 16]
+            Small: size 8, align 4, offsets [0, 4]
+            Nested: size 48, align 8, offsets [0, 8, 32]
+        "#]],
+    )
+}
+
+#[test]
+fn test_recursive_struct() {
+    check_diagnostics(
+        r#"
+struct List { next: List }
+struct A { b: B }
+struct B { a: A }
+struct C { a: A }
+struct Cb { f: fn(Cb) -> i64, g: fn() -> Option<Cb> }
+struct Node { v: i64, kids: [Node] }
+enum Tree { Leaf, Branch(i64, [Tree]) }
+struct Grid { cells: [Grid; 2] }
+"#,
+        expect![[r#"
+            error: recursive type `List` has infinite size
+             --> test.cy:2:1
+              |
+            2 | struct List { next: List }
+              | ^^^^^^^^^^^^^^^^^^^^^^^^^^
+              = note: a type cannot contain itself, directly or through other types
+            error: recursive type `A` has infinite size
+             --> test.cy:3:1
+              |
+            3 | struct A { b: B }
+              | ^^^^^^^^^^^^^^^^^
+              = note: a type cannot contain itself, directly or through other types
+            error: recursive type `Grid` has infinite size
+             --> test.cy:9:1
+              |
+            9 | struct Grid { cells: [Grid; 2] }
+              | ^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^
+              = note: a type cannot contain itself, directly or through other types
+        "#]],
+    )
+}
+
+#[test]
+fn test_too_big() {
+    check_diagnostics(
+        r#"
+struct Huge { a: [i64; 4611686018427387904] }
+struct Halves { a: [u8; 6000000000000000000], b: [u8; 6000000000000000000] }
+struct HasHuge { h: Huge, w: Wrap<[i64; 4611686018427387904]> }
+struct Wrap<T> { t: T }
+enum E { A([i64; 4611686018427387904]) }
+fn f(a: [i64; 4611686018427387904]) {
+    let b = [0; 4611686018427387904];
+    let c: Wrap<[u8; 3]> = Wrap { t: [0; 3] };
+    let d: [i32; 4611686018427387904] = [0; 4611686018427387904];
+}
+"#,
+        expect![[r#"
+            error: the type `[i64; 4611686018427387904]` is too big
+             --> test.cy:2:15
+              |
+            2 | struct Huge { a: [i64; 4611686018427387904] }
+              |               ^^^^^^^^^^^^^^^^^^^^^^^^^^^^^
+              = note: a value can take at most 9223372036854775807 bytes
+            error: the type `Halves` is too big
+             --> test.cy:3:1
+              |
+            3 | struct Halves { a: [u8; 6000000000000000000], b: [u8; 6000000000000000000] }
+              | ^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^
+              = note: a value can take at most 9223372036854775807 bytes
+            error: the type `Wrap<[i64; 4611686018427387904]>` is too big
+             --> test.cy:4:27
+              |
+            4 | struct HasHuge { h: Huge, w: Wrap<[i64; 4611686018427387904]> }
+              |                           ^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^
+              = note: a value can take at most 9223372036854775807 bytes
+            error: the type `[i64; 4611686018427387904]` is too big
+             --> test.cy:6:12
+              |
+            6 | enum E { A([i64; 4611686018427387904]) }
+              |            ^^^^^^^^^^^^^^^^^^^^^^^^^^
+              = note: a value can take at most 9223372036854775807 bytes
+            error: the type `[i64; 4611686018427387904]` is too big
+             --> test.cy:7:9
+              |
+            7 | fn f(a: [i64; 4611686018427387904]) {
+              |         ^^^^^^^^^^^^^^^^^^^^^^^^^^
+              = note: a value can take at most 9223372036854775807 bytes
+            error: the type `[i64; 4611686018427387904]` is too big
+             --> test.cy:8:13
+              |
+            8 |     let b = [0; 4611686018427387904];
+              |             ^^^^^^^^^^^^^^^^^^^^^^^^
+              = note: a value can take at most 9223372036854775807 bytes
+            error: the type `[i32; 4611686018427387904]` is too big
+              --> test.cy:10:12
+               |
+            10 |     let d: [i32; 4611686018427387904] = [0; 4611686018427387904];
+               |            ^^^^^^^^^^^^^^^^^^^^^^^^^^
+               = note: a value can take at most 9223372036854775807 bytes
+            error: the type `[i32; 4611686018427387904]` is too big
+              --> test.cy:10:41
+               |
+            10 |     let d: [i32; 4611686018427387904] = [0; 4611686018427387904];
+               |                                         ^^^^^^^^^^^^^^^^^^^^^^^^
+               = note: a value can take at most 9223372036854775807 bytes
+        "#]],
+    )
+}
+
+#[test]
+fn test_struct_errors() {
+    check_diagnostics(
+        r#"
+struct Point { x: i64, y: i64, x: bool }
+struct Bad { a: Unknown }
+fn f(mut p: Point) {
+    p.z;
+    p.x.y;
+    Point { x: 1, z: 2 };
+    Point { x: 1, x: 2, y: 3 };
+    Point {};
+    Nope { a: 1 };
+    f { a: 1 };
+    let q: Point = 1;
+    p.x = true;
+    1 = 2;
+    p == p;
+    -'a';
+    y;
+}
+fn Point() {}
+"#,
+        expect![[r#"
+            error: the name `Point` is defined multiple times
+              --> test.cy:19:4
+               |
+            19 | fn Point() {}
+               |    ^^^^^
+            error: field `x` is already declared
+             --> test.cy:2:32
+              |
+            2 | struct Point { x: i64, y: i64, x: bool }
+              |                                ^
+            error: cannot find type `Unknown` in this scope
+             --> test.cy:3:17
+              |
+            3 | struct Bad { a: Unknown }
+              |                 ^^^^^^^
+            error: no field `z` on type `Point`
+             --> test.cy:5:7
+              |
+            5 |     p.z;
+              |       ^
+            error: no field `y` on type `i64`
+             --> test.cy:6:9
+              |
+            6 |     p.x.y;
+              |         ^
+            error: struct `Point` has no field named `z`
+             --> test.cy:7:19
+              |
+            7 |     Point { x: 1, z: 2 };
+              |                   ^
+            error: missing field `y` in initializer of `Point`
+             --> test.cy:7:5
+              |
+            7 |     Point { x: 1, z: 2 };
+              |     ^^^^^^^^^^^^^^^^^^^^
+            error: field `x` specified more than once
+             --> test.cy:8:19
+              |
+            8 |     Point { x: 1, x: 2, y: 3 };
+              |                   ^
+            error: missing fields `x`, `y` in initializer of `Point`
+             --> test.cy:9:5
+              |
+            9 |     Point {};
+              |     ^^^^^^^^
+            error: cannot find struct `Nope` in this scope
+              --> test.cy:10:5
+               |
+            10 |     Nope { a: 1 };
+               |     ^^^^
+            error: expected struct, found function `f`
+              --> test.cy:11:5
+               |
+            11 |     f { a: 1 };
+               |     ^
+            error: mismatched types
+              --> test.cy:12:20
+               |
+            12 |     let q: Point = 1;
+               |                    ^
+               = note: expected `Point`, found `i64`
+            error: mismatched types
+              --> test.cy:13:11
+               |
+            13 |     p.x = true;
+               |           ^^^^
+               = note: expected `i64`, found `bool`
+            error: invalid left-hand side of assignment
+              --> test.cy:14:5
+               |
+            14 |     1 = 2;
+               |     ^
+               = note: only variables, their fields and their elements can be assigned to
+            error: binary operation `==` cannot be applied to type `Point`
+              --> test.cy:15:5
+               |
+            15 |     p == p;
+               |     ^^^^^^
+               = note: an implementation of `Eq` might be missing for `Point`
+            error: cannot apply unary operator `-` to type `char`
+              --> test.cy:16:5
+               |
+            16 |     -'a';
+               |     ^^^^
+            error: cannot find value `y` in this scope
+              --> test.cy:17:5
+               |
+            17 |     y;
+               |     ^
+        "#]],
+    )
+}
+
+#[test]
+fn test_enum_layout() {
+    check_layouts(
+        r#"
+enum Never {}
+enum Ordering { Less, Equal, Greater }
+enum Shape { Circle(f64), Rect { w: f64, h: f64 }, Dot }
+enum Small { A(bool, char), B(u64) }
+"#,
+        expect![[r#"
+            Never: size 0, align 1, offsets []
+                tag size 0
+            Ordering: size 1, align 1, offsets []
+                tag size 1
+                Less: offsets []
+                Equal: offsets []
+                Greater: offsets []
+            Shape: size 24, align 8, offsets []
+                tag size 1
+                Circle: offsets [8]
+                Rect: offsets [8, 16]
+                Dot: offsets []
+            Small: size 16, align 8, offsets []
+                tag size 1
+                A: offsets [1, 4]
+                B: offsets [8]
+        "#]],
+    )
+}
+
+#[test]
+fn test_match_types() {
+    check_local_types(
+        r#"
+enum Shape { Circle(f64), Rect { w: f64, h: f64 } }
+fn area(s: Shape) -> f64 {
+    let a = match s {
+        Shape::Circle(r) => r * r * 3.14,
+        Shape::Rect { w, h: height } if w > 0.0 => w * height,
+        Shape::Rect { .. } => 0.0,
+    };
+    let n = match 3 { 1 | 2 => true, x => x > 5 };
+    a
+}
+"#,
+        expect![[r#"
+            a: f64
+            n: bool
+        "#]],
+    )
+}
+
+#[test]
+fn test_non_exhaustive() {
+    check_diagnostics(
+        r#"
+enum Shape { Circle(f64), Rect { w: f64, h: f64 }, Dot }
+enum Pair { P(bool, bool) }
+fn f(s: Shape, p: Pair, n: i64, b: bool) {
+    match s { Shape::Circle(_) => {} }
+    match s { Shape::Dot => {} }
+    match p { Pair::P(true, _) => {}, Pair::P(_, true) => {} }
+    match n { 0 => {} 1 => {} }
+    match b { true if n > 0 => {} false => {} }
+    match b { true | false => {} }
+}
+"#,
+        expect![[r#"
+            error: non-exhaustive patterns: patterns `Shape::Rect { .. }` and `Shape::Dot` not covered
+             --> test.cy:5:5
+              |
+            5 |     match s { Shape::Circle(_) => {} }
+              |     ^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^
+              = help: add arms for the missing patterns, or a wildcard `_` arm
+            error: non-exhaustive patterns: patterns `Shape::Circle(_)` and `Shape::Rect { .. }` not covered
+             --> test.cy:6:5
+              |
+            6 |     match s { Shape::Dot => {} }
+              |     ^^^^^^^^^^^^^^^^^^^^^^^^^^^^
+              = help: add arms for the missing patterns, or a wildcard `_` arm
+            error: non-exhaustive patterns: pattern `Pair::P(false, false)` not covered
+             --> test.cy:7:5
+              |
+            7 |     match p { Pair::P(true, _) => {}, Pair::P(_, true) => {} }
+              |     ^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^
+              = help: add arms for the missing patterns, or a wildcard `_` arm
+            error: non-exhaustive patterns: pattern `_` not covered
+             --> test.cy:8:5
+              |
+            8 |     match n { 0 => {} 1 => {} }
+              |     ^^^^^^^^^^^^^^^^^^^^^^^^^^^
+              = help: add arms for the missing patterns, or a wildcard `_` arm
+            error: non-exhaustive patterns: pattern `true` not covered
+             --> test.cy:9:5
+              |
+            9 |     match b { true if n > 0 => {} false => {} }
+              |     ^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^
+              = help: add arms for the missing patterns, or a wildcard `_` arm
+        "#]],
+    )
+}
+
+#[test]
+fn test_unreachable_arms() {
+    check_diagnostics(
+        r#"
+enum Shape { Circle(f64), Rect { w: f64, h: f64 }, Dot }
+fn f(s: Shape, n: i64) {
+    match s {
+        _ => {}
+        Shape::Dot => {}
+    }
+    match s {
+        Shape::Dot | Shape::Circle(_) => {}
+        Shape::Rect { .. } | Shape::Dot => {}
+        Shape::Circle(_) | Shape::Dot => {}
+    }
+    match n {
+        1 => {}
+        x if x > 2 => {}
+        1 | 2 => {}
+        _ => {}
+    }
+}
+"#,
+        expect![[r#"
+            warning: unreachable pattern
+             --> test.cy:6:9
+              |
+            6 |         Shape::Dot => {}
+              |         ^^^^^^^^^^
+            warning: unreachable pattern
+              --> test.cy:10:30
+               |
+            10 |         Shape::Rect { .. } | Shape::Dot => {}
+               |                              ^^^^^^^^^^
+            warning: unreachable pattern
+              --> test.cy:11:9
+               |
+            11 |         Shape::Circle(_) | Shape::Dot => {}
+               |         ^^^^^^^^^^^^^^^^^^^^^^^^^^^^^
+            warning: unreachable pattern
+              --> test.cy:16:9
+               |
+            16 |         1 | 2 => {}
+               |         ^
+        "#]],
+    )
+}
+
+#[test]
+fn test_pattern_errors() {
+    check_diagnostics(
+        r#"
+enum Shape { Circle(f64), Rect { w: f64, h: f64 }, Dot }
+fn f(s: Shape, n: i64) {
+    match s {
+        Shape::Circle(a, b) => {}
+        Shape::Rect { w } => {}
+        Shape::Rect { w, d, .. } => {}
+        Shape::Square => {}
+        Shape::Dot(x) => {}
+        Circle(r) => {}
+    }
+    match n {
+        1.5 => {}
+        true => {}
+        x | y => {}
+    }
+    match n {}
+    let a = match n { 1 => 1, _ => 'a' };
+    let x = Shape::Circle(1.0, 2.0);
+    let y = Shape::Rect;
+    let z: Shape = Nope::A;
+}
+"#,
+        expect![[r#"
+            error: this pattern has 2 fields, but the corresponding tuple variant has 1 field
+             --> test.cy:5:9
+              |
+            5 |         Shape::Circle(a, b) => {}
+              |         ^^^^^^^^^^^^^^^^^^^
+            error: pattern does not mention field `h`
+             --> test.cy:6:9
+              |
+            6 |         Shape::Rect { w } => {}
+              |         ^^^^^^^^^^^^^^^^^
+              = help: ignore the remaining fields with `..`
+            error: variant `Shape::Rect` does not have a field named `d`
+             --> test.cy:7:26
+              |
+            7 |         Shape::Rect { w, d, .. } => {}
+              |                          ^
+            error: no variant named `Square` in enum `Shape`
+             --> test.cy:8:16
+              |
+            8 |         Shape::Square => {}
+              |                ^^^^^^
+            error: expected tuple variant, found unit variant `Shape::Dot`
+             --> test.cy:9:9
+              |
+            9 |         Shape::Dot(x) => {}
+              |         ^^^^^^^^^^
+            error: cannot find tuple variant `Circle` in this scope
+              --> test.cy:10:9
+               |
+            10 |         Circle(r) => {}
+               |         ^^^^^^
+            error: floating-point literals cannot be used in patterns
+              --> test.cy:13:9
+               |
+            13 |         1.5 => {}
+               |         ^^^
+            error: mismatched types
+              --> test.cy:14:9
+               |
+            14 |         true => {}
+               |         ^^^^
+               = note: expected `i64`, found `bool`
+            error: variable `y` is not bound in all patterns
+              --> test.cy:15:13
+               |
+            15 |         x | y => {}
+               |             ^
+            error: variable `x` is not bound in all patterns
+              --> test.cy:15:13
+               |
+            15 |         x | y => {}
+               |             ^
+            error: non-exhaustive patterns: pattern `_` not covered
+              --> test.cy:17:5
+               |
+            17 |     match n {}
+               |     ^^^^^^^^^^
+               = help: add arms for the missing patterns, or a wildcard `_` arm
+            error: mismatched types
+              --> test.cy:18:36
+               |
+            18 |     let a = match n { 1 => 1, _ => 'a' };
+               |                                    ^^^
+               = note: expected `{integer}`, found `char`
+            error: this enum variant takes 1 argument but 2 arguments were supplied
+              --> test.cy:19:13
+               |
+            19 |     let x = Shape::Circle(1.0, 2.0);
+               |             ^^^^^^^^^^^^^
+            error: expected value, found struct variant `Shape::Rect`
+              --> test.cy:20:13
+               |
+            20 |     let y = Shape::Rect;
+               |             ^^^^^^^^^^^
+            error: failed to resolve: use of undeclared type or module `Nope`
+              --> test.cy:21:20
+               |
+            21 |     let z: Shape = Nope::A;
+               |                    ^^^^
+        "#]],
+    )
+}
+
+#[test]
+fn test_generic_types() {
+    check_local_types(
+        r#"
+struct Pair<A, B> { a: A, b: B }
+enum Option<T> { Some(T), None }
+fn max<T: Ord>(a: T, b: T) -> T { match a > b { true => a, false => b } }
+fn swap<A, B>(p: Pair<A, B>) -> Pair<B, A> { Pair { a: p.b, b: p.a } }
+fn f(n: u64) {
+    let a = max(1, n);
+    let b = max(1, 2);
+    let p = swap(Pair { a: 'c', b: true });
+    let c = p.a;
+    let o = Option::None;
+    let q: Option<Pair<bool, i64>> = o;
+    let r = match Option::Some(2.0) { Option::Some(x) => x, Option::None => 0.0 };
+}
+"#,
+        expect![[r#"
+            a: u64
+            b: i64
+            p: Pair<bool, char>
+            c: bool
+            o: Option<Pair<bool, i64>>
+            q: Option<Pair<bool, i64>>
+            r: f64
+        "#]],
+    )
+}
+
+#[test]
+fn test_generic_errors() {
+    check_diagnostics(
+        r#"
+struct Point { x: i64 }
+struct Sorted<T: Ord> { t: T }
+fn max<T: Ord>(a: T, b: T) -> T { a }
+fn add<T>(a: T, b: T) -> T { a + b }
+fn eq<T, T: Eq + Hash>(a: T) -> bool { a == a }
+fn f(s: Sorted<Point>, t: Sorted, u: Sorted<i64, i64>, v: T<i64>) {
+    let a = max(Point { x: 1 }, Point { x: 2 });
+    let b = max(1, true);
+    let c = Option::None;
+    let d = c.x;
+}
+enum Option<T> { Some(T), None }
+fn g<T>(x: T<i64>) {}
+fn h() { let o = Option::None; }
+fn k() -> Sorted<Point> { Sorted { t: Point { x: 1 } } }
+"#,
+        expect![[r#"
+            error: the name `T` is already used for a generic parameter
+             --> test.cy:6:10
+              |
+            6 | fn eq<T, T: Eq + Hash>(a: T) -> bool { a == a }
+              |          ^
+            error: cannot find trait `Hash` in this scope
+             --> test.cy:6:18
+              |
+            6 | fn eq<T, T: Eq + Hash>(a: T) -> bool { a == a }
+              |                  ^^^^
+            error: the trait bound `Point: Ord` is not satisfied
+             --> test.cy:7:16
+              |
+            7 | fn f(s: Sorted<Point>, t: Sorted, u: Sorted<i64, i64>, v: T<i64>) {
+              |                ^^^^^
+              = note: required by a bound in `Sorted`
+            error: missing generics for struct `Sorted`
+             --> test.cy:7:27
+              |
+            7 | fn f(s: Sorted<Point>, t: Sorted, u: Sorted<i64, i64>, v: T<i64>) {
+              |                           ^^^^^^
+              = help: add the missing generic arguments, e.g. `Sorted<_>`
+            error: struct takes 1 generic argument but 2 generic arguments were supplied
+             --> test.cy:7:45
+              |
+            7 | fn f(s: Sorted<Point>, t: Sorted, u: Sorted<i64, i64>, v: T<i64>) {
+              |                                             ^^^
+            error: cannot find type `T` in this scope
+             --> test.cy:7:59
+              |
+            7 | fn f(s: Sorted<Point>, t: Sorted, u: Sorted<i64, i64>, v: T<i64>) {
+              |                                                           ^
+            error: type arguments are not allowed on type parameter `T`
+              --> test.cy:14:14
+               |
+            14 | fn g<T>(x: T<i64>) {}
+               |              ^^^
+            error: the trait bound `Point: Ord` is not satisfied
+              --> test.cy:16:18
+               |
+            16 | fn k() -> Sorted<Point> { Sorted { t: Point { x: 1 } } }
+               |                  ^^^^^
+               = note: required by a bound in `Sorted`
+            error: binary operation `+` cannot be applied to type `T`
+             --> test.cy:5:30
+              |
+            5 | fn add<T>(a: T, b: T) -> T { a + b }
+              |                              ^^^^^
+              = help: consider restricting type parameter `T` with trait `Add`
+            error: binary operation `==` cannot be applied to type `T`
+             --> test.cy:6:40
+              |
+            6 | fn eq<T, T: Eq + Hash>(a: T) -> bool { a == a }
+              |                                        ^^^^^^
+              = help: consider restricting type parameter `T` with trait `Eq`
+            error: mismatched types
+             --> test.cy:9:20
+              |
+            9 |     let b = max(1, true);
+              |                    ^^^^
+              = note: expected `{integer}`, found `bool`
+            error: no field `x` on type `Option<_>`
+              --> test.cy:11:15
+               |
+            11 |     let d = c.x;
+               |               ^
+            error: the trait bound `Point: Ord` is not satisfied
+             --> test.cy:8:13
+              |
+            8 |     let a = max(Point { x: 1 }, Point { x: 2 });
+              |             ^^^
+              = note: required by a bound in `max`
+            error: type annotations needed
+              --> test.cy:15:18
+               |
+            15 | fn h() { let o = Option::None; }
+               |                  ^^^^^^^^^^^^
+            error: the trait bound `Point: Ord` is not satisfied
+              --> test.cy:16:27
+               |
+            16 | fn k() -> Sorted<Point> { Sorted { t: Point { x: 1 } } }
+               |                           ^^^^^^^^^^^^^^^^^^^^^^^^^^^^
+               = note: required by a bound in `Sorted`
+        "#]],
+    )
+}
+
+#[test]
+fn test_method_types() {
+    check_local_types(
+        r#"
+trait Shape { fn area(self) -> f64; }
+trait Scale { fn scaled(self, k: f64) -> Self; }
+struct Circle { r: f64 }
+impl Circle { fn new(r: f64) -> Circle { Circle { r: r } } }
+impl Shape for Circle { fn area(self) -> f64 { 3.0 * self.r * self.r } }
+impl Scale for Circle { fn scaled(self, k: f64) -> Circle { Circle { r: self.r * k } } }
+fn f<T: Scale>(t: T, s: dyn Shape) {
+    let a = Circle::new(1.0);
+    let b = a.scaled(2.0);
+    let c = t.scaled(2.0);
+    let d = s.area();
+    let e = Shape::area(b);
+    let g: dyn Shape = a;
+}
+"#,
+        expect![[r#"
+            a: Circle
+            b: Circle
+            c: T
+            d: f64
+            e: f64
+            g: dyn Shape
+        "#]],
+    );
+}
+
+#[test]
+fn test_trait_errors() {
+    check_diagnostics(
+        r#"
+trait Shape { fn area(self) -> f64; fn name(self) -> str; }
+trait Make { fn make() -> Self; }
+struct Circle { r: f64 }
+struct Square { s: f64 }
+impl Shape for Circle {
+    fn area(self) -> i64 { 1 }
+    fn perimeter(self) -> f64 { 1.0 }
+}
+impl Shape for Circle { fn area(self) -> f64 { 1.0 } fn name(self) -> str { "c" } }
+impl i64 { fn double(self) -> i64 { self } }
+impl<T> Square { fn f(self) {} }
+fn f(c: Circle, s: Square, m: dyn Make) {
+    let a = s.area();
+    let b = Circle::area(c);
+    let d: dyn Shape = s;
+    let e = Circle::make();
+}
+"#,
+        expect![[r#"
+            error: cannot define inherent `impl` for primitive types
+              --> test.cy:11:6
+               |
+            11 | impl i64 { fn double(self) -> i64 { self } }
+               |      ^^^
+               = help: define a trait and implement it for the type instead
+            error: the type parameter `T` is not constrained by the impl self type
+              --> test.cy:12:6
+               |
+            12 | impl<T> Square { fn f(self) {} }
+               |      ^
+            error: method `area` has an incompatible type for trait `Shape`
+             --> test.cy:7:8
+              |
+            7 |     fn area(self) -> i64 { 1 }
+              |        ^^^^
+              = note: expected `fn(Circle) -> f64`, found `fn(Circle) -> i64`
+            error: method `perimeter` is not a member of trait `Shape`
+             --> test.cy:8:8
+              |
+            8 |     fn perimeter(self) -> f64 { 1.0 }
+              |        ^^^^^^^^^
+            error: not all trait items implemented, missing: `name`
+             --> test.cy:6:16
+              |
+            6 | impl Shape for Circle {
+              |                ^^^^^^
+            error: conflicting implementations of trait `Shape` for type `Circle`
+              --> test.cy:10:1
+               |
+            10 | impl Shape for Circle { fn area(self) -> f64 { 1.0 } fn name(self) -> str { "c" } }
+               | ^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^
+               = note: an implementation for `Circle` already exists
+            error: the trait `Make` cannot be made into an object
+              --> test.cy:13:31
+               |
+            13 | fn f(c: Circle, s: Square, m: dyn Make) {
+               |                               ^^^^^^^^
+               = note: associated function `make` has no `self` parameter
+            error: no method named `area` found for type `Square` in the current scope
+              --> test.cy:14:15
+               |
+            14 |     let a = s.area();
+               |               ^^^^
+               = help: the trait `Shape` defines an item `area`, perhaps you need to implement it
+            error: no function or associated item named `area` found for struct `Circle`
+              --> test.cy:15:21
+               |
+            15 |     let b = Circle::area(c);
+               |                     ^^^^
+            error: no function or associated item named `make` found for struct `Circle`
+              --> test.cy:17:21
+               |
+            17 |     let e = Circle::make();
+               |                     ^^^^
+            error: the trait bound `Square: Shape` is not satisfied
+              --> test.cy:16:24
+               |
+            16 |     let d: dyn Shape = s;
+               |                        ^
+               = note: required for the cast from `Square` to `dyn Shape`
+        "#]],
+    );
+}
+
+#[test]
+fn test_method_lookup_errors() {
+    check_diagnostics(
+        r#"
+trait A { fn go(self) -> i64; }
+trait B { fn go(self) -> i64; }
+struct S { x: i64 }
+impl A for S { fn go(self) -> i64 { 1 } }
+impl B for S { fn go(self) -> i64 { 2 } }
+impl S { fn new() -> S { S { x: 1 } } fn get(self) -> i64 { self.x } }
+impl S { fn get(self) -> i64 { 0 } }
+fn f(s: S) {
+    let a = s.go();
+    let b = s.new();
+    let c = s.missing();
+    let d = s.get(1);
+    let e = A::stop(s);
+    let g = S::nope();
+}
+"#,
+        expect![[r#"
+            error: duplicate definitions with name `get`
+             --> test.cy:8:10
+              |
+            8 | impl S { fn get(self) -> i64 { 0 } }
+              |          ^^^^^^^^^^^^^^^^^^^^^^^^^
+            error: multiple applicable items in scope
+              --> test.cy:10:15
+               |
+            10 |     let a = s.go();
+               |               ^^
+               = note: candidates are defined in the trait `A` and the trait `B`
+            error: no method named `new` found for type `S` in the current scope
+              --> test.cy:11:15
+               |
+            11 |     let b = s.new();
+               |               ^^^
+               = note: `new` is an associated function, not a method
+            error: no method named `missing` found for type `S` in the current scope
+              --> test.cy:12:15
+               |
+            12 |     let c = s.missing();
+               |               ^^^^^^^
+            error: multiple applicable items in scope
+              --> test.cy:13:15
+               |
+            13 |     let d = s.get(1);
+               |               ^^^
+               = note: candidates are defined in an impl for `S` and an impl for `S`
+            error: cannot find method `stop` in trait `A`
+              --> test.cy:14:16
+               |
+            14 |     let e = A::stop(s);
+               |                ^^^^
+            error: no function or associated item named `nope` found for struct `S`
+              --> test.cy:15:16
+               |
+            15 |     let g = S::nope();
+               |                ^^^^
+        "#]],
+    );
+}
+
+#[test]
+fn test_operator_types() {
+    check_local_types(
+        r#"
+struct V { x: i64 }
+impl Add for V { fn add(self, rhs: V) -> V { V { x: self.x + rhs.x } } }
+impl Neg for V { fn neg(self) -> V { V { x: -self.x } } }
+impl Eq for V { fn eq(self, other: V) -> bool { self.x == other.x } }
+impl Ord for V { fn lt(self, other: V) -> bool { self.x < other.x } }
+fn sum<T: Add>(a: T, b: T) -> T { a + b }
+fn f(v: V) {
+    let a = v + v;
+    let b = -v;
+    let c = v == v;
+    let d = v >= v;
+    let e = sum(v, v);
+    let g = sum(1, 2);
+    let h = v.add(v);
+}
+"#,
+        expect![[r#"
+            a: V
+            b: V
+            c: bool
+            d: bool
+            e: V
+            g: i64
+            h: V
+        "#]],
+    );
+}
+
+#[test]
+fn test_operator_errors() {
+    check_diagnostics(
+        r#"
+struct V { x: i64 }
+struct W { x: i64 }
+impl Ord for V { fn lt(self, other: V) -> bool { self.x < other.x } }
+impl Add for i64 { fn add(self, rhs: i64) -> i64 { self } }
+impl Neg for W { fn neg(self) -> i64 { self.x } }
+fn f(v: V, w: W) {
+    let a = v + v;
+    let b = w == w;
+    let c = -v;
+    let d = v < v;
+}
+"#,
+        expect![[r#"
+            error: only traits defined in the current module can be implemented for primitive types
+             --> test.cy:5:14
+              |
+            5 | impl Add for i64 { fn add(self, rhs: i64) -> i64 { self } }
+              |              ^^^
+              = note: `i64` implements the built-in traits already
+            error: the trait bound `V: Eq` is not satisfied
+             --> test.cy:4:14
+              |
+            4 | impl Ord for V { fn lt(self, other: V) -> bool { self.x < other.x } }
+              |              ^
+              = note: required by the trait `Ord`
+            error: method `neg` has an incompatible type for trait `Neg`
+             --> test.cy:6:21
+              |
+            6 | impl Neg for W { fn neg(self) -> i64 { self.x } }
+              |                     ^^^
+              = note: expected `fn(W) -> W`, found `fn(W) -> i64`
+            error: binary operation `+` cannot be applied to type `V`
+             --> test.cy:8:13
+              |
+            8 |     let a = v + v;
+              |             ^^^^^
+              = note: an implementation of `Add` might be missing for `V`
+            error: binary operation `==` cannot be applied to type `W`
+             --> test.cy:9:13
+              |
+            9 |     let b = w == w;
+              |             ^^^^^^
+              = note: an implementation of `Eq` might be missing for `W`
+            error: cannot apply unary operator `-` to type `V`
+              --> test.cy:10:13
+               |
+            10 |     let c = -v;
+               |             ^^
+               = note: an implementation of `Neg` might be missing for `V`
+        "#]],
+    );
+}
+
+#[test]
+fn test_closure_types() {
+    check_local_types(
+        r#"
+enum Option<T> { Some(T), None }
+fn map<T, U>(o: Option<T>, f: fn(T) -> U) -> Option<U> {
+    match o { Option::Some(x) => Option::Some(f(x)), Option::None => Option::None }
+}
+fn double(x: i64) -> i64 { x * 2 }
+fn f() {
+    let offset = 1;
+    let add = |x: i64| x + offset;
+    let is_big = |x| x > 10;
+    let big = is_big(add(2));
+    let mapped = map(Option::Some(1), |x| x == 1);
+    let d = double;
+    let m: fn(Option<char>, fn(char) -> i64) -> Option<i64> = map;
+    let log = || {};
+}
+"#,
+        expect![[r#"
+            offset: i64
+            add: fn(i64) -> i64
+            is_big: fn(i64) -> bool
+            big: bool
+            mapped: Option<bool>
+            d: fn(i64) -> i64
+            m: fn(Option<char>, fn(char) -> i64) -> Option<i64>
+            log: fn()
+        "#]],
+    );
+}
+
+#[test]
+fn test_closure_errors() {
+    check_diagnostics(
+        r#"
+fn f(g: fn(i64) -> bool) {
+    let x = 1;
+    x(2);
+    g(1, 2);
+    let h: fn(i64) -> bool = |a| a + 1;
+    let k = |a| a.field;
+    let c: fn(i64) = |a, b| {};
+}
+"#,
+        expect![[r#"
+            error: expected function, found `{integer}`
+             --> test.cy:4:5
+              |
+            4 |     x(2);
+              |     ^
+            error: this function takes 1 argument but 2 arguments were supplied
+             --> test.cy:5:5
+              |
+            5 |     g(1, 2);
+              |     ^
+            error: mismatched types
+             --> test.cy:6:34
+              |
+            6 |     let h: fn(i64) -> bool = |a| a + 1;
+              |                                  ^^^^^
+              = note: expected `bool`, found `i64`
+            error: type annotations needed
+             --> test.cy:7:17
+              |
+            7 |     let k = |a| a.field;
+              |                 ^
+            error: mismatched types
+             --> test.cy:8:22
+              |
+            8 |     let c: fn(i64) = |a, b| {};
+              |                      ^^^^^^^^^
+              = note: expected `fn(i64)`, found `fn(_, _)`
+        "#]],
+    );
+}
+
+#[test]
+fn test_module_types() {
+    check_local_types(
+        r#"
+mod geometry {
+    pub mod shapes {
+        pub enum Shape { Circle(i64), Square(i64) }
+        pub fn unit() -> Shape { Shape::Square(1) }
+    }
+    pub import geometry::shapes::Shape;
+    pub fn scale(s: Shape, k: i64) -> Shape {
+        match s { Shape::Circle(r) => Shape::Circle(r * k), Shape::Square(a) => Shape::Square(a * k) }
+    }
+}
+import geometry::scale;
+import geometry::Shape;
+fn f() {
+    let a = geometry::shapes::unit();
+    let b = scale(a, 2);
+    let c = geometry::shapes::Shape::Circle(3);
+    let d: Shape = geometry::scale(c, 2);
+}
+"#,
+        expect![[r#"
+            a: Shape
+            b: Shape
+            c: Shape
+            d: Shape
+        "#]],
+    );
+}
+
+#[test]
+fn test_module_errors() {
+    check_diagnostics(
+        r#"
+mod a {
+    fn secret() -> i64 { 1 }
+    pub struct Point { x: i64 }
+    pub fn f() -> i64 { secret() + helper() }
+    pub mod inner { pub fn g() -> i64 { 2 } }
+}
+fn helper() -> i64 { 0 }
+import a::secret;
+import a::missing;
+pub import a::inner;
+fn main() -> i64 {
+    let p = a::Point { x: 1 };
+    let q: Point = p;
+    a::secret() + a::nope() + a() + inner::g()
+}
+"#,
+        expect![[r#"
+            error: function `secret` is private
+             --> test.cy:9:11
+              |
+            9 | import a::secret;
+              |           ^^^^^^
+              = note: items without `pub` can only be used in their module and its submodules
+            error: unresolved import `a::missing`
+              --> test.cy:10:11
+               |
+            10 | import a::missing;
+               |           ^^^^^^^
+               = note: there is no `missing` in module `a`
+            error: cannot find function `helper` in this scope
+             --> test.cy:5:36
+              |
+            5 |     pub fn f() -> i64 { secret() + helper() }
+              |                                    ^^^^^^
+              = help: consider importing it: `import helper;`
+            error: cannot find type `Point` in this scope
+              --> test.cy:14:12
+               |
+            14 |     let q: Point = p;
+               |            ^^^^^
+               = help: consider importing it: `import a::Point;`
+            error: function `secret` is private
+              --> test.cy:15:8
+               |
+            15 |     a::secret() + a::nope() + a() + inner::g()
+               |        ^^^^^^
+               = note: items without `pub` can only be used in their module and its submodules
+            error: cannot find `nope` in module `a`
+              --> test.cy:15:22
+               |
+            15 |     a::secret() + a::nope() + a() + inner::g()
+               |                      ^^^^
+            error: expected function, found module `a`
+              --> test.cy:15:31
+               |
+            15 |     a::secret() + a::nope() + a() + inner::g()
+               |                               ^
+        "#]],
+    );
+}
+
+#[test]
+fn test_import_cycle() {
+    check_diagnostics(
+        r#"
+mod a { import b::g; pub fn f() -> i64 { g() } }
+mod b { import c::h; pub fn g() -> i64 { h() } }
+mod c { import a::f; pub fn h() -> i64 { f() } }
+mod d { pub import e::x; }
+mod e { pub import d::x; }
+"#,
+        expect![[r#"
+            error: unresolved import `e::x`
+             --> test.cy:5:9
+              |
+            5 | mod d { pub import e::x; }
+              |         ^^^^^^^^^^^^^^^^
+              = note: the imports re-export each other in a cycle
+            error: unresolved import `d::x`
+             --> test.cy:6:9
+              |
+            6 | mod e { pub import d::x; }
+              |         ^^^^^^^^^^^^^^^^
+              = note: the imports re-export each other in a cycle
+            error: import cycle not allowed
+             --> test.cy:4:9
+              |
+            4 | mod c { import a::f; pub fn h() -> i64 { f() } }
+              |         ^^^^^^^^^^^^
+              = note: the modules import each other: `a` -> `b` -> `c` -> `a`
+              = help: move the items they share into a module of their own
+        "#]],
+    );
+}
+
+#[test]
+fn test_attributes() {
+    check_diagnostics(
+        "@deprecated(\"use `Point`\") struct Pt { x: i64 }\n\
+         mod geo { @deprecated pub fn area() -> i64 { 1 } }\n\
+         fn f(p: Pt) -> i64 { match p.x { _ => 1, 2 => 2 } }\n\
+         @allow(unreachable_patterns) fn g() -> i64 { geo::area(); match 1 { _ => 1, 2 => 2 } }\n\
+         fn h() -> i64 { @allow(unreachable_patterns) let x = match 1 { _ => 1, 2 => 2 }; x }\n\
+         @test fn t1(x: i64) {}\n@test fn t2<T>() {}\n@test fn t3() {}\n\
+         @allow(deprecated) fn k(p: Pt) -> i64 { p.x }\n\
+         fn m() -> i64 { @allow(deprecated) let a = geo::area(); a + geo::area() }",
+        expect![[r#"
+            warning: use of deprecated struct `Pt`
+             --> test.cy:3:9
+              |
+            3 | fn f(p: Pt) -> i64 { match p.x { _ => 1, 2 => 2 } }
+              |         ^^
+              = note: use `Point`
+            error: test functions must not take arguments
+             --> test.cy:6:10
+              |
+            6 | @test fn t1(x: i64) {}
+              |          ^^
+            error: test functions must not be generic
+             --> test.cy:7:12
+              |
+            7 | @test fn t2<T>() {}
+              |            ^^^
+            warning: unreachable pattern
+             --> test.cy:3:42
+              |
+            3 | fn f(p: Pt) -> i64 { match p.x { _ => 1, 2 => 2 } }
+              |                                          ^
+            warning: use of deprecated function `area`
+             --> test.cy:4:51
+              |
+            4 | @allow(unreachable_patterns) fn g() -> i64 { geo::area(); match 1 { _ => 1, 2 => 2 } }
+              |                                                   ^^^^
+            warning: use of deprecated function `area`
+              --> test.cy:10:66
+               |
+            10 | fn m() -> i64 { @allow(deprecated) let a = geo::area(); a + geo::area() }
+               |                                                                  ^^^^
+        "#]],
+    );
+    let (_, tcx, _) = check("@inline fn f() {} fn g() {} @test fn t() {}");
+    let inline: Vec<bool> = tcx.fns.iter().map(|sig| sig.inline).collect();
+    assert!(inline.ends_with(&[true, false, false]));
+    assert_eq!(tcx.tests.len(), 1);
+}
+
+#[test]
+fn test_try_types() {
+    check_local_types(
+        r#"
+fn parse(s: str) -> Result<i64, str> { Result::Err(s) }
+fn f(o: Option<bool>) -> Option<i64> {
+    let a = o?;
+    let g = |s| { let b = parse(s)?; Result::Ok(b == 1) };
+    let c = g("1");
+    Option::None
+}
+"#,
+        expect![[r#"
+            a: bool
+            g: fn(str) -> Result<bool, str>
+            b: i64
+            c: Result<bool, str>
+        "#]],
+    );
+}
+
+#[test]
+fn test_try_errors() {
+    check_diagnostics(
+        r#"
+fn parse(s: str) -> Result<i64, str> { Result::Err(s) }
+fn a(o: Option<i64>) -> i64 { o? }
+fn b(s: str) -> Option<i64> { Option::Some(parse(s)?) }
+fn c(s: str) -> Result<i64, bool> { parse(s) }
+fn d(s: str) -> Result<i64, bool> { Result::Ok(parse(s)?) }
+fn e(n: i64) -> Option<i64> { n? }
+fn f() -> Result<i64, str> { let g = || -> i64 { parse("1")? }; Result::Ok(g()) }
+mod m { pub enum Option<T> { Some(T), None } }
+fn g(o: m::Option<i64>) -> Option<i64> { Option::Some(o?) }
+fn h(o: Option<i64>) -> m::Option<i64> { m::Option::Some(o?) }
+"#,
+        expect![[r#"
+            error: the `?` operator on an `Option` can only be used in a function that returns an `Option`
+             --> test.cy:3:31
+              |
+            3 | fn a(o: Option<i64>) -> i64 { o? }
+              |                               ^^
+              = note: the function returns `i64`
+            error: the `?` operator on a `Result` can only be used in a function that returns a `Result`
+             --> test.cy:4:44
+              |
+            4 | fn b(s: str) -> Option<i64> { Option::Some(parse(s)?) }
+              |                                            ^^^^^^^^^
+              = note: the function returns `Option<i64>`
+            error: mismatched types
+             --> test.cy:5:37
+              |
+            5 | fn c(s: str) -> Result<i64, bool> { parse(s) }
+              |                                     ^^^^^^^^
+              = note: expected `Result<i64, bool>`, found `Result<i64, str>`
+            error: `?` cannot return the error of a `Result` with another error type
+             --> test.cy:6:48
+              |
+            6 | fn d(s: str) -> Result<i64, bool> { Result::Ok(parse(s)?) }
+              |                                                ^^^^^^^^^
+              = note: the error type is `str`, but the function returns errors of type `bool`
+            error: the `?` operator can only be applied to values of type `Option` or `Result`
+             --> test.cy:7:31
+              |
+            7 | fn e(n: i64) -> Option<i64> { n? }
+              |                               ^^
+              = note: found `i64`
+            error: the `?` operator on a `Result` can only be used in a function that returns a `Result`
+             --> test.cy:8:50
+              |
+            8 | fn f() -> Result<i64, str> { let g = || -> i64 { parse("1")? }; Result::Ok(g()) }
+              |                                                  ^^^^^^^^^^^
+              = note: the function returns `i64`
+            error: the `?` operator can only be applied to values of type `Option` or `Result`
+              --> test.cy:10:55
+               |
+            10 | fn g(o: m::Option<i64>) -> Option<i64> { Option::Some(o?) }
+               |                                                       ^^
+               = note: found `Option<i64>`
+               = note: `Option` is the enum `m::Option`, not the built-in `Option`
+            error: the `?` operator on an `Option` can only be used in a function that returns an `Option`
+              --> test.cy:11:58
+               |
+            11 | fn h(o: Option<i64>) -> m::Option<i64> { m::Option::Some(o?) }
+               |                                                          ^^
+               = note: the function returns `Option<i64>`
+               = note: `Option` is the enum `m::Option`, not the built-in `Option`
+        "#]],
+    );
+}
+
+#[test]
+fn test_cast_errors() {
+    check_diagnostics(
+        r#"
+struct S {}
+fn f(x: i64, c: char, s: S) -> u64 {
+    let a = x as f64 as u64 + c as u64 + true as u64 + ~3 as u64;
+    let b = 1.5 as char;
+    let d = x as bool;
+    let e = s as i64;
+    let g = ~1.5;
+    a
+}
+"#,
+        expect![[r#"
+            error: casting `f64` as `char` is invalid
+             --> test.cy:5:13
+              |
+            5 |     let b = 1.5 as char;
+              |             ^^^^^^^^^^^
+            error: casting `i64` as `bool` is invalid
+             --> test.cy:6:13
+              |
+            6 |     let d = x as bool;
+              |             ^^^^^^^^^
+            error: non-primitive cast: `S` as `i64`
+             --> test.cy:7:13
+              |
+            7 |     let e = s as i64;
+              |             ^^^^^^^^
+              = note: only primitive types can be cast to other types
+            error: cannot apply unary operator `~` to type `f64`
+             --> test.cy:8:13
+              |
+            8 |     let g = ~1.5;
+              |             ^^^^
+        "#]],
+    );
+}
+
+#[test]
+fn test_int_literal_ranges() {
+    check_diagnostics(
+        r#"
+fn f(x: i32) -> u8 {
+    let a = 255u8 + 300u8;
+    let b: i8 = -128;
+    let c: i8 = -129 + 128;
+    let d: u128 = 340282366920938463463374607431768211455;
+    let e: i128 = -170141183460469231731687303715884105728;
+    let g = 1_000_000 + x;
+    let h: i64 = 9223372036854775808;
+    let i = 2147483648;
+    let j: u16 = i;
+    a
+}
+"#,
+        expect![[r#"
+            error: literal out of range for `u8`
+             --> test.cy:3:21
+              |
+            3 |     let a = 255u8 + 300u8;
+              |                     ^^^^^
+              = note: the literal `300` does not fit into the type `u8` whose range is `0..=255`
+            error: literal out of range for `i8`
+             --> test.cy:5:17
+              |
+            5 |     let c: i8 = -129 + 128;
+              |                 ^^^^
+              = note: the literal `-129` does not fit into the type `i8` whose range is `-128..=127`
+            error: literal out of range for `i8`
+             --> test.cy:5:24
+              |
+            5 |     let c: i8 = -129 + 128;
+              |                        ^^^
+              = note: the literal `128` does not fit into the type `i8` whose range is `-128..=127`
+            error: literal out of range for `i64`
+             --> test.cy:9:18
+              |
+            9 |     let h: i64 = 9223372036854775808;
+              |                  ^^^^^^^^^^^^^^^^^^^
+              = note: the literal `9223372036854775808` does not fit into the type `i64` whose range is `-9223372036854775808..=9223372036854775807`
+            error: literal out of range for `u16`
+              --> test.cy:10:13
+               |
+            10 |     let i = 2147483648;
+               |             ^^^^^^^^^^
+               = note: the literal `2147483648` does not fit into the type `u16` whose range is `0..=65535`
+        "#]],
+    );
+}
+
+#[test]
+fn test_float_literal_ranges() {
+    check_diagnostics(
+        r#"
+fn f() -> f32 {
+    let a = 340282366920938463463374607431768211455.0f32;
+    let b: f32 = 340282346638528859811704183484516925440.0;
+    let c: f32 = 1000000000000000000000000000000000000000.0;
+    let d = 1000000000000000000000000000000000000000.0;
+    a
+}
+"#,
+        expect![[r#"
+            error: literal out of range for `f32`
+             --> test.cy:3:13
+              |
+            3 |     let a = 340282366920938463463374607431768211455.0f32;
+              |             ^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^
+              = note: the largest finite `f32` is `3.4028235e38`
+            error: literal out of range for `f32`
+             --> test.cy:5:18
+              |
+            5 |     let c: f32 = 1000000000000000000000000000000000000000.0;
+              |                  ^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^
+              = note: the largest finite `f32` is `3.4028235e38`
+        "#]],
+    );
+    // No `f64` is as large as 10^309.
+    let src = format!("fn f() -> f64 {{ 1{}.0 }}", "0".repeat(309));
+    let (_, _, diagnostics) = check(&src);
+    let messages: Vec<&str> = diagnostics
+        .iter()
+        .map(|diag| diag.message.as_str())
+        .collect();
+    assert_eq!(messages, ["literal out of range for `f64`"]);
+}
+
+#[test]
+fn test_numeric_types() {
+    check_diagnostics(
+        r#"
+fn f(a: i8, b: u32, x: f32, s: usize) -> f32 {
+    let c = a + 1i16;
+    let d = b.checked_add(1);
+    let e: Option<u32> = d;
+    let g = x.wrapping_add(1.0);
+    let h = 65u8 as char;
+    let i = b as char;
+    let j = 1.5f32 as f64 + 2.5;
+    let k = -b;
+    s + 1;
+    x * 2.0
+}
+"#,
+        expect![[r#"
+            error: mismatched types
+             --> test.cy:3:17
+              |
+            3 |     let c = a + 1i16;
+              |                 ^^^^
+              = note: expected `i8`, found `i16`
+            error: no method named `wrapping_add` found for type `f32` in the current scope
+             --> test.cy:6:15
+              |
+            6 |     let g = x.wrapping_add(1.0);
+              |               ^^^^^^^^^^^^
+              = help: the trait `Wrapping` defines an item `wrapping_add`, perhaps you need to implement it
+            error: casting `u32` as `char` is invalid
+             --> test.cy:8:13
+              |
+            8 |     let i = b as char;
+              |             ^^^^^^^^^
+            error: cannot apply unary operator `-` to type `u32`
+              --> test.cy:10:13
+               |
+            10 |     let k = -b;
+               |             ^^
+        "#]],
+    );
+}
+
+#[test]
+fn test_arrays_and_tuples() {
+    check_local_types(
+        r#"
+fn f(xs: [u8]) {
+    let a = [1, 2, 3];
+    let b: [i32; 4] = [0; 4];
+    let s: [u8] = [7, 8];
+    let t = (a[0], xs[1], (true,));
+    let n = xs.len() + a.len();
+    let c = t.2.0;
+    let u = a[1..=2];
+    let v = xs[n..][0];
+    let r = 1..3;
+    let w = xs[r];
+}
+"#,
+        expect![[r#"
+            a: [i64; 3]
+            b: [i32; 4]
+            s: [u8]
+            t: (i64, u8, (bool,))
+            n: usize
+            c: bool
+            u: [i64]
+            v: u8
+            r: Range<usize>
+            w: [u8]
+        "#]],
+    );
+    check_diagnostics(
+        r#"
+fn f(xs: [i64], t: (i64, bool)) {
+    let a = [1, 2, 3];
+    let b = a[3] + a[2u8];
+    let c = t.2;
+    let d = t.0[0];
+    let e: [i64; 2] = a;
+    let s: [bool] = a;
+    let g = [1, true];
+    let h = 1..;
+    let mut m = [0; 2];
+    m[0..1] = xs;
+    let k = xs[0..true];
+    let l = a.len;
+    let r = 0..2u8;
+    let o = xs[r];
+}
+"#,
+        expect![[r#"
+            error: index out of bounds: the len is 3 but the index is 3
+             --> test.cy:4:15
+              |
+            4 |     let b = a[3] + a[2u8];
+              |               ^
+            error: mismatched types
+             --> test.cy:4:22
+              |
+            4 |     let b = a[3] + a[2u8];
+              |                      ^^^
+              = note: expected `usize`, found `u8`
+            error: no field `2` on type `(i64, bool)`
+             --> test.cy:5:15
+              |
+            5 |     let c = t.2;
+              |               ^
+            error: cannot index into a value of type `i64`
+             --> test.cy:6:13
+              |
+            6 |     let d = t.0[0];
+              |             ^^^
+            error: mismatched types
+             --> test.cy:7:23
+              |
+            7 |     let e: [i64; 2] = a;
+              |                       ^
+              = note: expected `[i64; 2]`, found `[{integer}; 3]`
+            error: mismatched types
+             --> test.cy:8:21
+              |
+            8 |     let s: [bool] = a;
+              |                     ^
+              = note: expected `[bool]`, found `[{integer}; 3]`
+            error: mismatched types
+             --> test.cy:9:17
+              |
+            9 |     let g = [1, true];
+              |                 ^^^^
+              = note: expected `{integer}`, found `bool`
+            error: a range without an end must be an index
+              --> test.cy:10:13
+               |
+            10 |     let h = 1..;
+               |             ^^^
+               = help: `base[start..]` is the slice of the elements from `start` on
+            error: invalid left-hand side of assignment
+              --> test.cy:12:5
+               |
+            12 |     m[0..1] = xs;
+               |     ^^^^^^^
+               = note: only variables, their fields and their elements can be assigned to
+            error: mismatched types
+              --> test.cy:13:19
+               |
+            13 |     let k = xs[0..true];
+               |                   ^^^^
+               = note: expected `usize`, found `bool`
+            error: no field `len` on type `[{integer}; 3]`
+              --> test.cy:14:15
+               |
+            14 |     let l = a.len;
+               |               ^^^
+            error: mismatched types
+              --> test.cy:16:16
+               |
+            16 |     let o = xs[r];
+               |                ^
+               = note: expected `usize`, found `u8`
+        "#]],
+    );
+}
+
+#[test]
+fn test_ranges_and_for_loops() {
+    check_local_types(
+        r#"
+enum E { A(u8), B(u8) }
+fn f(n: u32, xs: [E], a: [char; 2]) {
+    let r = 0..n;
+    let s = 1..=10;
+    for i in r { let k = i; }
+    for j in 0..10u8 { let l = j; }
+    for E::A(x) | E::B(x) in xs { let y = x; }
+    for c in a { let d = c; }
+}
+"#,
+        expect![[r#"
+            r: Range<u32>
+            s: RangeInclusive<i64>
+            k: u32
+            l: u8
+            y: u8
+            d: char
+        "#]],
+    );
+    check_diagnostics(
+        r#"
+fn f(n: u32, xs: [Option<i64>]) {
+    let r = 0..true;
+    for x in 1.0..2.0 {}
+    for x in n {}
+    for Option::Some(x) in xs {}
+    for i in 0..n { let j: i64 = i; }
+}
+"#,
+        expect![[r#"
+            error: mismatched types
+             --> test.cy:3:16
+              |
+            3 |     let r = 0..true;
+              |                ^^^^
+              = note: expected `{integer}`, found `bool`
+            error: `Range<f64>` is not an iterator
+             --> test.cy:4:14
+              |
+            4 |     for x in 1.0..2.0 {}
+              |              ^^^^^^^^
+              = note: only ranges of integers, arrays and slices can be iterated over
+            error: `u32` is not an iterator
+             --> test.cy:5:14
+              |
+            5 |     for x in n {}
+              |              ^
+              = note: only ranges of integers, arrays and slices can be iterated over
+            error: refutable pattern in `for` loop binding: pattern `Option::None` not covered
+             --> test.cy:6:9
+              |
+            6 |     for Option::Some(x) in xs {}
+              |         ^^^^^^^^^^^^^^^
+            error: mismatched types
+             --> test.cy:7:34
+              |
+            7 |     for i in 0..n { let j: i64 = i; }
+              |                                  ^
+              = note: expected `i64`, found `u32`
+        "#]],
+    );
+}
+
+#[test]
+fn test_mutability_and_compound_assignment() {
+    check_local_types(
+        r#"
+fn f(a: u8, b: bool, x: f64) {
+    let c = a & 3 | a ^ a << 2 >> 1;
+    let d = b & !b | b ^ true;
+    let e = x % 2.0;
+    let mut g = 7i16;
+    g %= 2;
+    g <<= g;
+}
+"#,
+        expect![[r#"
+            c: u8
+            d: bool
+            e: f64
+            g: i16
+        "#]],
+    );
+    check_diagnostics(
+        r#"
+struct P { x: i64, ys: [i64; 2] }
+enum E { A(i64), B(i64) }
+impl P { fn reset(self) { self.x = 0; } }
+fn f(p: P, mut q: P, s: str, e: E) {
+    let n = 1;
+    n += 1;
+    p.ys[0] = 2;
+    q.ys[1] <<= 3;
+    q.x %= 2.0;
+    q += q;
+    s += "a";
+    let b = 1.5 & 2.0;
+    1 += 2;
+    match e { E::A(mut x) | E::B(x) => {} }
+    let h = |y: i64| y = 1;
+}
+"#,
+        expect![[r#"
+            error: cannot assign to a part of immutable parameter `self`
+             --> test.cy:4:27
+              |
+            4 | impl P { fn reset(self) { self.x = 0; } }
+              |                           ^^^^^^
+            note: `self` is declared here
+             --> test.cy:4:19
+              |
+            4 | impl P { fn reset(self) { self.x = 0; } }
+              |                   ^^^^
+            error: cannot assign twice to immutable variable `n`
+             --> test.cy:7:5
+              |
+            7 |     n += 1;
+              |     ^
+              = help: consider adding `mut` to the declaration of `n`: `mut n`
+            error: cannot assign to a part of immutable parameter `p`
+             --> test.cy:8:5
+              |
+            8 |     p.ys[0] = 2;
+              |     ^^^^^^^
+              = help: consider adding `mut` to the declaration of `p`: `mut p`
+            note: `p` is declared here
+             --> test.cy:5:6
+              |
+            5 | fn f(p: P, mut q: P, s: str, e: E) {
+              |      ^
+            error: mismatched types
+              --> test.cy:10:12
+               |
+            10 |     q.x %= 2.0;
+               |            ^^^
+               = note: expected `i64`, found `f64`
+            error: binary assignment operation `+=` cannot be applied to type `P`
+              --> test.cy:11:5
+               |
+            11 |     q += q;
+               |     ^^^^^^
+               = note: an implementation of `Add` might be missing for `P`
+            error: binary assignment operation `+=` cannot be applied to type `str`
+              --> test.cy:12:5
+               |
+            12 |     s += "a";
+               |     ^^^^^^^^
+            error: cannot assign to immutable parameter `s`
+              --> test.cy:12:5
+               |
+            12 |     s += "a";
+               |     ^
+               = help: consider adding `mut` to the declaration of `s`: `mut s`
+            note: `s` is declared here
+             --> test.cy:5:22
+              |
+            5 | fn f(p: P, mut q: P, s: str, e: E) {
+              |                      ^
+            error: binary operation `&` cannot be applied to type `f64`
+              --> test.cy:13:13
+               |
+            13 |     let b = 1.5 & 2.0;
+               |             ^^^^^^^^^
+            error: invalid left-hand side of assignment
+              --> test.cy:14:5
+               |
+            14 |     1 += 2;
+               |     ^
+               = note: only variables, their fields and their elements can be assigned to
+            error: variable `x` is bound with different mutability in different patterns
+              --> test.cy:15:34
+               |
+            15 |     match e { E::A(mut x) | E::B(x) => {} }
+               |                                  ^
+            error: cannot assign to immutable parameter `y`
+              --> test.cy:16:22
+               |
+            16 |     let h = |y: i64| y = 1;
+               |                      ^
+               = help: consider adding `mut` to the declaration of `y`: `mut y`
+            note: `y` is declared here
+              --> test.cy:16:14
+               |
+            16 |     let h = |y: i64| y = 1;
+               |              ^
+        "#]],
+    );
+}
+
+#[test]
+fn test_tuple_patterns() {
+    check_local_types(
+        r#"
+fn f(t: (u8, Option<char>)) {
+    let (a, (b, mut c)) = (1, (true, t));
+    let (x,) = (t.1,);
+    let n = match t { (0, _) => 1, (_, Option::Some(_)) => 2, (_, Option::None) => 3 };
+    for (i, s) in [(1u16, "a")] { let j = i; }
+}
+"#,
+        expect![[r#"
+            a: i64
+            b: bool
+            c: (u8, Option<char>)
+            x: Option<char>
+            n: i64
+            j: u16
+        "#]],
+    );
+    check_diagnostics(
+        r#"
+fn f(t: (u8, bool), o: Option<i64>) {
+    match t { (0, true) => {}, (_, false) => {} }
+    match (t, o) { (_, Option::Some(_)) => {} }
+    let (a, b, c) = t;
+    let (x, y) = 1;
+    let Option::Some(z) = o;
+    match t { (_, _) => {}, (1, _) => {} }
+}
+"#,
+        expect![[r#"
+            error: non-exhaustive patterns: pattern `(_, true)` not covered
+             --> test.cy:3:5
+              |
+            3 |     match t { (0, true) => {}, (_, false) => {} }
+              |     ^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^
+              = help: add arms for the missing patterns, or a wildcard `_` arm
+            error: non-exhaustive patterns: pattern `(_, Option::None)` not covered
+             --> test.cy:4:5
+              |
+            4 |     match (t, o) { (_, Option::Some(_)) => {} }
+              |     ^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^
+              = help: add arms for the missing patterns, or a wildcard `_` arm
+            error: mismatched types
+             --> test.cy:5:9
+              |
+            5 |     let (a, b, c) = t;
+              |         ^^^^^^^^^
+              = note: expected a tuple with 2 elements, found one with 3 elements
+            error: mismatched types
+             --> test.cy:6:9
+              |
+            6 |     let (x, y) = 1;
+              |         ^^^^^^
+              = note: expected `{integer}`, found `(_, _)`
+            error: refutable pattern in local binding: pattern `Option::None` not covered
+             --> test.cy:7:9
+              |
+            7 |     let Option::Some(z) = o;
+              |         ^^^^^^^^^^^^^^^
+            warning: unreachable pattern
+             --> test.cy:8:29
+              |
+            8 |     match t { (_, _) => {}, (1, _) => {} }
+              |                             ^^^^^^
+        "#]],
+    );
+}
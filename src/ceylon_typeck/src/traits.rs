@@ -0,0 +1,265 @@
+//! Trait resolution: finding the impl a type uses for a trait, checking that
+//! impls don't overlap, and that traits used as `dyn` types can be.
+
+use super::*;
+use crate::collect::path_to_string;
+use crate::infer::InferCtxt;
+use crate::resolve::{ModId, PathRes};
+use crate::ty::{FnContainer, ImplDef, ImplId, TraitDef, TraitId};
+use ceylon_parser::visit::{self, Visitor};
+
+/// The function a call runs, once the generic arguments are known.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Instance {
+    /// A function with its generic arguments.
+    Fn(FnId, Vec<Ty>),
+    /// A method of a trait called on a `dyn` value, identified by its index
+    /// in the trait. The method is looked up in the vtable of the value.
+    Virtual(TraitId, usize),
+    /// A method of a built-in trait called on a type implementing it without
+    /// an impl, e.g. on a primitive type, which is an operator of the IR.
+    Intrinsic(FnId),
+}
+
+impl Tcx {
+    pub fn trait_def(&self, id: TraitId) -> &TraitDef {
+        &self.traits[id.as_usize()]
+    }
+
+    pub fn impl_def(&self, id: ImplId) -> &ImplDef {
+        &self.impls[id.as_usize()]
+    }
+
+    /// Returns the method of a trait with the given name.
+    pub fn trait_method(&self, id: TraitId, name: Symbol) -> Option<FnId> {
+        self.trait_def(id)
+            .methods
+            .iter()
+            .copied()
+            .find(|&method| self.fn_sig(method).name == name)
+    }
+
+    /// Finds the impl of a trait for a type, where `generics` declares the
+    /// type parameters in scope. Returns the impl and its generic arguments.
+    /// The type must not contain inference variables.
+    pub fn find_impl(
+        &self,
+        trait_id: TraitId,
+        ty: &Ty,
+        generics: &Generics,
+    ) -> Option<(ImplId, Vec<Ty>)> {
+        self.impls.iter().enumerate().find_map(|(i, imp)| {
+            if imp.trait_ref != Some(trait_id) {
+                return None;
+            }
+            let mut infcx = InferCtxt::default();
+            let args: Vec<Ty> = imp
+                .generics
+                .params
+                .iter()
+                .map(|param| infcx.next_var(false, param.span))
+                .collect();
+            if !infcx.unify(&imp.self_ty.subst(&args), ty) {
+                return None;
+            }
+            let args: Vec<Ty> = args.iter().map(|arg| infcx.resolve(arg)).collect();
+            let satisfied = args.iter().zip(&imp.generics.params).all(|(arg, param)| {
+                param
+                    .bounds
+                    .iter()
+                    .all(|&bound| self.satisfies(arg, bound, generics))
+            });
+            satisfied.then(|| (ImplId::from_usize(i), args))
+        })
+    }
+
+    /// Resolves a call of a function with fully known generic arguments to
+    /// the function which runs: methods of traits run the method of the
+    /// impl for the `Self` type, or the default body of the trait if the
+    /// impl doesn't define it.
+    pub fn resolve_instance(&self, fn_id: FnId, args: Vec<Ty>) -> Instance {
+        let sig = self.fn_sig(fn_id);
+        let FnContainer::Trait(trait_id) = sig.container else {
+            return Instance::Fn(fn_id, args);
+        };
+        if let Ty::Dyn(_) = args[0] {
+            let index = self
+                .trait_def(trait_id)
+                .methods
+                .iter()
+                .position(|&method| method == fn_id)
+                .unwrap();
+            return Instance::Virtual(trait_id, index);
+        }
+        if self.has_builtin_impl(trait_id, &args[0]) {
+            return Instance::Intrinsic(fn_id);
+        }
+        let (impl_id, mut impl_args) = self
+            .find_impl(trait_id, &args[0], &Generics::default())
+            .expect("the type checker made sure the trait is implemented");
+        let method = self
+            .impl_def(impl_id)
+            .methods
+            .iter()
+            .copied()
+            .find(|&method| self.fn_sig(method).name == sig.name);
+        match method {
+            Some(method) => {
+                // The impl's parameters come first, then the method's own.
+                impl_args.extend(args.into_iter().skip(1));
+                Instance::Fn(method, impl_args)
+            }
+            None => Instance::Fn(fn_id, args),
+        }
+    }
+
+    /// Explains why a trait cannot be used as a `dyn` type, if it can't.
+    /// The methods of such traits must be callable without knowing the type
+    /// of the value.
+    pub fn object_safety_violation(&self, id: TraitId) -> Option<String> {
+        self.trait_def(id).methods.iter().find_map(|&method| {
+            let sig = self.fn_sig(method);
+            let mut mentions_self = false;
+            for ty in sig.inputs.iter().skip(sig.has_self as usize) {
+                ty.walk(&mut |ty| mentions_self |= matches!(ty, Ty::Param(p) if p.index == 0));
+            }
+            sig.output
+                .walk(&mut |ty| mentions_self |= matches!(ty, Ty::Param(p) if p.index == 0));
+            if !sig.has_self {
+                Some(format!(
+                    "associated function `{}` has no `self` parameter",
+                    sig.name
+                ))
+            } else if sig.generics.params.len() > 1 {
+                Some(format!("method `{}` has generic type parameters", sig.name))
+            } else if mentions_self {
+                Some(format!(
+                    "method `{}` references the `Self` type in its parameters or return type",
+                    sig.name
+                ))
+            } else {
+                None
+            }
+        })
+    }
+}
+
+/// Checks that no two impls of a trait apply to the same type, and that
+/// inherent impls which may apply to the same type don't define the same
+/// method.
+pub(crate) fn check_coherence(tcx: &Tcx, diagnostics: &mut Vec<Diagnostic>) {
+    for (i, imp) in tcx.impls.iter().enumerate() {
+        for other in &tcx.impls[..i] {
+            if imp.trait_ref != other.trait_ref {
+                continue;
+            }
+            let Some(ty) = overlap(imp, other) else {
+                continue;
+            };
+            match imp.trait_ref {
+                Some(trait_id) => diagnostics.push(
+                    Diagnostic::error(
+                        imp.span,
+                        format!(
+                            "conflicting implementations of trait `{}` for type `{}`",
+                            tcx.trait_def(trait_id).name,
+                            tcx.ty_to_string(&ty)
+                        ),
+                    )
+                    .note(format!(
+                        "an implementation for `{}` already exists",
+                        tcx.ty_to_string(&other.self_ty)
+                    )),
+                ),
+                None => {
+                    for &method in &imp.methods {
+                        let sig = tcx.fn_sig(method);
+                        let duplicate = other
+                            .methods
+                            .iter()
+                            .any(|&other| tcx.fn_sig(other).name == sig.name);
+                        if duplicate {
+                            diagnostics.push(Diagnostic::error(
+                                sig.span,
+                                format!("duplicate definitions with name `{}`", sig.name),
+                            ));
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Returns a type both impls apply to, if there is one.
+fn overlap(a: &ImplDef, b: &ImplDef) -> Option<Ty> {
+    if a.self_ty == Ty::Error || b.self_ty == Ty::Error {
+        return None;
+    }
+    let mut infcx = InferCtxt::default();
+    let mut fresh = |imp: &ImplDef| -> Vec<Ty> {
+        imp.generics
+            .params
+            .iter()
+            .map(|param| infcx.next_var(false, param.span))
+            .collect()
+    };
+    let (a_args, b_args) = (fresh(a), fresh(b));
+    let a_ty = a.self_ty.subst(&a_args);
+    infcx
+        .unify(&a_ty, &b.self_ty.subst(&b_args))
+        .then(|| infcx.resolve(&a_ty))
+}
+
+/// Reports the `dyn` types naming traits which cannot be made into objects.
+pub(crate) fn check_dyn_tys(tcx: &Tcx, module: &ast::Module, diagnostics: &mut Vec<Diagnostic>) {
+    struct DynTys<'a> {
+        tcx: &'a Tcx,
+        /// The module the visited item is in.
+        scope: ModId,
+        diagnostics: &'a mut Vec<Diagnostic>,
+    }
+    impl<'ast> Visitor<'ast> for DynTys<'_> {
+        fn visit_item(&mut self, item: &'ast ast::Item) {
+            let ast::ItemKind::Mod(_) = item.kind else {
+                return visit::walk_item(self, item);
+            };
+            let Def::Mod(module) = self.tcx.item_def(item.id) else {
+                unreachable!()
+            };
+            let outer = std::mem::replace(&mut self.scope, module);
+            visit::walk_item(self, item);
+            self.scope = outer;
+        }
+
+        fn visit_ty(&mut self, ty: &'ast ast::Ty) {
+            if let ast::TyKind::Dyn(path) = &ty.kind {
+                // Unresolved paths were reported when the type was lowered.
+                let mut ignored = Vec::new();
+                let res = self.tcx.resolve_path_prefix(self.scope, path, &mut ignored);
+                if let PathRes::Def(Def::Trait(id), []) = res {
+                    if let Some(violation) = self.tcx.object_safety_violation(id) {
+                        self.diagnostics.push(
+                            Diagnostic::error(
+                                ty.span,
+                                format!(
+                                    "the trait `{}` cannot be made into an object",
+                                    path_to_string(path)
+                                ),
+                            )
+                            .note(violation),
+                        );
+                    }
+                }
+            }
+            visit::walk_ty(self, ty)
+        }
+    }
+
+    DynTys {
+        tcx,
+        scope: ModId::ROOT,
+        diagnostics,
+    }
+    .visit_module(module);
+}
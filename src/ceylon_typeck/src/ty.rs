@@ -0,0 +1,416 @@
+//! Semantic types. Unlike `ast::Ty`, which is what the user wrote, these have
+//! their names resolved.
+
+use ceylon_span::{Span, Symbol};
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Ty {
+    I8,
+    I16,
+    I32,
+    I64,
+    I128,
+    U8,
+    U16,
+    U32,
+    U64,
+    U128,
+    /// An unsigned integer as wide as a pointer, 64 bits.
+    Usize,
+    F32,
+    F64,
+    Bool,
+    Char,
+    Str,
+    Void,
+    /// A struct or an enum with its generic arguments.
+    Adt(AdtId, Vec<Ty>),
+    /// A type parameter of the function or ADT being checked.
+    Param(ParamTy),
+    /// A trait object: a value of any type implementing the trait, whose
+    /// methods are looked up at runtime.
+    Dyn(TraitId),
+    /// A function value, i.e. a closure or a function used as a value, with
+    /// the types of its parameters and its return type.
+    Fn(Vec<Ty>, Box<Ty>),
+    /// A tuple of one or more values.
+    Tuple(Vec<Ty>),
+    /// An array of values of the same type, with its length.
+    Array(Box<Ty>, u64),
+    /// An array of values of the same type whose length is only known at
+    /// runtime. Arrays are turned into slices where one is expected.
+    Slice(Box<Ty>),
+    /// A type which is not known yet. These only exist while type checking
+    /// a function.
+    Infer(InferTy),
+    /// The type of something which failed to type check. It is compatible with
+    /// every type so that a single mistake is not reported several times.
+    Error,
+}
+
+impl Ty {
+    pub fn is_integral(&self) -> bool {
+        self.is_signed_int()
+            || self.is_unsigned_int()
+            || matches!(self, Ty::Infer(InferTy::IntVar(_)))
+    }
+
+    pub fn is_float(&self) -> bool {
+        matches!(self, Ty::F32 | Ty::F64)
+    }
+
+    pub fn is_numeric(&self) -> bool {
+        self.is_integral() || self.is_float()
+    }
+
+    pub fn is_signed(&self) -> bool {
+        self.is_signed_int() || self.is_float() || matches!(self, Ty::Infer(InferTy::IntVar(_)))
+    }
+
+    fn is_signed_int(&self) -> bool {
+        matches!(self, Ty::I8 | Ty::I16 | Ty::I32 | Ty::I64 | Ty::I128)
+    }
+
+    fn is_unsigned_int(&self) -> bool {
+        matches!(
+            self,
+            Ty::U8 | Ty::U16 | Ty::U32 | Ty::U64 | Ty::U128 | Ty::Usize
+        )
+    }
+
+    /// The smallest and the largest value of an integer type.
+    pub fn int_range(&self) -> Option<(i128, u128)> {
+        let range = match self {
+            Ty::I8 => (i8::MIN as i128, i8::MAX as u128),
+            Ty::I16 => (i16::MIN as i128, i16::MAX as u128),
+            Ty::I32 => (i32::MIN as i128, i32::MAX as u128),
+            Ty::I64 => (i64::MIN as i128, i64::MAX as u128),
+            Ty::I128 => (i128::MIN, i128::MAX as u128),
+            Ty::U8 => (0, u8::MAX as u128),
+            Ty::U16 => (0, u16::MAX as u128),
+            Ty::U32 => (0, u32::MAX as u128),
+            Ty::U64 | Ty::Usize => (0, u64::MAX as u128),
+            Ty::U128 => (0, u128::MAX),
+            _ => return None,
+        };
+        Some(range)
+    }
+
+    /// Whether `e as to` is allowed for a value `e` of this type, which is
+    /// another type than `to`. The conversions are:
+    ///
+    /// - between integers, truncating to the width of the target and then
+    ///   sign-extending or zero-extending it,
+    /// - from integers to floats, rounding to the nearest float,
+    /// - between floats, rounding to the nearest `f32`,
+    /// - from floats to integers, rounding toward zero and saturating at the
+    ///   bounds of the target, with NaN converted to 0,
+    /// - from `char` to integers, its code point being truncated like an
+    ///   unsigned integer,
+    /// - from `bool` to integers, `true` being 1,
+    /// - from `u8` to `char`, which is the only integer type all of whose
+    ///   values are code points.
+    pub fn can_cast_to(&self, to: &Ty) -> bool {
+        match self {
+            Ty::U8 => to.is_numeric() || *to == Ty::Char,
+            Ty::Char | Ty::Bool => to.is_integral(),
+            ty => ty.is_numeric() && to.is_numeric(),
+        }
+    }
+
+    /// Whether the type is built into the language.
+    pub fn is_primitive(&self) -> bool {
+        !matches!(
+            self,
+            Ty::Adt(..)
+                | Ty::Param(_)
+                | Ty::Dyn(_)
+                | Ty::Fn(..)
+                | Ty::Tuple(_)
+                | Ty::Array(..)
+                | Ty::Slice(_)
+                | Ty::Infer(_)
+                | Ty::Error
+        )
+    }
+
+    /// Replaces the type parameters in a type with the corresponding
+    /// generic arguments.
+    pub fn subst(&self, args: &[Ty]) -> Ty {
+        match self {
+            Ty::Param(param) => args[param.index as usize].clone(),
+            Ty::Adt(id, adt_args) => {
+                Ty::Adt(*id, adt_args.iter().map(|ty| ty.subst(args)).collect())
+            }
+            Ty::Fn(inputs, output) => Ty::Fn(
+                inputs.iter().map(|ty| ty.subst(args)).collect(),
+                Box::new(output.subst(args)),
+            ),
+            Ty::Tuple(tys) => Ty::Tuple(tys.iter().map(|ty| ty.subst(args)).collect()),
+            Ty::Array(ty, len) => Ty::Array(Box::new(ty.subst(args)), *len),
+            Ty::Slice(ty) => Ty::Slice(Box::new(ty.subst(args))),
+            ty => ty.clone(),
+        }
+    }
+
+    /// Calls `f` on this type and every type it contains.
+    pub fn walk(&self, f: &mut impl FnMut(&Ty)) {
+        f(self);
+        match self {
+            Ty::Adt(_, args) => {
+                for arg in args {
+                    arg.walk(f);
+                }
+            }
+            Ty::Fn(inputs, output) => {
+                for input in inputs {
+                    input.walk(f);
+                }
+                output.walk(f);
+            }
+            Ty::Tuple(tys) => {
+                for ty in tys {
+                    ty.walk(f);
+                }
+            }
+            Ty::Array(ty, _) | Ty::Slice(ty) => ty.walk(f),
+            _ => {}
+        }
+    }
+}
+
+/// A type parameter, e.g. `T` in `fn max<T: Ord>(a: T, b: T) -> T`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ParamTy {
+    /// The position of the parameter in the generics of its item.
+    pub index: u32,
+    pub name: Symbol,
+}
+
+/// An inference variable.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum InferTy {
+    /// Any type.
+    TyVar(u32),
+    /// An integer type, falling back to `i64` if nothing else is known.
+    IntVar(u32),
+}
+
+/// The type parameters of a function or an ADT.
+///
+/// Methods have the parameters of their trait or impl first, followed by
+/// their own. The parameter of a trait is `Self`, the type implementing it.
+#[derive(Debug, Default)]
+pub struct Generics {
+    pub params: Vec<GenericParamDef>,
+    /// What `Self` stands for in a trait or an impl.
+    pub self_ty: Option<Ty>,
+}
+
+impl Generics {
+    /// Returns a `Ty::Param` for each parameter, i.e. the generic arguments
+    /// which leave types unchanged when substituted.
+    pub fn identity_args(&self) -> Vec<Ty> {
+        self.params
+            .iter()
+            .enumerate()
+            .map(|(i, param)| {
+                Ty::Param(ParamTy {
+                    index: i as u32,
+                    name: param.name,
+                })
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct GenericParamDef {
+    pub name: Symbol,
+    /// The traits the arguments for the parameter must implement.
+    pub bounds: Vec<TraitId>,
+    pub span: Span,
+}
+
+/// Index of a struct or an enum in `Tcx::adts`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct AdtId(u32);
+
+impl AdtId {
+    pub fn from_usize(n: usize) -> Self {
+        AdtId(n as u32)
+    }
+
+    pub fn as_usize(self) -> usize {
+        self.0 as usize
+    }
+}
+
+/// Index of a function in `Tcx::fns`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct FnId(u32);
+
+impl FnId {
+    pub fn from_usize(n: usize) -> Self {
+        FnId(n as u32)
+    }
+
+    pub fn as_usize(self) -> usize {
+        self.0 as usize
+    }
+}
+
+/// Index of a trait in `Tcx::traits`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct TraitId(u32);
+
+impl TraitId {
+    pub fn from_usize(n: usize) -> Self {
+        TraitId(n as u32)
+    }
+
+    pub fn as_usize(self) -> usize {
+        self.0 as usize
+    }
+}
+
+/// Index of an impl block in `Tcx::impls`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ImplId(u32);
+
+impl ImplId {
+    pub fn from_usize(n: usize) -> Self {
+        ImplId(n as u32)
+    }
+
+    pub fn as_usize(self) -> usize {
+        self.0 as usize
+    }
+}
+
+/// The definition of a struct or an enum. A struct is represented as an
+/// enum with a single variant.
+#[derive(Debug)]
+pub struct AdtDef {
+    pub name: Symbol,
+    pub kind: AdtKind,
+    pub generics: Generics,
+    pub variants: Vec<VariantDef>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AdtKind {
+    Struct,
+    Enum,
+}
+
+impl AdtDef {
+    pub fn is_enum(&self) -> bool {
+        self.kind == AdtKind::Enum
+    }
+
+    /// Returns the only variant of a struct.
+    pub fn non_enum_variant(&self) -> &VariantDef {
+        assert!(!self.is_enum());
+        &self.variants[0]
+    }
+
+    pub fn variant_index(&self, name: Symbol) -> Option<usize> {
+        self.variants
+            .iter()
+            .position(|variant| variant.name == name)
+    }
+
+    /// Formats a variant the way it is written in the source, e.g.
+    /// `Shape::Circle` or `Point`.
+    pub fn variant_path(&self, index: usize) -> String {
+        match self.kind {
+            AdtKind::Struct => self.name.to_string(),
+            AdtKind::Enum => format!("{}::{}", self.name, self.variants[index].name),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct VariantDef {
+    pub name: Symbol,
+    pub ctor_kind: CtorKind,
+    /// The fields of the variant. Fields of tuple variants are named `0`,
+    /// `1`, ...
+    pub fields: Vec<FieldDef>,
+    pub span: Span,
+}
+
+impl VariantDef {
+    pub fn field_index(&self, name: Symbol) -> Option<usize> {
+        self.fields.iter().position(|field| field.name == name)
+    }
+}
+
+/// How a variant is constructed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CtorKind {
+    /// `Rect { w: 1.0, h: 2.0 }`, or a struct.
+    Struct,
+    /// `Circle(1.0)`
+    Fn,
+    /// `Empty`
+    Const,
+}
+
+#[derive(Debug)]
+pub struct FieldDef {
+    pub name: Symbol,
+    pub ty: Ty,
+    pub span: Span,
+}
+
+/// The signature of a function.
+#[derive(Debug)]
+pub struct FnSig {
+    pub name: Symbol,
+    pub container: FnContainer,
+    pub generics: Generics,
+    /// The types of the parameters, starting with `self` for methods.
+    pub inputs: Vec<Ty>,
+    pub output: Ty,
+    /// Whether the function takes `self`, i.e. can be called with the
+    /// method call syntax.
+    pub has_self: bool,
+    /// False for a method of a trait without a default body.
+    pub has_body: bool,
+    /// Whether `@inline` asks for calls of the function to be inlined, a
+    /// hint for code generation.
+    pub inline: bool,
+    pub span: Span,
+}
+
+/// Where a function is defined.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FnContainer {
+    /// An item of the module.
+    Free,
+    Trait(TraitId),
+    Impl(ImplId),
+}
+
+/// `trait Shape { fn area(self) -> f64; }`
+#[derive(Debug)]
+pub struct TraitDef {
+    pub name: Symbol,
+    /// The methods of the trait, in declaration order.
+    pub methods: Vec<FnId>,
+    pub span: Span,
+}
+
+/// An impl block, either of a trait or of inherent methods.
+#[derive(Debug)]
+pub struct ImplDef {
+    pub generics: Generics,
+    /// The implemented trait, `None` for inherent methods.
+    pub trait_ref: Option<TraitId>,
+    pub self_ty: Ty,
+    pub methods: Vec<FnId>,
+    pub span: Span,
+}
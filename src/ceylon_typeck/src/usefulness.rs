@@ -0,0 +1,470 @@
+//! Exhaustiveness and reachability checking of `match` arms, and of the
+//! patterns of `let` statements and `for` loops, which must match every
+//! value.
+//!
+//! This is the usefulness algorithm from "Warnings for pattern matching" by
+//! Luc Maranget. A pattern is useful with respect to a list of rows of
+//! patterns if it matches a value which none of the rows match. An arm is
+//! unreachable if its pattern isn't useful with respect to the arms above it,
+//! and a match is exhaustive if the wildcard isn't useful with respect to all
+//! of its arms. Along the way the algorithm builds witnesses, patterns
+//! matching the values which are left over, to show in error messages.
+
+use super::*;
+use crate::ty::CtorKind;
+use ceylon_span::Span;
+
+/// A pattern reduced to what matters for usefulness.
+#[derive(Debug, Clone)]
+enum Pat {
+    Wild,
+    Ctor(Ctor, Vec<Pat>),
+    Or(Vec<Pat>),
+}
+
+/// What a pattern checks the head of a value against.
+#[derive(Debug, Clone, PartialEq)]
+enum Ctor {
+    /// A variant of an enum, or a struct.
+    Variant(usize),
+    /// The only constructor of a tuple type.
+    Tuple,
+    Bool(bool),
+    Int(i128),
+    Char(char),
+    Str(Symbol),
+}
+
+/// At most this many witnesses are computed.
+const MAX_WITNESSES: usize = 8;
+
+/// Reports unreachable arms of a match and values it doesn't cover.
+pub(crate) fn check_match(
+    tcx: &Tcx,
+    results: &TypeckResults,
+    span: Span,
+    scrutinee_ty: &Ty,
+    arms: &[ast::Arm],
+    lint_unreachable: bool,
+    diagnostics: &mut Vec<Diagnostic>,
+) {
+    if *scrutinee_ty == Ty::Error {
+        return;
+    }
+    // Patterns with errors have been reported already and would only lead to
+    // confusing results.
+    let Some(pats) = arms
+        .iter()
+        .map(|arm| lower_pat(tcx, results, &arm.pat))
+        .collect::<Option<Vec<Pat>>>()
+    else {
+        return;
+    };
+
+    let cx = MatchCx { tcx };
+    let tys = std::slice::from_ref(scrutinee_ty);
+    let mut rows: Vec<Vec<Pat>> = Vec::new();
+    for (arm, pat) in arms.iter().zip(&pats) {
+        let mut unreachable = Vec::new();
+        match (&arm.pat.kind, pat) {
+            // Alternatives are checked one by one, so that the redundant ones
+            // can be pointed out.
+            (ast::PatKind::Or(ast_alts), Pat::Or(alts)) => {
+                let mut alt_rows = rows.clone();
+                for (ast_alt, alt) in ast_alts.iter().zip(alts) {
+                    if !cx.is_useful(&alt_rows, alt, tys) {
+                        unreachable.push(ast_alt.span);
+                    }
+                    alt_rows.push(vec![alt.clone()]);
+                }
+                if unreachable.len() == alts.len() {
+                    unreachable = vec![arm.pat.span];
+                }
+            }
+            _ => {
+                if !cx.is_useful(&rows, pat, tys) {
+                    unreachable.push(arm.pat.span);
+                }
+            }
+        }
+        if lint_unreachable {
+            for span in unreachable {
+                diagnostics.push(Diagnostic::warning(span, "unreachable pattern"));
+            }
+        }
+        // An arm with a guard may not match even if its pattern does.
+        if arm.guard.is_none() {
+            rows.push(vec![pat.clone()]);
+        }
+    }
+
+    let witnesses = cx.usefulness(&rows, &[Pat::Wild], tys);
+    if !witnesses.is_empty() {
+        diagnostics.push(
+            Diagnostic::error(
+                span,
+                format!(
+                    "non-exhaustive patterns: {} not covered",
+                    cx.witness_list(&witnesses, scrutinee_ty)
+                ),
+            )
+            .help("add arms for the missing patterns, or a wildcard `_` arm"),
+        );
+    }
+}
+
+/// Reports the values a pattern which must always match doesn't cover, e.g.
+/// the one of a `for` loop. `what` names where the pattern is written.
+pub(crate) fn check_irrefutable(
+    tcx: &Tcx,
+    results: &TypeckResults,
+    pat: &ast::Pat,
+    ty: &Ty,
+    what: &str,
+    diagnostics: &mut Vec<Diagnostic>,
+) {
+    if *ty == Ty::Error {
+        return;
+    }
+    let Some(lowered) = lower_pat(tcx, results, pat) else {
+        return;
+    };
+    let cx = MatchCx { tcx };
+    let witnesses = cx.usefulness(&[vec![lowered]], &[Pat::Wild], std::slice::from_ref(ty));
+    if !witnesses.is_empty() {
+        diagnostics.push(Diagnostic::error(
+            pat.span,
+            format!(
+                "refutable pattern in {}: {} not covered",
+                what,
+                cx.witness_list(&witnesses, ty)
+            ),
+        ));
+    }
+}
+
+/// Converts a type checked pattern, or returns `None` if it has errors.
+fn lower_pat(tcx: &Tcx, results: &TypeckResults, pat: &ast::Pat) -> Option<Pat> {
+    if results.node_types.get(&pat.id)? == &Ty::Error {
+        return None;
+    }
+    let pat = match &pat.kind {
+        ast::PatKind::Wild | ast::PatKind::Ident(..) => Pat::Wild,
+        ast::PatKind::Lit(expr) => Pat::Ctor(lit_ctor(expr)?, Vec::new()),
+        ast::PatKind::Path(_) | ast::PatKind::TupleStruct(..) | ast::PatKind::Struct(..) => {
+            let Res::Variant(adt, index) = *results.path_res.get(&pat.id)? else {
+                return None;
+            };
+            let variant = &tcx.adt(adt).variants[index];
+            let mut fields = vec![Pat::Wild; variant.fields.len()];
+            match &pat.kind {
+                ast::PatKind::TupleStruct(_, pats) => {
+                    for (field, pat) in fields.iter_mut().zip(pats) {
+                        *field = lower_pat(tcx, results, pat)?;
+                    }
+                }
+                ast::PatKind::Struct(_, pat_fields, _) => {
+                    for field in pat_fields {
+                        let i = variant.field_index(field.ident.name)?;
+                        fields[i] = lower_pat(tcx, results, &field.pat)?;
+                    }
+                }
+                _ => (),
+            }
+            Pat::Ctor(Ctor::Variant(index), fields)
+        }
+        ast::PatKind::Tuple(pats) => Pat::Ctor(
+            Ctor::Tuple,
+            pats.iter()
+                .map(|pat| lower_pat(tcx, results, pat))
+                .collect::<Option<_>>()?,
+        ),
+        ast::PatKind::Or(alts) => Pat::Or(
+            alts.iter()
+                .map(|alt| lower_pat(tcx, results, alt))
+                .collect::<Option<_>>()?,
+        ),
+    };
+    Some(pat)
+}
+
+fn lit_ctor(expr: &ast::Expr) -> Option<Ctor> {
+    let ctor = match &expr.kind {
+        ast::ExprKind::Literal(lit) => match lit {
+            ast::LiteralKind::Int(n, _) => Ctor::Int(*n as i128),
+            ast::LiteralKind::Bool(b) => Ctor::Bool(*b),
+            ast::LiteralKind::Char(c) => Ctor::Char(*c),
+            ast::LiteralKind::Str(s) => Ctor::Str(*s),
+            ast::LiteralKind::Float(..) => return None,
+        },
+        ast::ExprKind::Unary(ast::UnOp::Neg, operand) => match lit_ctor(operand)? {
+            Ctor::Int(n) => Ctor::Int(-n),
+            _ => return None,
+        },
+        _ => return None,
+    };
+    Some(ctor)
+}
+
+struct MatchCx<'a> {
+    tcx: &'a Tcx,
+}
+
+impl MatchCx<'_> {
+    fn is_useful(&self, rows: &[Vec<Pat>], pat: &Pat, tys: &[Ty]) -> bool {
+        !self
+            .usefulness(rows, std::slice::from_ref(pat), tys)
+            .is_empty()
+    }
+
+    /// Returns witnesses of the values matched by the row `v` but by none of
+    /// `rows`, each a row of patterns. `tys` are the types of the columns.
+    fn usefulness(&self, rows: &[Vec<Pat>], v: &[Pat], tys: &[Ty]) -> Vec<Vec<Pat>> {
+        let Some((head, rest)) = v.split_first() else {
+            return if rows.is_empty() {
+                vec![Vec::new()]
+            } else {
+                Vec::new()
+            };
+        };
+        let rows = expand_or(rows);
+        let mut witnesses = match head {
+            Pat::Or(alts) => alts
+                .iter()
+                .flat_map(|alt| {
+                    let mut v = vec![alt.clone()];
+                    v.extend_from_slice(rest);
+                    self.usefulness(&rows, &v, tys)
+                })
+                .collect(),
+            Pat::Ctor(ctor, fields) => self.usefulness_ctor(&rows, ctor, fields, rest, tys),
+            Pat::Wild => {
+                let mut used: Vec<&Ctor> = Vec::new();
+                for row in &rows {
+                    if let Pat::Ctor(ctor, _) = &row[0] {
+                        if !used.contains(&ctor) {
+                            used.push(ctor);
+                        }
+                    }
+                }
+                match self.all_ctors(&tys[0]) {
+                    // Every constructor is mentioned, so each is tried.
+                    Some(all) if all.iter().all(|ctor| used.contains(&ctor)) => all
+                        .iter()
+                        .flat_map(|ctor| {
+                            let fields = vec![Pat::Wild; self.field_tys(&tys[0], ctor).len()];
+                            self.usefulness_ctor(&rows, ctor, &fields, rest, tys)
+                        })
+                        .collect(),
+                    // Otherwise the wildcard matches a constructor which no
+                    // row mentions, so only rows starting with a wildcard
+                    // matter.
+                    all => {
+                        let default: Vec<Vec<Pat>> = rows
+                            .iter()
+                            .filter(|row| matches!(row[0], Pat::Wild))
+                            .map(|row| row[1..].to_vec())
+                            .collect();
+                        let witnesses = self.usefulness(&default, rest, &tys[1..]);
+                        let heads = match all {
+                            Some(all) if !used.is_empty() => all
+                                .into_iter()
+                                .filter(|ctor| !used.contains(&ctor))
+                                .map(|ctor| {
+                                    let fields =
+                                        vec![Pat::Wild; self.field_tys(&tys[0], &ctor).len()];
+                                    Pat::Ctor(ctor, fields)
+                                })
+                                .collect(),
+                            _ => vec![Pat::Wild],
+                        };
+                        let mut result = Vec::new();
+                        for head in heads {
+                            for witness in &witnesses {
+                                let mut row = vec![head.clone()];
+                                row.extend_from_slice(witness);
+                                result.push(row);
+                            }
+                        }
+                        result
+                    }
+                }
+            }
+        };
+        witnesses.truncate(MAX_WITNESSES);
+        witnesses
+    }
+
+    /// Usefulness of a row starting with a constructor pattern.
+    fn usefulness_ctor(
+        &self,
+        rows: &[Vec<Pat>],
+        ctor: &Ctor,
+        fields: &[Pat],
+        rest: &[Pat],
+        tys: &[Ty],
+    ) -> Vec<Vec<Pat>> {
+        let rows = specialize(rows, ctor, fields.len());
+        let mut v = fields.to_vec();
+        v.extend_from_slice(rest);
+        let mut field_tys = self.field_tys(&tys[0], ctor);
+        field_tys.extend_from_slice(&tys[1..]);
+        self.usefulness(&rows, &v, &field_tys)
+            .into_iter()
+            .map(|witness| {
+                let (fields, rest) = witness.split_at(fields.len());
+                let mut row = vec![Pat::Ctor(ctor.clone(), fields.to_vec())];
+                row.extend_from_slice(rest);
+                row
+            })
+            .collect()
+    }
+
+    /// Lists every constructor of a type, or returns `None` if there are too
+    /// many to list.
+    fn all_ctors(&self, ty: &Ty) -> Option<Vec<Ctor>> {
+        match ty {
+            Ty::Bool => Some(vec![Ctor::Bool(false), Ctor::Bool(true)]),
+            Ty::Tuple(_) => Some(vec![Ctor::Tuple]),
+            Ty::Adt(id, _) => Some(
+                (0..self.tcx.adt(*id).variants.len())
+                    .map(Ctor::Variant)
+                    .collect(),
+            ),
+            _ => None,
+        }
+    }
+
+    fn field_tys(&self, ty: &Ty, ctor: &Ctor) -> Vec<Ty> {
+        match (ty, ctor) {
+            (Ty::Adt(id, args), Ctor::Variant(index)) => self.tcx.adt(*id).variants[*index]
+                .fields
+                .iter()
+                .map(|field| field.ty.subst(args))
+                .collect(),
+            (Ty::Tuple(tys), Ctor::Tuple) => tys.clone(),
+            _ => Vec::new(),
+        }
+    }
+
+    /// Lists the first few witnesses, e.g. "patterns `A` and `B`".
+    fn witness_list(&self, witnesses: &[Vec<Pat>], ty: &Ty) -> String {
+        let witnesses: Vec<String> = witnesses
+            .iter()
+            .map(|w| format!("`{}`", self.witness_to_string(&w[0], ty)))
+            .collect();
+        match &witnesses[..] {
+            [w] => format!("pattern {}", w),
+            [init @ .., last] if witnesses.len() <= 3 => {
+                format!("patterns {} and {}", init.join(", "), last)
+            }
+            _ => format!("patterns {} and more", witnesses[..3].join(", ")),
+        }
+    }
+
+    /// Formats a witness the way it would be written in the source.
+    fn witness_to_string(&self, pat: &Pat, ty: &Ty) -> String {
+        match pat {
+            Pat::Wild => "_".to_string(),
+            Pat::Ctor(ctor, fields) => match ctor {
+                Ctor::Bool(b) => b.to_string(),
+                Ctor::Int(n) => n.to_string(),
+                Ctor::Char(c) => format!("{:?}", c),
+                Ctor::Str(s) => format!("{:?}", s.as_str()),
+                Ctor::Tuple => {
+                    let Ty::Tuple(tys) = ty else { unreachable!() };
+                    let fields: Vec<String> = fields
+                        .iter()
+                        .zip(tys)
+                        .map(|(pat, ty)| self.witness_to_string(pat, ty))
+                        .collect();
+                    match &fields[..] {
+                        [field] => format!("({},)", field),
+                        _ => format!("({})", fields.join(", ")),
+                    }
+                }
+                Ctor::Variant(index) => {
+                    let Ty::Adt(id, args) = ty else {
+                        unreachable!()
+                    };
+                    let adt = self.tcx.adt(*id);
+                    let variant = &adt.variants[*index];
+                    let path = adt.variant_path(*index);
+                    let fields: Vec<(Symbol, String)> = variant
+                        .fields
+                        .iter()
+                        .zip(fields)
+                        .map(|(field, pat)| {
+                            (
+                                field.name,
+                                self.witness_to_string(pat, &field.ty.subst(args)),
+                            )
+                        })
+                        .collect();
+                    match variant.ctor_kind {
+                        CtorKind::Const => path,
+                        CtorKind::Fn => {
+                            let fields: Vec<String> = fields.into_iter().map(|(_, f)| f).collect();
+                            format!("{}({})", path, fields.join(", "))
+                        }
+                        CtorKind::Struct => {
+                            let mut shown: Vec<String> = fields
+                                .iter()
+                                .filter(|(_, pat)| pat != "_")
+                                .map(|(name, pat)| format!("{}: {}", name, pat))
+                                .collect();
+                            if shown.len() < fields.len() {
+                                shown.push("..".to_string());
+                            }
+                            if shown.is_empty() {
+                                format!("{} {{}}", path)
+                            } else {
+                                format!("{} {{ {} }}", path, shown.join(", "))
+                            }
+                        }
+                    }
+                }
+            },
+            Pat::Or(_) => unreachable!(),
+        }
+    }
+}
+
+/// Replaces rows starting with an or-pattern by one row per alternative.
+fn expand_or(rows: &[Vec<Pat>]) -> Vec<Vec<Pat>> {
+    let mut expanded = Vec::with_capacity(rows.len());
+    for row in rows {
+        match &row[0] {
+            Pat::Or(alts) => {
+                let alt_rows: Vec<Vec<Pat>> = alts
+                    .iter()
+                    .map(|alt| {
+                        let mut alt_row = vec![alt.clone()];
+                        alt_row.extend_from_slice(&row[1..]);
+                        alt_row
+                    })
+                    .collect();
+                expanded.extend(expand_or(&alt_rows));
+            }
+            _ => expanded.push(row.clone()),
+        }
+    }
+    expanded
+}
+
+/// Keeps the rows which match values built with `ctor`, replacing their first
+/// pattern by the patterns for the fields of the constructor.
+fn specialize(rows: &[Vec<Pat>], ctor: &Ctor, arity: usize) -> Vec<Vec<Pat>> {
+    rows.iter()
+        .filter_map(|row| {
+            let mut specialized = match &row[0] {
+                Pat::Wild => vec![Pat::Wild; arity],
+                Pat::Ctor(head, fields) if head == ctor => fields.clone(),
+                Pat::Ctor(..) => return None,
+                Pat::Or(_) => unreachable!("or-patterns are expanded first"),
+            };
+            specialized.extend_from_slice(&row[1..]);
+            Some(specialized)
+        })
+        .collect()
+}